@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::db::catalog::Catalog;
+use crate::db::diff::diff;
+use crate::db::service::{Service, TenguService};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Compares the schemas of two saved connections, printing what differs or,
+/// with `script`, the statements turning `from`'s schema into `to`'s.
+pub async fn run(from: String, to: String, script: bool) -> Result<()> {
+    let from_repo = FsTenguRepository::pinned(&from)?;
+    let to_repo = FsTenguRepository::pinned(&to)?;
+    let engine_of = |repo: &FsTenguRepository| {
+        repo.get_active_connection()
+            .map(|c| c.engine)
+            .ok_or_else(|| anyhow!("no saved connection"))
+    };
+    let (engine, to_engine) = (engine_of(&from_repo)?, engine_of(&to_repo)?);
+    if engine != to_engine {
+        bail!(
+            "{} is a {} connection and {} a {} one; only schemas of the same engine can be compared",
+            from,
+            engine,
+            to,
+            to_engine
+        );
+    }
+    let from_service = TenguService::new(engine.clone(), from_repo);
+    let to_service = TenguService::new(engine.clone(), to_repo);
+    let (from_catalog, to_catalog) =
+        tokio::try_join!(Catalog::load(&from_service), Catalog::load(&to_service))?;
+    let diff = diff(&from_catalog, &to_catalog);
+    if !script {
+        print!("{}", diff.report());
+        return Ok(());
+    }
+    let mut create_tables = HashMap::new();
+    for table in &diff.added_tables {
+        let ddl = to_service.get_table_ddl(&table.table).await?;
+        create_tables.insert(table.table.clone(), ddl);
+    }
+    println!("{}", diff.script(&engine, &create_tables));
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::db::export::{export, exportable, numbered_path, ExportFormat};
+use crate::db::service::{Service, TenguService};
+use crate::prelude::read_file_to_string;
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Runs the SQL on the active connection and writes each result set to
+/// `output`, or stdout without one. Result sets after the first go to
+/// `name-2.ext`, `name-3.ext`, ...
+pub async fn run(
+    sql: Option<String>,
+    file: Option<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<ExportFormat>,
+    table: Option<String>,
+) -> Result<()> {
+    let sql = match (sql, file) {
+        (Some(sql), None) => sql,
+        (None, Some(file)) => read_file_to_string(file)?,
+        _ => bail!("pass either the SQL or --file"),
+    };
+    let format = format
+        .or_else(|| output.as_deref().and_then(ExportFormat::from_path))
+        .unwrap_or(ExportFormat::Csv);
+    let repo = FsTenguRepository::new();
+    let connection = repo
+        .get_active_connection()
+        .ok_or_else(|| anyhow!("no active connection"))?;
+    let engine = connection.engine.clone();
+    let service = TenguService::new(connection.engine, repo);
+    let results = service.execute(&sql).await?;
+    let table = table.unwrap_or_else(|| "results".to_string());
+    for (i, result) in exportable(&results)?.into_iter().enumerate() {
+        match &output {
+            Some(path) => {
+                let path = numbered_path(path, i);
+                let mut out = BufWriter::new(File::create(&path)?);
+                export(result, format, &engine, &table, &mut out)?;
+                out.flush()?;
+                eprintln!("{} rows written to {}", result.rows.len(), path.display());
+            }
+            None => export(result, format, &engine, &table, &mut std::io::stdout())?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use super::sql_files;
+use crate::config::WorkspaceConfig;
+use crate::db::engine::Engine;
+use crate::prelude::read_file_to_string;
+use crate::sql::format::format_sql;
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Formats every SQL file under `paths` in place. With `check`, files are left
+/// untouched and the return value tells whether any of them would change.
+pub fn run(paths: Vec<PathBuf>, check: bool, dialect: Option<Engine>) -> Result<bool> {
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let config = WorkspaceConfig::load(&std::env::current_dir()?);
+    let engine = dialect.unwrap_or_else(|| {
+        config.dialect_or(
+            FsTenguRepository::new()
+                .get_active_connection()
+                .map(|c| c.engine),
+        )
+    });
+    let mut unformatted = Vec::new();
+    for file in sql_files(&paths) {
+        let content = read_file_to_string(file.clone())?;
+        let formatted = format_sql(&content, &engine, &config.format);
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("Would reformat {}", file.display());
+        } else {
+            fs::write(&file, formatted)?;
+            println!("Formatted {}", file.display());
+        }
+        unformatted.push(file);
+    }
+    Ok(unformatted.is_empty())
+}
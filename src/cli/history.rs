@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+
+use crate::db::query::{render, ResultFormat};
+use crate::db::service::{Service, TenguService};
+use crate::history::{search, History, Status};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Lists the statements matching `query`, most recent first, numbered so one
+/// of them can be picked with `run`. With `run`, that statement is executed
+/// again on the active connection and its results printed instead.
+pub async fn run(query: Option<String>, limit: usize, rerun: Option<usize>) -> Result<()> {
+    let entries = search(&query.unwrap_or_default(), History::open().load());
+    if let Some(n) = rerun {
+        let entry = n
+            .checked_sub(1)
+            .and_then(|i| entries.get(i))
+            .ok_or_else(|| anyhow!("no history entry {}", n))?;
+        let repo = FsTenguRepository::new();
+        let connection = repo
+            .get_active_connection()
+            .ok_or_else(|| anyhow!("no active connection"))?;
+        let service = TenguService::new(connection.engine, repo);
+        let results = service.execute(&entry.sql).await?;
+        print!("{}", render(&results, ResultFormat::Markdown));
+        return Ok(());
+    }
+    for (i, entry) in entries.iter().take(limit).enumerate() {
+        let outcome = match (entry.status, entry.rows) {
+            (Status::Ok, Some(rows)) => format!("{} rows", rows),
+            (Status::Ok, None) => "ok".to_string(),
+            (Status::Error, _) => "error".to_string(),
+        };
+        println!(
+            "{:>4}  {}  {}  {}  {} ms",
+            i + 1,
+            entry.time(),
+            entry.connection,
+            outcome,
+            entry.duration_ms
+        );
+        println!("      {}", entry.summary(100));
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::db::engine::Engine;
+use crate::db::import::{coerce, map_columns, read, ImportFormat};
+use crate::db::service::{Service, TenguService};
+use crate::db::table::Table;
+use crate::prelude::read_file_to_string;
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Loads a CSV or JSON file into `table` on the active connection, `batch_size`
+/// rows per bulk load. The whole file is checked against the table's columns
+/// first; with `dry_run` that's all that happens.
+pub async fn run(
+    file: PathBuf,
+    table: String,
+    format: Option<ImportFormat>,
+    batch_size: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let format = format
+        .or_else(|| ImportFormat::from_path(&file))
+        .ok_or_else(|| anyhow!("can't tell the format of {}, pass --format", file.display()))?;
+    let mut data = read(&read_file_to_string(file.clone())?, format)?;
+    let repo = FsTenguRepository::new();
+    let connection = repo
+        .get_active_connection()
+        .ok_or_else(|| anyhow!("no active connection"))?;
+    let table = match table.split_once('.') {
+        Some((schema, name)) => Table {
+            name: name.to_string(),
+            schema: schema.to_string(),
+            description: None,
+        },
+        None => Table {
+            name: table,
+            schema: match connection.engine {
+                Engine::SqlServer => "dbo",
+                _ => "public",
+            }
+            .to_string(),
+            description: None,
+        },
+    };
+    let service = TenguService::new(connection.engine, repo);
+    let columns: Vec<_> = service
+        .get_table_columns(HashSet::from([table.clone()]))
+        .await?
+        .into_iter()
+        .collect();
+    let columns = map_columns(&data.columns, &table, &columns)?;
+    coerce(&mut data, &columns)?;
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    if dry_run {
+        println!(
+            "{} rows would be loaded into {}.{} ({})",
+            data.rows.len(),
+            table.schema,
+            table.name,
+            names.join(", ")
+        );
+        return Ok(());
+    }
+    let mut loaded = 0;
+    for batch in data.rows.chunks(batch_size.max(1)) {
+        loaded += service
+            .bulk_insert(&table, &columns, batch)
+            .await
+            .map_err(|e| e.context(format!("{} rows were loaded before the error", loaded)))?;
+        eprintln!("{} of {} rows", loaded, data.rows.len());
+    }
+    println!(
+        "{} rows loaded into {}.{} ({})",
+        loaded,
+        table.schema,
+        table.name,
+        names.join(", ")
+    );
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use super::sql_files;
+use crate::config::WorkspaceConfig;
+use crate::db::engine::Engine;
+use crate::prelude::read_file_to_string;
+use crate::sql::lint::{lint, LintContext, Severity, RULES};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Lints every SQL file under `paths` and prints the findings. Returns whether
+/// the files are clean of warnings and errors.
+pub fn run(paths: Vec<PathBuf>, dialect: Option<Engine>) -> Result<bool> {
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let config = WorkspaceConfig::load(&std::env::current_dir()?);
+    let engine = dialect.unwrap_or_else(|| {
+        config.dialect_or(
+            FsTenguRepository::new()
+                .get_active_connection()
+                .map(|c| c.engine),
+        )
+    });
+    let context = LintContext::new(engine);
+    let mut clean = true;
+    for file in sql_files(&paths) {
+        let content = read_file_to_string(file.clone())?;
+        for finding in lint(&content, &context, &config.lint) {
+            if finding.severity >= Severity::Warning {
+                clean = false;
+            }
+            println!(
+                "{}:{}:{}: {} [{}] {}",
+                file.display(),
+                finding.range.start.line + 1,
+                finding.range.start.character + 1,
+                severity_name(finding.severity),
+                finding.rule,
+                finding.message
+            );
+        }
+    }
+    Ok(clean)
+}
+
+pub fn print_rules() {
+    for rule in RULES {
+        println!(
+            "{:<30} {:<8} {}",
+            rule.id,
+            severity_name(rule.severity),
+            rule.description
+        );
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Off => "off",
+        Severity::Hint => "hint",
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
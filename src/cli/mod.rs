@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub mod diff;
+pub mod export;
+pub mod fmt;
+pub mod history;
+pub mod import;
+pub mod lint;
+pub mod schema;
+pub mod snippet;
+
+/// Collects the `.sql` files under `paths`, walking directories recursively.
+/// Files passed explicitly are kept whatever their extension.
+pub fn sql_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_sql_files(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn collect_sql_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_sql_files(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+        {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::db::catalog::Catalog;
+use crate::db::service::{Service, TenguService};
+use crate::db::snapshot::{object_path, Snapshot};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Writes the schema of the saved connection `name` to `out`: a snapshot the
+/// LSP can be pointed at when `out` is a `.json` file, otherwise a directory
+/// with a `.sql` file per table, view and routine.
+pub async fn dump(name: String, out: PathBuf) -> Result<()> {
+    let repo = FsTenguRepository::pinned(&name)?;
+    let connection = repo
+        .get_active_connection()
+        .ok_or_else(|| anyhow!("no saved connection named {}", name))?;
+    let service = TenguService::new(connection.engine.clone(), repo);
+    let is_json = out
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        let snapshot = Snapshot::take(
+            &service,
+            connection.engine,
+            &connection.name,
+            &connection.database,
+        )
+        .await?;
+        snapshot.save(&out)?;
+        println!(
+            "{} tables and {} views and routines written to {}",
+            snapshot.catalog.tables.len(),
+            snapshot.catalog.objects.len(),
+            out.display()
+        );
+        return Ok(());
+    }
+    let catalog = Catalog::load(&service).await?;
+    let mut files = Vec::new();
+    for table in &catalog.tables {
+        let ddl = service.get_table_ddl(&table.table).await?;
+        files.push((
+            object_path(&table.table.schema, None, &table.table.name),
+            ddl,
+        ));
+    }
+    for object in catalog.objects {
+        let path = object_path(
+            &object.object.schema,
+            Some(object.object.kind),
+            &object.object.name,
+        );
+        files.push((path, object.definition));
+    }
+    for (path, sql) in &files {
+        let path = out.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("can't create {}", parent.display()))?;
+        }
+        fs::write(&path, format!("{}\n", sql.trim_end()))
+            .with_context(|| format!("can't write {}", path.display()))?;
+    }
+    println!("{} files written under {}", files.len(), out.display());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+use crate::prelude::read_file_to_string;
+use crate::snippet::{placeholders, Snippet, Snippets};
+
+/// Saves a snippet from `sql` or the contents of `file`.
+pub fn add(
+    name: String,
+    sql: Option<String>,
+    file: Option<PathBuf>,
+    connection: Option<String>,
+    description: Option<String>,
+) -> Result<()> {
+    let sql = match (sql, file) {
+        (Some(sql), None) => sql,
+        (None, Some(file)) => read_file_to_string(file)?,
+        _ => bail!("pass either the SQL or --file"),
+    };
+    Snippets::open().add(Snippet {
+        name,
+        sql: sql.trim().to_string(),
+        connection,
+        description,
+    })
+}
+
+pub fn list() -> Result<()> {
+    for snippet in Snippets::open().load()? {
+        let params: Vec<String> = placeholders(&snippet.sql)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| format!("{}:{}", p.name, p.param_type))
+            .collect();
+        let line = format!(
+            "{} ({})  {}",
+            snippet.name,
+            snippet.connection.as_deref().unwrap_or("global"),
+            params.join(", ")
+        );
+        println!("{}", line.trim_end());
+        if let Some(description) = &snippet.description {
+            println!("    {}", description);
+        }
+    }
+    Ok(())
+}
+
+pub fn remove(name: String, connection: Option<String>) -> Result<()> {
+    if !Snippets::open().remove(&name, connection.as_deref())? {
+        bail!("no snippet named {}", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::engine::Engine;
+use crate::db::query::ResultFormat;
+use crate::prelude::read_file_to_string;
+use crate::sql::format::FormatOptions;
+use crate::sql::lint::LintOptions;
+
+pub const CONFIG_FILE_NAME: &str = "tengu.json";
+
+/// Per-workspace settings, read from a `tengu.json` at the root of the project.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    pub dialect: Option<Engine>,
+    pub format: FormatOptions,
+    pub lint: LintOptions,
+    pub rename: RenameOptions,
+    /// How results of statements run from the editor are written.
+    pub results: ResultFormat,
+    /// Folder, relative to `tengu.json`, of the Flyway, sqitch or dbmate
+    /// migrations whose schema the language server completes and lints
+    /// against.
+    pub migrations: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenameOptions {
+    /// Folder, relative to `tengu.json`, where renaming a table or column of
+    /// the database also writes a migration script doing the same rename.
+    pub migrations: Option<PathBuf>,
+}
+
+impl WorkspaceConfig {
+    /// Looks for `tengu.json` in `start` and its ancestors, falling back to the
+    /// defaults when there is none or it can't be parsed.
+    pub fn load(start: &Path) -> WorkspaceConfig {
+        let Some(path) = Self::find(start) else {
+            return WorkspaceConfig::default();
+        };
+        match read_file_to_string(path.clone()).map(|c| serde_json::from_str(&c)) {
+            Ok(Ok(config)) => config,
+            Ok(Err(e)) => {
+                eprintln!("Error reading {}: {}", path.display(), e);
+                WorkspaceConfig::default()
+            }
+            Err(_) => WorkspaceConfig::default(),
+        }
+    }
+
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Where rename migrations go, if the workspace asked for them.
+    pub fn migrations_dir(&self, start: &Path) -> Option<PathBuf> {
+        let migrations = self.rename.migrations.as_ref()?;
+        let root = Self::find(start)?.parent()?.to_path_buf();
+        Some(root.join(migrations))
+    }
+
+    /// The migrations folder the schema comes from, if any.
+    pub fn schema_migrations_dir(&self, start: &Path) -> Option<PathBuf> {
+        let migrations = self.migrations.as_ref()?;
+        let root = Self::find(start)?.parent()?.to_path_buf();
+        Some(root.join(migrations))
+    }
+
+    /// The dialect from the config, or the engine of the active connection.
+    pub fn dialect_or(&self, engine: Option<Engine>) -> Engine {
+        self.dialect.clone().or(engine).unwrap_or(Engine::SqlServer)
+    }
+}
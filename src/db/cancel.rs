@@ -0,0 +1,163 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::{Lazy, OnceCell};
+use tokio::task::JoinHandle;
+
+type Cancel = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+type Report = Box<dyn Fn(String) + Send + Sync>;
+
+/// Where a cancel the server refused is told of as it happens, set by the
+/// language server to show it in the editor.
+static REPORT: OnceCell<Report> = OnceCell::new();
+
+/// Cancels started and not yet awaited by `cancelled`, while nothing is set
+/// to report them.
+static STARTED: Lazy<Mutex<Vec<JoinHandle<Result<()>>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+tokio::task_local! {
+    /// How to stop what the connections opened by the current call run.
+    static CANCELS: Arc<Mutex<Vec<Cancel>>>;
+}
+
+/// Whether connections should say how to cancel them, being opened within
+/// `cancellable`.
+pub fn in_scope() -> bool {
+    CANCELS.try_with(|_| ()).is_ok()
+}
+
+/// Notes how to cancel, on the server, what a connection the current call
+/// opened is running.
+pub fn on_cancel(cancel: impl Future<Output = Result<()>> + Send + 'static) {
+    let _ = CANCELS.try_with(|cancels| cancels.lock().unwrap().push(Box::pin(cancel)));
+}
+
+/// Cancels what's registered unless the call finished.
+struct Guard {
+    cancels: Arc<Mutex<Vec<Cancel>>>,
+    finished: bool,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        for cancel in self.cancels.lock().unwrap().drain(..) {
+            match REPORT.get() {
+                Some(report) => {
+                    runtime.spawn(async move {
+                        if let Err(e) = cancel.await {
+                            report(format!("Couldn't cancel on the server: {:#}", e));
+                        }
+                    });
+                }
+                None => STARTED.lock().unwrap().push(runtime.spawn(cancel)),
+            }
+        }
+    }
+}
+
+/// Shows cancels that fail with `report` from now on, instead of leaving
+/// them to `cancelled`.
+pub fn report_to(report: impl Fn(String) + Send + Sync + 'static) {
+    let _ = REPORT.set(Box::new(report));
+}
+
+/// Waits for the cancels started so far, failing with why if the server
+/// refused one, e.g. SQL Server without ALTER ANY CONNECTION.
+pub async fn cancelled() -> Result<()> {
+    let started: Vec<_> = STARTED.lock().unwrap().drain(..).collect();
+    for cancel in started {
+        cancel.await??;
+    }
+    Ok(())
+}
+
+/// Runs `future`, and if it's dropped before finishing, because the editor
+/// cancelled the request, Ctrl-C was pressed or time ran out, cancels on the
+/// server whatever its connections were still running.
+pub async fn cancellable<F: Future>(future: F) -> F::Output {
+    let cancels = Arc::new(Mutex::new(Vec::new()));
+    let mut guard = Guard {
+        cancels: cancels.clone(),
+        finished: false,
+    };
+    let output = CANCELS.scope(cancels, future).await;
+    guard.finished = true;
+    output
+}
+
+/// Runs `future` like `cancellable`, giving up after `seconds` if set.
+pub async fn timed<T>(seconds: Option<u64>, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let Some(seconds) = seconds else {
+        return cancellable(future).await;
+    };
+    match tokio::time::timeout(Duration::from_secs(seconds), cancellable(future)).await {
+        Ok(result) => result,
+        Err(_) => match cancelled().await {
+            Ok(()) => Err(anyhow!("Statement timed out after {}s", seconds)),
+            Err(e) => Err(anyhow!(
+                "Statement timed out after {}s and couldn't be cancelled: {:#}",
+                seconds,
+                e
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Held by tests awaiting `cancelled`, which they'd otherwise steal each
+    /// other's cancels from.
+    static STARTED_CANCELS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn should_cancel_only_what_didnt_finish() {
+        let _started = STARTED_CANCELS.lock().await;
+        let finished = Arc::new(AtomicBool::new(false));
+        let dropped = Arc::new(AtomicBool::new(false));
+        let register = |flag: Arc<AtomicBool>| {
+            on_cancel(async move {
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+        cancellable(async { register(finished.clone()) }).await;
+        tokio::select! {
+            biased;
+            _ = cancellable(async {
+                register(dropped.clone());
+                std::future::pending::<()>().await
+            }) => {}
+            _ = async {} => {}
+        }
+        cancelled().await.unwrap();
+        assert!(!finished.load(Ordering::SeqCst));
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn should_tell_why_a_cancel_failed() {
+        let _started = STARTED_CANCELS.lock().await;
+        let result = timed(Some(0), async {
+            on_cancel(async { Err(anyhow!("no permission")) });
+            std::future::pending::<Result<()>>().await
+        })
+        .await;
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("couldn't be cancelled"));
+        assert!(message.ends_with("no permission"));
+    }
+}
@@ -0,0 +1,110 @@
+//! Everything the catalog says about a database's tables and routines,
+//! loaded at once so two databases can be compared.
+
+use anyhow::Result;
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::column::Column;
+use super::ddl::{create_table, ColumnDefinition};
+use super::engine::Engine;
+use super::index::{Constraint, ConstraintKind, Index};
+use super::object::{DbObject, ObjectKind};
+use super::service::Service;
+use super::table::Table;
+
+/// Object definitions queried at once, each on its own connection.
+const CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub table: Table,
+    /// Sorted by name.
+    pub columns: Vec<Column>,
+    pub indexes: Vec<Index>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl TableSchema {
+    /// A `CREATE TABLE` from what the catalog holds, without identities.
+    pub fn create_table(&self, engine: &Engine) -> String {
+        let mut columns: Vec<&Column> = self.columns.iter().collect();
+        columns.sort_by_key(|c| c.ordinal_position);
+        let columns: Vec<ColumnDefinition> = columns
+            .into_iter()
+            .map(|c| ColumnDefinition {
+                name: c.name.clone(),
+                data_type: c.declared_type(),
+                nullable: c.is_nullable != "NO",
+                extra: c.column_default.as_ref().map(|d| format!("DEFAULT {}", d)),
+            })
+            .collect();
+        let constraints: Vec<Constraint> = self
+            .constraints
+            .iter()
+            .filter(|c| c.kind != ConstraintKind::Default)
+            .cloned()
+            .collect();
+        create_table(engine, &self.table, &columns, &constraints, &self.indexes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectSchema {
+    pub object: DbObject,
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catalog {
+    pub tables: Vec<TableSchema>,
+    pub objects: Vec<ObjectSchema>,
+}
+
+impl Catalog {
+    pub async fn load<S: Service + Sync>(service: &S) -> Result<Catalog> {
+        let objects = service.get_objects().await?;
+        // Postgres lists views with the tables; they're compared as objects.
+        let tables: Vec<Table> = service
+            .get_tables()
+            .await?
+            .into_iter()
+            .filter(|t| {
+                !objects
+                    .iter()
+                    .any(|o| o.kind == ObjectKind::View && o.schema == t.schema && o.name == t.name)
+            })
+            .collect();
+        let mut columns = service.get_all_columns().await?;
+        let mut indexes = service.get_all_indexes().await?;
+        let mut constraints = service.get_all_constraints().await?;
+        let tables: Vec<TableSchema> = tables
+            .into_iter()
+            .map(|table| {
+                let mut table_columns = columns.remove(&table).unwrap_or_default();
+                table_columns.sort_by(|a, b| a.name.cmp(&b.name));
+                TableSchema {
+                    indexes: indexes.remove(&table).unwrap_or_default(),
+                    constraints: constraints.remove(&table).unwrap_or_default(),
+                    columns: table_columns,
+                    table,
+                }
+            })
+            .collect();
+        let objects: Vec<ObjectSchema> = futures::stream::iter(objects)
+            .map(|object| async move {
+                Ok::<_, anyhow::Error>(ObjectSchema {
+                    definition: service.get_object_ddl(&object).await?,
+                    object,
+                })
+            })
+            .buffered(CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(Catalog { tables, objects })
+    }
+
+    pub fn table(&self, table: &Table) -> Option<&TableSchema> {
+        self.tables.iter().find(|t| t.table == *table)
+    }
+}
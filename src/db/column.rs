@@ -1,10 +1,57 @@
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
+/// A column as `information_schema.columns` describes it.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, FromRow, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
     pub table: String,
     pub schema: String,
     pub data_type: String,
     pub is_nullable: String,
+    /// The default expression as the catalog spells it, e.g. `((0))` or
+    /// `now()`.
+    pub column_default: Option<String>,
+    /// `-1` for `max`.
+    pub character_maximum_length: Option<i32>,
+    pub numeric_precision: Option<i32>,
+    pub numeric_scale: Option<i32>,
+    /// Where the column sits in the table, counting from 1. SQL Server leaves
+    /// gaps where columns were dropped.
+    pub ordinal_position: i32,
+    /// The `MS_Description` extended property or `COMMENT ON COLUMN`.
+    #[sqlx(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Column {
+    /// The type with its length or precision, e.g. `nvarchar(50)`,
+    /// `numeric(10,2)` or `character varying(20)`.
+    pub fn declared_type(&self) -> String {
+        let data_type = self.data_type.as_str();
+        let sized = matches!(
+            data_type,
+            "char"
+                | "varchar"
+                | "nchar"
+                | "nvarchar"
+                | "binary"
+                | "varbinary"
+                | "character"
+                | "character varying"
+                | "bit varying"
+        );
+        match (self.character_maximum_length, self.numeric_precision) {
+            (Some(-1), _) if sized => format!("{}(max)", data_type),
+            (Some(length), _) if sized => format!("{}({})", data_type, length),
+            (_, Some(precision)) if matches!(data_type, "decimal" | "numeric") => format!(
+                "{}({},{})",
+                data_type,
+                precision,
+                self.numeric_scale.unwrap_or_default()
+            ),
+            _ => data_type.to_string(),
+        }
+    }
 }
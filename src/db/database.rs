@@ -0,0 +1,44 @@
+/// A database other than the connection's, as the first parts of a three- or
+/// four-part name spell it: `OtherDb.dbo.t` or `[LinkedSrv].Db.dbo.t`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Database {
+    /// The linked server the database is on, if not the connection's.
+    pub server: Option<String>,
+    pub name: String,
+}
+
+impl Database {
+    pub fn new(server: Option<String>, name: String) -> Self {
+        Self { server, name }
+    }
+
+    /// `[server].[name].`, to put in front of the catalog views.
+    pub fn prefix(&self) -> String {
+        let quote = |name: &str| format!("[{}]", name.replace(']', "]]"));
+        match &self.server {
+            Some(server) => format!("{}.{}.", quote(server), quote(&self.name)),
+            None => format!("{}.", quote(&self.name)),
+        }
+    }
+
+    /// The same database whatever case it was written in, for cache keys.
+    pub fn key(&self) -> Database {
+        Database {
+            server: self.server.as_ref().map(|s| s.to_lowercase()),
+            name: self.name.to_lowercase(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_prefix_catalog_views_with_the_database_and_linked_server() {
+        let local = Database::new(None, "Sales".to_string());
+        let linked = Database::new(Some("Linked]Srv".to_string()), "Sales DB".to_string());
+        assert_eq!(local.prefix(), "[Sales].");
+        assert_eq!(linked.prefix(), "[Linked]]Srv].[Sales DB].");
+    }
+}
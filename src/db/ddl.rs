@@ -0,0 +1,123 @@
+use super::engine::Engine;
+use super::index::{Constraint, Index};
+use super::table::Table;
+
+/// A column as the catalog describes it, with its type already spelled the
+/// way the engine writes it, e.g. `nvarchar(50)` or `numeric(10,2)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    /// Anything after the nullability: `IDENTITY(1,1)`, `DEFAULT now()`, ...
+    pub extra: Option<String>,
+}
+
+/// A `CREATE TABLE` script for the table, followed by its constraints and the
+/// indexes that don't back one of them.
+pub fn create_table(
+    engine: &Engine,
+    table: &Table,
+    columns: &[ColumnDefinition],
+    constraints: &[Constraint],
+    indexes: &[Index],
+) -> String {
+    let name = engine.qualified(&table.schema, &table.name);
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let mut line = format!(
+                "    {} {} {}",
+                engine.quote(&c.name),
+                c.data_type,
+                if c.nullable { "NULL" } else { "NOT NULL" }
+            );
+            if let Some(extra) = &c.extra {
+                line.push(' ');
+                line.push_str(extra);
+            }
+            line
+        })
+        .collect();
+    let mut ddl = format!("CREATE TABLE {} (\n{}\n);\n", name, columns.join(",\n"));
+    for constraint in constraints {
+        ddl.push_str(&format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {};\n",
+            name,
+            engine.quote(&constraint.name),
+            constraint.definition
+        ));
+    }
+    for index in indexes
+        .iter()
+        .filter(|i| !i.primary_key && !constraints.iter().any(|c| c.name == i.name))
+    {
+        ddl.push_str(&format!("{};\n", index.create(engine, &name)));
+    }
+    ddl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::index::ConstraintKind;
+
+    #[test]
+    fn should_script_a_table() {
+        let table = Table {
+            name: "order lines".to_string(),
+            schema: "dbo".to_string(),
+            description: None,
+        };
+        let columns = vec![
+            ColumnDefinition {
+                name: "id".to_string(),
+                data_type: "int".to_string(),
+                nullable: false,
+                extra: Some("IDENTITY(1,1)".to_string()),
+            },
+            ColumnDefinition {
+                name: "order_id".to_string(),
+                data_type: "int".to_string(),
+                nullable: true,
+                extra: None,
+            },
+        ];
+        let constraints = vec![Constraint {
+            name: "pk_order_lines".to_string(),
+            kind: ConstraintKind::PrimaryKey,
+            definition: "PRIMARY KEY (id)".to_string(),
+            columns: vec!["id".to_string()],
+        }];
+        let indexes = vec![
+            Index {
+                name: "pk_order_lines".to_string(),
+                columns: vec!["id".to_string()],
+                included: vec![],
+                unique: true,
+                primary_key: true,
+                filter: None,
+                kind: Some("CLUSTERED".to_string()),
+            },
+            Index {
+                name: "ix_order".to_string(),
+                columns: vec!["order_id".to_string()],
+                included: vec!["id".to_string()],
+                unique: false,
+                primary_key: false,
+                filter: Some("order_id IS NOT NULL".to_string()),
+                kind: Some("NONCLUSTERED".to_string()),
+            },
+        ];
+        assert_eq!(
+            create_table(&Engine::SqlServer, &table, &columns, &constraints, &indexes),
+            "CREATE TABLE dbo.[order lines] (
+    id int NOT NULL IDENTITY(1,1),
+    order_id int NULL
+);
+ALTER TABLE dbo.[order lines] ADD CONSTRAINT pk_order_lines PRIMARY KEY (id);
+CREATE NONCLUSTERED INDEX ix_order ON dbo.[order lines] (order_id) INCLUDE (id) WHERE order_id IS NOT NULL;
+"
+        );
+    }
+}
@@ -0,0 +1,862 @@
+//! Differences between the schemas of two databases, as a report and as a
+//! script turning the first into the second.
+
+use std::collections::HashMap;
+
+use super::catalog::{Catalog, ObjectSchema, TableSchema};
+use super::column::Column;
+use super::engine::Engine;
+use super::index::{Constraint, ConstraintKind, Index};
+use super::object::{DbObject, ObjectKind};
+use super::table::Table;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnChange {
+    pub from: Column,
+    pub to: Column,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    pub table: Table,
+    pub added_columns: Vec<Column>,
+    pub dropped_columns: Vec<Column>,
+    pub changed_columns: Vec<ColumnChange>,
+    pub added_indexes: Vec<Index>,
+    pub dropped_indexes: Vec<Index>,
+    pub added_constraints: Vec<Constraint>,
+    pub dropped_constraints: Vec<Constraint>,
+    /// The constraint holding each column's default in the first database;
+    /// SQL Server has to drop it before changing the default or the column.
+    pub default_constraints: HashMap<String, String>,
+}
+
+impl TableDiff {
+    fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.dropped_columns.is_empty()
+            && self.changed_columns.is_empty()
+            && self.added_indexes.is_empty()
+            && self.dropped_indexes.is_empty()
+            && self.added_constraints.is_empty()
+            && self.dropped_constraints.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Schemas the added tables and objects are in that the first database
+    /// has nothing in.
+    pub added_schemas: Vec<String>,
+    pub added_tables: Vec<TableSchema>,
+    pub dropped_tables: Vec<TableSchema>,
+    pub changed_tables: Vec<TableDiff>,
+    pub added_objects: Vec<ObjectSchema>,
+    pub dropped_objects: Vec<ObjectSchema>,
+    /// With the definition they have in the second database.
+    pub changed_objects: Vec<ObjectSchema>,
+}
+
+/// What changes between `from` and `to`. Tables, columns and routines are
+/// matched by name; indexes by their columns and constraints by their
+/// definition, since the engine often names those itself.
+pub fn diff(from: &Catalog, to: &Catalog) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+    for table in &to.tables {
+        match from.table(&table.table) {
+            Some(old) => {
+                let table_diff = diff_table(old, table);
+                if !table_diff.is_empty() {
+                    diff.changed_tables.push(table_diff);
+                }
+            }
+            None => diff.added_tables.push(table.clone()),
+        }
+    }
+    diff.dropped_tables = from
+        .tables
+        .iter()
+        .filter(|t| to.table(&t.table).is_none())
+        .cloned()
+        .collect();
+    for object in &to.objects {
+        match from.objects.iter().find(|o| o.object == object.object) {
+            Some(old) if same_definition(&old.definition, &object.definition) => {}
+            Some(_) => diff.changed_objects.push(object.clone()),
+            None => diff.added_objects.push(object.clone()),
+        }
+    }
+    diff.dropped_objects = from
+        .objects
+        .iter()
+        .filter(|o| !to.objects.iter().any(|p| p.object == o.object))
+        .cloned()
+        .collect();
+    let table_key = |t: &Table| (t.schema.clone(), t.name.clone());
+    diff.added_tables.sort_by_key(|t| table_key(&t.table));
+    diff.dropped_tables.sort_by_key(|t| table_key(&t.table));
+    diff.changed_tables.sort_by_key(|t| table_key(&t.table));
+    let object_key = |o: &DbObject| (o.schema.clone(), o.name.clone());
+    diff.added_objects.sort_by_key(|o| object_key(&o.object));
+    diff.dropped_objects.sort_by_key(|o| object_key(&o.object));
+    diff.changed_objects.sort_by_key(|o| object_key(&o.object));
+    let used = |schema: &str| {
+        from.tables.iter().any(|t| t.table.schema == schema)
+            || from.objects.iter().any(|o| o.object.schema == schema)
+    };
+    let added = diff.added_tables.iter().map(|t| &t.table.schema);
+    for schema in added.chain(diff.added_objects.iter().map(|o| &o.object.schema)) {
+        if !used(schema) && !diff.added_schemas.contains(schema) {
+            diff.added_schemas.push(schema.clone());
+        }
+    }
+    diff.added_schemas.sort();
+    diff
+}
+
+fn diff_table(from: &TableSchema, to: &TableSchema) -> TableDiff {
+    let mut diff = TableDiff {
+        table: to.table.clone(),
+        ..TableDiff::default()
+    };
+    for column in &to.columns {
+        match from.columns.iter().find(|c| c.name == column.name) {
+            Some(old)
+                if old.declared_type() != column.declared_type()
+                    || old.is_nullable != column.is_nullable
+                    || old.column_default != column.column_default =>
+            {
+                diff.changed_columns.push(ColumnChange {
+                    from: old.clone(),
+                    to: column.clone(),
+                })
+            }
+            Some(_) => {}
+            None => diff.added_columns.push(column.clone()),
+        }
+    }
+    diff.dropped_columns = from
+        .columns
+        .iter()
+        .filter(|c| !to.columns.iter().any(|d| d.name == c.name))
+        .cloned()
+        .collect();
+
+    let (from_indexes, to_indexes) = (own_indexes(from), own_indexes(to));
+    // Migrations and older snapshots may not know an index's kind.
+    let same_index = |a: &Index, b: &Index| {
+        a.columns == b.columns
+            && a.unique == b.unique
+            && a.included == b.included
+            && match (&a.filter, &b.filter) {
+                (Some(a), Some(b)) => same_definition(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && match (&a.kind, &b.kind) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                _ => true,
+            }
+    };
+    diff.added_indexes = to_indexes
+        .iter()
+        .filter(|i| !from_indexes.iter().any(|j| same_index(i, j)))
+        .map(|i| (*i).clone())
+        .collect();
+    diff.dropped_indexes = from_indexes
+        .iter()
+        .filter(|i| !to_indexes.iter().any(|j| same_index(i, j)))
+        .map(|i| (*i).clone())
+        .collect();
+
+    // Defaults are compared with their columns.
+    let constraints = |schema: &TableSchema| -> Vec<Constraint> {
+        schema
+            .constraints
+            .iter()
+            .filter(|c| c.kind != ConstraintKind::Default)
+            .cloned()
+            .collect()
+    };
+    let (from_constraints, to_constraints) = (constraints(from), constraints(to));
+    let same_constraint =
+        |a: &Constraint, b: &Constraint| a.kind == b.kind && a.definition == b.definition;
+    diff.added_constraints = to_constraints
+        .iter()
+        .filter(|c| !from_constraints.iter().any(|d| same_constraint(c, d)))
+        .cloned()
+        .collect();
+    diff.dropped_constraints = from_constraints
+        .iter()
+        .filter(|c| !to_constraints.iter().any(|d| same_constraint(c, d)))
+        .cloned()
+        .collect();
+    diff.default_constraints = from
+        .constraints
+        .iter()
+        .filter(|c| c.kind == ConstraintKind::Default)
+        .filter_map(|c| {
+            let (_, column) = c.definition.rsplit_once(" FOR ")?;
+            let column = column.trim_matches(['[', ']', '"']).to_string();
+            Some((column, c.name.clone()))
+        })
+        .collect();
+    diff
+}
+
+/// The indexes that don't back the primary key or another constraint.
+fn own_indexes(schema: &TableSchema) -> Vec<&Index> {
+    schema
+        .indexes
+        .iter()
+        .filter(|i| !i.primary_key && !schema.constraints.iter().any(|c| c.name == i.name))
+        .collect()
+}
+
+fn same_definition(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+/// `nvarchar(50) NOT NULL DEFAULT (N'new')`
+fn describe(column: &Column) -> String {
+    let mut description = format!(
+        "{} {}",
+        column.declared_type(),
+        if column.is_nullable == "NO" {
+            "NOT NULL"
+        } else {
+            "NULL"
+        }
+    );
+    if let Some(default) = &column.column_default {
+        description.push_str(&format!(" DEFAULT {}", default));
+    }
+    description
+}
+
+fn object_name(object: &DbObject) -> String {
+    format!(
+        "{} {}.{}",
+        object.kind.to_string().to_lowercase(),
+        object.schema,
+        object.name
+    )
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty()
+            && self.dropped_tables.is_empty()
+            && self.changed_tables.is_empty()
+            && self.added_objects.is_empty()
+            && self.dropped_objects.is_empty()
+            && self.changed_objects.is_empty()
+    }
+
+    /// The constraints to drop, those of dropped tables included.
+    fn dropped_constraints(&self) -> impl Iterator<Item = (&Table, &Constraint)> {
+        let changed = self
+            .changed_tables
+            .iter()
+            .flat_map(|t| t.dropped_constraints.iter().map(move |c| (&t.table, c)));
+        let dropped = self.dropped_tables.iter().flat_map(|t| {
+            t.constraints
+                .iter()
+                .filter(|c| c.kind == ConstraintKind::ForeignKey)
+                .map(move |c| (&t.table, c))
+        });
+        changed.chain(dropped)
+    }
+
+    fn added_constraints(&self) -> impl Iterator<Item = (&Table, &Constraint)> {
+        self.changed_tables
+            .iter()
+            .flat_map(|t| t.added_constraints.iter().map(move |c| (&t.table, c)))
+    }
+
+    /// One line per difference: `+` for what only the second database has,
+    /// `-` for what only the first has and `~` for what they both have but
+    /// differently.
+    pub fn report(&self) -> String {
+        if self.is_empty() {
+            return "No differences\n".to_string();
+        }
+        let mut lines: Vec<String> = self
+            .added_schemas
+            .iter()
+            .map(|s| format!("+ schema {}", s))
+            .collect();
+        for table in &self.added_tables {
+            lines.push(format!(
+                "+ table {}.{}",
+                table.table.schema, table.table.name
+            ));
+        }
+        for table in &self.dropped_tables {
+            lines.push(format!(
+                "- table {}.{}",
+                table.table.schema, table.table.name
+            ));
+        }
+        for table in &self.changed_tables {
+            lines.push(format!(
+                "~ table {}.{}",
+                table.table.schema, table.table.name
+            ));
+            for column in &table.added_columns {
+                lines.push(format!("    + column {} {}", column.name, describe(column)));
+            }
+            for column in &table.dropped_columns {
+                lines.push(format!("    - column {} {}", column.name, describe(column)));
+            }
+            for change in &table.changed_columns {
+                lines.push(format!(
+                    "    ~ column {}: {} -> {}",
+                    change.to.name,
+                    describe(&change.from),
+                    describe(&change.to)
+                ));
+            }
+            let index = |sign: char, index: &Index| {
+                let mut line = format!(
+                    "    {} {}index {} ({})",
+                    sign,
+                    if index.unique { "unique " } else { "" },
+                    index.name,
+                    index.columns.join(", ")
+                );
+                if !index.included.is_empty() {
+                    line.push_str(&format!(" include ({})", index.included.join(", ")));
+                }
+                if let Some(filter) = &index.filter {
+                    line.push_str(&format!(" where {}", filter));
+                }
+                line
+            };
+            lines.extend(table.added_indexes.iter().map(|i| index('+', i)));
+            lines.extend(table.dropped_indexes.iter().map(|i| index('-', i)));
+            for constraint in &table.added_constraints {
+                lines.push(format!(
+                    "    + constraint {} {}",
+                    constraint.name, constraint.definition
+                ));
+            }
+            for constraint in &table.dropped_constraints {
+                lines.push(format!(
+                    "    - constraint {} {}",
+                    constraint.name, constraint.definition
+                ));
+            }
+        }
+        for object in &self.added_objects {
+            lines.push(format!("+ {}", object_name(&object.object)));
+        }
+        for object in &self.dropped_objects {
+            lines.push(format!("- {}", object_name(&object.object)));
+        }
+        for object in &self.changed_objects {
+            lines.push(format!("~ {} (definition)", object_name(&object.object)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// The statements turning the first database's schema into the second's,
+    /// in the engine's dialect. `create_tables` holds the script of each
+    /// added table, as `get_table_ddl` gives it on the second database;
+    /// tables missing from it are scripted from their columns.
+    pub fn script(&self, engine: &Engine, create_tables: &HashMap<Table, String>) -> String {
+        let mut script = Script::new(engine);
+        let is_foreign_key = |c: &&Constraint| c.kind == ConstraintKind::ForeignKey;
+        // Changed views are dropped up front too, since neither engine can
+        // replace a view with different columns and their tables may change.
+        let changed_views = self
+            .changed_objects
+            .iter()
+            .filter(|o| o.object.kind == ObjectKind::View);
+        for object in self.dropped_objects.iter().chain(changed_views) {
+            script.drop_object(object);
+        }
+        // Foreign keys go first and come back last, as they lean on the
+        // other tables' keys.
+        for (table, constraint) in self
+            .dropped_constraints()
+            .filter(|(_, c)| is_foreign_key(c))
+        {
+            script.drop_constraint(table, constraint);
+        }
+        for (table, constraint) in self
+            .dropped_constraints()
+            .filter(|(_, c)| !is_foreign_key(c))
+        {
+            script.drop_constraint(table, constraint);
+        }
+        for table in &self.changed_tables {
+            for index in &table.dropped_indexes {
+                script.push(match engine {
+                    Engine::SqlServer => format!(
+                        "DROP INDEX {} ON {};",
+                        engine.quote(&index.name),
+                        engine.qualified(&table.table.schema, &table.table.name)
+                    ),
+                    _ => format!(
+                        "DROP INDEX {};",
+                        engine.qualified(&table.table.schema, &index.name)
+                    ),
+                });
+            }
+        }
+        for table in &self.dropped_tables {
+            script.push(format!(
+                "DROP TABLE {};",
+                engine.qualified(&table.table.schema, &table.table.name)
+            ));
+        }
+        for schema in &self.added_schemas {
+            script.push(format!("CREATE SCHEMA {};", engine.quote(schema)));
+        }
+        let mut foreign_keys = Vec::new();
+        for table in &self.added_tables {
+            let ddl = create_tables
+                .get(&table.table)
+                .cloned()
+                .unwrap_or_else(|| table.create_table(engine));
+            // The tables a new one references may come after it.
+            let (keys, rest): (Vec<&str>, Vec<&str>) = ddl
+                .lines()
+                .partition(|l| l.starts_with("ALTER TABLE") && l.contains(" FOREIGN KEY ("));
+            foreign_keys.extend(keys.into_iter().map(str::to_string));
+            script.push_with_sequences(rest.join("\n").trim_end().to_string());
+        }
+        for table in &self.changed_tables {
+            script.alter_columns(table);
+        }
+        for (table, constraint) in self.added_constraints().filter(|(_, c)| !is_foreign_key(c)) {
+            script.add_constraint(table, constraint);
+        }
+        for table in &self.changed_tables {
+            let name = engine.qualified(&table.table.schema, &table.table.name);
+            for index in &table.added_indexes {
+                script.push(format!("{};", index.create(engine, &name)));
+            }
+        }
+        for statement in foreign_keys {
+            script.push(statement);
+        }
+        for (table, constraint) in self.added_constraints().filter(|(_, c)| is_foreign_key(c)) {
+            script.add_constraint(table, constraint);
+        }
+        for object in &self.changed_objects {
+            script.replace_object(object);
+        }
+        for object in &self.added_objects {
+            script.create_object(&object.definition);
+        }
+        script.statements.join("\n")
+    }
+}
+
+struct Script<'a> {
+    engine: &'a Engine,
+    statements: Vec<String>,
+}
+
+impl<'a> Script<'a> {
+    fn new(engine: &'a Engine) -> Script<'a> {
+        Script {
+            engine,
+            statements: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, statement: String) {
+        self.statements.push(statement);
+    }
+
+    /// Pushes the statement after creating the sequences its defaults draw
+    /// from, which `serial` columns leave out of their table's definition.
+    fn push_with_sequences(&mut self, statement: String) {
+        if *self.engine == Engine::Postgres {
+            let mut rest = statement.as_str();
+            while let Some(start) = rest.find("nextval('") {
+                rest = &rest[start + "nextval('".len()..];
+                let Some(end) = rest.find('\'') else {
+                    break;
+                };
+                let create = format!("CREATE SEQUENCE IF NOT EXISTS {};", &rest[..end]);
+                if !self.statements.contains(&create) {
+                    self.push(create);
+                }
+            }
+        }
+        self.push(statement);
+    }
+
+    fn drop_constraint(&mut self, table: &Table, constraint: &Constraint) {
+        self.push(format!(
+            "ALTER TABLE {} DROP CONSTRAINT {};",
+            self.engine.qualified(&table.schema, &table.name),
+            self.engine.quote(&constraint.name)
+        ));
+    }
+
+    fn add_constraint(&mut self, table: &Table, constraint: &Constraint) {
+        self.push(format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {};",
+            self.engine.qualified(&table.schema, &table.name),
+            self.engine.quote(&constraint.name),
+            constraint.definition
+        ));
+    }
+
+    fn alter_columns(&mut self, table: &TableDiff) {
+        let engine = self.engine;
+        let name = engine.qualified(&table.table.schema, &table.table.name);
+        let drop_default = |script: &mut Script, column: &str| {
+            if let Some(constraint) = table.default_constraints.get(column) {
+                script.push(format!(
+                    "ALTER TABLE {} DROP CONSTRAINT {};",
+                    name,
+                    engine.quote(constraint)
+                ));
+            }
+        };
+        for column in &table.dropped_columns {
+            if *engine == Engine::SqlServer {
+                drop_default(self, &column.name);
+            }
+            self.push(format!(
+                "ALTER TABLE {} DROP COLUMN {};",
+                name,
+                engine.quote(&column.name)
+            ));
+        }
+        for column in &table.added_columns {
+            let keyword = match engine {
+                Engine::SqlServer => "ADD",
+                _ => "ADD COLUMN",
+            };
+            self.push_with_sequences(format!(
+                "ALTER TABLE {} {} {} {};",
+                name,
+                keyword,
+                engine.quote(&column.name),
+                describe(column)
+            ));
+        }
+        for change in &table.changed_columns {
+            let (from, to) = (&change.from, &change.to);
+            let column = engine.quote(&to.name);
+            let retyped = from.declared_type() != to.declared_type();
+            let renulled = from.is_nullable != to.is_nullable;
+            match engine {
+                Engine::SqlServer => {
+                    if retyped || renulled {
+                        self.push(format!(
+                            "ALTER TABLE {} ALTER COLUMN {} {} {};",
+                            name,
+                            column,
+                            to.declared_type(),
+                            if to.is_nullable == "NO" {
+                                "NOT NULL"
+                            } else {
+                                "NULL"
+                            }
+                        ));
+                    }
+                    if from.column_default != to.column_default {
+                        drop_default(self, &to.name);
+                        if let Some(default) = &to.column_default {
+                            self.push(format!(
+                                "ALTER TABLE {} ADD DEFAULT {} FOR {};",
+                                name, default, column
+                            ));
+                        }
+                    }
+                }
+                _ => {
+                    if retyped {
+                        self.push(format!(
+                            "ALTER TABLE {} ALTER COLUMN {} TYPE {};",
+                            name,
+                            column,
+                            to.declared_type()
+                        ));
+                    }
+                    if renulled {
+                        self.push(format!(
+                            "ALTER TABLE {} ALTER COLUMN {} {} NOT NULL;",
+                            name,
+                            column,
+                            if to.is_nullable == "NO" {
+                                "SET"
+                            } else {
+                                "DROP"
+                            }
+                        ));
+                    }
+                    if from.column_default != to.column_default {
+                        self.push_with_sequences(match &to.column_default {
+                            Some(default) => format!(
+                                "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};",
+                                name, column, default
+                            ),
+                            None => {
+                                format!(
+                                    "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;",
+                                    name, column
+                                )
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn drop_object(&mut self, object: &ObjectSchema) {
+        let materialized = object
+            .definition
+            .trim_start()
+            .starts_with("CREATE MATERIALIZED VIEW");
+        let kind = match object.object.kind {
+            ObjectKind::View if materialized => "MATERIALIZED VIEW".to_string(),
+            kind => kind.to_string(),
+        };
+        self.push(format!(
+            "DROP {} {};",
+            kind,
+            self.engine
+                .qualified(&object.object.schema, &object.object.name)
+        ));
+    }
+
+    /// Routine definitions have to be alone in their batch on SQL Server.
+    fn create_object(&mut self, definition: &str) {
+        let definition = definition.trim();
+        match self.engine {
+            Engine::SqlServer => {
+                self.push("GO".to_string());
+                self.push(definition.to_string());
+                self.push("GO".to_string());
+            }
+            _ if definition.ends_with(';') => self.push(definition.to_string()),
+            _ => self.push(format!("{};", definition)),
+        }
+    }
+
+    /// Views were dropped already. Postgres routine definitions say
+    /// `CREATE OR REPLACE`; SQL Server ones are dropped and created again.
+    fn replace_object(&mut self, object: &ObjectSchema) {
+        if object.object.kind != ObjectKind::View && *self.engine == Engine::SqlServer {
+            self.drop_object(object);
+        }
+        self.create_object(&object.definition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, length: Option<i32>, default: Option<&str>) -> Column {
+        Column {
+            name: name.to_string(),
+            table: "orders".to_string(),
+            schema: "sales".to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: "YES".to_string(),
+            column_default: default.map(str::to_string),
+            character_maximum_length: length,
+            ..Column::default()
+        }
+    }
+
+    fn catalog(columns: Vec<Column>, constraints: Vec<Constraint>, view: &str) -> Catalog {
+        let table = Table {
+            name: "orders".to_string(),
+            schema: "sales".to_string(),
+            description: None,
+        };
+        Catalog {
+            tables: vec![TableSchema {
+                table,
+                columns,
+                indexes: vec![],
+                constraints,
+            }],
+            objects: vec![ObjectSchema {
+                object: DbObject {
+                    name: "v_orders".to_string(),
+                    schema: "sales".to_string(),
+                    kind: ObjectKind::View,
+                },
+                definition: view.to_string(),
+            }],
+        }
+    }
+
+    fn constraint(name: &str, kind: ConstraintKind, definition: &str) -> Constraint {
+        Constraint {
+            name: name.to_string(),
+            kind,
+            definition: definition.to_string(),
+            columns: vec![],
+        }
+    }
+
+    #[test]
+    fn should_report_and_script_differences() {
+        let from = catalog(
+            vec![
+                column("id", "int", None, None),
+                column("note", "varchar", Some(50), None),
+                column("status", "varchar", Some(10), Some("('new')")),
+            ],
+            vec![
+                constraint(
+                    "PK__orders__3213E8",
+                    ConstraintKind::PrimaryKey,
+                    "PRIMARY KEY ([id])",
+                ),
+                constraint(
+                    "DF__orders__status",
+                    ConstraintKind::Default,
+                    "DEFAULT ('new') FOR [status]",
+                ),
+            ],
+            "CREATE VIEW sales.v_orders AS SELECT id FROM sales.orders",
+        );
+        let to = catalog(
+            vec![
+                column("id", "int", None, None),
+                column("note", "varchar", Some(100), None),
+                column("status", "varchar", Some(10), None),
+            ],
+            vec![constraint(
+                "PK__orders__99AF12",
+                ConstraintKind::PrimaryKey,
+                "PRIMARY KEY ([id])",
+            )],
+            "CREATE VIEW sales.v_orders AS\n  SELECT id FROM sales.orders",
+        );
+        assert!(diff(&from, &from).is_empty());
+        let diff = diff(&from, &to);
+        assert_eq!(
+            diff.report(),
+            "~ table sales.orders
+    ~ column note: varchar(50) NULL -> varchar(100) NULL
+    ~ column status: varchar(10) NULL DEFAULT ('new') -> varchar(10) NULL
+"
+        );
+        assert_eq!(
+            diff.script(&Engine::SqlServer, &HashMap::new()),
+            "ALTER TABLE sales.orders ALTER COLUMN note varchar(100) NULL;
+ALTER TABLE sales.orders DROP CONSTRAINT DF__orders__status;"
+        );
+        assert_eq!(
+            diff.script(&Engine::Postgres, &HashMap::new()),
+            "ALTER TABLE sales.orders ALTER COLUMN note TYPE varchar(100);
+ALTER TABLE sales.orders ALTER COLUMN status DROP DEFAULT;"
+        );
+    }
+
+    fn index(name: &str, columns: &[&str], unique: bool) -> Index {
+        Index {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            included: vec![],
+            unique,
+            primary_key: false,
+            filter: None,
+            kind: None,
+        }
+    }
+
+    fn object(name: &str, kind: ObjectKind, definition: &str) -> ObjectSchema {
+        ObjectSchema {
+            object: DbObject {
+                name: name.to_string(),
+                schema: "sales".to_string(),
+                kind,
+            },
+            definition: definition.to_string(),
+        }
+    }
+
+    #[test]
+    fn should_find_added_dropped_and_altered_parts() {
+        let mut from = catalog(
+            vec![
+                column("id", "int", None, None),
+                column("note", "varchar", Some(50), None),
+            ],
+            vec![constraint(
+                "CK__orders__id",
+                ConstraintKind::Check,
+                "CHECK (id > 0)",
+            )],
+            "CREATE VIEW sales.v_orders AS SELECT id FROM sales.orders",
+        );
+        from.tables[0].indexes = vec![
+            index("ix_orders_id", &["id"], false),
+            index("ix_orders_note", &["note"], false),
+        ];
+        from.objects.push(object(
+            "p_archive",
+            ObjectKind::Procedure,
+            "CREATE PROCEDURE sales.p_archive AS SELECT 1",
+        ));
+        let mut to = catalog(
+            vec![
+                column("id", "bigint", None, None),
+                column("email", "varchar", Some(200), None),
+            ],
+            vec![constraint(
+                "CK__orders__id__2",
+                ConstraintKind::Check,
+                "CHECK (id >= 0)",
+            )],
+            "CREATE VIEW sales.v_orders AS SELECT id, email FROM sales.orders",
+        );
+        to.tables[0].indexes = vec![
+            index("IX_ORDERS_ID_RENAMED", &["id"], false),
+            index("ux_orders_email", &["email"], true),
+        ];
+        to.objects.push(object(
+            "f_total",
+            ObjectKind::Function,
+            "CREATE FUNCTION sales.f_total() RETURNS int AS BEGIN RETURN 1 END",
+        ));
+
+        let diff = diff(&from, &to);
+        assert_eq!(
+            diff.report(),
+            "~ table sales.orders
+    + column email varchar(200) NULL
+    - column note varchar(50) NULL
+    ~ column id: int NULL -> bigint NULL
+    + unique index ux_orders_email (email)
+    - index ix_orders_note (note)
+    + constraint CK__orders__id__2 CHECK (id >= 0)
+    - constraint CK__orders__id CHECK (id > 0)
++ function sales.f_total
+- procedure sales.p_archive
+~ view sales.v_orders (definition)
+"
+        );
+        assert_eq!(
+            diff.script(&Engine::Postgres, &HashMap::new()),
+            r#"DROP PROCEDURE sales.p_archive;
+DROP VIEW sales.v_orders;
+ALTER TABLE sales.orders DROP CONSTRAINT "CK__orders__id";
+DROP INDEX sales.ix_orders_note;
+ALTER TABLE sales.orders DROP COLUMN note;
+ALTER TABLE sales.orders ADD COLUMN email varchar(200) NULL;
+ALTER TABLE sales.orders ALTER COLUMN id TYPE bigint;
+ALTER TABLE sales.orders ADD CONSTRAINT "CK__orders__id__2" CHECK (id >= 0);
+CREATE UNIQUE INDEX ux_orders_email ON sales.orders (email);
+CREATE VIEW sales.v_orders AS SELECT id, email FROM sales.orders;
+CREATE FUNCTION sales.f_total() RETURNS int AS BEGIN RETURN 1 END;"#
+        );
+    }
+}
@@ -2,7 +2,9 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::sql::format::CORE_KEYWORDS;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Engine {
     #[serde(rename = "sqlserver")]
     SqlServer,
@@ -42,3 +44,43 @@ impl From<Engine> for String {
         }
     }
 }
+
+impl Engine {
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Engine::SqlServer => super::mssql::KEYWORDS,
+            Engine::Postgres | Engine::Mysql => super::postgres::KEYWORDS,
+        }
+    }
+
+    /// Where unqualified names are looked up first.
+    pub fn default_schema(&self) -> &'static str {
+        match self {
+            Engine::SqlServer => "dbo",
+            Engine::Postgres | Engine::Mysql => "public",
+        }
+    }
+
+    /// Quotes an identifier when it needs it: reserved words, names with special
+    /// characters and, in Postgres, names with capitals.
+    pub fn quote(&self, name: &str) -> String {
+        let plain = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !(*self == Engine::Postgres && name.chars().any(|c| c.is_ascii_uppercase()))
+            && !CORE_KEYWORDS.contains(&name.to_uppercase().as_str());
+        match (plain, self) {
+            (true, _) => name.to_string(),
+            (false, Engine::SqlServer) => format!("[{}]", name.replace(']', "]]")),
+            (false, Engine::Mysql) => format!("`{}`", name.replace('`', "``")),
+            (false, Engine::Postgres) => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    /// `schema.name`, each part quoted as needed.
+    pub fn qualified(&self, schema: &str, name: &str) -> String {
+        format!("{}.{}", self.quote(schema), self.quote(name))
+    }
+}
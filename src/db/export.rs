@@ -0,0 +1,517 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::engine::Engine;
+use super::query::{QueryResult, ValueKind};
+
+/// Rows per `INSERT` statement, SQL Server's limit for a `VALUES` list.
+const INSERT_BATCH: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// RFC 4180: CRLF line endings, fields quoted only when needed.
+    Csv,
+    /// CSV with a byte order mark so Excel reads it as UTF-8, and text that
+    /// would be taken for a formula prefixed with `'`.
+    ExcelCsv,
+    /// An array of objects, one per row.
+    Json,
+    /// One object per line.
+    Ndjson,
+    /// `INSERT` statements for the dialect of the connection.
+    Insert,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ExportFormat {
+    pub const NAMES: &'static [&'static str] = &[
+        "csv",
+        "excel-csv",
+        "json",
+        "ndjson",
+        "sql",
+        #[cfg(feature = "parquet")]
+        "parquet",
+    ];
+
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "excel-csv" | "excel" => Some(ExportFormat::ExcelCsv),
+            "json" => Some(ExportFormat::Json),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            "sql" | "insert" => Some(ExportFormat::Insert),
+            #[cfg(feature = "parquet")]
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    /// The format a file name's extension asks for.
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        ExportFormat::parse(path.extension()?.to_str()?)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::ExcelCsv => "excel-csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Insert => "sql",
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::ExcelCsv => "csv",
+            _ => self.name(),
+        }
+    }
+}
+
+/// Writes the rows of `result` to `out`. `engine` and `table` only matter for
+/// `INSERT` scripts.
+pub fn export<W: Write + Send>(
+    result: &QueryResult,
+    format: ExportFormat,
+    engine: &Engine,
+    table: &str,
+    out: &mut W,
+) -> Result<()> {
+    if result.columns.is_empty() {
+        bail!("the statement returned no rows to export");
+    }
+    match format {
+        ExportFormat::Csv => write_csv(result, false, out),
+        ExportFormat::ExcelCsv => write_csv(result, true, out),
+        ExportFormat::Json => write_json(result, false, out),
+        ExportFormat::Ndjson => write_json(result, true, out),
+        ExportFormat::Insert => write_inserts(result, engine, table, out),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => parquet::write(result, out),
+    }
+}
+
+/// The result sets of a run worth exporting, failing if there are none.
+pub fn exportable(results: &[QueryResult]) -> Result<Vec<&QueryResult>> {
+    let results: Vec<&QueryResult> = results.iter().filter(|r| !r.columns.is_empty()).collect();
+    if results.is_empty() {
+        return Err(anyhow!("the statement returned no rows to export"));
+    }
+    Ok(results)
+}
+
+/// Where the `index`th result set of a run goes: `path` for the first,
+/// `name-2.ext`, `name-3.ext`, ... for the rest.
+pub fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, index + 1, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, index + 1),
+    };
+    path.with_file_name(name)
+}
+
+fn write_csv<W: Write>(result: &QueryResult, excel: bool, out: &mut W) -> Result<()> {
+    let field = |value: &str, kind: ValueKind| {
+        let formula =
+            excel && kind == ValueKind::Text && value.starts_with(['=', '+', '-', '@', '\t', '\r']);
+        let value = if formula {
+            format!("'{}", value)
+        } else {
+            value.to_string()
+        };
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value
+        }
+    };
+    if excel {
+        out.write_all("\u{feff}".as_bytes())?;
+    }
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .map(|c| field(c, ValueKind::Text))
+        .collect();
+    write!(out, "{}\r\n", header.join(","))?;
+    for row in &result.rows {
+        let values: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.as_deref()
+                    .map_or(String::new(), |v| field(v, result.kind(i)))
+            })
+            .collect();
+        write!(out, "{}\r\n", values.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(result: &QueryResult, lines: bool, out: &mut W) -> Result<()> {
+    let keys: Vec<String> = result
+        .columns
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<_, _>>()?;
+    if !lines {
+        out.write_all(b"[")?;
+    }
+    for (n, row) in result.rows.iter().enumerate() {
+        let fields: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("{}:{}", keys[i], json_value(v.as_deref(), result.kind(i))))
+            .collect();
+        let object = format!("{{{}}}", fields.join(","));
+        if lines {
+            writeln!(out, "{}", object)?;
+        } else {
+            let separator = if n == 0 { "\n  " } else { ",\n  " };
+            write!(out, "{}{}", separator, object)?;
+        }
+    }
+    if !lines {
+        out.write_all(b"\n]\n")?;
+    }
+    Ok(())
+}
+
+fn json_value(value: Option<&str>, kind: ValueKind) -> String {
+    let Some(value) = value else {
+        return "null".to_string();
+    };
+    let string = || serde_json::Value::String(value.to_string()).to_string();
+    match kind {
+        ValueKind::Integer | ValueKind::Float | ValueKind::Decimal => {
+            // Keep the digits as the engine sent them rather than round
+            // decimals through a float; `NaN` and the like become strings.
+            if serde_json::from_str::<serde_json::Number>(value).is_ok() {
+                value.to_string()
+            } else {
+                string()
+            }
+        }
+        ValueKind::Bool => match bool_value(value) {
+            Some(b) => b.to_string(),
+            None => string(),
+        },
+        _ => string(),
+    }
+}
+
+/// SQL Server sends bits as `1`/`0`, Postgres text as `t`/`f`.
+fn bool_value(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "t" | "true" => Some(true),
+        "0" | "f" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// The hex digits of a binary value, without its `0x` or `\x` prefix.
+fn hex_digits(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("\\x"))
+        .unwrap_or(value)
+}
+
+fn write_inserts<W: Write>(
+    result: &QueryResult,
+    engine: &Engine,
+    table: &str,
+    out: &mut W,
+) -> Result<()> {
+    let table = match table.split_once('.') {
+        Some((schema, name)) => engine.qualified(schema, name),
+        None => engine.quote(table),
+    };
+    let columns: Vec<String> = result.columns.iter().map(|c| engine.quote(c)).collect();
+    for batch in result.rows.chunks(INSERT_BATCH) {
+        let rows: Vec<String> = batch
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| sql_literal(v.as_deref(), result.kind(i), engine))
+                    .collect();
+                format!("    ({})", values.join(", "))
+            })
+            .collect();
+        write!(
+            out,
+            "INSERT INTO {} ({})\nVALUES\n{};\n",
+            table,
+            columns.join(", "),
+            rows.join(",\n")
+        )?;
+    }
+    Ok(())
+}
+
+fn sql_literal(value: Option<&str>, kind: ValueKind, engine: &Engine) -> String {
+    let Some(value) = value else {
+        return "NULL".to_string();
+    };
+    let quoted = || {
+        let value = value.replace('\'', "''");
+        match engine {
+            Engine::SqlServer if kind == ValueKind::Text => format!("N'{}'", value),
+            Engine::Mysql => format!("'{}'", value.replace('\\', "\\\\")),
+            _ => format!("'{}'", value),
+        }
+    };
+    match kind {
+        ValueKind::Integer | ValueKind::Float | ValueKind::Decimal => match value.parse::<f64>() {
+            Ok(n) if n.is_finite() => value.to_string(),
+            _ => quoted(),
+        },
+        ValueKind::Bool => match (bool_value(value), engine) {
+            (Some(b), Engine::Postgres) => if b { "TRUE" } else { "FALSE" }.to_string(),
+            (Some(b), _) => if b { "1" } else { "0" }.to_string(),
+            (None, _) => quoted(),
+        },
+        ValueKind::Binary => {
+            let hex = hex_digits(value);
+            match engine {
+                Engine::SqlServer => format!("0x{}", hex),
+                Engine::Postgres => format!("'\\x{}'::bytea", hex),
+                _ => format!("X'{}'", hex),
+            }
+        }
+        ValueKind::Temporal | ValueKind::Text => quoted(),
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use anyhow::{anyhow, Result};
+    use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::types::Type;
+
+    use super::{bool_value, hex_digits};
+    use crate::db::query::{QueryResult, ValueKind};
+
+    /// One row group with a column per result column: integers as INT64,
+    /// floats as DOUBLE, booleans as BOOLEAN, binary as raw bytes and the
+    /// rest, decimals included, as UTF-8 strings.
+    pub fn write<W: Write + Send>(result: &QueryResult, out: &mut W) -> Result<()> {
+        let fields = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (physical, logical) = match result.kind(i) {
+                    ValueKind::Integer => (PhysicalType::INT64, None),
+                    ValueKind::Float => (PhysicalType::DOUBLE, None),
+                    ValueKind::Bool => (PhysicalType::BOOLEAN, None),
+                    ValueKind::Binary => (PhysicalType::BYTE_ARRAY, None),
+                    _ => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+                };
+                Type::primitive_type_builder(name, physical)
+                    .with_repetition(Repetition::OPTIONAL)
+                    .with_logical_type(logical)
+                    .build()
+                    .map(Arc::new)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let schema = Arc::new(
+            Type::group_type_builder("schema")
+                .with_fields(fields)
+                .build()?,
+        );
+        let mut writer =
+            SerializedFileWriter::new(out, schema, Arc::new(WriterProperties::builder().build()))?;
+        let mut row_group = writer.next_row_group()?;
+        let mut i = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            let values: Vec<Option<&str>> =
+                result.rows.iter().map(|row| row[i].as_deref()).collect();
+            let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+            let present = values.iter().flatten();
+            let name = &result.columns[i];
+            let invalid =
+                |value: &str| anyhow!("{} in {} isn't a {:?}", value, name, result.kind(i));
+            match result.kind(i) {
+                ValueKind::Integer => {
+                    let values = present
+                        .map(|v| v.parse::<i64>().map_err(|_| invalid(v)))
+                        .collect::<Result<Vec<_>>>()?;
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                ValueKind::Float => {
+                    let values = present
+                        .map(|v| v.parse::<f64>().map_err(|_| invalid(v)))
+                        .collect::<Result<Vec<_>>>()?;
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                ValueKind::Bool => {
+                    let values = present
+                        .map(|v| bool_value(v).ok_or_else(|| invalid(v)))
+                        .collect::<Result<Vec<_>>>()?;
+                    column
+                        .typed::<BoolType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                ValueKind::Binary => {
+                    let values = present
+                        .map(|v| {
+                            hex_bytes(hex_digits(v))
+                                .map(ByteArray::from)
+                                .ok_or_else(|| invalid(v))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                _ => {
+                    let values: Vec<ByteArray> = present.map(|v| ByteArray::from(*v)).collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+            }
+            column.close()?;
+            i += 1;
+        }
+        row_group.close()?;
+        writer.close()?;
+        Ok(())
+    }
+
+    fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+        if !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> QueryResult {
+        QueryResult {
+            columns: vec![
+                "id".to_string(),
+                "name".to_string(),
+                "active".to_string(),
+                "photo".to_string(),
+            ],
+            kinds: vec![
+                ValueKind::Integer,
+                ValueKind::Text,
+                ValueKind::Bool,
+                ValueKind::Binary,
+            ],
+            rows: vec![
+                vec![
+                    Some("1".to_string()),
+                    Some("O'Brien, \"Pat\"".to_string()),
+                    Some("t".to_string()),
+                    Some("\\x0aff".to_string()),
+                ],
+                vec![Some("2".to_string()), Some("=1+1".to_string()), None, None],
+            ],
+            ..QueryResult::default()
+        }
+    }
+
+    fn exported(format: ExportFormat, engine: Engine) -> String {
+        let mut out = Vec::new();
+        export(&result(), format, &engine, "dbo.people", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn should_export_csv_and_json() {
+        assert_eq!(
+            exported(ExportFormat::Csv, Engine::Postgres),
+            "id,name,active,photo\r\n1,\"O'Brien, \"\"Pat\"\"\",t,\\x0aff\r\n2,=1+1,,\r\n"
+        );
+        assert!(exported(ExportFormat::ExcelCsv, Engine::Postgres).starts_with("\u{feff}id,name"));
+        assert!(exported(ExportFormat::ExcelCsv, Engine::Postgres).contains("2,'=1+1,,"));
+        assert_eq!(
+            exported(ExportFormat::Ndjson, Engine::Postgres),
+            "{\"id\":1,\"name\":\"O'Brien, \\\"Pat\\\"\",\"active\":true,\"photo\":\"\\\\x0aff\"}\n\
+             {\"id\":2,\"name\":\"=1+1\",\"active\":null,\"photo\":null}\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&exported(ExportFormat::Json, Engine::Postgres)).unwrap();
+        assert_eq!(json[0]["id"], 1);
+    }
+
+    #[test]
+    fn should_export_insert_statements() {
+        assert_eq!(
+            exported(ExportFormat::Insert, Engine::SqlServer),
+            "INSERT INTO dbo.people (id, name, active, photo)
+VALUES
+    (1, N'O''Brien, \"Pat\"', 1, 0x0aff),
+    (2, N'=1+1', NULL, NULL);
+"
+        );
+        assert!(exported(ExportFormat::Insert, Engine::Postgres)
+            .contains("(1, 'O''Brien, \"Pat\"', TRUE, '\\x0aff'::bytea)"));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn should_export_parquet() {
+        use ::parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let path =
+            std::env::temp_dir().join(format!("tengu-export-{}.parquet", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        export(
+            &result(),
+            ExportFormat::Parquet,
+            &Engine::Postgres,
+            "people",
+            &mut file,
+        )
+        .unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                "{id: 1, name: \"O'Brien, \"Pat\"\", active: true, photo: [10, 255]}",
+                "{id: 2, name: \"=1+1\", active: null, photo: null}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Reading CSV and JSON files into rows for a table, checked against its
+//! columns before anything is sent to the database.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+
+use super::column::Column;
+use super::table::Table;
+
+/// Rows sent to the engine in one bulk load.
+pub const BATCH_SIZE: usize = 1000;
+/// Bad values listed before giving up on a file.
+const MAX_ERRORS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// RFC 4180, with a header row. An empty field is NULL, `""` an empty
+    /// string.
+    Csv,
+    /// An array of objects, or one object per line.
+    Json,
+}
+
+impl ImportFormat {
+    pub const NAMES: &'static [&'static str] = &["csv", "json", "ndjson"];
+
+    pub fn parse(name: &str) -> Option<ImportFormat> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "json" | "ndjson" | "jsonl" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// The format a file name's extension asks for.
+    pub fn from_path(path: &Path) -> Option<ImportFormat> {
+        ImportFormat::parse(path.extension()?.to_str()?)
+    }
+}
+
+/// The columns a file names and the rows under them, `None` for NULL.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportData {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+pub fn read(text: &str, format: ImportFormat) -> Result<ImportData> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let data = match format {
+        ImportFormat::Csv => read_csv(text)?,
+        ImportFormat::Json => read_json(text)?,
+    };
+    if data.columns.is_empty() {
+        bail!("the file has no columns");
+    }
+    Ok(data)
+}
+
+fn read_csv(text: &str) -> Result<ImportData> {
+    let mut records: Vec<Vec<Option<String>>> = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' => end_field(&mut field, &mut quoted, &mut record),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_field(&mut field, &mut quoted, &mut record);
+                // Blank lines, such as the one a trailing newline leaves, aren't rows.
+                if record != [None] {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("line {}: a quoted field is never closed", line);
+    }
+    end_field(&mut field, &mut quoted, &mut record);
+    if record != [None] {
+        records.push(record);
+    }
+    let mut records = records.into_iter();
+    let columns: Vec<String> = records
+        .next()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.unwrap_or_default().trim().to_string())
+        .collect();
+    let rows: Vec<Vec<Option<String>>> = records.collect();
+    for (i, row) in rows.iter().enumerate() {
+        if row.len() != columns.len() {
+            bail!(
+                "row {} has {} fields, the header has {}",
+                i + 1,
+                row.len(),
+                columns.len()
+            );
+        }
+    }
+    Ok(ImportData { columns, rows })
+}
+
+fn end_field(field: &mut String, quoted: &mut bool, record: &mut Vec<Option<String>>) {
+    let value = std::mem::take(field);
+    record.push(if value.is_empty() && !*quoted {
+        None
+    } else {
+        Some(value)
+    });
+    *quoted = false;
+}
+
+fn read_json(text: &str) -> Result<ImportData> {
+    let objects: Vec<serde_json::Map<String, serde_json::Value>> =
+        if text.trim_start().starts_with('[') {
+            serde_json::from_str(text)?
+        } else {
+            text.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    serde_json::from_str(line).map_err(|e| anyhow!("line {}: {}", i + 1, e))
+                })
+                .collect::<Result<_>>()?
+        };
+    let mut columns: Vec<String> = Vec::new();
+    for object in &objects {
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let rows = objects
+        .iter()
+        .map(|object| {
+            columns
+                .iter()
+                .map(|c| match object.get(c) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    // Numbers and booleans as written; nested values as JSON text.
+                    Some(value) => Some(value.to_string()),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(ImportData { columns, rows })
+}
+
+/// What a column's values have to look like, from its declared type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Integer,
+    Decimal,
+    Float,
+    Bool,
+    Date,
+    Time,
+    Timestamp,
+    /// A timestamp with an offset; values without one are taken as UTC.
+    TimestampTz,
+    Uuid,
+    Binary,
+    Text,
+}
+
+impl FieldType {
+    /// The type of a column given its `information_schema` data type.
+    pub fn of(data_type: &str) -> FieldType {
+        match data_type.to_lowercase().as_str() {
+            "tinyint" | "smallint" | "int" | "integer" | "bigint" => FieldType::Integer,
+            "decimal" | "numeric" | "money" | "smallmoney" => FieldType::Decimal,
+            "real" | "float" | "double precision" => FieldType::Float,
+            "bit" | "boolean" => FieldType::Bool,
+            "date" => FieldType::Date,
+            "time" | "time without time zone" => FieldType::Time,
+            "datetime" | "datetime2" | "smalldatetime" | "timestamp without time zone" => {
+                FieldType::Timestamp
+            }
+            "datetimeoffset" | "timestamp with time zone" => FieldType::TimestampTz,
+            "uniqueidentifier" | "uuid" => FieldType::Uuid,
+            "binary" | "varbinary" | "image" | "bytea" => FieldType::Binary,
+            _ => FieldType::Text,
+        }
+    }
+
+    /// The value in the one form the engines are sent: integers and
+    /// decimals without padding, `true`/`false`, ISO dates and times,
+    /// lowercase UUIDs and bare lowercase hex. Fails saying what was expected.
+    pub fn normalize(&self, value: &str) -> Result<String> {
+        let text = value.trim();
+        let normalized = match self {
+            FieldType::Text => return Ok(value.to_string()),
+            FieldType::Integer => text.parse::<i64>().ok().map(|n| n.to_string()),
+            FieldType::Decimal => decimal(text).then(|| text.trim_start_matches('+').to_string()),
+            FieldType::Float => text
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(|_| text.to_string()),
+            FieldType::Bool => match text.to_lowercase().as_str() {
+                "1" | "t" | "true" | "y" | "yes" => Some("true".to_string()),
+                "0" | "f" | "false" | "n" | "no" => Some("false".to_string()),
+                _ => None,
+            },
+            FieldType::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.to_string()),
+            FieldType::Time => NaiveTime::parse_from_str(text, "%H:%M:%S%.f")
+                .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+                .ok()
+                .map(|t| t.format("%H:%M:%S%.f").to_string()),
+            FieldType::Timestamp => timestamp(text).map(|t| t.format(TIMESTAMP).to_string()),
+            FieldType::TimestampTz => DateTime::parse_from_rfc3339(text)
+                .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%:z"))
+                .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%#z"))
+                .ok()
+                .or_else(|| timestamp(text).map(|t| t.and_utc().fixed_offset()))
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string()),
+            FieldType::Uuid => {
+                let hex: String = text
+                    .trim_matches(['{', '}'])
+                    .chars()
+                    .filter(|c| *c != '-')
+                    .collect();
+                (hex.len() == 32 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| {
+                    let hex = hex.to_lowercase();
+                    format!(
+                        "{}-{}-{}-{}-{}",
+                        &hex[..8],
+                        &hex[8..12],
+                        &hex[12..16],
+                        &hex[16..20],
+                        &hex[20..]
+                    )
+                })
+            }
+            FieldType::Binary => {
+                let hex = text
+                    .strip_prefix("0x")
+                    .or_else(|| text.strip_prefix("\\x"))
+                    .unwrap_or(text);
+                (hex.len().is_multiple_of(2) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .then(|| hex.to_lowercase())
+            }
+        };
+        normalized.ok_or_else(|| anyhow!("{:?} isn't {}", value, self.expected()))
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            FieldType::Integer => "an integer",
+            FieldType::Decimal => "a decimal number",
+            FieldType::Float => "a number",
+            FieldType::Bool => "true or false",
+            FieldType::Date => "a date like 2024-03-02",
+            FieldType::Time => "a time like 10:11:12",
+            FieldType::Timestamp => "a timestamp like 2024-03-02 10:11:12",
+            FieldType::TimestampTz => "a timestamp like 2024-03-02 10:11:12+01:00",
+            FieldType::Uuid => "a UUID",
+            FieldType::Binary => "hex like 0x0aff",
+            FieldType::Text => "text",
+        }
+    }
+}
+
+const TIMESTAMP: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+fn timestamp(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, TIMESTAMP)
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(NaiveTime::MIN))
+        })
+}
+
+/// Digits with an optional sign and decimal point, e.g. `-12.50`.
+fn decimal(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    !(whole.is_empty() && fraction.is_empty())
+        && whole.chars().all(|c| c.is_ascii_digit())
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The columns of `table` the file's header names, in the file's order.
+/// Names match ignoring case; `columns` may hold other tables' columns.
+pub fn map_columns(header: &[String], table: &Table, columns: &[Column]) -> Result<Vec<Column>> {
+    let columns: Vec<&Column> = columns
+        .iter()
+        .filter(|c| c.schema == table.schema && c.table == table.name)
+        .collect();
+    if columns.is_empty() {
+        bail!("table {}.{} not found", table.schema, table.name);
+    }
+    let mut mapped: Vec<Column> = Vec::new();
+    let mut unknown = Vec::new();
+    for name in header {
+        match columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)) {
+            Some(column) if mapped.contains(column) => bail!("column {} appears twice", name),
+            Some(column) => mapped.push((*column).clone()),
+            None => unknown.push(name.as_str()),
+        }
+    }
+    if !unknown.is_empty() {
+        let mut available: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        available.sort_by_key(|c| c.to_lowercase());
+        bail!(
+            "{}.{} has no column {}; its columns are {}",
+            table.schema,
+            table.name,
+            unknown.join(", "),
+            available.join(", ")
+        );
+    }
+    Ok(mapped)
+}
+
+/// Checks every value against the column it's loaded into, `columns` being
+/// the mapped columns in the file's order, and rewrites it with
+/// [`FieldType::normalize`]. Fails listing the first bad values.
+pub fn coerce(data: &mut ImportData, columns: &[Column]) -> Result<()> {
+    let types: Vec<FieldType> = columns
+        .iter()
+        .map(|c| FieldType::of(&c.data_type))
+        .collect();
+    let mut errors = Vec::new();
+    let mut count = 0;
+    for (i, row) in data.rows.iter_mut().enumerate() {
+        for ((value, column), field_type) in row.iter_mut().zip(columns).zip(&types) {
+            let error = match value {
+                None if column.is_nullable == "NO" => Some("NULL in a NOT NULL column".to_string()),
+                None => None,
+                Some(v) => match field_type.normalize(v) {
+                    Ok(normalized) => {
+                        *v = normalized;
+                        None
+                    }
+                    Err(e) => Some(e.to_string()),
+                },
+            };
+            if let Some(error) = error {
+                count += 1;
+                if errors.len() < MAX_ERRORS {
+                    errors.push(format!("row {}, {}: {}", i + 1, column.name, error));
+                }
+            }
+        }
+    }
+    if count > 0 {
+        bail!("{} invalid values\n  {}", count, errors.join("\n  "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, is_nullable: &str) -> Column {
+        Column {
+            name: name.to_string(),
+            table: "people".to_string(),
+            schema: "dbo".to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: is_nullable.to_string(),
+            ..Column::default()
+        }
+    }
+
+    #[test]
+    fn should_read_csv_and_json() {
+        let csv =
+            "\u{feff}id,name,note\r\n1,\"O'Brien, \"\"Pat\"\"\",\r\n2,\"two\nlines\",\"\"\r\n\r\n";
+        let data = read(csv, ImportFormat::Csv).unwrap();
+        assert_eq!(data.columns, vec!["id", "name", "note"]);
+        assert_eq!(
+            data.rows,
+            vec![
+                vec![
+                    Some("1".to_string()),
+                    Some("O'Brien, \"Pat\"".to_string()),
+                    None
+                ],
+                vec![
+                    Some("2".to_string()),
+                    Some("two\nlines".to_string()),
+                    Some(String::new())
+                ],
+            ]
+        );
+        assert!(read("id,name\n1\n", ImportFormat::Csv).is_err());
+        let json = "{\"id\": 1, \"tags\": [\"a\"]}\n{\"id\": 2, \"active\": true}\n";
+        let data = read(json, ImportFormat::Json).unwrap();
+        assert_eq!(data.columns, vec!["id", "tags", "active"]);
+        assert_eq!(
+            data.rows[0],
+            vec![Some("1".to_string()), Some("[\"a\"]".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn should_map_and_coerce_columns() {
+        let table = Table {
+            name: "people".to_string(),
+            schema: "dbo".to_string(),
+            description: None,
+        };
+        let columns = vec![
+            column("Id", "int", "NO"),
+            column("born", "date", "YES"),
+            column("active", "bit", "YES"),
+            column("seen", "datetime2", "YES"),
+        ];
+        let header: Vec<String> = ["id", "active", "seen", "born"].map(String::from).to_vec();
+        let mapped = map_columns(&header, &table, &columns).unwrap();
+        assert_eq!(mapped[0].name, "Id");
+        let unknown = map_columns(&["nope".to_string()], &table, &columns).unwrap_err();
+        assert!(unknown
+            .to_string()
+            .contains("its columns are active, born, Id, seen"));
+
+        let value = |v: &str| Some(v.to_string());
+        let mut data = ImportData {
+            columns: header,
+            rows: vec![vec![
+                value(" 7"),
+                value("Yes"),
+                value("2024-03-02T10:11:12"),
+                None,
+            ]],
+        };
+        coerce(&mut data, &mapped).unwrap();
+        assert_eq!(
+            data.rows[0],
+            vec![
+                value("7"),
+                value("true"),
+                value("2024-03-02 10:11:12"),
+                None
+            ]
+        );
+        data.rows = vec![vec![None, value("maybe"), value("x"), value("2024-02-30")]];
+        let error = coerce(&mut data, &mapped).unwrap_err().to_string();
+        assert!(error.starts_with("4 invalid values"));
+        assert!(error.contains("row 1, born: \"2024-02-30\" isn't a date like 2024-03-02"));
+    }
+}
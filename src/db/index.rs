@@ -0,0 +1,101 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::engine::Engine;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    /// Key columns in index order, or the expression for Postgres expression
+    /// indexes, e.g. `lower(email)`.
+    pub columns: Vec<String>,
+    /// Non-key columns carried in the leaf level, `INCLUDE (...)`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<String>,
+    pub unique: bool,
+    pub primary_key: bool,
+    /// The WHERE of a filtered or partial index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// The access method in Postgres, e.g. `btree` or `gin`; `CLUSTERED`,
+    /// `NONCLUSTERED`, `NONCLUSTERED COLUMNSTORE`, ... in SQL Server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+impl Index {
+    /// A `CREATE INDEX` statement for the index on `table`, already
+    /// qualified and quoted, without the final semicolon.
+    pub fn create(&self, engine: &Engine, table: &str) -> String {
+        let quote = |columns: &[String]| -> String {
+            columns
+                .iter()
+                .map(|c| {
+                    if c.contains('(') {
+                        c.clone()
+                    } else {
+                        engine.quote(c)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let kind = self.kind.as_deref().unwrap_or_default().to_uppercase();
+        let mut sql = format!("CREATE {}", if self.unique { "UNIQUE " } else { "" });
+        if *engine == Engine::SqlServer && !kind.is_empty() {
+            sql.push_str(&kind);
+            sql.push(' ');
+        }
+        sql.push_str(&format!("INDEX {} ON {}", engine.quote(&self.name), table));
+        if *engine == Engine::Postgres && !kind.is_empty() && kind != "BTREE" {
+            sql.push_str(&format!(" USING {}", kind.to_lowercase()));
+        }
+        // A clustered columnstore index stores every column.
+        if kind != "CLUSTERED COLUMNSTORE" {
+            sql.push_str(&format!(" ({})", quote(&self.columns)));
+        }
+        if !self.included.is_empty() {
+            sql.push_str(&format!(" INCLUDE ({})", quote(&self.included)));
+        }
+        if let Some(filter) = &self.filter {
+            sql.push_str(&format!(" WHERE {}", filter));
+        }
+        sql
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintKind {
+    PrimaryKey,
+    ForeignKey,
+    Unique,
+    Check,
+    Default,
+}
+
+impl Display for ConstraintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintKind::PrimaryKey => write!(f, "PRIMARY KEY"),
+            ConstraintKind::ForeignKey => write!(f, "FOREIGN KEY"),
+            ConstraintKind::Unique => write!(f, "UNIQUE"),
+            ConstraintKind::Check => write!(f, "CHECK"),
+            ConstraintKind::Default => write!(f, "DEFAULT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Constraint {
+    pub name: String,
+    pub kind: ConstraintKind,
+    /// The clause, e.g. `FOREIGN KEY (customer_id) REFERENCES dbo.customers (id)`
+    /// or `DEFAULT (getdate()) FOR created_at`.
+    pub definition: String,
+    /// The columns it's on, in key order. Empty for a SQL Server CHECK that
+    /// isn't tied to a single column.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
@@ -0,0 +1,710 @@
+//! Migration scripts laid out for Flyway, sqitch or dbmate, and a service that
+//! answers catalog questions from the schema they leave behind.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+
+use super::column::Column;
+use super::database::Database;
+use super::engine::Engine;
+use super::index::{Constraint, Index};
+use super::object::DbObject;
+use super::plan::PlanNode;
+use super::query::QueryResult;
+use super::service::{Service, TenguService};
+use super::stats::TableStats;
+use super::table::Table;
+use crate::sql::schema::{key, SchemaBuilder, VirtualSchema};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// `V1__init.sql`, `V1.1__orders.sql`, then repeatable `R__views.sql`.
+    Flyway,
+    /// `sqitch.plan` listing changes deployed from `deploy/<change>.sql`.
+    Sqitch,
+    /// `20240302101100_init.sql` with `-- migrate:up` and `-- migrate:down`.
+    Dbmate,
+}
+
+/// The layout of `dir` and its scripts in the order they're applied.
+pub fn scripts(dir: &Path) -> Result<(Layout, Vec<PathBuf>)> {
+    let plan = dir.join("sqitch.plan");
+    if plan.is_file() {
+        let plan =
+            fs::read_to_string(&plan).with_context(|| format!("can't read {}", plan.display()))?;
+        return Ok((Layout::Sqitch, sqitch_scripts(dir, &plan)));
+    }
+    let mut files = Vec::new();
+    collect_sql_files(dir, &mut files)
+        .with_context(|| format!("can't read the migrations in {}", dir.display()))?;
+    let name = |path: &PathBuf| {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let mut versioned: Vec<(Vec<u64>, PathBuf)> = files
+        .iter()
+        .filter_map(|path| Some((flyway_version(&name(path))?, path.clone())))
+        .collect();
+    if !versioned.is_empty() {
+        versioned.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut repeatable: Vec<PathBuf> = files
+            .iter()
+            .filter(|path| name(path).starts_with("R__"))
+            .cloned()
+            .collect();
+        repeatable.sort_by_key(name);
+        let scripts = versioned.into_iter().map(|(_, path)| path);
+        return Ok((Layout::Flyway, scripts.chain(repeatable).collect()));
+    }
+    // dbmate only looks at the folder itself.
+    let mut timestamped: Vec<(u64, String, PathBuf)> = files
+        .into_iter()
+        .filter(|path| path.parent() == Some(dir))
+        .filter_map(|path| {
+            let name = name(&path);
+            let (version, _) = name.split_once('_')?;
+            Some((version.parse().ok()?, name, path))
+        })
+        .collect();
+    timestamped.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    Ok((
+        Layout::Dbmate,
+        timestamped.into_iter().map(|(_, _, path)| path).collect(),
+    ))
+}
+
+/// `V2_1__orders` is version 2.1; underscores and dots both separate parts,
+/// which compare as numbers so 1.10 comes after 1.9.
+fn flyway_version(name: &str) -> Option<Vec<u64>> {
+    let (version, _) = name.strip_prefix('V')?.split_once("__")?;
+    version
+        .split(['.', '_'])
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+fn sqitch_scripts(dir: &Path, plan: &str) -> Vec<PathBuf> {
+    plan.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['%', '#', '@']))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|change| dir.join("deploy").join(change).with_extension("sql"))
+        .collect()
+}
+
+fn collect_sql_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_sql_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The part of a dbmate script that migrates up; scripts without the markers
+/// are taken whole. What follows is blanked rather than cut so positions in
+/// the file still hold.
+fn up_section(text: &str) -> String {
+    let Some(down) = text.find("-- migrate:down") else {
+        return text.to_string();
+    };
+    let (up, rest) = text.split_at(down);
+    let blank: String = rest
+        .chars()
+        .map(|c| if c == '\n' { '\n' } else { ' ' })
+        .collect();
+    format!("{}{}", up, blank)
+}
+
+/// The schema after every script in `dir` ran, in order.
+pub fn build(engine: &Engine, dir: &Path) -> Result<VirtualSchema> {
+    let (layout, scripts) = scripts(dir)?;
+    let mut builder = SchemaBuilder::new(engine.clone());
+    for script in &scripts {
+        let text = fs::read_to_string(script)
+            .with_context(|| format!("can't read {}", script.display()))?;
+        let text = match layout {
+            Layout::Dbmate => up_section(&text),
+            Layout::Flyway | Layout::Sqitch => text,
+        };
+        builder.apply(&text, script);
+    }
+    Ok(builder.finish())
+}
+
+/// A difference between the database and the schema the migrations build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    /// Lower-cased `schema.name` of the table or object, which keys its
+    /// origin in the migrations.
+    pub key: String,
+    pub message: String,
+    /// Whether the migrations create something the database doesn't have
+    /// yet, which is what pending migrations look like.
+    pub pending: bool,
+}
+
+/// What differs between the migrations' schema and the database's tables,
+/// columns and objects. Tables and objects the migrations never mention are
+/// only reported in the schemas they work in, so a baseline that predates
+/// them doesn't count.
+pub fn drift(
+    schema: &VirtualSchema,
+    tables: &[Table],
+    columns: &HashSet<Column>,
+    objects: &[DbObject],
+) -> Vec<Drift> {
+    let same = |schema: &str, name: &str, other_schema: &str, other_name: &str| {
+        schema.eq_ignore_ascii_case(other_schema) && name.eq_ignore_ascii_case(other_name)
+    };
+    let describe = |c: &Column| {
+        format!(
+            "{} {}",
+            c.declared_type(),
+            if c.is_nullable == "NO" {
+                "NOT NULL"
+            } else {
+                "NULL"
+            }
+        )
+    };
+    let mut drift = Vec::new();
+    for table in &schema.catalog.tables {
+        let (s, n) = (&table.table.schema, &table.table.name);
+        let name = format!("{}.{}", s, n);
+        if !tables.iter().any(|t| same(s, n, &t.schema, &t.name)) {
+            drift.push(Drift {
+                key: key(s, n),
+                message: format!("{} isn't in the database yet", name),
+                pending: true,
+            });
+            continue;
+        }
+        let live: Vec<&Column> = columns
+            .iter()
+            .filter(|c| same(s, n, &c.schema, &c.table))
+            .collect();
+        for column in &table.columns {
+            let Some(other) = live
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(&column.name))
+            else {
+                drift.push(Drift {
+                    key: key(s, n),
+                    message: format!("{}.{} isn't in the database yet", name, column.name),
+                    pending: true,
+                });
+                continue;
+            };
+            let comparable = !matches!(other.data_type.as_str(), "USER-DEFINED" | "ARRAY");
+            let retyped = comparable
+                && !other
+                    .declared_type()
+                    .eq_ignore_ascii_case(&column.declared_type());
+            if retyped || other.is_nullable != column.is_nullable {
+                drift.push(Drift {
+                    key: key(s, n),
+                    message: format!(
+                        "{}.{} is {} in the migrations but {} in the database",
+                        name,
+                        column.name,
+                        describe(column),
+                        describe(other)
+                    ),
+                    pending: false,
+                });
+            }
+        }
+        for other in live {
+            if !table
+                .columns
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(&other.name))
+            {
+                drift.push(Drift {
+                    key: key(s, n),
+                    message: format!(
+                        "{}.{} is in the database but no migration creates it",
+                        name, other.name
+                    ),
+                    pending: false,
+                });
+            }
+        }
+    }
+    for object in &schema.catalog.objects {
+        let (s, n) = (&object.object.schema, &object.object.name);
+        if !objects.iter().any(|o| same(s, n, &o.schema, &o.name)) {
+            drift.push(Drift {
+                key: key(s, n),
+                message: format!(
+                    "{} {}.{} isn't in the database yet",
+                    object.object.kind, s, n
+                ),
+                pending: true,
+            });
+        }
+    }
+    let schemas: HashSet<String> = schema
+        .catalog
+        .tables
+        .iter()
+        .map(|t| t.table.schema.to_lowercase())
+        .chain(
+            schema
+                .catalog
+                .objects
+                .iter()
+                .map(|o| o.object.schema.to_lowercase()),
+        )
+        .collect();
+    let unexplained =
+        |s: &str, n: &str| schemas.contains(&s.to_lowercase()) && !schema.touches(s, n);
+    for table in tables {
+        // Postgres lists views with its tables.
+        let view = objects
+            .iter()
+            .any(|o| same(&table.schema, &table.name, &o.schema, &o.name));
+        if !view && unexplained(&table.schema, &table.name) {
+            drift.push(Drift {
+                key: key(&table.schema, &table.name),
+                message: format!(
+                    "{}.{} is in the database but no migration creates it",
+                    table.schema, table.name
+                ),
+                pending: false,
+            });
+        }
+    }
+    for object in objects {
+        if unexplained(&object.schema, &object.name) {
+            drift.push(Drift {
+                key: key(&object.schema, &object.name),
+                message: format!(
+                    "{} {}.{} is in the database but no migration creates it",
+                    object.kind, object.schema, object.name
+                ),
+                pending: false,
+            });
+        }
+    }
+    drift
+}
+
+/// Answers from the schema the migrations in `dir` build, built again when a
+/// script is added, removed or saved so edits to them show up. Tables they
+/// never mention, and everything that runs SQL, go to the live database when
+/// there is one.
+#[derive(Debug)]
+pub struct MigrationService {
+    engine: Engine,
+    dir: PathBuf,
+    live: Option<Box<TenguService>>,
+    built: Mutex<Option<(Vec<Stamp>, Arc<VirtualSchema>)>>,
+}
+
+/// A script and when it last changed, which tell whether the schema built
+/// from the scripts is still current.
+type Stamp = (PathBuf, Option<SystemTime>);
+
+impl MigrationService {
+    pub fn new(engine: Engine, dir: PathBuf, live: Option<TenguService>) -> Self {
+        Self {
+            engine,
+            dir,
+            live: live.map(Box::new),
+            built: Mutex::new(None),
+        }
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine.clone()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn schema(&self) -> Result<Arc<VirtualSchema>> {
+        let (_, scripts) = scripts(&self.dir)?;
+        let stamps: Vec<Stamp> = scripts
+            .into_iter()
+            .map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, modified)
+            })
+            .collect();
+        let mut built = self.built.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((built_from, schema)) = built.as_ref() {
+            if *built_from == stamps {
+                return Ok(schema.clone());
+            }
+        }
+        let schema = Arc::new(build(&self.engine, &self.dir)?);
+        *built = Some((stamps, schema.clone()));
+        Ok(schema)
+    }
+
+    /// How the database differs from the migrations, nothing without one.
+    pub async fn drift(&self) -> Result<Vec<Drift>> {
+        let Some(live) = &self.live else {
+            return Ok(vec![]);
+        };
+        let schema = self.schema()?;
+        let tables = live.get_tables().await?;
+        let columns: HashSet<Column> = live
+            .get_all_columns()
+            .await?
+            .into_values()
+            .flatten()
+            .collect();
+        let objects = live.get_objects().await?;
+        Ok(drift(&schema, &tables, &columns, &objects))
+    }
+
+    fn live(&self) -> Result<&TenguService> {
+        self.live.as_deref().ok_or_else(|| {
+            anyhow!(
+                "there's no database behind the migrations in {} to run anything on",
+                self.dir.display()
+            )
+        })
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Service for MigrationService {
+    async fn get_tables(&self) -> Result<Vec<Table>> {
+        let schema = self.schema()?;
+        let mut tables: Vec<Table> = schema
+            .catalog
+            .tables
+            .iter()
+            .map(|t| t.table.clone())
+            .collect();
+        if let Some(live) = &self.live {
+            // The migrations are enough to go on when the database is out of reach.
+            let untouched = live.get_tables().await.unwrap_or_default();
+            tables.extend(
+                untouched
+                    .into_iter()
+                    .filter(|t| !schema.touches(&t.schema, &t.name)),
+            );
+        }
+        Ok(tables)
+    }
+
+    async fn get_table_columns(&self, tables: HashSet<Table>) -> Result<HashSet<Column>> {
+        let schema = self.schema()?;
+        let (migrated, rest): (HashSet<Table>, HashSet<Table>) = tables
+            .into_iter()
+            .partition(|t| schema.touches(&t.schema, &t.name));
+        let mut columns: HashSet<Column> = schema
+            .catalog
+            .tables
+            .iter()
+            .filter(|t| migrated.contains(&t.table))
+            .flat_map(|t| t.columns.iter().cloned())
+            .collect();
+        if let (Some(live), false) = (&self.live, rest.is_empty()) {
+            let live_columns = live.get_table_columns(rest).await.unwrap_or_default();
+            columns.extend(
+                live_columns
+                    .into_iter()
+                    .filter(|c| !schema.touches(&c.schema, &c.table)),
+            );
+        }
+        Ok(columns)
+    }
+
+    async fn get_objects(&self) -> Result<Vec<DbObject>> {
+        let schema = self.schema()?;
+        let mut objects: Vec<DbObject> = schema
+            .catalog
+            .objects
+            .iter()
+            .map(|o| o.object.clone())
+            .collect();
+        if let Some(live) = &self.live {
+            let untouched = live.get_objects().await.unwrap_or_default();
+            objects.extend(
+                untouched
+                    .into_iter()
+                    .filter(|o| !schema.touches(&o.schema, &o.name)),
+            );
+        }
+        Ok(objects)
+    }
+
+    async fn execute(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        self.live()?.execute(sql).await
+    }
+
+    async fn get_indexes(&self, table: &Table) -> Result<Vec<Index>> {
+        let schema = self.schema()?;
+        match schema.catalog.table(table) {
+            Some(migrated) => Ok(migrated.indexes.clone()),
+            None if schema.touches(&table.schema, &table.name) => Ok(vec![]),
+            None => self.live()?.get_indexes(table).await,
+        }
+    }
+
+    async fn get_constraints(&self, table: &Table) -> Result<Vec<Constraint>> {
+        let schema = self.schema()?;
+        match schema.catalog.table(table) {
+            Some(migrated) => Ok(migrated.constraints.clone()),
+            None if schema.touches(&table.schema, &table.name) => Ok(vec![]),
+            None => self.live()?.get_constraints(table).await,
+        }
+    }
+
+    async fn get_all_columns(&self) -> Result<HashMap<Table, Vec<Column>>> {
+        let schema = self.schema()?;
+        let mut columns: HashMap<Table, Vec<Column>> = match &self.live {
+            Some(live) => live.get_all_columns().await.unwrap_or_default(),
+            None => HashMap::new(),
+        };
+        columns.retain(|t, _| !schema.touches(&t.schema, &t.name));
+        columns.extend(
+            schema
+                .catalog
+                .tables
+                .iter()
+                .map(|t| (t.table.clone(), t.columns.clone())),
+        );
+        Ok(columns)
+    }
+
+    async fn get_all_indexes(&self) -> Result<HashMap<Table, Vec<Index>>> {
+        let schema = self.schema()?;
+        let mut indexes: HashMap<Table, Vec<Index>> = match &self.live {
+            Some(live) => live.get_all_indexes().await.unwrap_or_default(),
+            None => HashMap::new(),
+        };
+        indexes.retain(|t, _| !schema.touches(&t.schema, &t.name));
+        indexes.extend(
+            schema
+                .catalog
+                .tables
+                .iter()
+                .map(|t| (t.table.clone(), t.indexes.clone())),
+        );
+        Ok(indexes)
+    }
+
+    async fn get_all_constraints(&self) -> Result<HashMap<Table, Vec<Constraint>>> {
+        let schema = self.schema()?;
+        let mut constraints: HashMap<Table, Vec<Constraint>> = match &self.live {
+            Some(live) => live.get_all_constraints().await.unwrap_or_default(),
+            None => HashMap::new(),
+        };
+        constraints.retain(|t, _| !schema.touches(&t.schema, &t.name));
+        constraints.extend(
+            schema
+                .catalog
+                .tables
+                .iter()
+                .map(|t| (t.table.clone(), t.constraints.clone())),
+        );
+        Ok(constraints)
+    }
+
+    async fn get_table_ddl(&self, table: &Table) -> Result<String> {
+        let schema = self.schema()?;
+        match schema.catalog.table(table) {
+            Some(migrated) => Ok(migrated.create_table(&self.engine)),
+            None => self.live()?.get_table_ddl(table).await,
+        }
+    }
+
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String> {
+        let schema = self.schema()?;
+        match schema.catalog.objects.iter().find(|o| o.object == *object) {
+            Some(migrated) => Ok(migrated.definition.clone()),
+            None => self.live()?.get_object_ddl(object).await,
+        }
+    }
+
+    async fn explain(&self, sql: &str) -> Result<Vec<PlanNode>> {
+        self.live()?.explain(sql).await
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        self.live()?.get_table_stats().await
+    }
+
+    async fn bulk_insert(
+        &self,
+        table: &Table,
+        columns: &[Column],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
+        self.live()?.bulk_insert(table, columns, rows).await
+    }
+
+    async fn get_search_path(&self) -> Result<Vec<String>> {
+        match &self.live {
+            Some(live) => live.get_search_path().await,
+            None => Ok(vec![self.engine.default_schema().to_string()]),
+        }
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        match &self.live {
+            Some(live) => live.list_databases().await,
+            None => Ok(vec![]),
+        }
+    }
+
+    // Migrations only build the connection's own database.
+    async fn get_databases(&self, server: Option<&str>) -> Result<Vec<String>> {
+        match &self.live {
+            Some(live) => live.get_databases(server).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn get_linked_servers(&self) -> Result<Vec<String>> {
+        match &self.live {
+            Some(live) => live.get_linked_servers().await,
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn get_database_tables(&self, database: &Database) -> Result<Vec<Table>> {
+        match &self.live {
+            Some(live) => live.get_database_tables(database).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn get_database_columns(
+        &self,
+        database: &Database,
+        tables: HashSet<Table>,
+    ) -> Result<HashSet<Column>> {
+        match &self.live {
+            Some(live) => live.get_database_columns(database, tables).await,
+            None => Ok(HashSet::new()),
+        }
+    }
+
+    fn get_keywords(&self) -> &[&str] {
+        self.engine.keywords()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_order_flyway_migrations_and_report_drift() {
+        let dir = std::env::temp_dir().join(format!("tengu-migrations-{}", std::process::id()));
+        fs::create_dir_all(dir.join("views")).unwrap();
+        let write = |name: &str, sql: &str| fs::write(dir.join(name), sql).unwrap();
+        write(
+            "V1__init.sql",
+            "CREATE TABLE orders (id int NOT NULL, code varchar(10));",
+        );
+        write("V1.10__qty.sql", "ALTER TABLE orders ADD COLUMN qty int;");
+        write(
+            "V1.9__code.sql",
+            "ALTER TABLE orders ALTER COLUMN code TYPE varchar(20);",
+        );
+        write(
+            "views/R__v.sql",
+            "CREATE OR REPLACE VIEW v_orders AS SELECT id FROM orders;",
+        );
+        write("notes.sql", "DROP TABLE orders;");
+
+        let (layout, scripts) = scripts(&dir).unwrap();
+        let schema = build(&Engine::Postgres, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(layout, Layout::Flyway);
+        assert_eq!(
+            scripts
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "V1__init.sql",
+                "V1.9__code.sql",
+                "V1.10__qty.sql",
+                "R__v.sql"
+            ]
+        );
+        let column = |name: &str, data_type: &str, length: Option<i32>| Column {
+            name: name.to_string(),
+            table: "orders".to_string(),
+            schema: "public".to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: "YES".to_string(),
+            character_maximum_length: length,
+            ..Column::default()
+        };
+        let tables = vec![
+            Table {
+                name: "orders".to_string(),
+                schema: "public".to_string(),
+                description: None,
+            },
+            Table {
+                name: "audit".to_string(),
+                schema: "public".to_string(),
+                description: None,
+            },
+        ];
+        let columns = HashSet::from([
+            Column {
+                is_nullable: "NO".to_string(),
+                ..column("id", "integer", None)
+            },
+            column("code", "character varying", Some(10)),
+        ]);
+        let messages: Vec<String> = drift(&schema, &tables, &columns, &[])
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "public.orders.code is character varying(20) NULL in the migrations but character varying(10) NULL in the database",
+                "public.orders.qty isn't in the database yet",
+                "VIEW public.v_orders isn't in the database yet",
+                "public.audit is in the database but no migration creates it",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_build_the_schema_again_only_when_a_script_changes() {
+        let dir = std::env::temp_dir().join(format!("tengu-built-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("V1__init.sql"), "CREATE TABLE orders (id int);").unwrap();
+        let service = MigrationService::new(Engine::Postgres, dir.clone(), None);
+
+        let first = service.schema().unwrap();
+        let again = service.schema().unwrap();
+        fs::write(dir.join("V2__items.sql"), "CREATE TABLE items (id int);").unwrap();
+        let changed = service.schema().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert_eq!(changed.catalog.tables.len(), 2);
+    }
+}
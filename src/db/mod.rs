@@ -1,6 +1,21 @@
+pub mod cancel;
+pub mod catalog;
 pub mod column;
+pub mod database;
+pub mod ddl;
+pub mod diff;
 pub mod engine;
+pub mod export;
+pub mod import;
+pub mod index;
+pub mod migrations;
 pub mod mssql;
+pub mod object;
+pub mod plan;
 pub mod postgres;
+pub mod query;
 pub mod service;
+pub mod snapshot;
+pub mod stats;
 pub mod table;
+pub mod tunnel;
@@ -1,17 +1,33 @@
 mod keywords;
+pub(crate) use keywords::KEYWORDS;
 
 use crate::db::column::Column as TenguColumn;
-use crate::db::table::Table as TenguTable;
+use crate::db::database::Database;
+use crate::db::ddl::{create_table, ColumnDefinition};
+use crate::db::engine::Engine;
+use crate::db::index::{Constraint, ConstraintKind, Index};
+use crate::db::object::{DbObject, ObjectKind};
+use crate::db::plan::{from_showplan_xml, PlanNode};
+use crate::db::query::{QueryResult, ValueKind};
+use crate::db::stats::TableStats;
+use crate::db::table::{by_table, Table as TenguTable};
+use crate::terminal_ui::models::Connection;
 use crate::terminal_ui::repository::TenguRepository;
 use anyhow::anyhow;
 use anyhow::Result;
-use std::collections::HashSet;
-use tiberius::ToSql;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use futures::TryStreamExt;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use tiberius::numeric::Numeric;
 use tiberius::{AuthMethod, Client, Config};
+use tiberius::{ColumnData, ColumnType, FromSql, IntoSql, QueryItem, ToSql, TokenRow};
 use tokio::net::TcpStream;
 use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 
+use super::cancel;
 use super::service::Service;
+use super::tunnel::endpoint;
 
 #[derive(Debug)]
 pub struct SqlServer<T: TenguRepository> {
@@ -26,30 +42,330 @@ impl<T: TenguRepository + Sync> SqlServer<T> {
         let Some(conn) = self.repo.get_active_connection() else {
             return Err(anyhow!("No active connection found"));
         };
-        let mut config = Config::new();
-        config.host(conn.host);
-        config.port(conn.port.parse::<u16>().unwrap());
-        config.database(conn.database);
-        config.authentication(AuthMethod::sql_server(&conn.username, &conn.password));
-        config.trust_cert();
+        let mut client = connect(&conn).await?;
+        if cancel::in_scope() {
+            let spid = client
+                .simple_query("SELECT @@SPID AS spid;")
+                .await?
+                .into_row()
+                .await?
+                .and_then(|row| row.get::<i16, _>("spid"))
+                .ok_or_else(|| anyhow!("@@SPID returned nothing"))?;
+            // tiberius can't send an attention, so the abandoned session is
+            // ended from another one, which takes ALTER ANY CONNECTION.
+            cancel::on_cancel(async move {
+                let mut other = connect(&conn).await?;
+                let kill = async {
+                    other
+                        .simple_query(format!("KILL {};", spid))
+                        .await?
+                        .into_results()
+                        .await
+                };
+                kill.await
+                    .map(|_| ())
+                    .map_err(|e| anyhow!("KILL {} failed: {}", spid, e))
+            });
+        }
+        Ok(client)
+    }
 
-        let tcp = TcpStream::connect(config.get_addr()).await?;
-        tcp.set_nodelay(true)?;
+    /// Indexes of `table`, or of every table and view when there's none, with
+    /// the one each is on.
+    async fn indexes(&self, table: Option<&TenguTable>) -> Result<Vec<(TenguTable, Index)>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = r#"
+            SELECT OBJECT_SCHEMA_NAME(i.object_id) AS schema_name,
+                OBJECT_NAME(i.object_id) AS table_name,
+                i.name AS index_name, i.is_unique, i.is_primary_key, i.type_desc,
+                i.filter_definition,
+                (
+                    SELECT STRING_AGG(c.name, CHAR(31)) WITHIN GROUP (ORDER BY ic.key_ordinal)
+                    FROM sys.index_columns ic
+                    JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+                    WHERE ic.object_id = i.object_id AND ic.index_id = i.index_id
+                        AND ic.is_included_column = 0
+                ) AS columns,
+                (
+                    SELECT STRING_AGG(c.name, CHAR(31)) WITHIN GROUP (ORDER BY ic.index_column_id)
+                    FROM sys.index_columns ic
+                    JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+                    WHERE ic.object_id = i.object_id AND ic.index_id = i.index_id
+                        AND ic.is_included_column = 1
+                ) AS included
+            FROM sys.indexes i
+            WHERE i.type > 0
+                AND (@P1 IS NULL AND OBJECTPROPERTY(i.object_id, 'IsMSShipped') = 0
+                    OR i.object_id = OBJECT_ID(QUOTENAME(@P1) + '.' + QUOTENAME(@P2)))
+            ORDER BY i.name;
+        "#;
+        let result = conn
+            .query(
+                sql,
+                &[
+                    &table.map(|t| t.schema.as_str()),
+                    &table.map(|t| t.name.as_str()),
+                ],
+            )
+            .await?
+            .into_first_result()
+            .await?
+            .into_iter()
+            .map(|row| {
+                let index = Index {
+                    name: row.get::<&str, _>("index_name").unwrap().to_string(),
+                    columns: split_names(row.get::<&str, _>("columns")),
+                    included: split_names(row.get::<&str, _>("included")),
+                    unique: row.get::<bool, _>("is_unique").unwrap_or_default(),
+                    primary_key: row.get::<bool, _>("is_primary_key").unwrap_or_default(),
+                    filter: row.get::<&str, _>("filter_definition").map(str::to_string),
+                    kind: row.get::<&str, _>("type_desc").map(str::to_string),
+                };
+                (row_table(&row), index)
+            })
+            .collect();
+        Ok(result)
+    }
 
-        Ok(Client::connect(config, tcp.compat_write()).await?)
+    /// Constraints of `table`, or of every table when there's none, with the
+    /// one each is on.
+    async fn constraints(
+        &self,
+        table: Option<&TenguTable>,
+    ) -> Result<Vec<(TenguTable, Constraint)>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = r#"
+            DECLARE @id int = OBJECT_ID(QUOTENAME(@P1) + '.' + QUOTENAME(@P2));
+            SELECT OBJECT_SCHEMA_NAME(kc.parent_object_id) AS schema_name,
+                OBJECT_NAME(kc.parent_object_id) AS table_name,
+                kc.name AS constraint_name, RTRIM(kc.type) AS constraint_type,
+                CASE kc.type WHEN 'PK' THEN 'PRIMARY KEY' ELSE 'UNIQUE' END + ' ('
+                    + STRING_AGG(QUOTENAME(c.name), ', ') WITHIN GROUP (ORDER BY ic.key_ordinal) + ')' AS definition,
+                STRING_AGG(c.name, CHAR(31)) WITHIN GROUP (ORDER BY ic.key_ordinal) AS columns
+            FROM sys.key_constraints kc
+            JOIN sys.index_columns ic ON ic.object_id = kc.parent_object_id AND ic.index_id = kc.unique_index_id
+            JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+            WHERE (@P1 IS NULL AND kc.is_ms_shipped = 0 OR kc.parent_object_id = @id)
+            GROUP BY kc.parent_object_id, kc.name, kc.type
+            UNION ALL
+            SELECT OBJECT_SCHEMA_NAME(fk.parent_object_id), OBJECT_NAME(fk.parent_object_id),
+                fk.name, 'F',
+                'FOREIGN KEY (' + STRING_AGG(QUOTENAME(pc.name), ', ') WITHIN GROUP (ORDER BY fkc.constraint_column_id)
+                    + ') REFERENCES ' + QUOTENAME(OBJECT_SCHEMA_NAME(fk.referenced_object_id)) + '.'
+                    + QUOTENAME(OBJECT_NAME(fk.referenced_object_id)) + ' ('
+                    + STRING_AGG(QUOTENAME(rc.name), ', ') WITHIN GROUP (ORDER BY fkc.constraint_column_id) + ')',
+                STRING_AGG(pc.name, CHAR(31)) WITHIN GROUP (ORDER BY fkc.constraint_column_id)
+            FROM sys.foreign_keys fk
+            JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+            JOIN sys.columns pc ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+            JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+            WHERE (@P1 IS NULL AND fk.is_ms_shipped = 0 OR fk.parent_object_id = @id)
+            GROUP BY fk.parent_object_id, fk.name, fk.referenced_object_id
+            UNION ALL
+            SELECT OBJECT_SCHEMA_NAME(cc.parent_object_id), OBJECT_NAME(cc.parent_object_id),
+                cc.name, 'C', 'CHECK ' + cc.definition,
+                COL_NAME(cc.parent_object_id, NULLIF(cc.parent_column_id, 0))
+            FROM sys.check_constraints cc
+            WHERE (@P1 IS NULL AND cc.is_ms_shipped = 0 OR cc.parent_object_id = @id)
+            UNION ALL
+            SELECT OBJECT_SCHEMA_NAME(dc.parent_object_id), OBJECT_NAME(dc.parent_object_id),
+                dc.name, 'D', 'DEFAULT ' + dc.definition + ' FOR ' + QUOTENAME(c.name), c.name
+            FROM sys.default_constraints dc
+            JOIN sys.columns c ON c.object_id = dc.parent_object_id AND c.column_id = dc.parent_column_id
+            WHERE (@P1 IS NULL AND dc.is_ms_shipped = 0 OR dc.parent_object_id = @id);
+        "#;
+        let result = conn
+            .query(
+                sql,
+                &[
+                    &table.map(|t| t.schema.as_str()),
+                    &table.map(|t| t.name.as_str()),
+                ],
+            )
+            .await?
+            .into_results()
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|row| {
+                let constraint = Constraint {
+                    name: row.get::<&str, _>("constraint_name").unwrap().to_string(),
+                    kind: match row.get::<&str, _>("constraint_type").unwrap() {
+                        "PK" => ConstraintKind::PrimaryKey,
+                        "UQ" => ConstraintKind::Unique,
+                        "F" => ConstraintKind::ForeignKey,
+                        "C" => ConstraintKind::Check,
+                        _ => ConstraintKind::Default,
+                    },
+                    definition: row.get::<&str, _>("definition").unwrap().to_string(),
+                    columns: split_names(row.get::<&str, _>("columns")),
+                };
+                (row_table(&row), constraint)
+            })
+            .collect();
+        Ok(result)
     }
-}
 
-#[tower_lsp::async_trait]
-impl<T: TenguRepository + Sync + Send> Service for SqlServer<T> {
-    async fn get_tables(&self) -> Result<Vec<TenguTable>> {
+    /// Runs a script, collecting a result per statement that produced one.
+    async fn run(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let mut stream = conn.simple_query(sql).await?;
+        let mut results: Vec<QueryResult> = Vec::new();
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                QueryItem::Metadata(metadata) => results.push(QueryResult {
+                    columns: metadata
+                        .columns()
+                        .iter()
+                        .map(|c| c.name().to_string())
+                        .collect(),
+                    kinds: metadata
+                        .columns()
+                        .iter()
+                        .map(|c| value_kind(c.column_type()))
+                        .collect(),
+                    ..QueryResult::default()
+                }),
+                QueryItem::Row(row) => {
+                    if let Some(result) = results.last_mut() {
+                        result.rows.push(row.into_iter().map(cell).collect());
+                    }
+                }
+            }
+        }
+        if results.is_empty() {
+            results.push(QueryResult::default());
+        }
+        Ok(results)
+    }
+
+    /// The estimated plans of a script's statements.
+    async fn plan(&self, sql: &str) -> Result<Vec<PlanNode>> {
+        let mut conn = self.get_conn::<T>().await?;
+        // SHOWPLAN_XML has to be alone in its batch; the statements that follow
+        // are compiled but not run, each returning its plan as a single row.
+        conn.simple_query("SET SHOWPLAN_XML ON")
+            .await?
+            .into_results()
+            .await?;
+        let mut plans = Vec::new();
+        for row in conn
+            .simple_query(sql)
+            .await?
+            .into_results()
+            .await?
+            .into_iter()
+            .flatten()
+        {
+            if let Some(xml) = row.into_iter().next().and_then(cell) {
+                plans.extend(from_showplan_xml(&xml)?);
+            }
+        }
+        Ok(plans)
+    }
+
+    /// Loads `rows` into `table`, `columns` naming what each value is.
+    async fn load(
+        &self,
+        table: &TenguTable,
+        columns: &[TenguColumn],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
         let mut conn = self.get_conn::<T>().await?;
         let sql = r#"
-            SELECT s.name AS schema_name, t.name AS table_name
-            FROM sys.tables t
-            JOIN sys.schemas s ON t.schema_id = s.schema_id
-            ORDER BY s.name, t.name;
+            SELECT c.name AS column_name, TYPE_NAME(c.system_type_id) AS type_name, c.scale,
+                c.is_nullable, CAST(c.is_identity | c.is_computed AS bit) AS is_generated
+            FROM sys.columns c
+            WHERE c.object_id = OBJECT_ID(QUOTENAME(@P1) + '.' + QUOTENAME(@P2))
+            ORDER BY c.column_id;
         "#;
+        let table_columns: Vec<BulkColumn> = conn
+            .query(sql, &[&table.schema, &table.name])
+            .await?
+            .into_first_result()
+            .await?
+            .into_iter()
+            .map(|row| BulkColumn {
+                name: row.get::<&str, _>("column_name").unwrap().to_string(),
+                type_name: row
+                    .get::<&str, _>("type_name")
+                    .unwrap_or_default()
+                    .to_string(),
+                scale: row.get::<u8, _>("scale").unwrap_or_default(),
+                nullable: row.get::<bool, _>("is_nullable").unwrap_or(true),
+                generated: row.get::<bool, _>("is_generated").unwrap_or_default(),
+            })
+            .collect();
+        // The bulk load takes every column the server lets it write, in
+        // table order, so the file's columns are placed among them and the
+        // others sent as NULL.
+        let targets: Vec<&BulkColumn> = table_columns
+            .iter()
+            .filter(|c| !c.generated && c.type_name != "timestamp")
+            .collect();
+        for column in columns {
+            if !targets.iter().any(|t| t.name == column.name) {
+                return Err(anyhow!(
+                    "{} is an identity or computed column and can't be loaded",
+                    column.name
+                ));
+            }
+        }
+        let positions: Vec<Option<usize>> = targets
+            .iter()
+            .map(|t| columns.iter().position(|c| c.name == t.name))
+            .collect();
+        for (target, position) in targets.iter().zip(&positions) {
+            if position.is_none() && !target.nullable {
+                return Err(anyhow!(
+                    "{} is NOT NULL and missing from the file",
+                    target.name
+                ));
+            }
+            bulk_value(None, target)?;
+        }
+        let mut token_rows = Vec::with_capacity(rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            let mut token_row = TokenRow::with_capacity(targets.len());
+            for (target, position) in targets.iter().zip(&positions) {
+                let value = position.and_then(|p| row[p].as_deref());
+                let data = bulk_value(value, target)
+                    .map_err(|e| anyhow!("row {}, {}: {}", i + 1, target.name, e))?;
+                token_row.push(data);
+            }
+            token_rows.push(token_row);
+        }
+        let name = Engine::SqlServer.qualified(&table.schema, &table.name);
+        let mut request = conn.bulk_insert(&name).await?;
+        for token_row in token_rows {
+            request.send(token_row).await?;
+        }
+        Ok(request.finalize().await?.total())
+    }
+
+    /// The active connection's statement timeout, in seconds.
+    pub fn statement_timeout(&self) -> Option<u64> {
+        self.repo
+            .get_active_connection()
+            .and_then(|c| c.statement_timeout)
+    }
+
+    /// Tables of the connection's database, or of the one `prefix`
+    /// (`[db].` or `[server].[db].`) names.
+    async fn tables_in(&self, prefix: &str) -> Result<Vec<TenguTable>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = format!(
+            r#"
+            SELECT s.name AS schema_name, t.name AS table_name,
+                CAST(ep.value AS nvarchar(max)) AS description
+            FROM {0}sys.tables t
+            JOIN {0}sys.schemas s ON t.schema_id = s.schema_id
+            LEFT JOIN {0}sys.extended_properties ep ON ep.class = 1 AND ep.major_id = t.object_id
+                AND ep.minor_id = 0 AND ep.name = 'MS_Description'
+            ORDER BY s.name, t.name;
+        "#,
+            prefix
+        );
         let result = conn
             .simple_query(sql)
             .await?
@@ -63,42 +379,62 @@ impl<T: TenguRepository + Sync + Send> Service for SqlServer<T> {
                 TenguTable {
                     name: name.to_string(),
                     schema: schema.to_string(),
+                    description: row.get::<&str, _>("description").map(str::to_string),
                 }
             })
             .collect();
         Ok(result)
     }
-    async fn get_table_columns(&self, tables: HashSet<TenguTable>) -> Result<HashSet<TenguColumn>> {
+
+    /// Columns of `tables`, or of every table when there's none.
+    async fn columns_in(
+        &self,
+        prefix: &str,
+        tables: Option<&HashSet<TenguTable>>,
+    ) -> Result<HashSet<TenguColumn>> {
+        if tables.is_some_and(|t| t.is_empty()) {
+            return Ok(HashSet::new());
+        }
         let mut conn = self.get_conn::<T>().await?;
         let mut params: Vec<&dyn ToSql> = Vec::new();
         let mut conditions = String::new();
 
-        for (i, table) in tables.iter().enumerate() {
+        for (i, table) in tables.into_iter().flatten().enumerate() {
             params.push(&table.schema);
             params.push(&table.name);
 
+            if i > 0 {
+                conditions.push_str(" OR ");
+            }
             conditions.push_str(&format!(
                 "(s.name = @P{} AND t.name = @P{})",
                 i * 2 + 1,
                 i * 2 + 2,
             ));
-
-            if i < tables.len() - 1 {
-                conditions.push_str(" OR ");
-            }
         }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions)
+        };
 
         let sql = format!(
             r#"
-            SELECT s.name AS schema_name, t.name AS table_name, c.name AS column_name, ic.data_type, ic.is_nullable
-            FROM sys.tables t
-            JOIN sys.schemas s ON t.schema_id = s.schema_id
-            JOIN sys.columns c ON t.object_id = c.object_id
-            JOIN information_schema.columns ic ON ic.table_name = t.name AND ic.column_name = c.name
-            WHERE {}
+            SELECT s.name AS schema_name, t.name AS table_name, c.name AS column_name, ic.data_type, ic.is_nullable,
+                ic.column_default, ic.character_maximum_length,
+                CAST(ic.numeric_precision AS int) AS numeric_precision, ic.numeric_scale,
+                c.column_id AS ordinal_position, CAST(ep.value AS nvarchar(max)) AS description
+            FROM {0}sys.tables t
+            JOIN {0}sys.schemas s ON t.schema_id = s.schema_id
+            JOIN {0}sys.columns c ON t.object_id = c.object_id
+            JOIN {0}information_schema.columns ic ON ic.table_schema = s.name
+                AND ic.table_name = t.name AND ic.column_name = c.name
+            LEFT JOIN {0}sys.extended_properties ep ON ep.class = 1 AND ep.major_id = c.object_id
+                AND ep.minor_id = c.column_id AND ep.name = 'MS_Description'
+            {1}
             ORDER BY s.name, t.name, c.column_id;
         "#,
-            conditions
+            prefix, filter
         );
 
         let results = conn
@@ -120,14 +456,553 @@ impl<T: TenguRepository + Sync + Send> Service for SqlServer<T> {
                     schema: schema.to_string(),
                     data_type: data_type.to_string(),
                     is_nullable: is_nullable.to_string(),
+                    column_default: row.get::<&str, _>("column_default").map(str::to_string),
+                    character_maximum_length: row.get::<i32, _>("character_maximum_length"),
+                    numeric_precision: row.get::<i32, _>("numeric_precision"),
+                    numeric_scale: row.get::<i32, _>("numeric_scale"),
+                    ordinal_position: row.get::<i32, _>("ordinal_position").unwrap_or_default(),
+                    description: row.get::<&str, _>("description").map(str::to_string),
                 }
             })
             .collect();
 
         Ok(results)
     }
+}
+
+#[tower_lsp::async_trait]
+impl<T: TenguRepository + Sync + Send> Service for SqlServer<T> {
+    async fn get_tables(&self) -> Result<Vec<TenguTable>> {
+        self.tables_in("").await
+    }
+    async fn get_table_columns(&self, tables: HashSet<TenguTable>) -> Result<HashSet<TenguColumn>> {
+        self.columns_in("", Some(&tables)).await
+    }
+
+    async fn get_objects(&self) -> Result<Vec<DbObject>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = r#"
+            SELECT s.name AS schema_name, o.name AS object_name, RTRIM(o.type) AS object_type
+            FROM sys.objects o
+            JOIN sys.schemas s ON o.schema_id = s.schema_id
+            WHERE o.type IN ('V', 'P', 'PC', 'FN', 'IF', 'TF', 'FS', 'FT')
+                AND o.is_ms_shipped = 0
+            ORDER BY s.name, o.name;
+        "#;
+        let result = conn
+            .simple_query(sql)
+            .await?
+            .into_results()
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|row| {
+                let schema = row.get::<&str, _>("schema_name").unwrap();
+                let name = row.get::<&str, _>("object_name").unwrap();
+                let kind = match row.get::<&str, _>("object_type").unwrap() {
+                    "V" => ObjectKind::View,
+                    "P" | "PC" => ObjectKind::Procedure,
+                    _ => ObjectKind::Function,
+                };
+                DbObject {
+                    name: name.to_string(),
+                    schema: schema.to_string(),
+                    kind,
+                }
+            })
+            .collect();
+        Ok(result)
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = r#"
+            SELECT s.name AS schema_name, t.name AS table_name,
+                SUM(CASE WHEN ps.index_id IN (0, 1) THEN ps.row_count ELSE 0 END) AS row_count,
+                SUM(ps.reserved_page_count) * 8192 AS bytes,
+                CONVERT(varchar(16), t.modify_date, 120) AS modified
+            FROM sys.tables t
+            JOIN sys.schemas s ON t.schema_id = s.schema_id
+            LEFT JOIN sys.dm_db_partition_stats ps ON ps.object_id = t.object_id
+            GROUP BY s.name, t.name, t.modify_date;
+        "#;
+        let result = conn
+            .simple_query(sql)
+            .await?
+            .into_results()
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|row| TableStats {
+                table: TenguTable {
+                    name: row.get::<&str, _>("table_name").unwrap().to_string(),
+                    schema: row.get::<&str, _>("schema_name").unwrap().to_string(),
+                    description: None,
+                },
+                rows: row.get::<i64, _>("row_count"),
+                bytes: row.get::<i64, _>("bytes"),
+                modified: row.get::<&str, _>("modified").map(str::to_string),
+                analyzed: None,
+            })
+            .collect();
+        Ok(result)
+    }
+
+    async fn execute(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        self.run(sql).await
+    }
+
+    async fn get_indexes(&self, table: &TenguTable) -> Result<Vec<Index>> {
+        Ok(self
+            .indexes(Some(table))
+            .await?
+            .into_iter()
+            .map(|(_, index)| index)
+            .collect())
+    }
+
+    async fn get_all_indexes(&self) -> Result<HashMap<TenguTable, Vec<Index>>> {
+        Ok(by_table(self.indexes(None).await?))
+    }
+
+    async fn get_constraints(&self, table: &TenguTable) -> Result<Vec<Constraint>> {
+        Ok(self
+            .constraints(Some(table))
+            .await?
+            .into_iter()
+            .map(|(_, constraint)| constraint)
+            .collect())
+    }
+
+    async fn get_all_columns(&self) -> Result<HashMap<TenguTable, Vec<TenguColumn>>> {
+        let columns = self.columns_in("", None).await?;
+        Ok(by_table(
+            columns
+                .into_iter()
+                .map(|c| (TenguTable::from(c.clone()), c))
+                .collect(),
+        ))
+    }
+
+    async fn get_all_constraints(&self) -> Result<HashMap<TenguTable, Vec<Constraint>>> {
+        Ok(by_table(self.constraints(None).await?))
+    }
 
+    async fn get_table_ddl(&self, table: &TenguTable) -> Result<String> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = r#"
+            SELECT c.name AS column_name, TYPE_NAME(c.user_type_id) AS type_name,
+                c.max_length, c.precision, c.scale, c.is_nullable,
+                CAST(ic.seed_value AS bigint) AS seed, CAST(ic.increment_value AS bigint) AS increment
+            FROM sys.columns c
+            LEFT JOIN sys.identity_columns ic ON ic.object_id = c.object_id AND ic.column_id = c.column_id
+            WHERE c.object_id = OBJECT_ID(QUOTENAME(@P1) + '.' + QUOTENAME(@P2))
+            ORDER BY c.column_id;
+        "#;
+        let columns: Vec<ColumnDefinition> = conn
+            .query(sql, &[&table.schema, &table.name])
+            .await?
+            .into_first_result()
+            .await?
+            .into_iter()
+            .map(|row| ColumnDefinition {
+                name: row.get::<&str, _>("column_name").unwrap().to_string(),
+                data_type: type_name(
+                    row.get::<&str, _>("type_name").unwrap_or_default(),
+                    row.get::<i16, _>("max_length").unwrap_or_default(),
+                    row.get::<u8, _>("precision").unwrap_or_default(),
+                    row.get::<u8, _>("scale").unwrap_or_default(),
+                ),
+                nullable: row.get::<bool, _>("is_nullable").unwrap_or(true),
+                extra: row.get::<i64, _>("seed").map(|seed| {
+                    format!(
+                        "IDENTITY({},{})",
+                        seed,
+                        row.get::<i64, _>("increment").unwrap_or(1)
+                    )
+                }),
+            })
+            .collect();
+        drop(conn);
+        if columns.is_empty() {
+            return Err(anyhow!("Table {}.{} not found", table.schema, table.name));
+        }
+        let constraints = self.get_constraints(table).await?;
+        let indexes = self.get_indexes(table).await?;
+        Ok(create_table(
+            &Engine::SqlServer,
+            table,
+            &columns,
+            &constraints,
+            &indexes,
+        ))
+    }
+
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = "SELECT OBJECT_DEFINITION(OBJECT_ID(QUOTENAME(@P1) + '.' + QUOTENAME(@P2))) AS definition";
+        let row = conn
+            .query(sql, &[&object.schema, &object.name])
+            .await?
+            .into_row()
+            .await?;
+        row.and_then(|row| row.get::<&str, _>("definition").map(str::to_string))
+            .ok_or_else(|| anyhow!("No definition for {}.{}", object.schema, object.name))
+    }
+
+    async fn explain(&self, sql: &str) -> Result<Vec<PlanNode>> {
+        self.plan(sql).await
+    }
+
+    async fn bulk_insert(
+        &self,
+        table: &TenguTable,
+        columns: &[TenguColumn],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
+        self.load(table, columns, rows).await
+    }
+
+    /// The user's default schema, then `dbo`.
+    async fn get_search_path(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let mut schemas = names(&mut conn, "SELECT SCHEMA_NAME() AS name;").await?;
+        if !schemas.iter().any(|s| s.eq_ignore_ascii_case("dbo")) {
+            schemas.push("dbo".to_string());
+        }
+        Ok(schemas)
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        self.get_databases(None).await
+    }
+
+    async fn get_databases(&self, server: Option<&str>) -> Result<Vec<String>> {
+        let mut conn = self.get_conn::<T>().await?;
+        let sql = match server {
+            Some(server) => format!(
+                "SELECT name FROM {}master.sys.databases WHERE state_desc = 'ONLINE' ORDER BY name;",
+                Database::new(None, server.to_string()).prefix()
+            ),
+            None => "SELECT name FROM sys.databases WHERE state_desc = 'ONLINE' \
+                AND HAS_DBACCESS(name) = 1 ORDER BY name;"
+                .to_string(),
+        };
+        names(&mut conn, &sql).await
+    }
+
+    async fn get_linked_servers(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_conn::<T>().await?;
+        names(
+            &mut conn,
+            "SELECT name FROM sys.servers WHERE is_linked = 1 ORDER BY name;",
+        )
+        .await
+    }
+
+    async fn get_database_tables(&self, database: &Database) -> Result<Vec<TenguTable>> {
+        self.tables_in(&database.prefix()).await
+    }
+
+    async fn get_database_columns(
+        &self,
+        database: &Database,
+        tables: HashSet<TenguTable>,
+    ) -> Result<HashSet<TenguColumn>> {
+        self.columns_in(&database.prefix(), Some(&tables)).await
+    }
+
+    #[allow(clippy::needless_return)]
     fn get_keywords(&self) -> &[&str] {
         return keywords::KEYWORDS;
     }
 }
+
+/// A new session to `conn`'s database, through its SSH tunnel if it has one.
+async fn connect(conn: &Connection) -> Result<Client<Compat<TcpStream>>> {
+    let (host, port) = endpoint(conn).await?;
+    let mut config = Config::new();
+    config.host(host);
+    config.port(port);
+    config.database(&conn.database);
+    config.authentication(AuthMethod::sql_server(&conn.username, &conn.password));
+    config.trust_cert();
+
+    let tcp = TcpStream::connect(config.get_addr()).await?;
+    tcp.set_nodelay(true)?;
+
+    Ok(Client::connect(config, tcp.compat_write()).await?)
+}
+
+/// The `name` column of every row `sql` returns.
+async fn names(conn: &mut Client<Compat<TcpStream>>, sql: &str) -> Result<Vec<String>> {
+    Ok(conn
+        .simple_query(sql)
+        .await?
+        .into_first_result()
+        .await?
+        .iter()
+        .filter_map(|row| row.get::<&str, _>("name").map(str::to_string))
+        .collect())
+}
+
+/// The table named by a row's `schema_name` and `table_name`.
+fn row_table(row: &tiberius::Row) -> TenguTable {
+    TenguTable {
+        name: row
+            .get::<&str, _>("table_name")
+            .unwrap_or_default()
+            .to_string(),
+        schema: row
+            .get::<&str, _>("schema_name")
+            .unwrap_or_default()
+            .to_string(),
+        description: None,
+    }
+}
+
+/// Names joined with `CHAR(31)` by STRING_AGG, none when it's null.
+fn split_names(names: Option<&str>) -> Vec<String> {
+    names
+        .unwrap_or_default()
+        .split('\u{1f}')
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Spells a column type the way it's declared, e.g. `nvarchar(50)`.
+fn type_name(name: &str, max_length: i16, precision: u8, scale: u8) -> String {
+    let length = |bytes_per_char: i16| {
+        if max_length == -1 {
+            "max".to_string()
+        } else {
+            (max_length / bytes_per_char).to_string()
+        }
+    };
+    match name {
+        "varchar" | "char" | "varbinary" | "binary" => format!("{}({})", name, length(1)),
+        "nvarchar" | "nchar" => format!("{}({})", name, length(2)),
+        "decimal" | "numeric" => format!("{}({},{})", name, precision, scale),
+        "datetime2" | "datetimeoffset" | "time" => format!("{}({})", name, scale),
+        _ => name.to_string(),
+    }
+}
+
+/// A column a bulk load writes, as `sys.columns` describes it.
+struct BulkColumn {
+    name: String,
+    type_name: String,
+    scale: u8,
+    nullable: bool,
+    generated: bool,
+}
+
+/// A value normalized by `import::coerce` as the exact type the column
+/// takes; the bulk load protocol doesn't convert.
+fn bulk_value(value: Option<&str>, column: &BulkColumn) -> Result<ColumnData<'static>> {
+    fn parsed<V: std::str::FromStr>(value: Option<&str>, type_name: &str) -> Result<Option<V>> {
+        value
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| anyhow!("{} doesn't fit in {}", v, type_name))
+            })
+            .transpose()
+    }
+    let type_name = column.type_name.as_str();
+    let temporal = |format: &str| -> Result<Option<NaiveDateTime>> {
+        value
+            .map(|v| {
+                NaiveDateTime::parse_from_str(v, format)
+                    .or_else(|_| {
+                        NaiveDate::parse_from_str(v, format).map(|d| d.and_time(NaiveTime::MIN))
+                    })
+                    .map_err(|_| anyhow!("{} isn't a valid {}", v, type_name))
+            })
+            .transpose()
+    };
+    Ok(match type_name {
+        "tinyint" => ColumnData::U8(parsed(value, type_name)?),
+        "smallint" => ColumnData::I16(parsed(value, type_name)?),
+        "int" => ColumnData::I32(parsed(value, type_name)?),
+        "bigint" => ColumnData::I64(parsed(value, type_name)?),
+        "real" => ColumnData::F32(parsed(value, type_name)?),
+        "float" => ColumnData::F64(parsed(value, type_name)?),
+        "bit" => ColumnData::Bit(value.map(|v| v == "true")),
+        "decimal" | "numeric" => {
+            ColumnData::Numeric(value.map(|v| numeric(v, column.scale)).transpose()?)
+        }
+        "char" | "varchar" | "text" | "nchar" | "nvarchar" | "ntext" => {
+            ColumnData::String(value.map(|v| Cow::Owned(v.to_string())))
+        }
+        "uniqueidentifier" => ColumnData::Guid(parsed(value, type_name)?),
+        "binary" | "varbinary" | "image" => ColumnData::Binary(
+            value
+                .map(|v| {
+                    (0..v.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&v[i..i + 2], 16))
+                        .collect::<Result<Vec<u8>, _>>()
+                        .map(Cow::Owned)
+                        .map_err(|_| anyhow!("{} isn't hex", v))
+                })
+                .transpose()?,
+        ),
+        "date" => match temporal("%Y-%m-%d")? {
+            Some(v) => v.date().into_sql(),
+            None => ColumnData::Date(None),
+        },
+        "datetime2" => match temporal(TIMESTAMP)? {
+            Some(v) => v.into_sql(),
+            None => ColumnData::DateTime2(None),
+        },
+        "datetime" => ColumnData::DateTime(temporal(TIMESTAMP)?.map(|v| {
+            let (days, nanos) = since_1900(v);
+            // Fractions of a second are counted in 1/300ths.
+            let fragments = (nanos * 300 + 500_000_000) / 1_000_000_000;
+            tiberius::time::DateTime::new(days as i32, fragments as u32)
+        })),
+        "smalldatetime" => ColumnData::SmallDateTime(temporal(TIMESTAMP)?.map(|v| {
+            let (mut days, nanos) = since_1900(v);
+            let mut minutes = (nanos + 30_000_000_000) / 60_000_000_000;
+            if minutes == 24 * 60 {
+                days += 1;
+                minutes = 0;
+            }
+            tiberius::time::SmallDateTime::new(days as u16, minutes as u16)
+        })),
+        "time" => ColumnData::Time(
+            value
+                .map(|v| {
+                    let time = NaiveTime::parse_from_str(v, "%H:%M:%S%.f")
+                        .map_err(|_| anyhow!("{} isn't a valid time", v))?;
+                    let nanos = time.num_seconds_from_midnight() as u64 * 1_000_000_000
+                        + time.nanosecond() as u64;
+                    // The scale has to be the column's, which the protocol
+                    // doesn't adjust for times as it does for datetime2.
+                    let increments = nanos / 10u64.pow(9 - column.scale as u32);
+                    Ok::<_, anyhow::Error>(tiberius::time::Time::new(increments, column.scale))
+                })
+                .transpose()?,
+        ),
+        "datetimeoffset" => match value {
+            Some(v) => DateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f%:z")
+                .map_err(|_| anyhow!("{} isn't a valid datetimeoffset", v))?
+                .into_sql(),
+            None => ColumnData::DateTimeOffset(None),
+        },
+        _ => return Err(anyhow!("{} columns can't be bulk loaded", type_name)),
+    })
+}
+
+const TIMESTAMP: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Days since 1900-01-01 and nanoseconds since midnight, how `datetime` and
+/// `smalldatetime` count.
+fn since_1900(value: NaiveDateTime) -> (i64, u64) {
+    let epoch = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap_or_default();
+    let days = value.date().signed_duration_since(epoch).num_days();
+    let time = value.time();
+    let nanos = time.num_seconds_from_midnight() as u64 * 1_000_000_000 + time.nanosecond() as u64;
+    (days, nanos)
+}
+
+/// A decimal string as a `Numeric` of the column's scale, rounding extra
+/// digits half away from zero.
+fn numeric(value: &str, scale: u8) -> Result<Numeric> {
+    let overflow = || anyhow!("{} is too large for the column", value);
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut number: i128 = 0;
+    let digits = whole.chars().chain(
+        fraction
+            .chars()
+            .chain(std::iter::repeat('0'))
+            .take(scale as usize),
+    );
+    for digit in digits {
+        let digit = digit
+            .to_digit(10)
+            .ok_or_else(|| anyhow!("{} isn't a decimal", value))?;
+        number = number
+            .checked_mul(10)
+            .and_then(|n| n.checked_add(digit as i128))
+            .ok_or_else(overflow)?;
+    }
+    if fraction
+        .chars()
+        .nth(scale as usize)
+        .is_some_and(|d| d >= '5')
+    {
+        number = number.checked_add(1).ok_or_else(overflow)?;
+    }
+    Ok(Numeric::new_with_scale(
+        if negative { -number } else { number },
+        scale,
+    ))
+}
+
+fn value_kind(column_type: ColumnType) -> ValueKind {
+    match column_type {
+        ColumnType::Int1
+        | ColumnType::Int2
+        | ColumnType::Int4
+        | ColumnType::Int8
+        | ColumnType::Intn => ValueKind::Integer,
+        ColumnType::Float4 | ColumnType::Float8 | ColumnType::Floatn => ValueKind::Float,
+        ColumnType::Decimaln | ColumnType::Numericn | ColumnType::Money | ColumnType::Money4 => {
+            ValueKind::Decimal
+        }
+        ColumnType::Bit | ColumnType::Bitn => ValueKind::Bool,
+        ColumnType::BigVarBin | ColumnType::BigBinary | ColumnType::Image => ValueKind::Binary,
+        ColumnType::Datetime4
+        | ColumnType::Datetime
+        | ColumnType::Datetimen
+        | ColumnType::Daten
+        | ColumnType::Timen
+        | ColumnType::Datetime2
+        | ColumnType::DatetimeOffsetn => ValueKind::Temporal,
+        _ => ValueKind::Text,
+    }
+}
+
+fn cell(data: ColumnData<'static>) -> Option<String> {
+    match data {
+        ColumnData::U8(v) => v.map(|v| v.to_string()),
+        ColumnData::I16(v) => v.map(|v| v.to_string()),
+        ColumnData::I32(v) => v.map(|v| v.to_string()),
+        ColumnData::I64(v) => v.map(|v| v.to_string()),
+        ColumnData::F32(v) => v.map(|v| v.to_string()),
+        ColumnData::F64(v) => v.map(|v| v.to_string()),
+        ColumnData::Bit(v) => v.map(|v| if v { "1" } else { "0" }.to_string()),
+        ColumnData::String(v) => v.map(|v| v.into_owned()),
+        ColumnData::Guid(v) => v.map(|v| v.to_string().to_uppercase()),
+        ColumnData::Binary(v) => v.map(|v| {
+            let hex: String = v.iter().map(|b| format!("{:02X}", b)).collect();
+            format!("0x{}", hex)
+        }),
+        ColumnData::Numeric(v) => v.map(|v| v.to_string()),
+        ColumnData::Xml(v) => v.map(|v| v.into_owned().into_string()),
+        ColumnData::Date(_) => NaiveDate::from_sql(&data)
+            .ok()
+            .flatten()
+            .map(|v| v.to_string()),
+        ColumnData::Time(_) => NaiveTime::from_sql(&data)
+            .ok()
+            .flatten()
+            .map(|v| v.to_string()),
+        ColumnData::DateTimeOffset(_) => DateTime::<FixedOffset>::from_sql(&data)
+            .ok()
+            .flatten()
+            .map(|v| v.to_string()),
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+            NaiveDateTime::from_sql(&data)
+                .ok()
+                .flatten()
+                .map(|v| v.to_string())
+        }
+    }
+}
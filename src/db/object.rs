@@ -0,0 +1,30 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// A view or routine in the catalog. Tables have their own type since most of
+/// the LSP only cares about them.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct DbObject {
+    pub name: String,
+    pub schema: String,
+    pub kind: ObjectKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    View,
+    Procedure,
+    Function,
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectKind::View => write!(f, "VIEW"),
+            ObjectKind::Procedure => write!(f, "PROCEDURE"),
+            ObjectKind::Function => write!(f, "FUNCTION"),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use anyhow::{anyhow, Result};
+use roxmltree::{Document, Node};
+use serde_json::Value;
+
+/// One operator of an estimated execution plan, the same shape for every
+/// engine so the TUI and the editor can show them alike.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanNode {
+    pub operation: String,
+    /// The table or index the operator reads, if any.
+    pub object: Option<String>,
+    /// Estimated cost of the operator and everything under it.
+    pub cost: Option<f64>,
+    pub rows: Option<f64>,
+    pub warnings: Vec<String>,
+    pub children: Vec<PlanNode>,
+}
+
+/// Postgres doesn't warn about plans; flag scans this costly that filter rows,
+/// which usually want an index.
+const SEQ_SCAN_COST: f64 = 1000.0;
+
+/// Reads the plans of a `SET SHOWPLAN_XML ON` document, one per statement.
+pub fn from_showplan_xml(xml: &str) -> Result<Vec<PlanNode>> {
+    let document = Document::parse(xml)?;
+    let mut plans = Vec::new();
+    for statement in document
+        .descendants()
+        .filter(|n| n.has_tag_name("StmtSimple"))
+    {
+        let Some(query_plan) = child(statement, "QueryPlan") else {
+            continue;
+        };
+        let Some(rel_op) = child(query_plan, "RelOp") else {
+            continue;
+        };
+        let mut root = rel_op_node(rel_op);
+        let mut warnings = missing_indexes(query_plan);
+        if let Some(plan_warnings) = child(query_plan, "Warnings") {
+            warnings.extend(showplan_warnings(plan_warnings));
+        }
+        warnings.append(&mut root.warnings);
+        root.warnings = warnings;
+        plans.push(root);
+    }
+    Ok(plans)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(tag))
+}
+
+fn number(node: Node, attribute: &str) -> Option<f64> {
+    node.attribute(attribute)?.parse().ok()
+}
+
+fn rel_op_node(rel_op: Node) -> PlanNode {
+    let physical = rel_op.attribute("PhysicalOp").unwrap_or_default();
+    let logical = rel_op.attribute("LogicalOp").unwrap_or_default();
+    let operation = if logical.is_empty() || logical == physical {
+        physical.to_string()
+    } else {
+        format!("{} ({})", physical, logical)
+    };
+    let mut node = PlanNode {
+        operation,
+        cost: number(rel_op, "EstimatedTotalSubtreeCost"),
+        rows: number(rel_op, "EstimateRows"),
+        ..PlanNode::default()
+    };
+    if let Some(warnings) = child(rel_op, "Warnings") {
+        node.warnings = showplan_warnings(warnings);
+    }
+    // Children and the object read hang at varying depths under
+    // operator-specific elements, but never below another RelOp.
+    let mut stack: Vec<Node> = rel_op.children().collect();
+    stack.reverse();
+    while let Some(n) = stack.pop() {
+        if n.has_tag_name("RelOp") {
+            node.children.push(rel_op_node(n));
+            continue;
+        }
+        if n.has_tag_name("Object") && node.object.is_none() {
+            let parts: Vec<&str> = ["Schema", "Table", "Index"]
+                .iter()
+                .filter_map(|a| n.attribute(*a))
+                .map(|p| p.trim_start_matches('[').trim_end_matches(']'))
+                .collect();
+            node.object = Some(parts.join("."));
+        }
+        stack.extend(n.children().collect::<Vec<_>>().into_iter().rev());
+    }
+    node
+}
+
+fn showplan_warnings(warnings: Node) -> Vec<String> {
+    warnings
+        .children()
+        .filter(|w| w.is_element())
+        .map(|w| match w.tag_name().name() {
+            "PlanAffectingConvert" => format!(
+                "Implicit conversion: {}",
+                w.attribute("Expression").unwrap_or_default()
+            ),
+            "NoJoinPredicate" => "No join predicate".to_string(),
+            "ColumnsWithNoStatistics" => "Columns with no statistics".to_string(),
+            "SpillToTempDb" => "Spills to tempdb".to_string(),
+            name => name.to_string(),
+        })
+        .collect()
+}
+
+fn missing_indexes(query_plan: Node) -> Vec<String> {
+    query_plan
+        .descendants()
+        .filter(|n| n.has_tag_name("MissingIndex"))
+        .map(|index| {
+            let unbracket = |s: &str| s.trim_start_matches('[').trim_end_matches(']').to_string();
+            let table = ["Schema", "Table"]
+                .iter()
+                .filter_map(|a| index.attribute(*a))
+                .map(unbracket)
+                .collect::<Vec<_>>()
+                .join(".");
+            let columns = |usage: &str| {
+                index
+                    .children()
+                    .filter(|g| g.attribute("Usage") == Some(usage))
+                    .flat_map(|g| g.children().filter_map(|c| c.attribute("Name")))
+                    .map(unbracket)
+                    .collect::<Vec<_>>()
+            };
+            let mut keys = columns("EQUALITY");
+            keys.extend(columns("INEQUALITY"));
+            let include = columns("INCLUDE");
+            let impact = index
+                .parent()
+                .and_then(|g| number(g, "Impact"))
+                .map(|i| format!(" (impact {:.0}%)", i))
+                .unwrap_or_default();
+            let mut warning = format!("Missing index on {} ({})", table, keys.join(", "));
+            if !include.is_empty() {
+                warning.push_str(&format!(" INCLUDE ({})", include.join(", ")));
+            }
+            warning + &impact
+        })
+        .collect()
+}
+
+/// Reads the output of `EXPLAIN (FORMAT JSON)`.
+pub fn from_postgres_json(json: &str) -> Result<Vec<PlanNode>> {
+    let value: Value = serde_json::from_str(json)?;
+    let plans = value
+        .as_array()
+        .ok_or_else(|| anyhow!("Unexpected EXPLAIN output"))?;
+    Ok(plans
+        .iter()
+        .filter_map(|p| p.get("Plan"))
+        .map(postgres_node)
+        .collect())
+}
+
+fn postgres_node(plan: &Value) -> PlanNode {
+    let text = |key: &str| plan.get(key).and_then(Value::as_str);
+    let relation = text("Relation Name").map(|r| match text("Schema") {
+        Some(schema) => format!("{}.{}", schema, r),
+        None => r.to_string(),
+    });
+    let object = match (relation, text("Index Name")) {
+        (Some(relation), Some(index)) => Some(format!("{} using {}", relation, index)),
+        (relation, index) => relation.or(index.map(str::to_string)),
+    };
+    let operation = match text("Join Type") {
+        Some(join) if join != "Inner" => {
+            format!("{} ({})", text("Node Type").unwrap_or_default(), join)
+        }
+        _ => text("Node Type").unwrap_or_default().to_string(),
+    };
+    let cost = plan.get("Total Cost").and_then(Value::as_f64);
+    let mut warnings = Vec::new();
+    if let Some(filter) = text("Filter") {
+        if text("Node Type") == Some("Seq Scan") && cost.is_some_and(|c| c >= SEQ_SCAN_COST) {
+            warnings.push(format!(
+                "Sequential scan filtering on {}; consider an index",
+                filter
+            ));
+        }
+    }
+    // Casts in an index condition didn't keep the index from being used.
+    for condition in ["Filter", "Hash Cond", "Merge Cond", "Join Filter"] {
+        if let Some(cast) = text(condition).and_then(column_cast) {
+            warnings.push(format!("Implicit conversion: {}", cast));
+        }
+    }
+    PlanNode {
+        operation,
+        object,
+        cost,
+        rows: plan.get("Plan Rows").and_then(Value::as_f64),
+        warnings,
+        children: plan
+            .get("Plans")
+            .and_then(Value::as_array)
+            .map(|plans| plans.iter().map(postgres_node).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// A column cast in a condition, e.g. `(code)::text`, which keeps an index on
+/// the column from being used. Literal casts such as `'1'::text` don't count.
+fn column_cast(condition: &str) -> Option<String> {
+    condition.match_indices(")::").find_map(|(at, _)| {
+        let open = condition[..at].rfind('(')?;
+        let column = &condition[open + 1..at];
+        let is_column = !column.is_empty()
+            && column
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '"'));
+        if !is_column || column.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let end = condition[at + 3..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ' '))
+            .map_or(condition.len(), |e| at + 3 + e);
+        Some(condition[open..end].trim_end().to_string())
+    })
+}
+
+/// The plans as an indented tree, one operator per line followed by its
+/// warnings.
+pub fn render(plans: &[PlanNode]) -> String {
+    let mut out = String::new();
+    for (i, plan) in plans.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        render_node(plan, "", None, &mut out);
+    }
+    out
+}
+
+fn render_node(node: &PlanNode, prefix: &str, last: Option<bool>, out: &mut String) {
+    let (branch, indent) = match last {
+        None => ("", String::new()),
+        Some(true) => ("└─ ", format!("{}   ", prefix)),
+        Some(false) => ("├─ ", format!("{}│  ", prefix)),
+    };
+    out.push_str(prefix);
+    out.push_str(branch);
+    out.push_str(&node.operation);
+    if let Some(object) = &node.object {
+        out.push_str(&format!(" on {}", object));
+    }
+    if let Some(cost) = node.cost {
+        out.push_str(&format!("  cost={}", short(cost)));
+    }
+    if let Some(rows) = node.rows {
+        out.push_str(&format!("  rows={}", short(rows)));
+    }
+    out.push('\n');
+    let bar = if node.children.is_empty() {
+        "  "
+    } else {
+        "│ "
+    };
+    for warning in &node.warnings {
+        out.push_str(&format!("{}{} ⚠ {}\n", indent, bar, warning));
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        render_node(child, &indent, Some(i == node.children.len() - 1), out);
+    }
+}
+
+fn short(n: f64) -> String {
+    let rounded = if n >= 100.0 {
+        format!("{:.0}", n)
+    } else if n >= 1.0 {
+        format!("{:.2}", n)
+    } else {
+        format!("{:.4}", n)
+    };
+    if rounded.contains('.') {
+        rounded
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        rounded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_a_showplan() {
+        let xml = r#"<ShowPlanXML xmlns="http://schemas.microsoft.com/sqlserver/2004/07/showplan"><BatchSequence><Batch><Statements>
+<StmtSimple StatementText="SELECT * FROM dbo.orders WHERE code = 1"><QueryPlan>
+<MissingIndexes><MissingIndexGroup Impact="97.5"><MissingIndex Database="[shop]" Schema="[dbo]" Table="[orders]"><ColumnGroup Usage="EQUALITY"><Column Name="[code]" ColumnId="2"/></ColumnGroup></MissingIndex></MissingIndexGroup></MissingIndexes>
+<Warnings><PlanAffectingConvert ConvertIssue="Seek Plan" Expression="CONVERT_IMPLICIT(int,[shop].[dbo].[orders].[code],0)=(1)"/></Warnings>
+<RelOp NodeId="0" PhysicalOp="Nested Loops" LogicalOp="Inner Join" EstimateRows="10" EstimatedTotalSubtreeCost="1.25">
+<NestedLoops><RelOp NodeId="1" PhysicalOp="Clustered Index Scan" LogicalOp="Clustered Index Scan" EstimateRows="10" EstimatedTotalSubtreeCost="0.5"><IndexScan><Object Database="[shop]" Schema="[dbo]" Table="[orders]" Index="[pk_orders]"/></IndexScan></RelOp>
+<RelOp NodeId="2" PhysicalOp="Constant Scan" LogicalOp="Constant Scan" EstimateRows="1" EstimatedTotalSubtreeCost="0.0001"><Warnings><NoJoinPredicate/></Warnings></RelOp></NestedLoops>
+</RelOp></QueryPlan></StmtSimple></Statements></Batch></BatchSequence></ShowPlanXML>"#;
+        let plans = from_showplan_xml(xml).unwrap();
+        assert_eq!(
+            render(&plans),
+            "Nested Loops (Inner Join)  cost=1.25  rows=10
+│  ⚠ Missing index on dbo.orders (code) (impact 98%)
+│  ⚠ Implicit conversion: CONVERT_IMPLICIT(int,[shop].[dbo].[orders].[code],0)=(1)
+├─ Clustered Index Scan on dbo.orders.pk_orders  cost=0.5  rows=10
+└─ Constant Scan  cost=0.0001  rows=1
+      ⚠ No join predicate
+"
+        );
+    }
+
+    #[test]
+    fn should_read_postgres_explain_output() {
+        let json = r#"[{"Plan": {"Node Type": "Hash Join", "Join Type": "Inner", "Total Cost": 2450.75, "Plan Rows": 120, "Hash Cond": "(o.customer_id = c.id)",
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "orders", "Alias": "o", "Total Cost": 2100.0, "Plan Rows": 120, "Filter": "((code)::text = '1'::text)"},
+                {"Node Type": "Index Scan", "Relation Name": "customers", "Index Name": "customers_pkey", "Total Cost": 8.3, "Plan Rows": 1}
+            ]}}]"#;
+        let plans = from_postgres_json(json).unwrap();
+        assert_eq!(
+            render(&plans),
+            "Hash Join  cost=2451  rows=120
+├─ Seq Scan on orders  cost=2100  rows=120
+│     ⚠ Sequential scan filtering on ((code)::text = '1'::text); consider an index
+│     ⚠ Implicit conversion: (code)::text
+└─ Index Scan on customers using customers_pkey  cost=8.3  rows=1
+"
+        );
+    }
+
+    #[test]
+    fn should_not_flag_casts_in_index_conditions() {
+        let json = r#"[{"Plan": {"Node Type": "Index Scan", "Relation Name": "orders", "Index Name": "orders_code_idx", "Total Cost": 8.3, "Plan Rows": 1, "Index Cond": "((code)::text = '1'::text)"}}]"#;
+        let plans = from_postgres_json(json).unwrap();
+        assert!(plans[0].warnings.is_empty(), "{:?}", plans[0].warnings);
+        assert_eq!(
+            render(&plans),
+            "Index Scan on orders using orders_code_idx  cost=8.3  rows=1\n"
+        );
+    }
+}
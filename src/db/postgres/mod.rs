@@ -1,18 +1,33 @@
 mod keywords;
-use keywords::KEYWORDS;
+pub(crate) use keywords::KEYWORDS;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use futures::TryStreamExt;
 use sqlx::postgres::PgConnectOptions;
 use sqlx::Connection;
 use sqlx::PgConnection;
-use std::collections::HashSet;
+use sqlx::{Column, Either, Executor, Row, TypeInfo, ValueRef};
+use std::collections::{HashMap, HashSet};
 
+use crate::sql::statement::parse;
 use crate::terminal_ui::repository::TenguRepository;
 
+use super::cancel;
 use super::column::Column as TenguColumn;
+use super::database::Database;
+use super::ddl::{create_table, ColumnDefinition};
+use super::engine::Engine;
+use super::import::FieldType;
+use super::index::{Constraint, ConstraintKind, Index};
+use super::object::{DbObject, ObjectKind};
+use super::plan::{from_postgres_json, PlanNode};
+use super::query::{QueryResult, ValueKind};
 use super::service::Service;
-use super::table::Table;
+use super::stats::TableStats;
+use super::table::{by_table, Table};
+use super::tunnel::endpoint;
+use crate::terminal_ui::models::Connection as TenguConnection;
 
 #[derive(Debug)]
 pub struct Postgres<T: TenguRepository> {
@@ -28,19 +43,208 @@ impl<T: TenguRepository + Sync> Postgres<T> {
         let Some(conn) = self.repo.get_active_connection() else {
             return Err(anyhow!("No active connection found"));
         };
-        let conn = PgConnection::connect_with(
-            &PgConnectOptions::new()
-                .database(&conn.database)
-                .username(&conn.username)
-                .password(&conn.password)
-                .host(&conn.host)
-                .port(conn.port.parse::<u16>().unwrap()),
+        let mut pg = connect(&conn).await?;
+        if cancel::in_scope() {
+            let (pid,): (i32,) = sqlx::query_as("SELECT pg_backend_pid()")
+                .fetch_one(&mut pg)
+                .await?;
+            cancel::on_cancel(async move {
+                let mut other = connect(&conn).await?;
+                let (cancelled,): (bool,) = sqlx::query_as("SELECT pg_cancel_backend($1)")
+                    .bind(pid)
+                    .fetch_one(&mut other)
+                    .await?;
+                if !cancelled {
+                    return Err(anyhow!("pg_cancel_backend({}) refused", pid));
+                }
+                Ok(())
+            });
+        }
+        Ok(pg)
+    }
+    /// Indexes of `table`, or of every table outside the system schemas when
+    /// there's none, with the one each is on.
+    async fn indexes(&self, table: Option<&Table>) -> Result<Vec<(Table, Index)>> {
+        let mut conn = self.get_conn().await?;
+        type Row = (
+            String,
+            String,
+            String,
+            bool,
+            bool,
+            Vec<String>,
+            Vec<String>,
+            Option<String>,
+            String,
+        );
+        // Key columns come first in indkey, then the INCLUDE ones; expressions
+        // have a 0 there and are printed by pg_get_indexdef.
+        let rows: Vec<Row> = sqlx::query_as(
+            r#"
+            SELECT n.nspname::text, t.relname::text, i.relname::text, ix.indisunique, ix.indisprimary,
+                ARRAY(
+                    SELECT COALESCE(a.attname::text, pg_get_indexdef(ix.indexrelid, k.ord, true))
+                    FROM generate_series(1, ix.indnkeyatts::int) AS k(ord)
+                    LEFT JOIN pg_attribute a ON a.attrelid = ix.indrelid
+                        AND a.attnum = ix.indkey[k.ord - 1] AND a.attnum <> 0
+                    ORDER BY k.ord
+                ),
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM generate_series(ix.indnkeyatts + 1, ix.indnatts::int) AS k(ord)
+                    JOIN pg_attribute a ON a.attrelid = ix.indrelid AND a.attnum = ix.indkey[k.ord - 1]
+                    ORDER BY k.ord
+                ),
+                pg_get_expr(ix.indpred, ix.indrelid), am.amname::text
+            FROM pg_index ix
+            JOIN pg_class i ON i.oid = ix.indexrelid
+            JOIN pg_am am ON am.oid = i.relam
+            JOIN pg_class t ON t.oid = ix.indrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            WHERE CASE WHEN $1::text IS NULL
+                THEN n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+                ELSE n.nspname = $1 AND t.relname = $2::text
+            END
+            ORDER BY 3
+            "#,
+        )
+        .bind(table.map(|t| &t.schema))
+        .bind(table.map(|t| &t.name))
+        .fetch_all(&mut conn)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(schema, table, name, unique, primary_key, columns, included, filter, kind)| {
+                    let index = Index {
+                        name,
+                        columns,
+                        included,
+                        unique,
+                        primary_key,
+                        filter,
+                        kind: Some(kind),
+                    };
+                    (table_of(schema, table), index)
+                },
+            )
+            .collect())
+    }
+
+    /// Constraints of `table`, or of every table outside the system schemas
+    /// when there's none, with the one each is on.
+    async fn constraints(&self, table: Option<&Table>) -> Result<Vec<(Table, Constraint)>> {
+        let mut conn = self.get_conn().await?;
+        // Defaults aren't constraints in Postgres, but they're listed with
+        // them as they are in SQL Server.
+        let rows: Vec<(String, String, String, String, String, Vec<String>)> = sqlx::query_as(
+            r#"
+            WITH tables AS (
+                SELECT t.oid, n.nspname::text AS schema_name, t.relname::text AS table_name
+                FROM pg_class t
+                JOIN pg_namespace n ON n.oid = t.relnamespace
+                WHERE CASE WHEN $1::text IS NULL
+                    THEN n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+                    ELSE n.nspname = $1 AND t.relname = $2::text
+                END
+            )
+            SELECT t.schema_name, t.table_name, c.conname::text, c.contype::text, pg_get_constraintdef(c.oid),
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                )
+            FROM pg_constraint c
+            JOIN tables t ON t.oid = c.conrelid
+            WHERE c.contype IN ('p', 'u', 'f', 'c')
+            UNION ALL
+            SELECT t.schema_name, t.table_name, a.attname::text, 'd',
+                'DEFAULT ' || pg_get_expr(d.adbin, d.adrelid) || ' FOR ' || quote_ident(a.attname),
+                ARRAY[a.attname::text]
+            FROM pg_attrdef d
+            JOIN pg_attribute a ON a.attrelid = d.adrelid AND a.attnum = d.adnum
+            JOIN tables t ON t.oid = d.adrelid
+            "#,
         )
+        .bind(table.map(|t| &t.schema))
+        .bind(table.map(|t| &t.name))
+        .fetch_all(&mut conn)
         .await?;
-        Ok(conn)
+        Ok(rows
+            .into_iter()
+            .map(|(schema, table, name, kind, definition, columns)| {
+                let constraint = Constraint {
+                    name,
+                    kind: match kind.as_str() {
+                        "p" => ConstraintKind::PrimaryKey,
+                        "u" => ConstraintKind::Unique,
+                        "f" => ConstraintKind::ForeignKey,
+                        "c" => ConstraintKind::Check,
+                        _ => ConstraintKind::Default,
+                    },
+                    definition,
+                    columns,
+                };
+                (table_of(schema, table), constraint)
+            })
+            .collect())
     }
 }
 
+fn table_of(schema: String, name: String) -> Table {
+    Table {
+        name,
+        schema,
+        description: None,
+    }
+}
+
+/// Columns as the catalog queries select them, to be filtered by table.
+const COLUMNS: &str = r#"
+    SELECT column_name AS name, table_name AS table, table_schema AS schema, data_type, is_nullable,
+        column_default::text, character_maximum_length::int,
+        numeric_precision::int, numeric_scale::int, ordinal_position::int,
+        col_description(format('%I.%I', table_schema, table_name)::regclass, ordinal_position::int) AS description
+    FROM information_schema.columns
+"#;
+
+/// An `EXPLAIN` of each statement of `sql`, one at a time.
+fn explain_statements(sql: &str) -> Vec<String> {
+    parse(sql, &Engine::Postgres)
+        .iter()
+        .map(|statement| statement.text(sql).trim().trim_end_matches(';'))
+        .filter(|text| !text.is_empty())
+        .map(|text| format!("EXPLAIN (FORMAT JSON) {}", text))
+        .collect()
+}
+
+async fn connect(conn: &TenguConnection) -> Result<PgConnection> {
+    let (host, port) = endpoint(conn).await?;
+    let mut pg = PgConnection::connect_with(
+        &PgConnectOptions::new()
+            .database(&conn.database)
+            .username(&conn.username)
+            .password(&conn.password)
+            .host(&host)
+            .port(port),
+    )
+    .await?;
+    if let Some(search_path) = &conn.search_path {
+        sqlx::query("SELECT set_config('search_path', $1, false)")
+            .bind(search_path)
+            .execute(&mut pg)
+            .await?;
+    }
+    if let Some(seconds) = conn.statement_timeout {
+        sqlx::query("SELECT set_config('statement_timeout', $1, false)")
+            .bind(format!("{}s", seconds))
+            .execute(&mut pg)
+            .await?;
+    }
+    Ok(pg)
+}
+
 #[tower_lsp::async_trait]
 impl<T: TenguRepository + Sync + Send> Service for Postgres<T> {
 
@@ -48,7 +252,8 @@ impl<T: TenguRepository + Sync + Send> Service for Postgres<T> {
         let mut conn = self.get_conn().await?;
         let tables: Vec<Table> = sqlx::query_as::<_, Table>(
             r#"
-            SELECT table_schema as schema, table_name as name
+            SELECT table_schema as schema, table_name as name,
+                obj_description(format('%I.%I', table_schema, table_name)::regclass, 'pg_class') AS description
             FROM information_schema.tables
             WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
             "#,
@@ -58,25 +263,406 @@ impl<T: TenguRepository + Sync + Send> Service for Postgres<T> {
         Ok(tables)
     }
     async fn get_table_columns(&self, tables: HashSet<Table>) -> Result<HashSet<TenguColumn>> {
+        if tables.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let (schemas, names): (Vec<String>, Vec<String>) =
+            tables.into_iter().map(|t| (t.schema, t.name)).unzip();
         let sql = format!(
-            r#"
-            SELECT column_name AS name, table_name AS table, table_schema AS schema, data_type, is_nullable
-            FROM information_schema.columns
-            WHERE table_name IN ({});
-        "#,
-            tables
-                .iter()
-                .map(|t| format!("'{}'", t.name))
-                .collect::<Vec<String>>()
-                .join(",")
+            "{} WHERE (table_schema, table_name) IN (SELECT * FROM UNNEST($1::text[], $2::text[]))",
+            COLUMNS
         );
         let mut conn = self.get_conn().await?;
         let columns: Vec<TenguColumn> = sqlx::query_as::<_, TenguColumn>(&sql)
+            .bind(schemas)
+            .bind(names)
             .fetch_all(&mut conn)
             .await?;
         Ok(columns.into_iter().collect())
     }
+    async fn get_objects(&self) -> Result<Vec<DbObject>> {
+        let mut conn = self.get_conn().await?;
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            r#"
+            SELECT table_schema, table_name, 'VIEW'
+            FROM information_schema.views
+            WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
+            UNION ALL
+            SELECT routine_schema, routine_name, COALESCE(routine_type, 'FUNCTION')
+            FROM information_schema.routines
+            WHERE routine_schema NOT IN ('pg_catalog', 'information_schema')
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(schema, name, kind)| DbObject {
+                name,
+                schema,
+                kind: match kind.as_str() {
+                    "VIEW" => ObjectKind::View,
+                    "PROCEDURE" => ObjectKind::Procedure,
+                    _ => ObjectKind::Function,
+                },
+            })
+            .collect())
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let mut conn = self.get_conn().await?;
+        // reltuples is -1 until the table is first vacuumed or analyzed.
+        let rows: Vec<(String, String, Option<i64>, Option<i64>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT n.nspname::text, c.relname::text,
+                CASE WHEN c.reltuples < 0 THEN s.n_live_tup ELSE c.reltuples::bigint END,
+                pg_total_relation_size(c.oid),
+                to_char(GREATEST(s.last_analyze, s.last_autoanalyze), 'YYYY-MM-DD HH24:MI')
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid
+            WHERE c.relkind IN ('r', 'p')
+                AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(schema, name, rows, bytes, analyzed)| TableStats {
+                table: Table {
+                    name,
+                    schema,
+                    description: None,
+                },
+                rows,
+                bytes,
+                modified: None,
+                analyzed,
+            })
+            .collect())
+    }
+
+    async fn execute(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        let mut conn = self.get_conn().await?;
+        let mut results: Vec<QueryResult> = Vec::new();
+        let mut current = QueryResult::default();
+        // Without bind parameters the simple protocol is used, so several
+        // statements can run at once and every value comes back as text.
+        let mut stream = conn.fetch_many(sql);
+        while let Some(item) = stream.try_next().await? {
+            match item {
+                Either::Left(done) => {
+                    if current.columns.is_empty() {
+                        current.rows_affected = Some(done.rows_affected());
+                    }
+                    results.push(std::mem::take(&mut current));
+                }
+                Either::Right(row) => {
+                    if current.columns.is_empty() {
+                        current.columns =
+                            row.columns().iter().map(|c| c.name().to_string()).collect();
+                        current.kinds = row
+                            .columns()
+                            .iter()
+                            .map(|c| value_kind(c.type_info().name()))
+                            .collect();
+                    }
+                    let values = (0..row.len())
+                        .map(|i| {
+                            let value = row.try_get_raw(i).ok()?;
+                            if value.is_null() {
+                                return None;
+                            }
+                            value.as_str().ok().map(|v| v.to_string())
+                        })
+                        .collect();
+                    current.rows.push(values);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    async fn get_indexes(&self, table: &Table) -> Result<Vec<Index>> {
+        Ok(self
+            .indexes(Some(table))
+            .await?
+            .into_iter()
+            .map(|(_, index)| index)
+            .collect())
+    }
+
+    async fn get_all_indexes(&self) -> Result<HashMap<Table, Vec<Index>>> {
+        Ok(by_table(self.indexes(None).await?))
+    }
+
+    async fn get_constraints(&self, table: &Table) -> Result<Vec<Constraint>> {
+        Ok(self
+            .constraints(Some(table))
+            .await?
+            .into_iter()
+            .map(|(_, constraint)| constraint)
+            .collect())
+    }
+
+    async fn get_all_columns(&self) -> Result<HashMap<Table, Vec<TenguColumn>>> {
+        let sql = format!(
+            "{} WHERE table_schema NOT IN ('pg_catalog', 'information_schema')",
+            COLUMNS
+        );
+        let mut conn = self.get_conn().await?;
+        let columns: Vec<TenguColumn> = sqlx::query_as::<_, TenguColumn>(&sql)
+            .fetch_all(&mut conn)
+            .await?;
+        Ok(by_table(
+            columns
+                .into_iter()
+                .map(|c| (Table::from(c.clone()), c))
+                .collect(),
+        ))
+    }
+
+    async fn get_all_constraints(&self) -> Result<HashMap<Table, Vec<Constraint>>> {
+        Ok(by_table(self.constraints(None).await?))
+    }
+
+    async fn get_table_ddl(&self, table: &Table) -> Result<String> {
+        let mut conn = self.get_conn().await?;
+        let rows: Vec<(String, String, bool, Option<String>, String)> = sqlx::query_as(
+            r#"
+            SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), a.attnotnull,
+                pg_get_expr(d.adbin, d.adrelid), a.attidentity::text
+            FROM pg_attribute a
+            JOIN pg_class t ON t.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+            WHERE n.nspname = $1 AND t.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped
+            ORDER BY a.attnum
+            "#,
+        )
+        .bind(&table.schema)
+        .bind(&table.name)
+        .fetch_all(&mut conn)
+        .await?;
+        if rows.is_empty() {
+            return Err(anyhow!("Table {}.{} not found", table.schema, table.name));
+        }
+        let columns: Vec<ColumnDefinition> = rows
+            .into_iter()
+            .map(
+                |(name, data_type, not_null, default, identity)| ColumnDefinition {
+                    name,
+                    data_type,
+                    nullable: !not_null,
+                    extra: match identity.as_str() {
+                        "a" => Some("GENERATED ALWAYS AS IDENTITY".to_string()),
+                        "d" => Some("GENERATED BY DEFAULT AS IDENTITY".to_string()),
+                        _ => default.map(|d| format!("DEFAULT {}", d)),
+                    },
+                },
+            )
+            .collect();
+        // Defaults are part of the columns already.
+        let constraints: Vec<Constraint> = self
+            .get_constraints(table)
+            .await?
+            .into_iter()
+            .filter(|c| c.kind != ConstraintKind::Default)
+            .collect();
+        let indexes = self.get_indexes(table).await?;
+        Ok(create_table(
+            &Engine::Postgres,
+            table,
+            &columns,
+            &constraints,
+            &indexes,
+        ))
+    }
+
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String> {
+        let mut conn = self.get_conn().await?;
+        let definitions: Vec<(String,)> = match object.kind {
+            ObjectKind::View => {
+                sqlx::query_as(
+                    r#"
+                    SELECT CASE c.relkind WHEN 'm' THEN 'CREATE MATERIALIZED VIEW ' ELSE 'CREATE OR REPLACE VIEW ' END
+                        || quote_ident(n.nspname) || '.' || quote_ident(c.relname) || E' AS\n'
+                        || pg_get_viewdef(c.oid, true)
+                    FROM pg_class c
+                    JOIN pg_namespace n ON n.oid = c.relnamespace
+                    WHERE n.nspname = $1 AND c.relname = $2 AND c.relkind IN ('v', 'm')
+                    "#,
+                )
+                .bind(&object.schema)
+                .bind(&object.name)
+                .fetch_all(&mut conn)
+                .await?
+            }
+            ObjectKind::Procedure | ObjectKind::Function => {
+                sqlx::query_as(
+                    r#"
+                    SELECT pg_get_functiondef(p.oid)
+                    FROM pg_proc p
+                    JOIN pg_namespace n ON n.oid = p.pronamespace
+                    WHERE n.nspname = $1 AND p.proname = $2 AND p.prokind IN ('f', 'p')
+                    "#,
+                )
+                .bind(&object.schema)
+                .bind(&object.name)
+                .fetch_all(&mut conn)
+                .await?
+            }
+        };
+        if definitions.is_empty() {
+            return Err(anyhow!(
+                "No definition for {}.{}",
+                object.schema,
+                object.name
+            ));
+        }
+        Ok(definitions
+            .into_iter()
+            .map(|(d,)| d)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    async fn explain(&self, sql: &str) -> Result<Vec<PlanNode>> {
+        let mut conn = self.get_conn().await?;
+        let mut plans = Vec::new();
+        // A prepared statement holds a single command, so whatever the
+        // splitting misses fails instead of running.
+        for explain in explain_statements(sql) {
+            let row = sqlx::query(&explain).fetch_one(&mut conn).await?;
+            let json = row
+                .try_get_raw(0)?
+                .as_str()
+                .map_err(|e| anyhow!("EXPLAIN returned no plan: {}", e))?
+                .to_string();
+            plans.extend(from_postgres_json(&json)?);
+        }
+        Ok(plans)
+    }
+
+    async fn bulk_insert(
+        &self,
+        table: &Table,
+        columns: &[TenguColumn],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
+        let names: Vec<String> = columns
+            .iter()
+            .map(|c| Engine::Postgres.quote(&c.name))
+            .collect();
+        let sql = format!(
+            "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+            Engine::Postgres.qualified(&table.schema, &table.name),
+            names.join(", ")
+        );
+        let types: Vec<FieldType> = columns
+            .iter()
+            .map(|c| FieldType::of(&c.data_type))
+            .collect();
+        // In COPY's CSV format an unquoted empty field is NULL and a quoted
+        // one an empty string, so every value is quoted.
+        let mut data = String::new();
+        for row in rows {
+            for (i, (value, field_type)) in row.iter().zip(&types).enumerate() {
+                if i > 0 {
+                    data.push(',');
+                }
+                if let Some(value) = value {
+                    data.push('"');
+                    if *field_type == FieldType::Binary {
+                        data.push_str("\\x");
+                    }
+                    data.push_str(&value.replace('"', "\"\""));
+                    data.push('"');
+                }
+            }
+            data.push('\n');
+        }
+        let mut conn = self.get_conn().await?;
+        let mut copy = conn.copy_in_raw(&sql).await?;
+        if let Err(e) = copy.send(data.into_bytes()).await {
+            copy.abort(e.to_string()).await?;
+            return Err(e.into());
+        }
+        Ok(copy.finish().await?)
+    }
+
+    async fn get_search_path(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_conn().await?;
+        let schemas: Vec<(String,)> = sqlx::query_as(
+            "SELECT s::text FROM unnest(current_schemas(true)) WITH ORDINALITY AS p(s, n) ORDER BY n",
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        Ok(schemas.into_iter().map(|(s,)| s).collect())
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        let mut conn = self.get_conn().await?;
+        let databases: Vec<(String,)> = sqlx::query_as(
+            "SELECT datname FROM pg_database WHERE datallowconn AND NOT datistemplate \
+            AND has_database_privilege(datname, 'CONNECT') ORDER BY datname",
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        Ok(databases.into_iter().map(|(d,)| d).collect())
+    }
+
+    // Postgres can't name another database's tables in a query.
+    async fn get_databases(&self, _server: Option<&str>) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn get_linked_servers(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn get_database_tables(&self, _database: &Database) -> Result<Vec<Table>> {
+        Ok(vec![])
+    }
+
+    async fn get_database_columns(
+        &self,
+        _database: &Database,
+        _tables: HashSet<Table>,
+    ) -> Result<HashSet<TenguColumn>> {
+        Ok(HashSet::new())
+    }
+
     fn get_keywords(&self) -> &[&str] {
         KEYWORDS
     }
 }
+
+fn value_kind(type_name: &str) -> ValueKind {
+    match type_name {
+        "INT2" | "INT4" | "INT8" | "OID" => ValueKind::Integer,
+        "FLOAT4" | "FLOAT8" => ValueKind::Float,
+        "NUMERIC" => ValueKind::Decimal,
+        "BOOL" => ValueKind::Bool,
+        "BYTEA" => ValueKind::Binary,
+        "DATE" | "TIME" | "TIMETZ" | "TIMESTAMP" | "TIMESTAMPTZ" => ValueKind::Temporal,
+        _ => ValueKind::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_explain_each_statement_on_its_own() {
+        assert_eq!(
+            explain_statements("select 1; delete from t;\n"),
+            vec![
+                "EXPLAIN (FORMAT JSON) select 1".to_string(),
+                "EXPLAIN (FORMAT JSON) delete from t".to_string(),
+            ]
+        );
+    }
+}
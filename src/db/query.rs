@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// One result of running a script: the rows a query returned, or how many
+/// rows a command changed when the engine reports it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    /// What each column holds; columns without an entry are text.
+    pub kinds: Vec<ValueKind>,
+    /// Values rendered as text, `None` for NULL.
+    pub rows: Vec<Vec<Option<String>>>,
+    pub rows_affected: Option<u64>,
+}
+
+/// The broad type of a column, enough to write its values back out typed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueKind {
+    Integer,
+    Float,
+    Decimal,
+    Bool,
+    /// Hex, as `0x...` from SQL Server or `\x...` from Postgres.
+    Binary,
+    /// Dates, times and timestamps.
+    Temporal,
+    #[default]
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    #[default]
+    Markdown,
+    Csv,
+}
+
+impl ResultFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ResultFormat::Markdown => "md",
+            ResultFormat::Csv => "csv",
+        }
+    }
+}
+
+impl QueryResult {
+    pub fn kind(&self, column: usize) -> ValueKind {
+        self.kinds.get(column).copied().unwrap_or_default()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        if self.columns.is_empty() {
+            return match self.rows_affected {
+                Some(n) => format!("({} rows affected)\n", n),
+                None => "Commands completed successfully.\n".to_string(),
+            };
+        }
+        let cell = |value: &str| value.replace('|', "\\|").replace(['\r', '\n'], " ");
+        let mut out = format!(
+            "| {} |\n|{}|\n",
+            self.columns
+                .iter()
+                .map(|c| cell(c))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            self.columns
+                .iter()
+                .map(|_| "---")
+                .collect::<Vec<_>>()
+                .join("|")
+        );
+        for row in &self.rows {
+            let values: Vec<String> = row
+                .iter()
+                .map(|v| v.as_deref().map_or("NULL".to_string(), cell))
+                .collect();
+            out.push_str(&format!("| {} |\n", values.join(" | ")));
+        }
+        out.push_str(&format!("\n({} rows)\n", self.rows.len()));
+        out
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        if self.columns.is_empty() {
+            return out;
+        }
+        out.push_str(
+            &self
+                .columns
+                .iter()
+                .map(|c| csv_field(c))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        out.push('\n');
+        for row in &self.rows {
+            out.push_str(
+                &row.iter()
+                    .map(|v| v.as_deref().map_or(String::new(), csv_field))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// All the results of a run as one document, separated by blank lines.
+pub fn render(results: &[QueryResult], format: ResultFormat) -> String {
+    results
+        .iter()
+        .map(|r| match format {
+            ResultFormat::Markdown => r.to_markdown(),
+            ResultFormat::Csv => r.to_csv(),
+        })
+        .filter(|r| !r.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> QueryResult {
+        QueryResult {
+            columns: vec!["id".to_string(), "note".to_string()],
+            rows: vec![
+                vec![Some("1".to_string()), Some("a|b, \"c\"".to_string())],
+                vec![Some("2".to_string()), None],
+            ],
+            ..QueryResult::default()
+        }
+    }
+
+    #[test]
+    fn should_render_results_as_markdown() {
+        assert_eq!(
+            result().to_markdown(),
+            "| id | note |\n|---|---|\n| 1 | a\\|b, \"c\" |\n| 2 | NULL |\n\n(2 rows)\n"
+        );
+        let command = QueryResult {
+            rows_affected: Some(3),
+            ..QueryResult::default()
+        };
+        assert_eq!(command.to_markdown(), "(3 rows affected)\n");
+    }
+
+    #[test]
+    fn should_render_results_as_csv() {
+        assert_eq!(result().to_csv(), "id,note\n1,\"a|b, \"\"c\"\"\"\n2,\n");
+    }
+}
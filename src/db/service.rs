@@ -1,18 +1,76 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::time::Instant;
 
 use anyhow::Result;
 
-use crate::terminal_ui::repository::FsTenguRepository;
+use crate::history::{History, HistoryEntry};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
 
+use super::cancel::timed;
+use super::database::Database;
 use super::engine::Engine;
+use super::index::{Constraint, Index};
+use super::migrations::MigrationService;
 use super::mssql::SqlServer;
+use super::object::DbObject;
+use super::plan::PlanNode;
 use super::postgres::Postgres;
+use super::query::QueryResult;
+use super::snapshot::SnapshotService;
+use super::stats::TableStats;
 use super::{column::Column, table::Table};
 
 #[tower_lsp::async_trait]
 pub trait Service {
     async fn get_tables(&self) -> Result<Vec<Table>>;
     async fn get_table_columns(&self, tables: HashSet<Table>) -> Result<HashSet<Column>>;
+    /// Views, procedures and functions outside the system schemas.
+    async fn get_objects(&self) -> Result<Vec<DbObject>>;
+    /// Runs a script, which may hold several statements, returning a result
+    /// per statement that produced one.
+    async fn execute(&self, sql: &str) -> Result<Vec<QueryResult>>;
+    async fn get_indexes(&self, table: &Table) -> Result<Vec<Index>>;
+    async fn get_constraints(&self, table: &Table) -> Result<Vec<Constraint>>;
+    /// Columns of every table, in one query.
+    async fn get_all_columns(&self) -> Result<HashMap<Table, Vec<Column>>>;
+    /// Indexes of every table, in one query.
+    async fn get_all_indexes(&self) -> Result<HashMap<Table, Vec<Index>>>;
+    /// Constraints of every table, in one query.
+    async fn get_all_constraints(&self) -> Result<HashMap<Table, Vec<Constraint>>>;
+    /// A script recreating the table with its constraints and indexes.
+    async fn get_table_ddl(&self, table: &Table) -> Result<String>;
+    /// The definition of a view or routine as the catalog stores it.
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String>;
+    /// The estimated plan of each statement in `sql`, without running it.
+    async fn explain(&self, sql: &str) -> Result<Vec<PlanNode>>;
+    /// Estimated size and last change of every table.
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>>;
+    /// Loads rows through the engine's bulk API, each value going to the
+    /// column at its position in `columns` and normalized as
+    /// [`import::coerce`](super::import::coerce) leaves it. Returns the
+    /// number of rows loaded.
+    async fn bulk_insert(
+        &self,
+        table: &Table,
+        columns: &[Column],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64>;
+    /// Schemas an unqualified table name is looked up in, in order.
+    async fn get_search_path(&self) -> Result<Vec<String>>;
+    /// Databases on the connection's server it can switch to.
+    async fn list_databases(&self) -> Result<Vec<String>>;
+    /// Databases that three-part names can reach on the connection's server,
+    /// or four-part names on `server`, a linked server.
+    async fn get_databases(&self, server: Option<&str>) -> Result<Vec<String>>;
+    async fn get_linked_servers(&self) -> Result<Vec<String>>;
+    /// Tables of another database, for cross-database names.
+    async fn get_database_tables(&self, database: &Database) -> Result<Vec<Table>>;
+    async fn get_database_columns(
+        &self,
+        database: &Database,
+        tables: HashSet<Table>,
+    ) -> Result<HashSet<Column>>;
     fn get_keywords(&self) -> &[&str] {
         &[]
     }
@@ -22,10 +80,15 @@ pub trait Service {
 pub enum TenguService {
     SqlServer(SqlServer<FsTenguRepository>),
     Postgres(Postgres<FsTenguRepository>),
+    Snapshot(SnapshotService),
+    Migrations(MigrationService),
 }
 
 impl TenguService {
     pub fn new(engine: Engine, repo: FsTenguRepository) -> Self {
+        if let Some(path) = repo.get_active_connection().and_then(|c| c.snapshot) {
+            return Self::Snapshot(SnapshotService::new(engine, path));
+        }
         match engine {
             Engine::SqlServer => {
                 let service = SqlServer::new(repo);
@@ -38,28 +101,296 @@ impl TenguService {
             _ => unimplemented!(),
         }
     }
+
+    /// Runs a call on the engine's service so that dropping it, when the
+    /// editor cancels the request or Ctrl-C is pressed, cancels on the server
+    /// what it was running. SQL Server calls also give up after the
+    /// connection's statement timeout, which Postgres applies itself.
+    async fn guarded<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        let timeout = match self {
+            Self::SqlServer(service) => service.statement_timeout(),
+            _ => None,
+        };
+        timed(timeout, call).await
+    }
+
+    pub fn engine(&self) -> Engine {
+        match self {
+            Self::SqlServer(_) => Engine::SqlServer,
+            Self::Postgres(_) => Engine::Postgres,
+            Self::Snapshot(service) => service.engine(),
+            Self::Migrations(service) => service.engine(),
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
 impl Service for TenguService {
     async fn get_tables(&self) -> Result<Vec<Table>> {
-        match self {
-            Self::SqlServer(service) => service.get_tables().await,
-            Self::Postgres(service) => service.get_tables().await,
-        }
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_tables().await,
+                Self::Postgres(service) => service.get_tables().await,
+                Self::Snapshot(service) => service.get_tables().await,
+                Self::Migrations(service) => service.get_tables().await,
+            }
+        })
+        .await
     }
 
     async fn get_table_columns(&self, tables: HashSet<Table>) -> Result<HashSet<Column>> {
-        match self {
-            Self::SqlServer(service) => service.get_table_columns(tables).await,
-            Self::Postgres(service) => service.get_table_columns(tables).await,
-        }
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_table_columns(tables).await,
+                Self::Postgres(service) => service.get_table_columns(tables).await,
+                Self::Snapshot(service) => service.get_table_columns(tables).await,
+                Self::Migrations(service) => service.get_table_columns(tables).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_objects(&self) -> Result<Vec<DbObject>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_objects().await,
+                Self::Postgres(service) => service.get_objects().await,
+                Self::Snapshot(service) => service.get_objects().await,
+                Self::Migrations(service) => service.get_objects().await,
+            }
+        })
+        .await
+    }
+
+    async fn execute(&self, sql: &str) -> Result<Vec<QueryResult>> {
+        let started = Instant::now();
+        let result = self
+            .guarded(async {
+                match self {
+                    Self::SqlServer(service) => service.execute(sql).await,
+                    Self::Postgres(service) => service.execute(sql).await,
+                    Self::Snapshot(service) => service.execute(sql).await,
+                    Self::Migrations(service) => service.execute(sql).await,
+                }
+            })
+            .await;
+        let connection = FsTenguRepository::new()
+            .get_active_connection()
+            .map(|c| c.name)
+            .unwrap_or_default();
+        let entry = HistoryEntry::new(&connection, sql, started.elapsed(), &result);
+        // A run shouldn't fail because its history couldn't be written.
+        let _ = History::open().record(&entry);
+        result
+    }
+
+    async fn get_indexes(&self, table: &Table) -> Result<Vec<Index>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_indexes(table).await,
+                Self::Postgres(service) => service.get_indexes(table).await,
+                Self::Snapshot(service) => service.get_indexes(table).await,
+                Self::Migrations(service) => service.get_indexes(table).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_constraints(&self, table: &Table) -> Result<Vec<Constraint>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_constraints(table).await,
+                Self::Postgres(service) => service.get_constraints(table).await,
+                Self::Snapshot(service) => service.get_constraints(table).await,
+                Self::Migrations(service) => service.get_constraints(table).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_all_columns(&self) -> Result<HashMap<Table, Vec<Column>>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_all_columns().await,
+                Self::Postgres(service) => service.get_all_columns().await,
+                Self::Snapshot(service) => service.get_all_columns().await,
+                Self::Migrations(service) => service.get_all_columns().await,
+            }
+        })
+        .await
+    }
+
+    async fn get_all_indexes(&self) -> Result<HashMap<Table, Vec<Index>>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_all_indexes().await,
+                Self::Postgres(service) => service.get_all_indexes().await,
+                Self::Snapshot(service) => service.get_all_indexes().await,
+                Self::Migrations(service) => service.get_all_indexes().await,
+            }
+        })
+        .await
+    }
+
+    async fn get_all_constraints(&self) -> Result<HashMap<Table, Vec<Constraint>>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_all_constraints().await,
+                Self::Postgres(service) => service.get_all_constraints().await,
+                Self::Snapshot(service) => service.get_all_constraints().await,
+                Self::Migrations(service) => service.get_all_constraints().await,
+            }
+        })
+        .await
+    }
+
+    async fn get_table_ddl(&self, table: &Table) -> Result<String> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_table_ddl(table).await,
+                Self::Postgres(service) => service.get_table_ddl(table).await,
+                Self::Snapshot(service) => service.get_table_ddl(table).await,
+                Self::Migrations(service) => service.get_table_ddl(table).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_object_ddl(object).await,
+                Self::Postgres(service) => service.get_object_ddl(object).await,
+                Self::Snapshot(service) => service.get_object_ddl(object).await,
+                Self::Migrations(service) => service.get_object_ddl(object).await,
+            }
+        })
+        .await
+    }
+
+    async fn explain(&self, sql: &str) -> Result<Vec<PlanNode>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.explain(sql).await,
+                Self::Postgres(service) => service.explain(sql).await,
+                Self::Snapshot(service) => service.explain(sql).await,
+                Self::Migrations(service) => service.explain(sql).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_table_stats().await,
+                Self::Postgres(service) => service.get_table_stats().await,
+                Self::Snapshot(service) => service.get_table_stats().await,
+                Self::Migrations(service) => service.get_table_stats().await,
+            }
+        })
+        .await
+    }
+
+    async fn bulk_insert(
+        &self,
+        table: &Table,
+        columns: &[Column],
+        rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.bulk_insert(table, columns, rows).await,
+                Self::Postgres(service) => service.bulk_insert(table, columns, rows).await,
+                Self::Snapshot(service) => service.bulk_insert(table, columns, rows).await,
+                Self::Migrations(service) => service.bulk_insert(table, columns, rows).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_search_path(&self) -> Result<Vec<String>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_search_path().await,
+                Self::Postgres(service) => service.get_search_path().await,
+                Self::Snapshot(service) => service.get_search_path().await,
+                Self::Migrations(service) => service.get_search_path().await,
+            }
+        })
+        .await
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.list_databases().await,
+                Self::Postgres(service) => service.list_databases().await,
+                Self::Snapshot(service) => service.list_databases().await,
+                Self::Migrations(service) => service.list_databases().await,
+            }
+        })
+        .await
+    }
+
+    async fn get_databases(&self, server: Option<&str>) -> Result<Vec<String>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_databases(server).await,
+                Self::Postgres(service) => service.get_databases(server).await,
+                Self::Snapshot(service) => service.get_databases(server).await,
+                Self::Migrations(service) => service.get_databases(server).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_linked_servers(&self) -> Result<Vec<String>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_linked_servers().await,
+                Self::Postgres(service) => service.get_linked_servers().await,
+                Self::Snapshot(service) => service.get_linked_servers().await,
+                Self::Migrations(service) => service.get_linked_servers().await,
+            }
+        })
+        .await
+    }
+
+    async fn get_database_tables(&self, database: &Database) -> Result<Vec<Table>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_database_tables(database).await,
+                Self::Postgres(service) => service.get_database_tables(database).await,
+                Self::Snapshot(service) => service.get_database_tables(database).await,
+                Self::Migrations(service) => service.get_database_tables(database).await,
+            }
+        })
+        .await
+    }
+
+    async fn get_database_columns(
+        &self,
+        database: &Database,
+        tables: HashSet<Table>,
+    ) -> Result<HashSet<Column>> {
+        self.guarded(async {
+            match self {
+                Self::SqlServer(service) => service.get_database_columns(database, tables).await,
+                Self::Postgres(service) => service.get_database_columns(database, tables).await,
+                Self::Snapshot(service) => service.get_database_columns(database, tables).await,
+                Self::Migrations(service) => service.get_database_columns(database, tables).await,
+            }
+        })
+        .await
     }
 
     fn get_keywords(&self) -> &[&str] {
         match self {
             Self::SqlServer(service) => service.get_keywords(),
             Self::Postgres(service) => service.get_keywords(),
+            Self::Snapshot(service) => service.get_keywords(),
+            Self::Migrations(service) => service.get_keywords(),
         }
     }
 }
@@ -0,0 +1,410 @@
+//! A catalog saved to a file by `tengu schema dump`, which stands in for a
+//! database nobody at hand can connect to.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use super::catalog::Catalog;
+use super::column::Column;
+use super::database::Database;
+use super::engine::Engine;
+use super::index::{Constraint, Index};
+use super::object::{DbObject, ObjectKind};
+use super::plan::PlanNode;
+use super::query::QueryResult;
+use super::service::Service;
+use super::stats::TableStats;
+use super::table::{by_table, Table};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub engine: Engine,
+    /// The saved connection it was taken from.
+    pub connection: String,
+    pub database: String,
+    pub taken_at: String,
+    pub catalog: Catalog,
+    /// Columns of the views the engine lists with its tables, which the
+    /// catalog only keeps the definitions of.
+    #[serde(default)]
+    pub view_columns: Vec<Column>,
+    #[serde(default)]
+    pub stats: Vec<TableStats>,
+}
+
+impl Snapshot {
+    pub async fn take<S: Service + Sync>(
+        service: &S,
+        engine: Engine,
+        connection: &str,
+        database: &str,
+    ) -> Result<Snapshot> {
+        let catalog = Catalog::load(service).await?;
+        let views: HashSet<Table> = service
+            .get_tables()
+            .await?
+            .into_iter()
+            .filter(|t| catalog.table(t).is_none())
+            .collect();
+        let mut view_columns: Vec<Column> = if views.is_empty() {
+            Vec::new()
+        } else {
+            service
+                .get_table_columns(views)
+                .await?
+                .into_iter()
+                .collect()
+        };
+        view_columns.sort_by(|a, b| {
+            (&a.schema, &a.table, a.ordinal_position).cmp(&(
+                &b.schema,
+                &b.table,
+                b.ordinal_position,
+            ))
+        });
+        Ok(Snapshot {
+            engine,
+            connection: connection.to_string(),
+            database: database.to_string(),
+            taken_at: chrono::Local::now().to_rfc3339(),
+            catalog,
+            view_columns,
+            stats: service.get_table_stats().await.unwrap_or_default(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("can't read the snapshot {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("{} isn't a schema snapshot", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("can't write {}", path.display()))
+    }
+}
+
+/// Answers catalog questions from a snapshot file, read on first use. Anything
+/// that would need the database fails.
+#[derive(Debug)]
+pub struct SnapshotService {
+    engine: Engine,
+    path: PathBuf,
+    snapshot: OnceCell<Snapshot>,
+}
+
+impl SnapshotService {
+    pub fn new(engine: Engine, path: PathBuf) -> Self {
+        Self {
+            engine,
+            path,
+            snapshot: OnceCell::new(),
+        }
+    }
+
+    /// A service over a snapshot already read, taking its engine from it.
+    pub fn from_snapshot(path: PathBuf, snapshot: Snapshot) -> Self {
+        Self {
+            engine: snapshot.engine.clone(),
+            path,
+            snapshot: OnceCell::new_with(Some(snapshot)),
+        }
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine.clone()
+    }
+
+    async fn snapshot(&self) -> Result<&Snapshot> {
+        self.snapshot
+            .get_or_try_init(|| async { Snapshot::load(&self.path) })
+            .await
+    }
+
+    fn offline(&self) -> anyhow::Error {
+        anyhow!(
+            "{} is a schema snapshot, there's no database to run anything on",
+            self.path.display()
+        )
+    }
+}
+
+#[tower_lsp::async_trait]
+impl Service for SnapshotService {
+    async fn get_tables(&self) -> Result<Vec<Table>> {
+        let snapshot = self.snapshot().await?;
+        let mut tables: Vec<Table> = snapshot
+            .catalog
+            .tables
+            .iter()
+            .map(|t| t.table.clone())
+            .collect();
+        for column in &snapshot.view_columns {
+            let view = Table::from(column.clone());
+            if !tables.contains(&view) {
+                tables.push(view);
+            }
+        }
+        Ok(tables)
+    }
+
+    async fn get_table_columns(&self, tables: HashSet<Table>) -> Result<HashSet<Column>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .tables
+            .iter()
+            .flat_map(|t| &t.columns)
+            .chain(&snapshot.view_columns)
+            .filter(|c| {
+                tables
+                    .iter()
+                    .any(|t| t.schema == c.schema && t.name == c.table)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_objects(&self) -> Result<Vec<DbObject>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .objects
+            .iter()
+            .map(|o| o.object.clone())
+            .collect())
+    }
+
+    async fn execute(&self, _sql: &str) -> Result<Vec<QueryResult>> {
+        Err(self.offline())
+    }
+
+    async fn get_indexes(&self, table: &Table) -> Result<Vec<Index>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .table(table)
+            .map(|t| t.indexes.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_constraints(&self, table: &Table) -> Result<Vec<Constraint>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .table(table)
+            .map(|t| t.constraints.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_all_columns(&self) -> Result<HashMap<Table, Vec<Column>>> {
+        let snapshot = self.snapshot().await?;
+        Ok(by_table(
+            snapshot
+                .catalog
+                .tables
+                .iter()
+                .flat_map(|t| &t.columns)
+                .chain(&snapshot.view_columns)
+                .map(|c| (Table::from(c.clone()), c.clone()))
+                .collect(),
+        ))
+    }
+
+    async fn get_all_indexes(&self) -> Result<HashMap<Table, Vec<Index>>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .tables
+            .iter()
+            .map(|t| (t.table.clone(), t.indexes.clone()))
+            .collect())
+    }
+
+    async fn get_all_constraints(&self) -> Result<HashMap<Table, Vec<Constraint>>> {
+        let snapshot = self.snapshot().await?;
+        Ok(snapshot
+            .catalog
+            .tables
+            .iter()
+            .map(|t| (t.table.clone(), t.constraints.clone()))
+            .collect())
+    }
+
+    async fn get_table_ddl(&self, table: &Table) -> Result<String> {
+        let snapshot = self.snapshot().await?;
+        match snapshot.catalog.table(table) {
+            Some(schema) => Ok(schema.create_table(&snapshot.engine)),
+            None => bail!("{}.{} isn't in the snapshot", table.schema, table.name),
+        }
+    }
+
+    async fn get_object_ddl(&self, object: &DbObject) -> Result<String> {
+        let snapshot = self.snapshot().await?;
+        snapshot
+            .catalog
+            .objects
+            .iter()
+            .find(|o| o.object == *object)
+            .map(|o| o.definition.clone())
+            .ok_or_else(|| anyhow!("{}.{} isn't in the snapshot", object.schema, object.name))
+    }
+
+    async fn explain(&self, _sql: &str) -> Result<Vec<PlanNode>> {
+        Err(self.offline())
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        Ok(self.snapshot().await?.stats.clone())
+    }
+
+    async fn bulk_insert(
+        &self,
+        _table: &Table,
+        _columns: &[Column],
+        _rows: &[Vec<Option<String>>],
+    ) -> Result<u64> {
+        Err(self.offline())
+    }
+
+    async fn get_search_path(&self) -> Result<Vec<String>> {
+        Ok(vec![self.engine.default_schema().to_string()])
+    }
+
+    // A snapshot holds the one database it was dumped from.
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn get_databases(&self, _server: Option<&str>) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn get_linked_servers(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    async fn get_database_tables(&self, _database: &Database) -> Result<Vec<Table>> {
+        Ok(vec![])
+    }
+
+    async fn get_database_columns(
+        &self,
+        _database: &Database,
+        _tables: HashSet<Table>,
+    ) -> Result<HashSet<Column>> {
+        Ok(HashSet::new())
+    }
+
+    fn get_keywords(&self) -> &[&str] {
+        self.engine.keywords()
+    }
+}
+
+/// Where `tengu schema dump` puts an object in its `.sql` layout, e.g.
+/// `sales/views/v_orders.sql`.
+pub fn object_path(schema: &str, kind: Option<ObjectKind>, name: &str) -> PathBuf {
+    let folder = match kind {
+        None => "tables",
+        Some(ObjectKind::View) => "views",
+        Some(ObjectKind::Function) => "functions",
+        Some(ObjectKind::Procedure) => "procedures",
+    };
+    PathBuf::from(schema)
+        .join(folder)
+        .join(name)
+        .with_extension("sql")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::TableSchema;
+
+    fn snapshot() -> Snapshot {
+        let column = |name: &str, table: &str, position: i32| Column {
+            name: name.to_string(),
+            table: table.to_string(),
+            schema: "sales".to_string(),
+            data_type: "integer".to_string(),
+            is_nullable: "NO".to_string(),
+            ordinal_position: position,
+            ..Column::default()
+        };
+        let orders = Table {
+            name: "orders".to_string(),
+            schema: "sales".to_string(),
+            description: None,
+        };
+        Snapshot {
+            engine: Engine::Postgres,
+            connection: "prod".to_string(),
+            database: "shop".to_string(),
+            taken_at: "2024-03-02T10:11:00+00:00".to_string(),
+            catalog: Catalog {
+                tables: vec![TableSchema {
+                    table: orders,
+                    columns: vec![
+                        column("id", "orders", 1),
+                        column("customer_id", "orders", 2),
+                    ],
+                    indexes: vec![],
+                    constraints: vec![],
+                }],
+                objects: vec![],
+            },
+            view_columns: vec![column("id", "v_orders", 1)],
+            stats: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn should_answer_catalog_questions_from_a_saved_snapshot() {
+        let path = std::env::temp_dir().join(format!("tengu-snapshot-{}.json", std::process::id()));
+        snapshot().save(&path).unwrap();
+        let service = SnapshotService::new(Engine::Postgres, path.clone());
+
+        let tables = service.get_tables().await.unwrap();
+        let columns = service
+            .get_table_columns(HashSet::from([tables[1].clone()]))
+            .await
+            .unwrap();
+        let ddl = service.get_table_ddl(&tables[0]).await.unwrap();
+        let error = service.execute("SELECT 1").await.unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["orders", "v_orders"]
+        );
+        assert_eq!(columns.len(), 1);
+        assert_eq!(
+            ddl,
+            "CREATE TABLE sales.orders (\n    id integer NOT NULL,\n    customer_id integer NOT NULL\n);\n"
+        );
+        assert!(error.to_string().contains("schema snapshot"));
+    }
+
+    #[tokio::test]
+    async fn should_group_every_tables_columns() {
+        let service = SnapshotService::from_snapshot(PathBuf::from("shop.json"), snapshot());
+
+        let columns = service.get_all_columns().await.unwrap();
+
+        let table = |name: &str| Table {
+            name: name.to_string(),
+            schema: "sales".to_string(),
+            description: None,
+        };
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[&table("orders")].len(), 2);
+        assert_eq!(columns[&table("v_orders")][0].name, "id");
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use super::table::Table;
+
+/// How big a table is and when it last changed, as far as the catalog knows.
+/// Row counts are the engine's estimates, not a `COUNT(*)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableStats {
+    pub table: Table,
+    pub rows: Option<i64>,
+    pub bytes: Option<i64>,
+    /// Last DDL change, which SQL Server keeps in `sys.objects`.
+    pub modified: Option<String>,
+    /// Last analyze, the closest Postgres has to a change date.
+    pub analyzed: Option<String>,
+}
+
+impl TableStats {
+    /// A one-line summary such as `~812.3M rows · 96.1 GB · altered 2024-03-02 10:11`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(rows) = self.rows {
+            parts.push(format!("~{} rows", compact(rows)));
+        }
+        if let Some(bytes) = self.bytes {
+            parts.push(size(bytes));
+        }
+        if let Some(modified) = &self.modified {
+            parts.push(format!("altered {}", modified));
+        }
+        if let Some(analyzed) = &self.analyzed {
+            parts.push(format!("analyzed {}", analyzed));
+        }
+        parts.join(" · ")
+    }
+}
+
+fn compact(n: i64) -> String {
+    let value = n as f64;
+    match n.abs() {
+        0..=9_999 => n.to_string(),
+        10_000..=999_999 => format!("{:.1}K", value / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1}M", value / 1e6),
+        _ => format!("{:.1}B", value / 1e9),
+    }
+}
+
+fn size(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_summarize_table_stats() {
+        let stats = TableStats {
+            table: Table {
+                name: "orders".to_string(),
+                schema: "dbo".to_string(),
+                description: None,
+            },
+            rows: Some(812_345_678),
+            bytes: Some(103_179_878_400),
+            modified: Some("2024-03-02 10:11".to_string()),
+            analyzed: None,
+        };
+        assert_eq!(
+            stats.summary(),
+            "~812.3M rows · 96.1 GB · altered 2024-03-02 10:11"
+        );
+        let empty = TableStats {
+            rows: Some(12),
+            bytes: Some(8192),
+            modified: None,
+            ..stats
+        };
+        assert_eq!(empty.summary(), "~12 rows · 8.0 KB");
+    }
+}
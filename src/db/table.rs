@@ -1,11 +1,36 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
 use super::column::Column;
 
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
+#[derive(Debug, Default, Clone, FromRow, Serialize, Deserialize)]
 pub struct Table {
     pub name: String,
     pub schema: String,
+    /// The `MS_Description` extended property or `COMMENT ON TABLE`.
+    #[sqlx(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+// A table is its schema and name, whatever the description says, so tables
+// read from the catalog match the ones named in SQL.
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.schema == other.schema
+    }
+}
+
+impl Eq for Table {}
+
+impl Hash for Table {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.schema.hash(state);
+    }
 }
 
 impl From<Column> for Table {
@@ -13,6 +38,80 @@ impl From<Column> for Table {
         Table {
             name: column.table,
             schema: column.schema,
+            description: None,
+        }
+    }
+}
+
+/// Groups what a catalog query returned for several tables by table.
+pub fn by_table<V>(rows: Vec<(Table, V)>) -> HashMap<Table, Vec<V>> {
+    let mut grouped: HashMap<Table, Vec<V>> = HashMap::new();
+    for (table, value) in rows {
+        grouped.entry(table).or_default().push(value);
+    }
+    grouped
+}
+
+/// The tables an unqualified `name` means: the one in the first schema of
+/// `search_path` that has a table by that name, or all of them when none on
+/// the path does. A `schema` picks just that schema's.
+pub fn resolve<'a>(
+    tables: impl IntoIterator<Item = &'a Table>,
+    search_path: &[String],
+    name: &str,
+    schema: Option<&str>,
+) -> Vec<Table> {
+    let mut named: Vec<Table> = tables
+        .into_iter()
+        .filter(|t| t.name.eq_ignore_ascii_case(name))
+        .filter(|t| schema.is_none_or(|s| t.schema.eq_ignore_ascii_case(s)))
+        .cloned()
+        .collect();
+    named.sort_by(|a, b| a.schema.cmp(&b.schema));
+    if schema.is_some() {
+        return named;
+    }
+    for path_schema in search_path {
+        if let Some(table) = named
+            .iter()
+            .find(|t| t.schema.eq_ignore_ascii_case(path_schema))
+        {
+            return vec![table.clone()];
         }
     }
+    named
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_resolve_unqualified_names_along_the_search_path() {
+        let table = |schema: &str, name: &str| Table {
+            name: name.to_string(),
+            schema: schema.to_string(),
+            description: None,
+        };
+        let tables = vec![
+            table("public", "events"),
+            table("audit", "events"),
+            table("audit", "logins"),
+            table("sales", "orders"),
+            table("archive", "orders"),
+        ];
+        let path = vec!["audit".to_string(), "public".to_string()];
+        assert_eq!(
+            resolve(&tables, &path, "events", None),
+            vec![table("audit", "events")]
+        );
+        assert_eq!(
+            resolve(&tables, &path, "EVENTS", Some("public")),
+            vec![table("public", "events")]
+        );
+        assert_eq!(
+            resolve(&tables, &path, "orders", None),
+            vec![table("archive", "orders"), table("sales", "orders")]
+        );
+    }
 }
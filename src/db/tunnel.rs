@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use crate::terminal_ui::models::Connection;
+
+const LOCALHOST: &str = "127.0.0.1";
+
+/// How long ssh gets to log in and start forwarding.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A jump host the database is only reachable through.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SshTunnel {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: String,
+    pub user: String,
+    /// A private key to log in with, besides ssh's own defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    /// Whether ssh may use the keys the agent holds.
+    #[serde(default)]
+    pub agent: bool,
+}
+
+fn default_port() -> String {
+    "22".to_string()
+}
+
+/// An `ssh -L` forwarding a local port to the database.
+struct Forward {
+    child: Child,
+    port: u16,
+}
+
+type ForwardsByTarget = HashMap<(SshTunnel, String, u16), Forward>;
+
+/// Forwards opened so far, reused by every connection to the same database.
+static FORWARDS: Lazy<Mutex<ForwardsByTarget>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Where to connect for `connection`: its host and port, or the local end of
+/// a forward through its SSH tunnel, opened the first time it's needed and
+/// again if ssh has exited.
+pub async fn endpoint(connection: &Connection) -> Result<(String, u16)> {
+    let port = connection.port.parse::<u16>()?;
+    let Some(tunnel) = &connection.ssh else {
+        return Ok((connection.host.clone(), port));
+    };
+    let target = (tunnel.clone(), connection.host.clone(), port);
+    let mut forwards = FORWARDS.lock().await;
+    if let Some(forward) = forwards.get_mut(&target) {
+        if forward.child.try_wait()?.is_none() {
+            return Ok((LOCALHOST.to_string(), forward.port));
+        }
+    }
+    let forward = open(tunnel, &connection.host, port).await?;
+    let local = forward.port;
+    forwards.insert(target, forward);
+    Ok((LOCALHOST.to_string(), local))
+}
+
+/// Stops every forward, for when tengu exits.
+pub async fn close_all() {
+    for (_, mut forward) in FORWARDS.lock().await.drain() {
+        let _ = forward.child.kill().await;
+    }
+}
+
+async fn open(tunnel: &SshTunnel, host: &str, port: u16) -> Result<Forward> {
+    // A port that's free now, for ssh to listen on.
+    let local = TcpListener::bind((LOCALHOST, 0))
+        .await?
+        .local_addr()?
+        .port();
+    let mut command = Command::new("ssh");
+    command.args(ssh_args(tunnel, local, host, port));
+    forward(command, &tunnel.host, local).await
+}
+
+/// Runs `command` until it listens on `local`, or says why it exited.
+async fn forward(mut command: Command, jump_host: &str, local: u16) -> Result<Forward> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Couldn't run ssh: {}", e))?;
+    // Read for as long as ssh runs, so a full pipe never blocks it.
+    let stderr = child.stderr.take().map(|mut pipe| {
+        tokio::spawn(async move {
+            let mut stderr = String::new();
+            let _ = pipe.read_to_string(&mut stderr).await;
+            stderr
+        })
+    });
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            let stderr = match stderr {
+                Some(reader) => reader.await.unwrap_or_default(),
+                None => String::new(),
+            };
+            return Err(anyhow!(
+                "SSH tunnel through {} failed ({}): {}",
+                jump_host,
+                status,
+                stderr.trim()
+            ));
+        }
+        if TcpStream::connect((LOCALHOST, local)).await.is_ok() {
+            return Ok(Forward { child, port: local });
+        }
+        if started.elapsed() > OPEN_TIMEOUT {
+            return Err(anyhow!(
+                "SSH tunnel through {} didn't open within {}s",
+                jump_host,
+                OPEN_TIMEOUT.as_secs()
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Forwards `local` to `host:port` as seen from the jump host, without a
+/// shell and without prompting. The jump host's key must already be known.
+fn ssh_args(tunnel: &SshTunnel, local: u16, host: &str, port: u16) -> Vec<String> {
+    let mut args = vec![
+        "-N".to_string(),
+        "-o".to_string(),
+        "BatchMode=yes".to_string(),
+        "-o".to_string(),
+        "ExitOnForwardFailure=yes".to_string(),
+        "-L".to_string(),
+        format!("{}:{}:{}:{}", LOCALHOST, local, host, port),
+        "-p".to_string(),
+        tunnel.port.clone(),
+    ];
+    if let Some(key) = &tunnel.key {
+        args.extend(["-i".to_string(), key.display().to_string()]);
+    }
+    if !tunnel.agent {
+        args.extend(["-o".to_string(), "IdentityAgent=none".to_string()]);
+    }
+    args.extend(["--".to_string(), format!("{}@{}", tunnel.user, tunnel.host)]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_forward_a_local_port_through_the_jump_host() {
+        let tunnel: SshTunnel = serde_json::from_str(
+            r#"{"host": "bastion.example.com", "user": "deploy", "key": "/home/me/.ssh/id_ed25519"}"#,
+        )
+        .unwrap();
+        assert_eq!(tunnel.port, "22");
+        let args = ssh_args(&tunnel, 40000, "db.internal", 5432);
+        assert!(args.contains(&"127.0.0.1:40000:db.internal:5432".to_string()));
+        assert!(args.contains(&"/home/me/.ssh/id_ed25519".to_string()));
+        assert!(args.contains(&"IdentityAgent=none".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("StrictHostKeyChecking")));
+        assert_eq!(
+            args[args.len() - 2..],
+            ["--".to_string(), "deploy@bastion.example.com".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_report_why_ssh_exited() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 'Host key verification failed.' >&2; exit 255"]);
+
+        let Err(e) = forward(command, "bastion.example.com", 1).await else {
+            panic!("expected the tunnel to fail");
+        };
+
+        let message = e.to_string();
+        assert!(message.starts_with("SSH tunnel through bastion.example.com failed"));
+        assert!(message.ends_with("Host key verification failed."));
+    }
+
+    #[tokio::test]
+    async fn should_report_ssh_that_cannot_run() {
+        let command = Command::new("/nonexistent/ssh");
+
+        let Err(e) = forward(command, "bastion.example.com", 1).await else {
+            panic!("expected the tunnel to fail");
+        };
+
+        assert!(e.to_string().starts_with("Couldn't run ssh"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Fuzzy matching for symbol and object pickers: every character of the
+//! pattern has to appear in the candidate in order, and matches at word
+//! boundaries and in runs score higher.
+
+const MATCH: i64 = 16;
+const BOUNDARY: i64 = 10;
+const CONSECUTIVE: i64 = 12;
+const START: i64 = 4;
+const SAME_CASE: i64 = 1;
+const GAP: i64 = 1;
+const NONE: i64 = i64::MIN / 2;
+
+/// Scores `candidate` against `pattern`, case-insensitively. `None` means it
+/// doesn't match at all; higher is better.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let candidate: Vec<char> = candidate.chars().collect();
+    if pattern.len() > candidate.len() {
+        return None;
+    }
+    let boundaries: Vec<bool> = (0..candidate.len())
+        .map(|j| is_boundary(&candidate, j))
+        .collect();
+    // best[j]: best score with the current pattern char matched at j.
+    let mut best = vec![NONE; candidate.len()];
+    for (i, p) in pattern.iter().enumerate() {
+        let mut next = vec![NONE; candidate.len()];
+        // Best score of the previous pattern char matched before j, with the
+        // gap since then already paid for.
+        let mut carried = NONE;
+        for (j, c) in candidate.iter().enumerate() {
+            if j > 0 {
+                carried = (carried - GAP).max(if i == 0 { NONE } else { best[j - 1] });
+            }
+            if !c.to_lowercase().eq(p.to_lowercase()) {
+                continue;
+            }
+            let mut points = MATCH;
+            if boundaries[j] {
+                points += BOUNDARY;
+            }
+            if c == p {
+                points += SAME_CASE;
+            }
+            if i == 0 && j == 0 {
+                points += START;
+            }
+            let previous = if i == 0 {
+                0
+            } else {
+                let run = if j > 0 && best[j - 1] > NONE {
+                    best[j - 1] + CONSECUTIVE
+                } else {
+                    NONE
+                };
+                run.max(carried)
+            };
+            if previous > NONE {
+                next[j] = previous + points;
+            }
+        }
+        best = next;
+    }
+    let top = best.into_iter().max().filter(|s| *s > NONE)?;
+    // Among equal matches, shorter candidates are closer to what was typed.
+    Some(top - (candidate.len() - pattern.len()) as i64 / 4)
+}
+
+fn is_boundary(candidate: &[char], j: usize) -> bool {
+    let Some(previous) = j.checked_sub(1).map(|p| candidate[p]) else {
+        return true;
+    };
+    let current = candidate[j];
+    !previous.is_alphanumeric()
+        || previous.is_lowercase() && current.is_uppercase()
+        || previous.is_alphabetic() && current.is_ascii_digit()
+}
+
+/// Sorts the matching `items` best first, dropping the ones that don't match.
+pub fn filter<T>(pattern: &str, items: Vec<T>, key: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut scored: Vec<(i64, T)> = items
+        .into_iter()
+        .filter_map(|item| Some((score(pattern, key(&item))?, item)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_subsequences_case_insensitively() {
+        assert!(score("uro", "usp_rebuild_orders").is_some());
+        assert!(score("USPREB", "usp_rebuild_orders").is_some());
+        assert!(score("ordersx", "usp_rebuild_orders").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn should_rank_boundaries_and_runs_first() {
+        let ranked = filter(
+            "rebuild",
+            vec![
+                "tbl_rebate_build",
+                "usp_rebuild_orders",
+                "rebuild_orders_log",
+            ],
+            |s| s,
+        );
+        assert_eq!(
+            ranked,
+            vec![
+                "rebuild_orders_log",
+                "usp_rebuild_orders",
+                "tbl_rebate_build"
+            ]
+        );
+        let ranked = filter("ord", vec!["tbl_word", "orders"], |s| s);
+        assert_eq!(ranked, vec!["orders", "tbl_word"]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Statements run through tengu, kept one JSON object per line under
+//! `~/.config/tengu/history` so they can be searched and run again.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::db::query::QueryResult;
+use crate::fuzzy;
+use crate::prelude::read_file_to_string;
+
+const FILE_NAME: &str = "history.jsonl";
+/// Entries kept when the file grows past `MAX_BYTES`.
+const MAX_ENTRIES: usize = 5000;
+const MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub connection: String,
+    pub sql: String,
+    /// RFC 3339, local time.
+    pub executed_at: String,
+    pub duration_ms: u64,
+    /// Rows returned or affected, when the engine says.
+    pub rows: Option<u64>,
+    pub status: Status,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        connection: &str,
+        sql: &str,
+        duration: Duration,
+        result: &Result<Vec<QueryResult>>,
+    ) -> HistoryEntry {
+        let (rows, status, error) = match result {
+            Ok(results) => {
+                let rows = results
+                    .iter()
+                    .map(|r| {
+                        if r.columns.is_empty() {
+                            r.rows_affected
+                        } else {
+                            Some(r.rows.len() as u64)
+                        }
+                    })
+                    .fold(None, |total: Option<u64>, rows| match (total, rows) {
+                        (Some(total), Some(rows)) => Some(total + rows),
+                        (total, rows) => total.or(rows),
+                    });
+                (rows, Status::Ok, None)
+            }
+            Err(e) => (None, Status::Error, Some(e.to_string())),
+        };
+        HistoryEntry {
+            connection: connection.to_string(),
+            sql: sql.trim().to_string(),
+            executed_at: chrono::Local::now().to_rfc3339(),
+            duration_ms: duration.as_millis() as u64,
+            rows,
+            status,
+            error,
+        }
+    }
+
+    /// `2024-03-02 10:11:12`, or the raw timestamp if it can't be parsed.
+    pub fn time(&self) -> String {
+        chrono::DateTime::parse_from_rfc3339(&self.executed_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.executed_at.clone())
+    }
+
+    /// The first line of the statement, cut to `width` characters.
+    pub fn summary(&self, width: usize) -> String {
+        let line = self.sql.lines().next().unwrap_or_default();
+        let mut summary: String = line.chars().take(width).collect();
+        if summary.len() < line.len() || self.sql.lines().nth(1).is_some() {
+            summary.push('…');
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn open() -> History {
+        let dir = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".config")
+            .join("tengu")
+            .join("history");
+        History::at(dir.join(FILE_NAME))
+    }
+
+    pub fn at(path: PathBuf) -> History {
+        History { path }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        if file.metadata()?.len() > MAX_BYTES {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<()> {
+        let mut entries = self.load();
+        entries.truncate(MAX_ENTRIES);
+        entries.reverse();
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(&entry)?);
+            contents.push('\n');
+        }
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+
+    /// Every entry, most recent first. Lines that can't be read are skipped.
+    pub fn load(&self) -> Vec<HistoryEntry> {
+        let Ok(contents) = read_file_to_string(self.path.clone()) else {
+            return vec![];
+        };
+        contents
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+/// Entries whose statement fuzzy-matches `query`, best first; all of them,
+/// most recent first, for an empty query.
+pub fn search(query: &str, entries: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+    if query.trim().is_empty() {
+        return entries;
+    }
+    fuzzy::filter(query, entries, |e| e.sql.as_str())
+}
+
+/// The latest successful run of each distinct statement, most recent first.
+pub fn recent_statements(entries: Vec<HistoryEntry>, limit: usize) -> Vec<HistoryEntry> {
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .into_iter()
+        .filter(|e| e.status == Status::Ok)
+        .filter(|e| seen.insert(e.sql.clone()))
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_record_and_search_history() {
+        let path = std::env::temp_dir()
+            .join(format!("tengu-history-{}", std::process::id()))
+            .join(FILE_NAME);
+        let _ = fs::remove_file(&path);
+        let history = History::at(path.clone());
+        let rows = QueryResult {
+            columns: vec!["n".to_string()],
+            rows: vec![vec![Some("1".to_string())], vec![None]],
+            ..QueryResult::default()
+        };
+        let statements = [
+            ("SELECT * FROM sys.dm_exec_requests", Ok(vec![rows])),
+            ("SELECT 1/0", Err(anyhow::anyhow!("Divide by zero"))),
+            ("SELECT * FROM sys.dm_exec_requests", Ok(vec![])),
+        ];
+        for (sql, result) in &statements {
+            let entry = HistoryEntry::new("prod", sql, Duration::from_millis(12), result);
+            history.record(&entry).unwrap();
+        }
+        let entries = history.load();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].status, Status::Error);
+        assert_eq!(entries[2].rows, Some(2));
+        let recent = recent_statements(entries.clone(), 10);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(search("divzero", entries.clone()).len(), 0);
+        assert_eq!(
+            search("dmexec", entries)[0].sql,
+            "SELECT * FROM sys.dm_exec_requests"
+        );
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
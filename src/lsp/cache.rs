@@ -1,46 +1,619 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use once_cell::sync::Lazy;
 use tokio::sync::Mutex;
-use tower_lsp::lsp_types::CompletionItem;
+use tower_lsp::lsp_types::{CompletionItem, Diagnostic, Range, Url};
 
+use crate::db::column::Column;
+use crate::db::database::Database;
+use crate::db::index::{Constraint, Index};
+use crate::db::object::DbObject;
 use crate::db::service::{Service, TenguService};
-use crate::db::table::Table;
+use crate::db::stats::TableStats;
+use crate::db::table::{resolve, Table};
+use crate::history::HistoryEntry;
+use crate::snippet::Snippet;
+use crate::terminal_ui::models::Connection;
 use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
 
 pub static ALL_TABLES: Lazy<Arc<Mutex<HashSet<Table>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
 
+/// Views and routines of the active connection.
+pub static ALL_OBJECTS: Lazy<Arc<Mutex<HashSet<DbObject>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
+
+/// Schemas unqualified names resolve in, in order.
+pub static SEARCH_PATH: Lazy<Arc<Mutex<Vec<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
 pub static TABLES_IN_FILE: Lazy<Arc<Mutex<HashSet<Table>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashSet::new())));
 
 pub static ALL_COLUMNS: Lazy<Arc<Mutex<Vec<CompletionItem>>>> =
     Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
 
+/// Text of the documents open in the editor, which may differ from what's on disk.
+pub static DOCUMENTS: Lazy<Arc<Mutex<HashMap<Url, String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// What's cached of a database's catalog, put aside while the connection is
+/// on another database of the same server.
+#[derive(Debug, Default)]
+struct Catalog {
+    tables: HashSet<Table>,
+    objects: HashSet<DbObject>,
+    search_path: Vec<String>,
+    columns: ColumnsByTable,
+    constraints: ConstraintsByTable,
+    indexes: IndexesByTable,
+}
+
+type CatalogsByDatabase = HashMap<(String, String), (Instant, Catalog)>;
+
+/// Catalogs put aside, by server and database, and when.
+static CATALOGS: Lazy<Arc<Mutex<CatalogsByDatabase>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// How long a catalog put aside is restored instead of queried again.
+const CATALOG_TTL: Duration = Duration::from_secs(300);
+
+/// The connection `.active` last named, kept while it's being rewritten.
+static ACTIVE_CONNECTION: Lazy<Arc<Mutex<Option<Connection>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// The connection whose catalog is loaded now.
+pub static LOADED_CONNECTION: Lazy<Arc<Mutex<Option<Connection>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+/// Columns of the tables the linter has looked at, so editing a file doesn't
+/// query the catalog on every keystroke.
+type ColumnsByTable = HashMap<Table, Vec<Column>>;
+
+pub static TABLE_COLUMNS: Lazy<Arc<Mutex<ColumnsByTable>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type ConstraintsByTable = HashMap<Table, Vec<Constraint>>;
+
+/// Constraints of the tables hovered over, for their keys.
+pub static TABLE_CONSTRAINTS: Lazy<Arc<Mutex<ConstraintsByTable>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type IndexesByTable = HashMap<Table, Vec<Index>>;
+
+/// Indexes of the tables hovered over or linted.
+pub static TABLE_INDEXES: Lazy<Arc<Mutex<IndexesByTable>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type DatabasesByServer = HashMap<Option<String>, Vec<String>>;
+
+/// Databases on the connection's server, under `None`, and on the linked
+/// servers asked about.
+pub static DATABASES: Lazy<Arc<Mutex<DatabasesByServer>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+pub static LINKED_SERVERS: Lazy<Arc<Mutex<Option<Vec<String>>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+type TablesByDatabase = HashMap<Database, Vec<Table>>;
+
+/// Tables of the other databases that three- and four-part names reach,
+/// loaded the first time a name needs them.
+pub static DATABASE_TABLES: Lazy<Arc<Mutex<TablesByDatabase>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type ColumnsByDatabaseTable = HashMap<(Database, Table), Vec<Column>>;
+
+pub static DATABASE_COLUMNS: Lazy<Arc<Mutex<ColumnsByDatabaseTable>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// Root folders of the workspace the editor opened.
+pub static WORKSPACE_FOLDERS: Lazy<Arc<Mutex<Vec<PathBuf>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(Vec::new())));
+
+/// An object a workspace file creates: its name, what it is and where.
+#[derive(Debug, Clone)]
+pub struct FileDefinition {
+    pub name: String,
+    pub object: String,
+    pub range: Range,
+}
+
+type DefinitionsByFile = HashMap<PathBuf, (SystemTime, Vec<FileDefinition>)>;
+
+/// Definitions found in workspace files, kept until the file changes on disk.
+pub static FILE_DEFINITIONS: Lazy<Arc<Mutex<DefinitionsByFile>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+type FetchedStats = Option<(Instant, HashMap<Table, TableStats>)>;
+
+/// Table sizes shown in code lenses and when they were fetched, `None` until
+/// a lens first needs them.
+pub static TABLE_STATS: Lazy<Arc<Mutex<FetchedStats>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+type RecentStatements = Option<(SystemTime, Vec<HistoryEntry>)>;
+
+/// Statements offered as completions, kept until the history file changes.
+pub static RECENT_STATEMENTS: Lazy<Arc<Mutex<RecentStatements>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+type NameUsage = Option<(SystemTime, HashMap<String, usize>)>;
+
+/// How often each name appears in the history, for ranking completions.
+pub static NAME_USAGE: Lazy<Arc<Mutex<NameUsage>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+type SavedSnippets = Option<(SystemTime, Vec<Snippet>)>;
+
+/// Every saved snippet, kept until the snippets file changes.
+pub static SNIPPETS: Lazy<Arc<Mutex<SavedSnippets>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+
+type DiagnosticsByFile = HashMap<Url, Vec<Diagnostic>>;
+
+/// Schema drift found in migration scripts, shown with their lint diagnostics.
+pub static DRIFT: Lazy<Arc<Mutex<DiagnosticsByFile>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+/// Held by tests that fill the caches, which they all share.
+#[cfg(test)]
+pub static TEST_CACHE: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// A service over a snapshot of `tables`, with the cache loaded from it.
+#[cfg(test)]
+pub async fn load_test_catalog(
+    engine: crate::db::engine::Engine,
+    tables: Vec<crate::db::catalog::TableSchema>,
+) -> TenguService {
+    let service = test_service(engine, tables);
+    reload_catalog(&service).await;
+    service
+}
+
+/// A service over a snapshot of `tables`.
+#[cfg(test)]
+pub fn test_service(
+    engine: crate::db::engine::Engine,
+    tables: Vec<crate::db::catalog::TableSchema>,
+) -> TenguService {
+    use crate::db::catalog::Catalog;
+    use crate::db::snapshot::{Snapshot, SnapshotService};
+
+    let snapshot = Snapshot {
+        engine,
+        connection: "test".to_string(),
+        database: "test".to_string(),
+        taken_at: String::new(),
+        catalog: Catalog {
+            tables,
+            objects: vec![],
+        },
+        view_columns: vec![],
+        stats: vec![],
+    };
+    TenguService::Snapshot(SnapshotService::from_snapshot(
+        PathBuf::from("test.json"),
+        snapshot,
+    ))
+}
+
 pub async fn reset_cache(e: notify::Result<notify::Event>) {
     match e {
         Ok(_) => {
-            let mut all_tables = ALL_TABLES.lock().await;
-            all_tables.clear();
-            let mut tables_in_file = TABLES_IN_FILE.lock().await;
-            tables_in_file.clear();
-            let mut all_columns = ALL_COLUMNS.lock().await;
-            all_columns.clear();
             let repo = FsTenguRepository::new();
             let Some(active_conn) = repo.get_active_connection() else {
+                unload_catalog().await;
                 return;
             };
-            let service = TenguService::new(active_conn.engine, repo);
-
-            if let Ok(tables) = service.get_tables().await {
-                for table in tables {
-                    all_tables.insert(table);
-                }
-            }
+            let service = TenguService::new(active_conn.engine.clone(), repo);
+            switch_catalog(&active_conn, &service).await;
         }
         Err(e) => {
             println!("watch error: {:?}", e);
         }
     }
 }
+
+/// Puts the loaded catalog aside and empties the cache, for when no
+/// connection is active. Writing `.active` empties it first, so a switch
+/// passes through here.
+async fn unload_catalog() {
+    if let Some(loaded) = LOADED_CONNECTION.lock().await.take() {
+        stash_catalog(&loaded).await;
+    }
+    clear_catalog().await;
+}
+
+/// Loads the catalog of `connection`'s database. Moving to another database
+/// on the same server keeps the one left behind for `CATALOG_TTL`, and moving
+/// back within it restores it instead of querying again. Activating the same
+/// database again always reloads, which is how a catalog is refreshed after
+/// DDL.
+async fn switch_catalog(connection: &Connection, service: &TenguService) {
+    let previous = ACTIVE_CONNECTION.lock().await.replace(connection.clone());
+    let loaded = LOADED_CONNECTION.lock().await.replace(connection.clone());
+    let same = |other: &Option<Connection>| {
+        other
+            .as_ref()
+            .is_some_and(|o| o.server() == connection.server() && o.database == connection.database)
+    };
+    let reactivated = same(&previous) || same(&loaded);
+    if let Some(loaded) = loaded.filter(|l| l.server() == connection.server() && !reactivated) {
+        stash_catalog(&loaded).await;
+    }
+
+    let server = connection.server();
+    let stashed = {
+        let mut catalogs = CATALOGS.lock().await;
+        catalogs
+            .retain(|(s, _), (stashed_at, _)| *s == server && stashed_at.elapsed() <= CATALOG_TTL);
+        catalogs.remove(&(server, connection.database.clone()))
+    };
+    match stashed {
+        Some((_, catalog)) if !reactivated => {
+            clear_catalog().await;
+            restore_catalog(catalog).await;
+        }
+        _ => reload_catalog(service).await,
+    }
+}
+
+/// Moves the cached catalog aside as `connection`'s, leaving the cache empty.
+async fn stash_catalog(connection: &Connection) {
+    let catalog = take_catalog().await;
+    CATALOGS.lock().await.insert(
+        (connection.server(), connection.database.clone()),
+        (Instant::now(), catalog),
+    );
+}
+
+async fn take_catalog() -> Catalog {
+    Catalog {
+        tables: std::mem::take(&mut *ALL_TABLES.lock().await),
+        objects: std::mem::take(&mut *ALL_OBJECTS.lock().await),
+        search_path: std::mem::take(&mut *SEARCH_PATH.lock().await),
+        columns: std::mem::take(&mut *TABLE_COLUMNS.lock().await),
+        constraints: std::mem::take(&mut *TABLE_CONSTRAINTS.lock().await),
+        indexes: std::mem::take(&mut *TABLE_INDEXES.lock().await),
+    }
+}
+
+async fn restore_catalog(catalog: Catalog) {
+    *ALL_TABLES.lock().await = catalog.tables;
+    *ALL_OBJECTS.lock().await = catalog.objects;
+    *SEARCH_PATH.lock().await = catalog.search_path;
+    *TABLE_COLUMNS.lock().await = catalog.columns;
+    *TABLE_CONSTRAINTS.lock().await = catalog.constraints;
+    *TABLE_INDEXES.lock().await = catalog.indexes;
+}
+
+/// Forgets what's cached of the catalog and loads the tables and objects
+/// again from `service`.
+pub async fn reload_catalog(service: &TenguService) {
+    clear_catalog().await;
+    if let Ok(tables) = service.get_tables().await {
+        ALL_TABLES.lock().await.extend(tables);
+    }
+    if let Ok(objects) = service.get_objects().await {
+        ALL_OBJECTS.lock().await.extend(objects);
+    }
+    if let Ok(search_path) = service.get_search_path().await {
+        *SEARCH_PATH.lock().await = search_path;
+    }
+}
+
+/// The search path, or the engine's default schema before it's known.
+pub async fn search_path(service: &TenguService) -> Vec<String> {
+    let search_path = SEARCH_PATH.lock().await;
+    if search_path.is_empty() {
+        vec![service.engine().default_schema().to_string()]
+    } else {
+        search_path.clone()
+    }
+}
+
+/// The catalog's tables `name` means, along the search path unless `schema`
+/// is given.
+pub async fn resolve_tables(
+    service: &TenguService,
+    name: &str,
+    schema: Option<&str>,
+) -> Vec<Table> {
+    let search_path = search_path(service).await;
+    resolve(ALL_TABLES.lock().await.iter(), &search_path, name, schema)
+}
+
+/// Columns of `tables`, sorted by name, querying the catalog only for the
+/// tables that aren't cached yet.
+pub async fn cached_columns(service: &TenguService, tables: &[Table]) -> Vec<Column> {
+    load_columns(service, tables).await;
+    known_columns(tables).await
+}
+
+/// Queries the columns of the `tables` that aren't cached yet. The cache
+/// isn't locked while the catalog answers, so readers don't wait on it.
+pub async fn load_columns(service: &TenguService, tables: &[Table]) {
+    let missing: HashSet<Table> = {
+        let table_columns = TABLE_COLUMNS.lock().await;
+        tables
+            .iter()
+            .filter(|t| !table_columns.contains_key(t))
+            .cloned()
+            .collect()
+    };
+    if missing.is_empty() {
+        return;
+    }
+    let Ok(columns) = service.get_table_columns(missing.clone()).await else {
+        return;
+    };
+    let mut table_columns = TABLE_COLUMNS.lock().await;
+    for table in missing {
+        let mut of_table: Vec<_> = columns
+            .iter()
+            .filter(|c| c.table == table.name && c.schema == table.schema)
+            .cloned()
+            .collect();
+        of_table.sort_by(|a, b| a.name.cmp(&b.name));
+        table_columns.insert(table, of_table);
+    }
+}
+
+/// Columns of `tables` as far as they're cached, without querying.
+pub async fn known_columns(tables: &[Table]) -> Vec<Column> {
+    let table_columns = TABLE_COLUMNS.lock().await;
+    tables
+        .iter()
+        .filter_map(|t| table_columns.get(t))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Constraints of `table`, queried once.
+pub async fn cached_constraints(service: &TenguService, table: &Table) -> Vec<Constraint> {
+    if let Some(constraints) = TABLE_CONSTRAINTS.lock().await.get(table) {
+        return constraints.clone();
+    }
+    let Ok(constraints) = service.get_constraints(table).await else {
+        return vec![];
+    };
+    TABLE_CONSTRAINTS
+        .lock()
+        .await
+        .insert(table.clone(), constraints.clone());
+    constraints
+}
+
+/// Indexes of `table`, queried once.
+pub async fn cached_indexes(service: &TenguService, table: &Table) -> Vec<Index> {
+    if let Some(indexes) = known_indexes(table).await {
+        return indexes;
+    }
+    let Ok(indexes) = service.get_indexes(table).await else {
+        return vec![];
+    };
+    TABLE_INDEXES
+        .lock()
+        .await
+        .insert(table.clone(), indexes.clone());
+    indexes
+}
+
+/// Indexes of `table` if they're cached, without querying.
+pub async fn known_indexes(table: &Table) -> Option<Vec<Index>> {
+    TABLE_INDEXES.lock().await.get(table).cloned()
+}
+
+/// Databases on `server`, or on the connection's server, queried once.
+pub async fn cached_databases(service: &TenguService, server: Option<&str>) -> Vec<String> {
+    let key = server.map(str::to_lowercase);
+    let mut databases = DATABASES.lock().await;
+    if let Some(names) = databases.get(&key) {
+        return names.clone();
+    }
+    let names = service.get_databases(server).await.unwrap_or_default();
+    databases.insert(key, names.clone());
+    names
+}
+
+pub async fn cached_linked_servers(service: &TenguService) -> Vec<String> {
+    let mut servers = LINKED_SERVERS.lock().await;
+    if let Some(names) = servers.as_ref() {
+        return names.clone();
+    }
+    let names = service.get_linked_servers().await.unwrap_or_default();
+    *servers = Some(names.clone());
+    names
+}
+
+/// Tables of another database, queried once.
+pub async fn cached_database_tables(service: &TenguService, database: &Database) -> Vec<Table> {
+    let mut database_tables = DATABASE_TABLES.lock().await;
+    if let Some(tables) = database_tables.get(&database.key()) {
+        return tables.clone();
+    }
+    let Ok(tables) = service.get_database_tables(database).await else {
+        return vec![];
+    };
+    database_tables.insert(database.key(), tables.clone());
+    tables
+}
+
+/// Columns of `tables` in another database, queried once per table.
+pub async fn cached_database_columns(
+    service: &TenguService,
+    database: &Database,
+    tables: &[Table],
+) -> Vec<Column> {
+    let mut database_columns = DATABASE_COLUMNS.lock().await;
+    let key = |table: &Table| (database.key(), table.clone());
+    let missing: HashSet<Table> = tables
+        .iter()
+        .filter(|t| !database_columns.contains_key(&key(t)))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        if let Ok(columns) = service
+            .get_database_columns(database, missing.clone())
+            .await
+        {
+            for table in missing {
+                let mut of_table: Vec<_> = columns
+                    .iter()
+                    .filter(|c| c.table == table.name && c.schema == table.schema)
+                    .cloned()
+                    .collect();
+                of_table.sort_by(|a, b| a.name.cmp(&b.name));
+                database_columns.insert(key(&table), of_table);
+            }
+        }
+    }
+    tables
+        .iter()
+        .filter_map(|t| database_columns.get(&key(t)))
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+async fn clear_catalog() {
+    ALL_TABLES.lock().await.clear();
+    SEARCH_PATH.lock().await.clear();
+    TABLES_IN_FILE.lock().await.clear();
+    ALL_COLUMNS.lock().await.clear();
+    TABLE_COLUMNS.lock().await.clear();
+    TABLE_CONSTRAINTS.lock().await.clear();
+    TABLE_INDEXES.lock().await.clear();
+    DATABASES.lock().await.clear();
+    *LINKED_SERVERS.lock().await = None;
+    DATABASE_TABLES.lock().await.clear();
+    DATABASE_COLUMNS.lock().await.clear();
+    *TABLE_STATS.lock().await = None;
+    ALL_OBJECTS.lock().await.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::TableSchema;
+    use crate::db::engine::Engine;
+
+    fn connection(host: &str, database: &str) -> Connection {
+        Connection::new(
+            "local".to_string(),
+            Engine::Postgres,
+            host.to_string(),
+            "5432".to_string(),
+            "postgres".to_string(),
+            "secret".to_string(),
+            database.to_string(),
+        )
+    }
+
+    fn service(table: &str) -> TenguService {
+        test_service(
+            Engine::Postgres,
+            vec![TableSchema {
+                table: Table {
+                    name: table.to_string(),
+                    schema: "public".to_string(),
+                    description: None,
+                },
+                columns: vec![],
+                indexes: vec![],
+                constraints: vec![],
+            }],
+        )
+    }
+
+    async fn loaded_tables() -> Vec<String> {
+        let mut tables: Vec<_> = ALL_TABLES
+            .lock()
+            .await
+            .iter()
+            .map(|t| t.name.clone())
+            .collect();
+        tables.sort();
+        tables
+    }
+
+    async fn forget_connections() {
+        *LOADED_CONNECTION.lock().await = None;
+        *ACTIVE_CONNECTION.lock().await = None;
+        CATALOGS.lock().await.clear();
+    }
+
+    #[tokio::test]
+    async fn should_restore_catalog_when_switching_back() {
+        let _cache = TEST_CACHE.lock().await;
+        forget_connections().await;
+        let shop = connection("db", "shop");
+
+        switch_catalog(&shop, &service("orders")).await;
+        switch_catalog(&shop.on_database("crm"), &service("leads")).await;
+        assert_eq!(loaded_tables().await, vec!["leads"]);
+
+        switch_catalog(&shop, &service("refunds")).await;
+        assert_eq!(loaded_tables().await, vec!["orders"]);
+    }
+
+    #[tokio::test]
+    async fn should_restore_catalog_after_active_file_is_emptied() {
+        let _cache = TEST_CACHE.lock().await;
+        forget_connections().await;
+        let shop = connection("db", "shop");
+
+        switch_catalog(&shop, &service("orders")).await;
+        unload_catalog().await;
+        switch_catalog(&shop.on_database("crm"), &service("leads")).await;
+        unload_catalog().await;
+        switch_catalog(&shop, &service("refunds")).await;
+
+        assert_eq!(loaded_tables().await, vec!["orders"]);
+    }
+
+    #[tokio::test]
+    async fn should_reload_catalog_when_same_database_is_activated_again() {
+        let _cache = TEST_CACHE.lock().await;
+        forget_connections().await;
+        let shop = connection("db", "shop");
+
+        switch_catalog(&shop, &service("orders")).await;
+        switch_catalog(&shop, &service("refunds")).await;
+        assert_eq!(loaded_tables().await, vec!["refunds"]);
+
+        unload_catalog().await;
+        switch_catalog(&shop, &service("payments")).await;
+        assert_eq!(loaded_tables().await, vec!["payments"]);
+    }
+
+    #[tokio::test]
+    async fn should_forget_catalogs_when_server_changes() {
+        let _cache = TEST_CACHE.lock().await;
+        forget_connections().await;
+        let shop = connection("db", "shop");
+
+        switch_catalog(&shop, &service("orders")).await;
+        switch_catalog(&shop.on_database("crm"), &service("leads")).await;
+        switch_catalog(&connection("replica", "shop"), &service("archive")).await;
+        assert_eq!(loaded_tables().await, vec!["archive"]);
+        assert!(CATALOGS.lock().await.is_empty());
+
+        switch_catalog(&shop, &service("refunds")).await;
+        assert_eq!(loaded_tables().await, vec!["refunds"]);
+    }
+
+    #[tokio::test]
+    async fn should_clear_catalog_without_active_connection() {
+        let _cache = TEST_CACHE.lock().await;
+        forget_connections().await;
+
+        switch_catalog(&connection("db", "shop"), &service("orders")).await;
+        unload_catalog().await;
+
+        assert!(loaded_tables().await.is_empty());
+        assert!(LOADED_CONNECTION.lock().await.is_none());
+    }
+}
@@ -0,0 +1,254 @@
+use std::fs;
+
+use anyhow::Result;
+use serde_json::Value;
+use tower_lsp::lsp_types::{CodeLens, Command, Position, Range, Url};
+
+use super::document::offset_at;
+use crate::db::engine::Engine;
+use crate::db::object::ObjectKind;
+use crate::sql::statement::parse;
+
+pub const RUN_STATEMENT: &str = "tengu.runStatement";
+pub const RUN_FILE: &str = "tengu.runFile";
+pub const RUN_SELECTION: &str = "tengu.runSelection";
+pub const EXPLAIN_STATEMENT: &str = "tengu.explainStatement";
+/// `tengu.switchDatabase [database]` moves the active connection to another
+/// database on its server; without an argument it returns their names.
+pub const SWITCH_DATABASE: &str = "tengu.switchDatabase";
+/// `tengu.showDdl [uri]` opens the DDL of the catalog object a `tengu-ddl:`
+/// URI from the workspace symbols names.
+pub const SHOW_DDL: &str = "tengu.showDdl";
+
+pub const COMMANDS: &[&str] = &[
+    RUN_STATEMENT,
+    RUN_FILE,
+    RUN_SELECTION,
+    EXPLAIN_STATEMENT,
+    SWITCH_DATABASE,
+    SHOW_DDL,
+];
+
+const DDL_SCHEME: &str = "tengu-ddl";
+
+/// A table, or a view or routine, of the catalog, as named by a
+/// `tengu-ddl:///table?schema=dbo&name=orders` URI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DdlTarget {
+    /// `None` for a table.
+    pub kind: Option<ObjectKind>,
+    pub schema: String,
+    pub name: String,
+}
+
+impl DdlTarget {
+    pub fn uri(&self) -> Option<Url> {
+        let kind = match self.kind {
+            None => "table",
+            Some(ObjectKind::View) => "view",
+            Some(ObjectKind::Procedure) => "procedure",
+            Some(ObjectKind::Function) => "function",
+        };
+        Url::parse_with_params(
+            &format!("{}:///{}", DDL_SCHEME, kind),
+            [("schema", &self.schema), ("name", &self.name)],
+        )
+        .ok()
+    }
+
+    pub fn parse(uri: &Url) -> Option<DdlTarget> {
+        if uri.scheme() != DDL_SCHEME {
+            return None;
+        }
+        let kind = match uri.path().trim_start_matches('/') {
+            "table" => None,
+            "view" => Some(ObjectKind::View),
+            "procedure" => Some(ObjectKind::Procedure),
+            "function" => Some(ObjectKind::Function),
+            _ => return None,
+        };
+        let param = |key: &str| {
+            uri.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+        Some(DdlTarget {
+            kind,
+            schema: param("schema")?,
+            name: param("name")?,
+        })
+    }
+}
+
+/// A run command and its arguments:
+/// `tengu.runStatement [uri, position]`, `tengu.runFile [uri]` and
+/// `tengu.runSelection [uri, range]`. `tengu.explainStatement` takes the same
+/// arguments as `tengu.runStatement`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Run {
+    Statement(Url, Position),
+    File(Url),
+    Selection(Url, Range),
+}
+
+impl Run {
+    pub fn parse(command: &str, arguments: &[Value]) -> Option<Run> {
+        let uri: Url = serde_json::from_value(arguments.first()?.clone()).ok()?;
+        let second = || arguments.get(1).cloned();
+        match command {
+            RUN_STATEMENT | EXPLAIN_STATEMENT => {
+                Some(Run::Statement(uri, serde_json::from_value(second()?).ok()?))
+            }
+            RUN_FILE => Some(Run::File(uri)),
+            RUN_SELECTION => Some(Run::Selection(uri, serde_json::from_value(second()?).ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn uri(&self) -> &Url {
+        match self {
+            Run::Statement(uri, _) | Run::File(uri) | Run::Selection(uri, _) => uri,
+        }
+    }
+
+    /// The scripts to send to the server, one per T-SQL batch since `GO`
+    /// isn't something the server understands.
+    pub fn scripts(&self, text: &str, engine: &Engine) -> Vec<String> {
+        match self {
+            Run::Statement(_, position) => parse(text, engine)
+                .into_iter()
+                .find(|s| s.contains(*position))
+                .map(|s| vec![s.text(text).to_string()])
+                .unwrap_or_default(),
+            Run::File(_) => batches(text, engine),
+            Run::Selection(_, range) => {
+                let start = offset_at(text, range.start);
+                let end = offset_at(text, range.end).max(start);
+                batches(&text[start..end], engine)
+            }
+        }
+    }
+}
+
+fn batches(sql: &str, engine: &Engine) -> Vec<String> {
+    let statements = parse(sql, engine);
+    let mut scripts: Vec<String> = Vec::new();
+    let mut batch = None;
+    let mut start = 0;
+    let mut end = 0;
+    for statement in &statements {
+        let (Some(first), Some(last)) = (statement.tokens.first(), statement.tokens.last()) else {
+            continue;
+        };
+        if batch != Some(statement.batch) {
+            if batch.is_some() {
+                scripts.push(sql[start..end].to_string());
+            }
+            batch = Some(statement.batch);
+            start = first.offset;
+        }
+        end = last.offset + last.text.len();
+    }
+    if batch.is_some() {
+        scripts.push(sql[start..end].to_string());
+    }
+    scripts
+}
+
+/// "Run" and "Explain" lenses above every statement of the document.
+pub fn code_lenses(uri: &Url, text: &str, engine: &Engine) -> Vec<CodeLens> {
+    parse(text, engine)
+        .iter()
+        .flat_map(|statement| {
+            let start = statement.range().start;
+            let lens = |title: &str, command: &str| CodeLens {
+                range: Range::new(start, start),
+                command: Some(Command {
+                    title: title.to_string(),
+                    command: command.to_string(),
+                    arguments: Some(vec![
+                        serde_json::to_value(uri).unwrap_or_default(),
+                        serde_json::to_value(start).unwrap_or_default(),
+                    ]),
+                }),
+                data: None,
+            };
+            [
+                lens("▶ Run", RUN_STATEMENT),
+                lens("Explain", EXPLAIN_STATEMENT),
+            ]
+        })
+        .collect()
+}
+
+/// Writes results or a plan to a new file in the temp folder for the editor
+/// to open.
+pub fn write_results(results: &str, extension: &str) -> Result<Url> {
+    let dir = std::env::temp_dir().join("tengu");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "results-{}.{}",
+        chrono::Local::now().format("%Y%m%d%H%M%S%3f"),
+        extension
+    ));
+    fs::write(&path, results)?;
+    Url::from_file_path(&path).map_err(|_| anyhow::anyhow!("Invalid path {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_pick_the_scripts_to_run() {
+        let text = "SELECT 1\nSELECT 2 -- two\nGO\nUPDATE t SET a = 1";
+        let uri = Url::parse("file:///tmp/a.sql").unwrap();
+        let statement = Run::Statement(uri.clone(), Position::new(1, 3));
+        assert_eq!(
+            statement.scripts(text, &Engine::SqlServer),
+            vec!["SELECT 2"]
+        );
+        let file = Run::File(uri.clone());
+        assert_eq!(
+            file.scripts(text, &Engine::SqlServer),
+            vec!["SELECT 1\nSELECT 2", "UPDATE t SET a = 1"]
+        );
+        let selection = Run::Selection(uri, Range::new(Position::new(1, 0), Position::new(1, 8)));
+        assert_eq!(
+            selection.scripts(text, &Engine::SqlServer),
+            vec!["SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn should_parse_command_arguments() {
+        let arguments = vec![
+            Value::String("file:///tmp/a.sql".to_string()),
+            serde_json::json!({"line": 2, "character": 4}),
+        ];
+        assert_eq!(
+            Run::parse(RUN_STATEMENT, &arguments),
+            Some(Run::Statement(
+                Url::parse("file:///tmp/a.sql").unwrap(),
+                Position::new(2, 4)
+            ))
+        );
+        assert_eq!(Run::parse("tengu.unknown", &arguments), None);
+    }
+
+    #[test]
+    fn should_round_trip_ddl_uris() {
+        let target = DdlTarget {
+            kind: Some(ObjectKind::Procedure),
+            schema: "dbo".to_string(),
+            name: "usp rebuild&orders".to_string(),
+        };
+        let uri = target.uri().unwrap();
+        assert_eq!(uri.scheme(), "tengu-ddl");
+        assert_eq!(DdlTarget::parse(&uri), Some(target));
+        assert_eq!(
+            DdlTarget::parse(&Url::parse("file:///tmp/a.sql").unwrap()),
+            None
+        );
+    }
+}
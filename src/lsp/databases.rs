@@ -0,0 +1,230 @@
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, Documentation,
+    InsertTextFormat, Position,
+};
+
+use super::cache::{
+    cached_database_columns, cached_database_tables, cached_databases, cached_linked_servers,
+};
+use super::ranking::{statement_at, CompletionContext, Slot};
+use crate::db::database::Database;
+use crate::db::engine::Engine;
+use crate::db::service::TenguService;
+use crate::db::table::Table;
+
+/// Databases and linked servers where a table can go, what a three- or
+/// four-part name typed so far reaches, and the columns of other databases'
+/// tables the statement reads. Each database is only asked about once.
+pub async fn database_completions(
+    text: &str,
+    position: Position,
+    context: &CompletionContext,
+    engine: &Engine,
+    service: &TenguService,
+) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    let path = context.path.join(".");
+    let item = |label: &str, kind, detail: &str| CompletionItem {
+        label: label.to_string(),
+        kind: Some(kind),
+        detail: Some(detail.to_string()),
+        insert_text: Some(engine.quote(label)),
+        insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+        ..CompletionItem::default()
+    };
+    match context.path.as_slice() {
+        [] if context.slot == Slot::Table => {
+            for name in cached_databases(service, None).await {
+                items.push(item(&name, CompletionItemKind::MODULE, "database"));
+            }
+            for name in cached_linked_servers(service).await {
+                items.push(item(&name, CompletionItemKind::MODULE, "linked server"));
+            }
+        }
+        [first] if context.slot == Slot::Table => {
+            if let Some(database) = database(service, None, first).await {
+                for schema in schemas(&cached_database_tables(service, &database).await) {
+                    items.push(item(&schema, CompletionItemKind::FOLDER, &path));
+                }
+            }
+            if linked_server(service, first).await {
+                for name in cached_databases(service, Some(first)).await {
+                    items.push(item(&name, CompletionItemKind::MODULE, &path));
+                }
+            }
+        }
+        [first, second] => {
+            if let Some(database) = database(service, None, first).await {
+                for table in cached_database_tables(service, &database).await {
+                    if table.schema.eq_ignore_ascii_case(second) {
+                        items.push(table_item(&table, &path, engine));
+                    }
+                }
+            } else if linked_server(service, first).await {
+                let database = Database::new(Some(first.clone()), second.clone());
+                for schema in schemas(&cached_database_tables(service, &database).await) {
+                    items.push(item(&schema, CompletionItemKind::FOLDER, &path));
+                }
+            }
+        }
+        [server, name, schema] => {
+            let database = Database::new(Some(server.clone()), name.clone());
+            for table in cached_database_tables(service, &database).await {
+                if table.schema.eq_ignore_ascii_case(schema) {
+                    items.push(table_item(&table, &path, engine));
+                }
+            }
+        }
+        _ => {}
+    }
+    if context.slot == Slot::Column {
+        items.extend(statement_columns(text, position, engine, service).await);
+    }
+    items
+}
+
+/// The database called `name` on `server`, if there's one.
+async fn database(service: &TenguService, server: Option<&str>, name: &str) -> Option<Database> {
+    cached_databases(service, server)
+        .await
+        .into_iter()
+        .find(|d| d.eq_ignore_ascii_case(name))
+        .map(|d| Database::new(server.map(str::to_string), d))
+}
+
+async fn linked_server(service: &TenguService, name: &str) -> bool {
+    cached_linked_servers(service)
+        .await
+        .iter()
+        .any(|s| s.eq_ignore_ascii_case(name))
+}
+
+fn schemas(tables: &[Table]) -> Vec<String> {
+    let mut schemas: Vec<String> = tables.iter().map(|t| t.schema.clone()).collect();
+    schemas.sort();
+    schemas.dedup();
+    schemas
+}
+
+fn table_item(table: &Table, path: &str, engine: &Engine) -> CompletionItem {
+    CompletionItem {
+        label: table.name.clone(),
+        kind: Some(CompletionItemKind::CLASS),
+        detail: Some(path.to_string()),
+        documentation: table.description.clone().map(Documentation::String),
+        insert_text: Some(engine.quote(&table.name)),
+        insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+        ..CompletionItem::default()
+    }
+}
+
+/// Columns of the tables the statement names with three or four parts.
+async fn statement_columns(
+    text: &str,
+    position: Position,
+    engine: &Engine,
+    service: &TenguService,
+) -> Vec<CompletionItem> {
+    let Some(statement) = statement_at(text, position, engine) else {
+        return vec![];
+    };
+    let mut items = Vec::new();
+    for r in statement.table_refs().iter().filter(|r| !r.function) {
+        let (Some(name), Some(schema)) = (r.name.database(), r.name.schema()) else {
+            continue;
+        };
+        let database = Database::new(r.name.server(), name);
+        // The catalog's spelling of the table, to match its columns.
+        let Some(table) = cached_database_tables(service, &database)
+            .await
+            .into_iter()
+            .find(|t| {
+                t.name.eq_ignore_ascii_case(&r.name.name())
+                    && t.schema.eq_ignore_ascii_case(&schema)
+            })
+        else {
+            continue;
+        };
+        for column in cached_database_columns(service, &database, &[table]).await {
+            items.push(CompletionItem {
+                label: column.name.clone(),
+                label_details: Some(CompletionItemLabelDetails {
+                    detail: Some(column.table.clone()),
+                    ..CompletionItemLabelDetails::default()
+                }),
+                kind: Some(CompletionItemKind::PROPERTY),
+                documentation: column.description.clone().map(Documentation::String),
+                insert_text: Some(column.name.clone()),
+                insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+                ..CompletionItem::default()
+            });
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::column::Column;
+    use crate::lsp::cache::{
+        load_test_catalog, DATABASES, DATABASE_COLUMNS, DATABASE_TABLES, LINKED_SERVERS, TEST_CACHE,
+    };
+    use crate::lsp::ranking::completion_context;
+
+    #[tokio::test]
+    async fn should_complete_names_reaching_into_other_databases() {
+        let _cache = TEST_CACHE.lock().await;
+        let service = load_test_catalog(Engine::SqlServer, vec![]).await;
+        let table = |schema: &str, name: &str| Table {
+            name: name.to_string(),
+            schema: schema.to_string(),
+            description: None,
+        };
+        let sales = Database::new(None, "Sales".to_string());
+        DATABASES
+            .lock()
+            .await
+            .insert(None, vec!["Sales".to_string(), "Archive".to_string()]);
+        *LINKED_SERVERS.lock().await = Some(vec!["REMOTE".to_string()]);
+        DATABASE_TABLES.lock().await.insert(
+            sales.key(),
+            vec![table("dbo", "orders"), table("audit", "log")],
+        );
+        DATABASE_COLUMNS.lock().await.insert(
+            (sales.key(), table("dbo", "orders")),
+            vec![Column {
+                name: "total".to_string(),
+                table: "orders".to_string(),
+                schema: "dbo".to_string(),
+                ..Column::default()
+            }],
+        );
+        let labels = |text: &'static str, character: u32| {
+            let service = &service;
+            async move {
+                let position = Position::new(0, character);
+                let context = completion_context(text, position, &Engine::SqlServer);
+                database_completions(text, position, &context, &Engine::SqlServer, service)
+                    .await
+                    .into_iter()
+                    .map(|item| item.label)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            labels("SELECT * FROM ", 14).await,
+            vec!["Sales", "Archive", "REMOTE"]
+        );
+        assert_eq!(
+            labels("SELECT * FROM sales.", 20).await,
+            vec!["audit", "dbo"]
+        );
+        assert_eq!(labels("SELECT * FROM Sales.dbo.", 24).await, vec!["orders"]);
+        assert_eq!(
+            labels("SELECT  FROM Sales.dbo.orders", 7).await,
+            vec!["total"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, DiagnosticSeverity,
+    NumberOrString, Url, WorkspaceEdit,
+};
+
+use super::cache::{
+    cached_indexes, known_columns, known_indexes, load_columns, resolve_tables, ALL_TABLES,
+    SEARCH_PATH,
+};
+use crate::config::WorkspaceConfig;
+use crate::db::engine::Engine;
+use crate::db::service::TenguService;
+use crate::db::table::Table;
+use crate::sql::lint::{lint, Finding, Fix, LintContext, Severity};
+use crate::sql::statement::parse;
+
+pub const SOURCE: &str = "tengu";
+
+/// Lints `text` against what's cached of the active connection's catalog.
+/// Nothing is queried here, since it runs on every edit; `load_catalog`
+/// fills the cache when a file is opened or saved.
+pub async fn diagnostics(
+    text: &str,
+    engine: Engine,
+    config: &WorkspaceConfig,
+    service: &TenguService,
+) -> Vec<Diagnostic> {
+    let context = lint_context(text, engine, service).await;
+    lint(text, &context, &config.lint)
+        .into_iter()
+        .map(to_diagnostic)
+        .collect()
+}
+
+/// Queries the columns and indexes of the tables `text` refers to that
+/// aren't cached yet.
+pub async fn load_catalog(text: &str, engine: Engine, service: &TenguService) {
+    let referenced = referenced_tables(text, &engine, service).await;
+    load_columns(service, &referenced).await;
+    for table in referenced {
+        cached_indexes(service, &table).await;
+    }
+}
+
+async fn referenced_tables(text: &str, engine: &Engine, service: &TenguService) -> Vec<Table> {
+    let mut referenced: Vec<Table> = Vec::new();
+    for r in parse(text, engine).iter().flat_map(|s| s.table_refs()) {
+        for table in resolve_tables(service, &r.name.name(), r.name.schema().as_deref()).await {
+            if !referenced.contains(&table) {
+                referenced.push(table);
+            }
+        }
+    }
+    referenced
+}
+
+async fn lint_context(text: &str, engine: Engine, service: &TenguService) -> LintContext {
+    let mut context = LintContext::new(engine);
+    context.tables = ALL_TABLES.lock().await.iter().cloned().collect();
+    context.search_path = SEARCH_PATH.lock().await.clone();
+    let referenced = referenced_tables(text, &context.engine, service).await;
+    context.columns = known_columns(&referenced).await;
+    for table in referenced {
+        // No indexes may also mean the catalog couldn't say.
+        if let Some(indexes) = known_indexes(&table).await.filter(|i| !i.is_empty()) {
+            context.indexes.insert(table, indexes);
+        }
+    }
+    context
+}
+
+fn to_diagnostic(finding: Finding) -> Diagnostic {
+    let severity = match finding.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Info => DiagnosticSeverity::INFORMATION,
+        Severity::Hint | Severity::Off => DiagnosticSeverity::HINT,
+    };
+    Diagnostic {
+        range: finding.range,
+        severity: Some(severity),
+        code: Some(NumberOrString::String(finding.rule.to_string())),
+        source: Some(SOURCE.to_string()),
+        message: finding.message,
+        data: finding.fix.and_then(|fix| serde_json::to_value(fix).ok()),
+        ..Diagnostic::default()
+    }
+}
+
+/// Quick fixes for the tengu diagnostics the editor sent back with a code
+/// action request; the fix itself travels in the diagnostic's data.
+pub fn quick_fixes(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter(|d| d.source.as_deref() == Some(SOURCE))
+        .filter_map(|d| {
+            let fix: Fix = serde_json::from_value(d.data.clone()?).ok()?;
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: fix.title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![d.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), fix.edits)])),
+                    ..WorkspaceEdit::default()
+                }),
+                is_preferred: Some(true),
+                ..CodeAction::default()
+            }))
+        })
+        .collect()
+}
@@ -1,78 +1,47 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use tower_lsp::lsp_types::{Position, Range, Url};
 
-pub fn get_word_at_position(line_num: u32, char_num: u32, file_path: PathBuf) -> Option<String> {
-    let file = File::open(file_path).ok()?;
-    let reader = BufReader::new(file);
-    // Iterate over each line in the file
-    for (i, line) in reader.lines().enumerate() {
-        let Ok(line) = line else {
-            continue;
-        };
-        // If this is the target line
-        if i == line_num as usize {
-            // If the target character is within the bounds of this line
-            if char_num < line.len() as u32 {
-                // Find the word that contains the target character
-                return find_word(line, char_num);
-            }
-        }
+use super::cache::DOCUMENTS;
+use crate::prelude::read_file_to_string;
+
+/// The editor's copy of the document if it's open, otherwise the file on disk.
+pub async fn document_text(uri: &Url) -> Option<String> {
+    if let Some(text) = DOCUMENTS.lock().await.get(uri) {
+        return Some(text.clone());
     }
-    None
+    read_file_to_string(uri.to_file_path().ok()?).ok()
 }
 
-fn find_word(line: String, position: u32) -> Option<String> {
-    let Some(char_at_position) = line.get(position as usize .. position as usize + 1) else {
-        return None;
-    };
-    if !char_at_position.chars().next().unwrap().is_alphabetic() {
-        return None;
-    };
-    let line_after_position = &line[position as usize..].to_string();
-    let line_before_position = &line[..position as usize]
-        .to_string()
-        .chars()
-        .rev()
-        .collect::<String>();
-    let left_part_of_word =
-        get_partial_word(line_before_position.to_string()).unwrap_or("".to_string());
-    let right_part_of_word =
-        get_partial_word(line_after_position.to_string()).unwrap_or("".to_string());
-    let word = format!(
-        "{}{}",
-        left_part_of_word.chars().rev().collect::<String>(),
-        right_part_of_word
-    );
-    Some(word)
+/// Range spanning the whole of `text`, with characters in UTF-16 code units.
+pub fn full_range(text: &str) -> Range {
+    let line = text.split('\n').count() as u32 - 1;
+    let last = text.rsplit('\n').next().unwrap_or_default();
+    Range::new(
+        Position::new(0, 0),
+        Position::new(line, last.encode_utf16().count() as u32),
+    )
 }
 
-fn get_partial_word(line: String) -> Option<String> {
-    let mut chars = line.chars().peekable();
-    let mut word = String::new();
-    while let Some(c) = chars.next() {
-        match c {
-            'a'..='z' | 'A'..='Z' | '_' => {
-                word.push(c);
-                while let Some(&c) = chars.peek() {
-                    match c {
-                        'a'..='z' | 'A'..='Z' | '_' => {
-                            word.push(c);
-                            chars.next();
-                        }
-                        _ => break,
-                    }
-                }
-                return Some(word);
-            }
-            ' ' | '\t' | '\r' | '\n' | '(' | ')' | ',' | ';' | '.' | '=' | '!' | '<' | '>'
-            | '+' | '-' | ':' => {
-                return Some(word);
-            }
-            _ => continue,
+/// Byte offset of `position` in `text`, whose characters count UTF-16 code
+/// units. Positions past the end of a line or of the text are clamped.
+pub fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(n) => line_start += n + 1,
+            None => return text.len(),
         }
     }
-    None
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |n| line_start + n);
+    let mut units = 0;
+    for (i, c) in text[line_start..line_end].char_indices() {
+        if units >= position.character {
+            return line_start + i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line_end
 }
 
 #[cfg(test)]
@@ -80,38 +49,27 @@ mod tests {
     use super::*;
 
     #[test]
-    fn should_be_able_to_find_word_in_a_line() {
-        let line = "SELECT * FROM dbo.tbl_users;".to_string();
-        let char_num = 10;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, Some("FROM".to_string()));
-
-        let line = "SELECT * FROM dbo.tbl_users;".to_string();
-        let char_num = 0;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, Some("SELECT".to_string()));
-
-        let char_num = line.len() as u32 - 2;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, Some("tbl_users".to_string()));
-
-        let char_num = line.len() as u32 - 1;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, None);
-
-        let char_num = 6;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, None);
-
-        let char_num = 17;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, None);
+    fn should_get_the_range_of_the_whole_document() {
+        assert_eq!(
+            full_range(""),
+            Range::new(Position::new(0, 0), Position::new(0, 0))
+        );
+        assert_eq!(
+            full_range("SELECT 1\nFROM tbl_ñ"),
+            Range::new(Position::new(0, 0), Position::new(1, 10))
+        );
+        assert_eq!(
+            full_range("SELECT 1\n"),
+            Range::new(Position::new(0, 0), Position::new(1, 0))
+        );
     }
+
     #[test]
-    fn should_get_the_table_name_if_cursor_is_at_beggining_of_the_word() {
-        let line = "SELECT id FROM tbl_users;".to_string();
-        let char_num = 21;
-        let word = find_word(line.clone(), char_num);
-        assert_eq!(word, Some("tbl_users".to_string()));
+    fn should_convert_positions_to_offsets() {
+        let text = "SELECT 'ñ', 1\nFROM t";
+        assert_eq!(offset_at(text, Position::new(0, 9)), 10);
+        assert_eq!(offset_at(text, Position::new(0, 99)), 14);
+        assert_eq!(offset_at(text, Position::new(1, 5)), 20);
+        assert_eq!(offset_at(text, Position::new(5, 0)), text.len());
     }
 }
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url};
+
+use super::diagnostics::SOURCE;
+use crate::db::migrations::{scripts, MigrationService};
+
+/// Diagnostics on the migration scripts for every way the database differs
+/// from them, on the statement that last shaped the table or object. What the
+/// migrations don't account for goes on the first line of the last script.
+/// Every script gets an entry, empty when it has nothing to report, so stale
+/// diagnostics get cleared.
+pub async fn drift_diagnostics(service: &MigrationService) -> HashMap<Url, Vec<Diagnostic>> {
+    let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+    let Ok((_, scripts)) = scripts(service.dir()) else {
+        return diagnostics;
+    };
+    for script in &scripts {
+        if let Ok(uri) = Url::from_file_path(script) {
+            diagnostics.insert(uri, vec![]);
+        }
+    }
+    let (Ok(schema), Ok(drift)) = (service.schema(), service.drift().await) else {
+        return diagnostics;
+    };
+    for drift in drift {
+        let (path, range) = match schema.origins.get(&drift.key) {
+            Some(origin) => (origin.path.clone(), origin.range),
+            None => match scripts.last() {
+                Some(last) => (last.clone(), Range::default()),
+                None => continue,
+            },
+        };
+        let Ok(uri) = Url::from_file_path(path) else {
+            continue;
+        };
+        diagnostics.entry(uri).or_default().push(Diagnostic {
+            range,
+            severity: Some(if drift.pending {
+                DiagnosticSeverity::INFORMATION
+            } else {
+                DiagnosticSeverity::WARNING
+            }),
+            code: Some(NumberOrString::String("schema-drift".to_string())),
+            source: Some(SOURCE.to_string()),
+            message: drift.message,
+            ..Diagnostic::default()
+        });
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::db::catalog::TableSchema;
+    use crate::db::column::Column;
+    use crate::db::engine::Engine;
+    use crate::db::table::Table;
+    use crate::lsp::cache::test_service;
+
+    #[tokio::test]
+    async fn should_put_drift_on_the_script_that_shaped_the_table() {
+        let dir = std::env::temp_dir().join(format!("tengu-drift-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("V1__init.sql"),
+            "CREATE TABLE orders (id integer NOT NULL, code varchar(10));",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("V2__items.sql"),
+            "\nCREATE TABLE items (id integer);",
+        )
+        .unwrap();
+        let table = |name: &str, columns: Vec<Column>| TableSchema {
+            table: Table {
+                name: name.to_string(),
+                schema: "public".to_string(),
+                description: None,
+            },
+            columns,
+            indexes: vec![],
+            constraints: vec![],
+        };
+        let live = test_service(
+            Engine::Postgres,
+            vec![
+                table(
+                    "orders",
+                    vec![Column {
+                        name: "id".to_string(),
+                        table: "orders".to_string(),
+                        schema: "public".to_string(),
+                        data_type: "integer".to_string(),
+                        is_nullable: "NO".to_string(),
+                        ..Column::default()
+                    }],
+                ),
+                table("audit", vec![]),
+            ],
+        );
+        let service = MigrationService::new(Engine::Postgres, dir.clone(), Some(live));
+
+        let diagnostics = drift_diagnostics(&service).await;
+        fs::remove_dir_all(&dir).unwrap();
+
+        let of = |script: &str| -> Vec<(u32, Option<DiagnosticSeverity>, String)> {
+            let uri = Url::from_file_path(dir.join(script)).unwrap();
+            diagnostics[&uri]
+                .iter()
+                .map(|d| (d.range.start.line, d.severity, d.message.clone()))
+                .collect()
+        };
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            of("V1__init.sql"),
+            vec![(
+                0,
+                Some(DiagnosticSeverity::INFORMATION),
+                "public.orders.code isn't in the database yet".to_string()
+            )]
+        );
+        assert_eq!(
+            of("V2__items.sql"),
+            vec![
+                (
+                    1,
+                    Some(DiagnosticSeverity::INFORMATION),
+                    "public.items isn't in the database yet".to_string()
+                ),
+                (
+                    0,
+                    Some(DiagnosticSeverity::WARNING),
+                    "public.audit is in the database but no migration creates it".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::fs;
+
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, InsertTextFormat, MarkupContent, MarkupKind,
+};
+
+use super::cache::{NAME_USAGE, RECENT_STATEMENTS};
+use crate::history::{recent_statements, History, HistoryEntry};
+use crate::tokenizer::{lex, LexemeKind};
+
+const LIMIT: usize = 50;
+
+/// Recent statements from the query history, offered while typing the first
+/// word of a line so they don't crowd out column and table names.
+pub async fn history_completions(line_prefix: &str) -> Vec<CompletionItem> {
+    if line_prefix.trim_start().contains(char::is_whitespace) {
+        return vec![];
+    }
+    let history = History::open();
+    let Ok(modified) = fs::metadata(history.path()).and_then(|m| m.modified()) else {
+        return vec![];
+    };
+    let mut cache = RECENT_STATEMENTS.lock().await;
+    if cache.as_ref().is_none_or(|(seen, _)| *seen != modified) {
+        *cache = Some((modified, recent_statements(history.load(), LIMIT)));
+    }
+    let Some((_, entries)) = cache.as_ref() else {
+        return vec![];
+    };
+    entries.iter().enumerate().map(completion_item).collect()
+}
+
+fn completion_item((i, entry): (usize, &HistoryEntry)) -> CompletionItem {
+    CompletionItem {
+        label: entry.summary(60),
+        kind: Some(CompletionItemKind::SNIPPET),
+        detail: Some(format!("{} · {}", entry.connection, entry.time())),
+        documentation: Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```sql\n{}\n```", entry.sql),
+        })),
+        filter_text: Some(entry.sql.clone()),
+        // After the catalog names, most recent first.
+        sort_text: Some(format!("~{:03}", i)),
+        insert_text: Some(entry.sql.clone()),
+        insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+        ..CompletionItem::default()
+    }
+}
+
+/// Lowercased names and how many times the history's statements use them,
+/// read again when the history file changes.
+pub async fn name_usage() -> HashMap<String, usize> {
+    let history = History::open();
+    let Ok(modified) = fs::metadata(history.path()).and_then(|m| m.modified()) else {
+        return HashMap::new();
+    };
+    let mut cache = NAME_USAGE.lock().await;
+    if cache.as_ref().is_none_or(|(seen, _)| *seen != modified) {
+        let mut usage = HashMap::new();
+        for entry in history.load() {
+            for lexeme in lex(&entry.sql) {
+                if matches!(lexeme.kind, LexemeKind::Word | LexemeKind::QuotedIdentifier) {
+                    *usage.entry(lexeme.unquoted().to_lowercase()).or_default() += 1;
+                }
+            }
+        }
+        *cache = Some((modified, usage));
+    }
+    cache
+        .as_ref()
+        .map(|(_, usage)| usage.clone())
+        .unwrap_or_default()
+}
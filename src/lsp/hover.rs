@@ -0,0 +1,527 @@
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Range};
+
+use super::cache::{
+    cached_columns, cached_constraints, cached_database_columns, cached_database_tables,
+    cached_indexes, resolve_tables, ALL_OBJECTS,
+};
+use crate::db::column::Column;
+use crate::db::database::Database;
+use crate::db::engine::Engine;
+use crate::db::index::{Constraint, ConstraintKind, Index};
+use crate::db::service::TenguService;
+use crate::db::table::Table;
+use crate::sql::docs::{function_doc, keyword_doc};
+use crate::sql::references::{target_at, TargetKind};
+use crate::sql::statement::{parse, QualifiedName, Statement};
+
+/// Markdown for the table, column, alias, routine, built-in function or
+/// keyword under `position`. Tables and columns come from the cache, so only
+/// the first hover over a table asks the catalog.
+pub async fn hover(
+    text: &str,
+    position: Position,
+    engine: &Engine,
+    service: &TenguService,
+) -> Option<Hover> {
+    let statement = parse(text, engine)
+        .iter()
+        .flat_map(|s| s.leaves(engine))
+        .find(|s| s.contains(position))?;
+    let tokens = &statement.tokens;
+    let i = tokens
+        .iter()
+        .position(|t| contains(t.range(), position) && t.is_identifier())?;
+    let token = &tokens[i];
+    let mut markdown = match target_at(text, engine, position) {
+        Some((target, _)) => match target.kind {
+            TargetKind::Table => {
+                let database = statement
+                    .table_refs()
+                    .into_iter()
+                    .find(|r| r.name.parts.iter().any(|p| p.offset == token.offset))
+                    .and_then(|r| database_of(&r.name));
+                match database {
+                    Some(database) => {
+                        database_markdown(
+                            &database,
+                            &target.name,
+                            target.schema.as_deref(),
+                            service,
+                        )
+                        .await
+                    }
+                    None => {
+                        let tables =
+                            resolve_tables(service, &target.name, target.schema.as_deref()).await;
+                        tables_markdown(&tables, service).await
+                    }
+                }
+            }
+            TargetKind::Alias => {
+                let aliased = statement.table_refs().into_iter().find(|r| {
+                    r.alias
+                        .as_ref()
+                        .is_some_and(|a| a.unquoted().eq_ignore_ascii_case(&target.name))
+                });
+                match aliased {
+                    Some(r) if r.name.database().is_some() => {
+                        let database = database_of(&r.name)?;
+                        database_markdown(
+                            &database,
+                            &r.name.name(),
+                            r.name.schema().as_deref(),
+                            service,
+                        )
+                        .await
+                    }
+                    Some(r) => {
+                        let tables =
+                            resolve_tables(service, &r.name.name(), r.name.schema().as_deref())
+                                .await;
+                        tables_markdown(&tables, service).await
+                    }
+                    None => None,
+                }
+            }
+            TargetKind::Column => {
+                column_markdown(&statement, &target.name, target.table, service).await
+            }
+            TargetKind::Cte => None,
+        },
+        None => None,
+    };
+    if markdown.is_none() {
+        let schema = (i >= 2 && tokens[i - 1].is_punct(".")).then(|| tokens[i - 2].unquoted());
+        markdown = object_markdown(&token.unquoted(), schema.as_deref()).await;
+    }
+    if markdown.is_none() && tokens.get(i + 1).is_some_and(|t| t.is_punct("(")) {
+        markdown = function_doc(engine, &token.text)
+            .map(|(signature, summary)| format!("```sql\n{}\n```\n\n{}", signature, summary));
+    }
+    if markdown.is_none() {
+        markdown = keyword_doc(engine, &token.text)
+            .map(|doc| format!("**{}**\n\n{}", token.text.to_uppercase(), doc));
+    }
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: markdown?,
+        }),
+        range: Some(token.range()),
+    })
+}
+
+fn contains(range: Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+async fn tables_markdown(tables: &[Table], service: &TenguService) -> Option<String> {
+    let mut sections = Vec::new();
+    for table in tables {
+        let mut columns = cached_columns(service, std::slice::from_ref(table)).await;
+        columns.sort_by_key(|c| c.ordinal_position);
+        let constraints = cached_constraints(service, table).await;
+        let indexes = cached_indexes(service, table).await;
+        let title = format!("{}.{}", table.schema, table.name);
+        let mut section =
+            columns_table(&title, table.description.as_deref(), &columns, &constraints);
+        if !indexes.is_empty() {
+            section.push_str(&format!("\n{}", indexes_list(&indexes)));
+        }
+        sections.push(section);
+    }
+    (!sections.is_empty()).then(|| sections.join("\n---\n\n"))
+}
+
+/// The column `name` of `table`, or when the statement doesn't say, of
+/// whichever of its tables has one.
+async fn column_markdown(
+    statement: &Statement,
+    name: &str,
+    table: Option<String>,
+    service: &TenguService,
+) -> Option<String> {
+    let refs = statement.table_refs();
+    let mut tables = Vec::new();
+    let mut remote = Vec::new();
+    for r in refs.iter().filter(|r| !r.function) {
+        if table
+            .as_ref()
+            .is_some_and(|t| !t.eq_ignore_ascii_case(&r.name.name()))
+        {
+            continue;
+        }
+        if let Some(database) = database_of(&r.name) {
+            for t in database_tables(
+                service,
+                &database,
+                &r.name.name(),
+                r.name.schema().as_deref(),
+            )
+            .await
+            {
+                remote.push((database.clone(), t));
+            }
+            continue;
+        }
+        tables.extend(resolve_tables(service, &r.name.name(), r.name.schema().as_deref()).await);
+    }
+    if let (Some(table), true) = (&table, tables.is_empty() && remote.is_empty()) {
+        // The table a CREATE or ALTER statement defines.
+        tables = resolve_tables(service, table, None).await;
+    }
+    let mut sections = Vec::new();
+    for table in tables {
+        let columns = cached_columns(service, std::slice::from_ref(&table)).await;
+        let Some(column) = columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)) else {
+            continue;
+        };
+        let constraints = cached_constraints(service, &table).await;
+        let title = format!("{}.{}.{}", table.schema, table.name, column.name);
+        sections.push(columns_table(
+            &title,
+            None,
+            std::slice::from_ref(column),
+            &constraints,
+        ));
+    }
+    for (database, table) in remote {
+        let columns =
+            cached_database_columns(service, &database, std::slice::from_ref(&table)).await;
+        let Some(column) = columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)) else {
+            continue;
+        };
+        let title = format!(
+            "{}{}.{}.{}",
+            database_title(&database),
+            table.schema,
+            table.name,
+            column.name
+        );
+        sections.push(columns_table(
+            &title,
+            None,
+            std::slice::from_ref(column),
+            &[],
+        ));
+    }
+    (!sections.is_empty()).then(|| sections.join("\n---\n\n"))
+}
+
+/// The other database a three- or four-part name points into.
+fn database_of(name: &QualifiedName) -> Option<Database> {
+    Some(Database::new(name.server(), name.database()?))
+}
+
+/// `server.database.`, the part of a title before the schema.
+fn database_title(database: &Database) -> String {
+    match &database.server {
+        Some(server) => format!("{}.{}.", server, database.name),
+        None => format!("{}.", database.name),
+    }
+}
+
+async fn database_tables(
+    service: &TenguService,
+    database: &Database,
+    name: &str,
+    schema: Option<&str>,
+) -> Vec<Table> {
+    cached_database_tables(service, database)
+        .await
+        .into_iter()
+        .filter(|t| t.name.eq_ignore_ascii_case(name))
+        .filter(|t| schema.is_none_or(|s| t.schema.eq_ignore_ascii_case(s)))
+        .collect()
+}
+
+/// Columns of a table in another database. Its keys and indexes stay there.
+async fn database_markdown(
+    database: &Database,
+    name: &str,
+    schema: Option<&str>,
+    service: &TenguService,
+) -> Option<String> {
+    let mut sections = Vec::new();
+    for table in database_tables(service, database, name, schema).await {
+        let mut columns =
+            cached_database_columns(service, database, std::slice::from_ref(&table)).await;
+        columns.sort_by_key(|c| c.ordinal_position);
+        let title = format!(
+            "{}{}.{}",
+            database_title(database),
+            table.schema,
+            table.name
+        );
+        sections.push(columns_table(
+            &title,
+            table.description.as_deref(),
+            &columns,
+            &[],
+        ));
+    }
+    (!sections.is_empty()).then(|| sections.join("\n---\n\n"))
+}
+
+async fn object_markdown(name: &str, schema: Option<&str>) -> Option<String> {
+    let objects = ALL_OBJECTS.lock().await;
+    let mut matches: Vec<String> = objects
+        .iter()
+        .filter(|o| o.name.eq_ignore_ascii_case(name))
+        .filter(|o| schema.is_none_or(|s| o.schema.eq_ignore_ascii_case(s)))
+        .map(|o| format!("{} **{}.{}**", o.kind, o.schema, o.name))
+        .collect();
+    matches.sort();
+    (!matches.is_empty()).then(|| matches.join("\n\n"))
+}
+
+/// A markdown table with a row per column: type, nullability, default, the
+/// keys it's part of and, when any column has one, its description.
+fn columns_table(
+    title: &str,
+    description: Option<&str>,
+    columns: &[Column],
+    constraints: &[Constraint],
+) -> String {
+    let mut markdown = format!("**{}**\n\n", title);
+    if let Some(description) = description {
+        markdown.push_str(&format!("{}\n\n", description.trim()));
+    }
+    let described = columns.iter().any(|c| c.description.is_some());
+    markdown.push_str(if described {
+        "| Column | Type | Nullable | Default | Key | Description |\n| --- | --- | --- | --- | --- | --- |\n"
+    } else {
+        "| Column | Type | Nullable | Default | Key |\n| --- | --- | --- | --- | --- |\n"
+    });
+    for column in columns {
+        let mut row = format!(
+            "| {} | {} | {} | {} | {} |",
+            escape(&column.name),
+            escape(&column.declared_type()),
+            if column.is_nullable.eq_ignore_ascii_case("YES") {
+                "NULL"
+            } else {
+                "NOT NULL"
+            },
+            escape(column.column_default.as_deref().unwrap_or_default()),
+            escape(&keys(&column.name, constraints).join(", ")),
+        );
+        if described {
+            row.push_str(&format!(
+                " {} |",
+                escape(column.description.as_deref().unwrap_or_default())
+            ));
+        }
+        markdown.push_str(&row);
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// A bullet per index: its kind, key columns, included columns and filter.
+fn indexes_list(indexes: &[Index]) -> String {
+    let mut markdown = String::from("**Indexes**\n\n");
+    for index in indexes {
+        let mut line = format!("- `{}`", index.name);
+        if index.primary_key {
+            line.push_str(" primary key");
+        } else if index.unique {
+            line.push_str(" unique");
+        }
+        if let Some(kind) = &index.kind {
+            line.push_str(&format!(" {}", kind.to_lowercase()));
+        }
+        line.push_str(&format!(" ({})", index.columns.join(", ")));
+        if !index.included.is_empty() {
+            line.push_str(&format!(" include ({})", index.included.join(", ")));
+        }
+        if let Some(filter) = &index.filter {
+            line.push_str(&format!(" where `{}`", filter));
+        }
+        markdown.push_str(&line);
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// `PK`, `UNIQUE` and `FK → schema.table (column)` for the constraints
+/// `column` is part of.
+fn keys(column: &str, constraints: &[Constraint]) -> Vec<String> {
+    constraints
+        .iter()
+        .filter(|c| {
+            // Snapshots written before constraints listed their columns.
+            let columns = if c.columns.is_empty() {
+                key_columns(&c.definition)
+            } else {
+                c.columns.clone()
+            };
+            columns.iter().any(|k| k.eq_ignore_ascii_case(column))
+        })
+        .filter_map(|c| match c.kind {
+            ConstraintKind::PrimaryKey => Some("PK".to_string()),
+            ConstraintKind::Unique => Some("UNIQUE".to_string()),
+            ConstraintKind::ForeignKey => {
+                let (_, referenced) = c.definition.split_once("REFERENCES ")?;
+                Some(format!("FK → {}", referenced.trim()))
+            }
+            ConstraintKind::Check | ConstraintKind::Default => None,
+        })
+        .collect()
+}
+
+/// The columns in the first parentheses of a key clause, e.g. `customer_id`
+/// in `FOREIGN KEY (customer_id) REFERENCES sales.customers(id)`.
+fn key_columns(definition: &str) -> Vec<String> {
+    let Some((_, rest)) = definition.split_once('(') else {
+        return vec![];
+    };
+    let Some((list, _)) = rest.split_once(')') else {
+        return vec![];
+    };
+    list.split(',')
+        .map(|c| c.trim().trim_matches(['[', ']', '"']).to_string())
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::TableSchema;
+    use crate::lsp::cache::{load_test_catalog, TEST_CACHE};
+
+    #[test]
+    fn should_render_columns_as_a_markdown_table_with_their_keys() {
+        let column = |name: &str, data_type: &str, nullable: &str, default: Option<&str>| Column {
+            name: name.to_string(),
+            table: "orders".to_string(),
+            schema: "sales".to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: nullable.to_string(),
+            column_default: default.map(str::to_string),
+            ..Column::default()
+        };
+        let constraint = |kind, definition: &str, columns: &[&str]| Constraint {
+            name: "c".to_string(),
+            kind,
+            definition: definition.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        };
+        let columns = vec![
+            column("id", "integer", "NO", None),
+            column("customer_id", "integer", "YES", None),
+            Column {
+                description: Some("new | paid | shipped".to_string()),
+                ..column("status", "text", "NO", Some("'new'::text"))
+            },
+        ];
+        let constraints = vec![
+            constraint(ConstraintKind::PrimaryKey, "PRIMARY KEY (id)", &["id"]),
+            constraint(
+                ConstraintKind::ForeignKey,
+                "FOREIGN KEY ([customer_id]) REFERENCES [sales].[customers] ([id])",
+                &[],
+            ),
+            constraint(
+                ConstraintKind::Check,
+                "CHECK (status <> '' OR id > 0)",
+                &["status"],
+            ),
+        ];
+
+        assert_eq!(
+            columns_table(
+                "sales.orders",
+                Some("Orders placed by customers"),
+                &columns,
+                &constraints
+            ),
+            "**sales.orders**\n\n\
+             Orders placed by customers\n\n\
+             | Column | Type | Nullable | Default | Key | Description |\n\
+             | --- | --- | --- | --- | --- | --- |\n\
+             | id | integer | NOT NULL |  | PK |  |\n\
+             | customer_id | integer | NULL |  | FK → [sales].[customers] ([id]) |  |\n\
+             | status | text | NOT NULL | 'new'::text |  | new \\| paid \\| shipped |\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_hover_descriptions_and_leave_out_missing_ones() {
+        let _cache = TEST_CACHE.lock().await;
+        let table = |name: &str, description: Option<&str>| Table {
+            name: name.to_string(),
+            schema: "public".to_string(),
+            description: description.map(str::to_string),
+        };
+        let column = |table: &str, name: &str, description: Option<&str>| Column {
+            name: name.to_string(),
+            table: table.to_string(),
+            schema: "public".to_string(),
+            data_type: "text".to_string(),
+            is_nullable: "YES".to_string(),
+            ordinal_position: 1,
+            description: description.map(str::to_string),
+            ..Column::default()
+        };
+        let schema = |table: Table, columns: Vec<Column>| TableSchema {
+            table,
+            columns,
+            indexes: vec![],
+            constraints: vec![],
+        };
+        let service = load_test_catalog(
+            Engine::Postgres,
+            vec![
+                schema(
+                    table("orders", Some("Orders placed by customers")),
+                    vec![column("orders", "status", Some("new, paid or shipped"))],
+                ),
+                schema(
+                    table("refunds", None),
+                    vec![column("refunds", "reason", None)],
+                ),
+            ],
+        )
+        .await;
+        let text = "SELECT status FROM orders;\nSELECT reason FROM refunds;";
+        let markdown = |line: u32, character: u32| {
+            let service = &service;
+            async move {
+                let hover = hover(
+                    text,
+                    Position::new(line, character),
+                    &Engine::Postgres,
+                    service,
+                )
+                .await
+                .unwrap();
+                match hover.contents {
+                    HoverContents::Markup(markup) => markup.value,
+                    _ => unreachable!(),
+                }
+            }
+        };
+
+        let orders = markdown(0, 20).await;
+        assert!(
+            orders.contains("\n\nOrders placed by customers\n\n"),
+            "{}",
+            orders
+        );
+        assert!(orders.contains("| new, paid or shipped |"), "{}", orders);
+        let status = markdown(0, 8).await;
+        assert!(status.contains("| new, paid or shipped |"), "{}", status);
+        let refunds = markdown(1, 21).await;
+        assert!(!refunds.contains("Description"), "{}", refunds);
+        assert!(
+            refunds.starts_with("**public.refunds**\n\n| Column |"),
+            "{}",
+            refunds
+        );
+    }
+}
@@ -1,4 +1,16 @@
 pub mod cache;
+pub mod commands;
+pub mod databases;
+pub mod diagnostics;
 pub mod document;
+pub mod drift;
 pub mod file_watch;
+pub mod history;
+pub mod hover;
+pub mod ranking;
+pub mod references;
 pub mod server;
+pub mod snippets;
+pub mod stats;
+pub mod symbols;
+pub mod workspace;
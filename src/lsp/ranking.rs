@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, CompletionList, Position};
+
+use crate::db::engine::Engine;
+use crate::sql::statement::{parse, Statement};
+use crate::tokenizer::{lex, Lexeme, LexemeKind};
+
+/// Most items of each kind sent back; the editor asks again as the word grows.
+const TABLES: usize = 100;
+const COLUMNS: usize = 200;
+const KEYWORDS: usize = 50;
+
+/// What the cursor expects, from the words before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Table,
+    Column,
+    Keyword,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionContext {
+    pub slot: Slot,
+    /// The part of the word before the cursor.
+    pub prefix: String,
+    /// The dotted parts before the word: the `o` of `o.cu`, an alias or a
+    /// table, or the `OtherDb`, `dbo` of `OtherDb.dbo.t`.
+    pub path: Vec<String>,
+    /// Tables the statement reads, keyed by their alias and their name,
+    /// lowercased.
+    pub tables: HashMap<String, String>,
+}
+
+/// Where the cursor is in the statement: the word being typed, what comes
+/// before it and the tables in scope.
+pub fn completion_context(text: &str, position: Position, engine: &Engine) -> CompletionContext {
+    let lexemes: Vec<Lexeme> = lex(text)
+        .into_iter()
+        .filter(|l| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment))
+        .filter(|l| Position::new(l.line, l.character) < position)
+        .collect();
+    let mut before = lexemes.as_slice();
+    let mut prefix = String::new();
+    if let Some(last) = before.last() {
+        let range = last.range();
+        if last.is_identifier() && position <= range.end && range.start.line == position.line {
+            let typed = (position.character - range.start.character) as usize;
+            prefix = last.unquoted().chars().take(typed).collect();
+            before = &before[..before.len() - 1];
+        }
+    }
+    let mut path = Vec::new();
+    while let [rest @ .., part, dot] = before {
+        if !dot.is_punct(".") || !part.is_identifier() {
+            break;
+        }
+        path.insert(0, part.unquoted());
+        before = rest;
+    }
+    // Only what follows the previous statement counts.
+    if let Some(end) = before.iter().rposition(|t| t.is_punct(";")) {
+        before = &before[end + 1..];
+    }
+    let tables = if before.is_empty() {
+        HashMap::new()
+    } else {
+        statement_tables(text, position, engine)
+    };
+    let slot = match path.as_slice() {
+        [] => slot(before),
+        [q] if tables.contains_key(&q.to_lowercase()) => Slot::Column,
+        _ => Slot::Table,
+    };
+    CompletionContext {
+        slot,
+        prefix,
+        path,
+        tables,
+    }
+}
+
+/// The statement the cursor is in, or the last one before it.
+pub fn statement_at(text: &str, position: Position, engine: &Engine) -> Option<Statement> {
+    parse(text, engine)
+        .iter()
+        .flat_map(|s| s.leaves(engine))
+        .rfind(|s| s.range().start <= position)
+}
+
+fn statement_tables(text: &str, position: Position, engine: &Engine) -> HashMap<String, String> {
+    let statement = statement_at(text, position, engine);
+    let mut tables = HashMap::new();
+    for r in statement.iter().flat_map(|s| s.table_refs()) {
+        let name = r.name.name().to_lowercase();
+        if let Some(alias) = &r.alias {
+            tables.insert(alias.unquoted().to_lowercase(), name.clone());
+        }
+        tables.insert(name.clone(), name);
+    }
+    tables
+}
+
+/// Tables right after FROM, JOIN, ... or a comma in their list; columns
+/// after SELECT, WHERE, ON, an operator or a comma in theirs.
+fn slot(before: &[Lexeme]) -> Slot {
+    let Some(previous) = before.last() else {
+        return Slot::Keyword;
+    };
+    let clause = before.iter().rev().find(|t| {
+        t.kind == LexemeKind::Word
+            && [
+                "FROM", "JOIN", "INTO", "UPDATE", "TABLE", "SELECT", "WHERE", "ON", "BY", "SET",
+                "HAVING", "VALUES",
+            ]
+            .iter()
+            .any(|w| t.is_word(w))
+    });
+    let expects_tables = clause.is_some_and(|c| {
+        ["FROM", "JOIN", "INTO", "UPDATE", "TABLE"]
+            .iter()
+            .any(|w| c.is_word(w))
+    });
+    let follows = previous.is_punct(",")
+        || clause.is_some_and(|c| c.offset == previous.offset)
+        || !expects_tables
+            && (previous.is_punct("(")
+                || previous.kind == LexemeKind::Operator
+                || [
+                    "AND", "OR", "NOT", "WHEN", "THEN", "ELSE", "CASE", "DISTINCT", "IN", "IS",
+                    "BETWEEN", "LIKE",
+                ]
+                .iter()
+                .any(|w| previous.is_word(w)));
+    match (clause, follows) {
+        (Some(_), true) if expects_tables => Slot::Table,
+        (Some(_), true) => Slot::Column,
+        _ => Slot::Keyword,
+    }
+}
+
+/// Keeps the items that fit the context, ranks them and caps each kind.
+/// Within a kind, names of the statement's tables come first, then the names
+/// the history uses most, then the alphabet.
+pub fn rank(
+    items: Vec<CompletionItem>,
+    context: &CompletionContext,
+    usage: &HashMap<String, usize>,
+) -> CompletionList {
+    let prefix = context.prefix.to_lowercase();
+    let path = context.path.join(".").to_lowercase();
+    let qualified = (!path.is_empty()).then(|| context.tables.get(&path).cloned().ok_or(path));
+    let mut ranked: Vec<(usize, bool, usize, CompletionItem)> = items
+        .into_iter()
+        .filter(|item| {
+            item.filter_text
+                .as_deref()
+                .unwrap_or(&item.label)
+                .to_lowercase()
+                .starts_with(&prefix)
+        })
+        .filter_map(|item| {
+            let kind = item.kind.unwrap_or(CompletionItemKind::TEXT);
+            let table = match kind {
+                CompletionItemKind::PROPERTY => item.label_details.as_ref()?.detail.clone(),
+                CompletionItemKind::CLASS => Some(item.label.clone()),
+                _ => None,
+            }
+            .map(|t| t.to_lowercase());
+            let group = match (&qualified, kind) {
+                // `alias.`: that table's columns.
+                (Some(Ok(t)), CompletionItemKind::PROPERTY) if table.as_ref() == Some(t) => 0,
+                // `schema.`, `OtherDb.`, ...: what's in there.
+                (Some(Err(s)), _)
+                    if item
+                        .detail
+                        .as_ref()
+                        .is_some_and(|d| d.eq_ignore_ascii_case(s)) =>
+                {
+                    0
+                }
+                (Some(_), _) => return None,
+                (None, CompletionItemKind::PROPERTY) => match context.slot {
+                    Slot::Table => return None,
+                    Slot::Column => 0,
+                    Slot::Keyword => 3,
+                },
+                (None, CompletionItemKind::CLASS) => match context.slot {
+                    Slot::Table => 0,
+                    Slot::Column => 2,
+                    Slot::Keyword => 2,
+                },
+                (None, CompletionItemKind::KEYWORD) => match context.slot {
+                    Slot::Keyword => 0,
+                    _ => 3,
+                },
+                (None, _) => 1,
+            };
+            let in_statement = table.is_some_and(|t| context.tables.values().any(|v| *v == t));
+            let uses = usage.get(&item.label.to_lowercase()).copied().unwrap_or(0);
+            Some((group, !in_statement, usize::MAX - uses, item))
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        (a.0, a.1, a.2)
+            .cmp(&(b.0, b.1, b.2))
+            .then_with(|| a.3.label.cmp(&b.3.label))
+    });
+    // Tables, columns and keywords sent so far.
+    let mut counts = [0; 3];
+    let mut is_incomplete = false;
+    let mut items = Vec::new();
+    for (_, _, _, mut item) in ranked {
+        let capped = match item.kind {
+            Some(
+                CompletionItemKind::CLASS | CompletionItemKind::MODULE | CompletionItemKind::FOLDER,
+            ) => Some((0, TABLES)),
+            Some(CompletionItemKind::PROPERTY) => Some((1, COLUMNS)),
+            Some(CompletionItemKind::KEYWORD) => Some((2, KEYWORDS)),
+            _ => None,
+        };
+        if let Some((i, limit)) = capped {
+            if counts[i] == limit {
+                is_incomplete = true;
+                continue;
+            }
+            counts[i] += 1;
+        }
+        item.sort_text = Some(format!("{:05}", items.len()));
+        items.push(item);
+    }
+    CompletionList {
+        is_incomplete,
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::CompletionItemLabelDetails;
+
+    fn item(label: &str, kind: CompletionItemKind, table: Option<&str>) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            kind: Some(kind),
+            label_details: table.map(|t| CompletionItemLabelDetails {
+                detail: Some(t.to_string()),
+                ..CompletionItemLabelDetails::default()
+            }),
+            ..CompletionItem::default()
+        }
+    }
+
+    fn labels(text: &str, position: Position, usage: &HashMap<String, usize>) -> Vec<String> {
+        let items = vec![
+            item("SELECT", CompletionItemKind::KEYWORD, None),
+            item("WHERE", CompletionItemKind::KEYWORD, None),
+            item("customers", CompletionItemKind::CLASS, None),
+            item("orders", CompletionItemKind::CLASS, None),
+            item("name", CompletionItemKind::PROPERTY, Some("customers")),
+            item("created_at", CompletionItemKind::PROPERTY, Some("orders")),
+            item("customer_id", CompletionItemKind::PROPERTY, Some("orders")),
+        ];
+        let context = completion_context(text, position, &Engine::Postgres);
+        rank(items, &context, usage)
+            .items
+            .into_iter()
+            .map(|i| i.label)
+            .collect()
+    }
+
+    #[test]
+    fn should_rank_by_what_the_cursor_expects() {
+        let usage = HashMap::from([("orders".to_string(), 3)]);
+        assert_eq!(
+            labels("SELECT * FROM ", Position::new(0, 14), &usage),
+            vec!["orders", "customers", "SELECT", "WHERE"]
+        );
+        assert_eq!(
+            labels("SELECT c FROM orders o", Position::new(0, 8), &usage),
+            vec!["created_at", "customer_id", "customers"]
+        );
+        assert_eq!(
+            labels("SELECT o. FROM orders o", Position::new(0, 9), &usage),
+            vec!["created_at", "customer_id"]
+        );
+        assert_eq!(
+            labels("SELECT 1; ", Position::new(0, 10), &usage),
+            vec![
+                "SELECT",
+                "WHERE",
+                "orders",
+                "customers",
+                "created_at",
+                "customer_id",
+                "name"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_keep_what_a_dotted_path_reaches() {
+        let local = CompletionItem {
+            detail: Some("dbo".to_string()),
+            ..item("orders", CompletionItemKind::CLASS, None)
+        };
+        let archived = CompletionItem {
+            detail: Some("Archive.dbo".to_string()),
+            ..item("orders_2019", CompletionItemKind::CLASS, None)
+        };
+        let text = "SELECT * FROM Archive.dbo.";
+        let context = completion_context(text, Position::new(0, 26), &Engine::SqlServer);
+        assert_eq!(context.path, vec!["Archive", "dbo"]);
+        let items = rank(vec![local, archived], &context, &HashMap::new()).items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "orders_2019");
+    }
+}
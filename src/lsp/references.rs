@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tower_lsp::lsp_types::{
+    CreateFile, DocumentChangeOperation, DocumentChanges, Location, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, PrepareRenameResponse, ResourceOp,
+    TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+};
+
+use super::cache::{ALL_TABLES, DOCUMENTS, WORKSPACE_FOLDERS};
+use super::document::document_text;
+use crate::cli::sql_files;
+use crate::db::engine::Engine;
+use crate::sql::references::{occurrences, rename_script, renamed, target_at, Target, TargetKind};
+
+/// The documents a search covers: the current one for local targets, the
+/// workspace's SQL files plus whatever is open in the editor otherwise.
+async fn documents(uri: &Url, target: &Target) -> Vec<(Url, String)> {
+    let mut uris = vec![uri.clone()];
+    if !target.is_local() {
+        let roots = WORKSPACE_FOLDERS.lock().await.clone();
+        uris.extend(
+            sql_files(&roots)
+                .into_iter()
+                .filter_map(|path| Url::from_file_path(path).ok()),
+        );
+        uris.extend(DOCUMENTS.lock().await.keys().cloned());
+    }
+    uris.sort();
+    uris.dedup();
+    let mut documents = Vec::new();
+    for uri in uris {
+        if let Some(text) = document_text(&uri).await {
+            documents.push((uri, text));
+        }
+    }
+    documents
+}
+
+pub async fn references(
+    uri: &Url,
+    position: Position,
+    engine: &Engine,
+    include_declaration: bool,
+) -> Option<Vec<Location>> {
+    let text = document_text(uri).await?;
+    let (target, _) = target_at(&text, engine, position)?;
+    let mut locations = Vec::new();
+    for (uri, text) in documents(uri, &target).await {
+        locations.extend(
+            occurrences(&text, engine, &target)
+                .into_iter()
+                .filter(|o| include_declaration || !o.declaration)
+                .map(|o| Location::new(uri.clone(), o.token.range())),
+        );
+    }
+    Some(locations)
+}
+
+pub async fn prepare_rename(
+    uri: &Url,
+    position: Position,
+    engine: &Engine,
+) -> Option<PrepareRenameResponse> {
+    let text = document_text(uri).await?;
+    let (target, token) = target_at(&text, engine, position)?;
+    Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: token.range(),
+        placeholder: target.name,
+    })
+}
+
+/// Edits renaming the target under `position` everywhere it's used. With a
+/// `migrations` folder, renaming a table or column that exists in the
+/// database also creates a script there doing the same rename.
+pub async fn rename(
+    uri: &Url,
+    position: Position,
+    new_name: &str,
+    engine: &Engine,
+    migrations: Option<PathBuf>,
+) -> Option<WorkspaceEdit> {
+    let text = document_text(uri).await?;
+    let (target, _) = target_at(&text, engine, position)?;
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for (uri, text) in documents(uri, &target).await {
+        let edits: Vec<TextEdit> = occurrences(&text, engine, &target)
+            .into_iter()
+            .map(|o| TextEdit::new(o.token.range(), renamed(&o.token, new_name)))
+            .collect();
+        if !edits.is_empty() {
+            changes.insert(uri, edits);
+        }
+    }
+    let script = match migrations {
+        Some(dir) => migration(&target, engine, new_name)
+            .await
+            .map(|script| (dir, script)),
+        None => None,
+    };
+    let Some((dir, script)) = script else {
+        return Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        });
+    };
+    let file_name = format!(
+        "{}_rename_{}_to_{}.sql",
+        chrono::Local::now().format("%Y%m%d%H%M%S"),
+        target.name,
+        new_name
+    );
+    let script_uri = Url::from_file_path(dir.join(file_name)).ok()?;
+    let mut operations = vec![
+        DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+            uri: script_uri.clone(),
+            options: None,
+            annotation_id: None,
+        })),
+        text_document_edit(script_uri, vec![TextEdit::new(Default::default(), script)]),
+    ];
+    operations.extend(
+        changes
+            .into_iter()
+            .map(|(uri, edits)| text_document_edit(uri, edits)),
+    );
+    Some(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(operations)),
+        ..WorkspaceEdit::default()
+    })
+}
+
+/// The rename script, when the target is a table, or a column of a table,
+/// that the active connection knows about.
+async fn migration(target: &Target, engine: &Engine, new_name: &str) -> Option<String> {
+    let table = match target.kind {
+        TargetKind::Table => &target.name,
+        TargetKind::Column => target.table.as_ref()?,
+        TargetKind::Alias | TargetKind::Cte => return None,
+    };
+    let all_tables = ALL_TABLES.lock().await;
+    let schema = all_tables
+        .iter()
+        .filter(|t| t.name.eq_ignore_ascii_case(table))
+        .find(|t| {
+            target
+                .schema
+                .as_ref()
+                .is_none_or(|s| t.schema.eq_ignore_ascii_case(s))
+        })?
+        .schema
+        .clone();
+    rename_script(engine, target, &schema, new_name)
+}
+
+fn text_document_edit(uri: Url, edits: Vec<TextEdit>) -> DocumentChangeOperation {
+    DocumentChangeOperation::Edit(TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+        edits: edits.into_iter().map(OneOf::Left).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::TableSchema;
+    use crate::db::table::Table;
+    use crate::lsp::cache::{load_test_catalog, TEST_CACHE};
+
+    #[tokio::test]
+    async fn should_rename_across_open_documents_and_script_the_rename() {
+        let _cache = TEST_CACHE.lock().await;
+        load_test_catalog(
+            Engine::Postgres,
+            vec![TableSchema {
+                table: Table {
+                    name: "orders".to_string(),
+                    schema: "public".to_string(),
+                    description: None,
+                },
+                columns: vec![],
+                indexes: vec![],
+                constraints: vec![],
+            }],
+        )
+        .await;
+        WORKSPACE_FOLDERS.lock().await.clear();
+        let a = Url::parse("file:///tengu-test/a.sql").unwrap();
+        let b = Url::parse("file:///tengu-test/b.sql").unwrap();
+        {
+            let mut documents = DOCUMENTS.lock().await;
+            documents.insert(a.clone(), "SELECT id FROM orders".to_string());
+            documents.insert(b.clone(), "DELETE FROM orders o WHERE o.id = 1".to_string());
+        }
+
+        let locations = references(&a, Position::new(0, 16), &Engine::Postgres, true)
+            .await
+            .unwrap();
+        let edit = rename(
+            &a,
+            Position::new(0, 16),
+            "purchases",
+            &Engine::Postgres,
+            Some(PathBuf::from("/tengu-test/migrations")),
+        )
+        .await
+        .unwrap();
+        DOCUMENTS.lock().await.clear();
+
+        let found: Vec<_> = locations
+            .iter()
+            .map(|l| (l.uri.clone(), l.range.start.character))
+            .collect();
+        assert_eq!(found, vec![(a.clone(), 15), (b.clone(), 12)]);
+        let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+            panic!("expected document operations");
+        };
+        let DocumentChangeOperation::Op(ResourceOp::Create(create)) = &operations[0] else {
+            panic!("expected the migration to be created first");
+        };
+        assert!(create
+            .uri
+            .path()
+            .ends_with("_rename_orders_to_purchases.sql"));
+        let mut edits: Vec<_> = operations[1..]
+            .iter()
+            .map(|operation| match operation {
+                DocumentChangeOperation::Edit(edit) => (
+                    edit.text_document.uri.path().to_string(),
+                    edit.edits
+                        .iter()
+                        .map(|e| match e {
+                            OneOf::Left(e) => e.new_text.clone(),
+                            OneOf::Right(e) => e.text_edit.new_text.clone(),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => panic!("expected text edits"),
+            })
+            .collect();
+        edits.sort();
+        assert_eq!(
+            edits,
+            vec![
+                (a.path().to_string(), vec!["purchases".to_string()]),
+                (b.path().to_string(), vec!["purchases".to_string()]),
+                (
+                    create.uri.path().to_string(),
+                    vec!["ALTER TABLE public.orders RENAME TO purchases;\n".to_string()]
+                ),
+            ]
+        );
+    }
+}
@@ -1,14 +1,42 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use super::cache::{ALL_COLUMNS, TABLES_IN_FILE};
-use super::document::get_word_at_position;
+use super::cache::{
+    reload_catalog, search_path, ALL_COLUMNS, ALL_OBJECTS, DOCUMENTS, DRIFT, SEARCH_PATH,
+    TABLES_IN_FILE, WORKSPACE_FOLDERS,
+};
+use super::commands::{
+    code_lenses, write_results, DdlTarget, Run, COMMANDS, EXPLAIN_STATEMENT, SHOW_DDL,
+    SWITCH_DATABASE,
+};
+use super::databases::database_completions;
+use super::diagnostics::{diagnostics, load_catalog, quick_fixes};
+use super::document::{document_text, full_range};
+use super::drift::drift_diagnostics;
 use super::file_watch::async_watch;
+use super::history::{history_completions, name_usage};
+use super::hover::hover;
+use super::ranking::{completion_context, rank};
+use super::references::{prepare_rename, references, rename};
+use super::snippets::snippet_completions;
+use super::stats::table_lenses;
+use super::symbols::document_symbols;
+use super::workspace::workspace_symbols;
+use crate::config::WorkspaceConfig;
+use crate::db::cancel;
+use crate::db::migrations::MigrationService;
+use crate::db::object::DbObject;
+use crate::db::plan::render as render_plan;
+use crate::db::query::render;
 use crate::db::service::{Service, TenguService};
-use crate::lsp::cache::{reset_cache, ALL_TABLES};
+use crate::db::snapshot::{Snapshot, SnapshotService};
+use crate::db::table::{resolve, Table};
+use crate::lsp::cache::{reset_cache, ALL_TABLES, LOADED_CONNECTION};
 use crate::prelude::*;
+use crate::sql::format::{format_fragment, format_sql};
 use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
 use crate::tokenizer::{intersection, tokenize, Token};
 
@@ -18,59 +46,62 @@ struct Backend {
     service: TenguService,
 }
 
-#[tower_lsp::async_trait]
-impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
-        let tables = self.service.get_tables().await.unwrap_or(vec![]);
-        let mut all_tables = ALL_TABLES.lock().await;
-        for table in tables {
-            all_tables.insert(table);
+impl Backend {
+    fn config(&self, uri: &Url) -> WorkspaceConfig {
+        match uri.to_file_path() {
+            Ok(path) => WorkspaceConfig::load(path.parent().unwrap_or(&path)),
+            Err(_) => WorkspaceConfig::default(),
         }
-        Ok(InitializeResult {
-            server_info: Some(ServerInfo {
-                name: "tengu-lsp".to_string(),
-                version: Some("0.1.0".to_string()),
-            }),
-            capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
-                )),
-                hover_provider: Some(HoverProviderCapability::Simple(true)),
-                completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
-                    trigger_characters: None,
-                    completion_item: Some(CompletionOptionsCompletionItem {
-                        label_details_support: Some(true),
-                    }),
-                    ..CompletionOptions::default()
-                }),
-                ..ServerCapabilities::default()
-            },
-            ..InitializeResult::default()
-        })
     }
 
-    async fn initialized(&self, _: InitializedParams) {
-        self.client
-            .log_message(MessageType::INFO, "server initialized!")
-            .await;
-    }
-
-    async fn shutdown(&self) -> Result<()> {
-        Ok(())
-    }
-
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+    /// Every table, keyword, snippet, recent statement and column of the
+    /// file's tables, before they're ranked. With `qualify`, tables the search
+    /// path doesn't reach by name alone come with their schema.
+    #[allow(
+        clippy::redundant_closure_call,
+        clippy::match_like_matches_macro,
+        clippy::map_clone
+    )]
+    async fn completion_items(
+        &self,
+        params: &CompletionParams,
+        qualify: bool,
+    ) -> Vec<CompletionItem> {
+        let search_path = search_path(&self.service).await;
+        let engine = self.service.engine();
         let all_tables = ALL_TABLES.lock().await;
         let mut tables_in_file = TABLES_IN_FILE.lock().await;
         let mut all_columns = ALL_COLUMNS.lock().await;
+        let mut by_name: HashMap<String, Vec<&Table>> = HashMap::new();
+        for table in all_tables.iter() {
+            by_name
+                .entry(table.name.to_lowercase())
+                .or_default()
+                .push(table);
+        }
+        // The table each unqualified name finds on the search path.
+        let reachable: HashSet<Table> = by_name
+            .iter()
+            .flat_map(|(name, tables)| resolve(tables.iter().copied(), &search_path, name, None))
+            .filter(|table| search_path.contains(&table.schema))
+            .collect();
         let mut completions = || -> Option<Vec<CompletionItem>> {
             let mut table_items = Vec::new();
             for table in all_tables.iter() {
                 table_items.push(CompletionItem {
                     label: table.name.clone(),
                     kind: Some(CompletionItemKind::CLASS),
-                    insert_text: Some(table.name.clone()),
+                    detail: Some(table.schema.clone()),
+                    documentation: table.description.clone().map(Documentation::String),
+                    insert_text: Some(if qualify && !reachable.contains(table) {
+                        format!(
+                            "{}.{}",
+                            engine.quote(&table.schema),
+                            engine.quote(&table.name)
+                        )
+                    } else {
+                        engine.quote(&table.name)
+                    }),
                     insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
                     ..CompletionItem::default()
                 });
@@ -91,6 +122,13 @@ impl LanguageServer for Backend {
             Some(keyword_items)
         }();
         completions.concat(&keyword_completions);
+        completions.concat(&Some(snippet_completions().await));
+        let position = params.text_document_position.position;
+        if let Some(text) = document_text(&params.text_document_position.text_document.uri).await {
+            let line = text.lines().nth(position.line as usize).unwrap_or_default();
+            let prefix: String = line.chars().take(position.character as usize).collect();
+            completions.concat(&Some(history_completions(&prefix).await));
+        }
         let sql_file_path = params
             .text_document_position
             .text_document
@@ -98,7 +136,7 @@ impl LanguageServer for Backend {
             .to_file_path()
             .unwrap();
         let Ok(sql_file_content) = read_file_to_string(sql_file_path) else {
-            return Ok(completions.map(CompletionResponse::Array));
+            return completions.unwrap_or_default();
         };
 
         let content_tokens: Vec<_> = tokenize(sql_file_content.clone())
@@ -110,15 +148,22 @@ impl LanguageServer for Backend {
             })
             .collect();
         let tables = all_tables.iter().map(|t| t.clone().name).collect();
-        let tables_to_query = intersection(tables, content_tokens);
+        let tables_to_query = intersection(tables, content_tokens.clone());
+        // Of same-named tables, the one the search path finds and those whose
+        // schema the file names.
         let tables_to_query = all_tables
             .iter()
             .filter(|&t| tables_to_query.contains(&t.name))
+            .filter(|&t| {
+                by_name[&t.name.to_lowercase()].len() == 1
+                    || reachable.contains(t)
+                    || content_tokens.contains(&t.schema)
+            })
             .map(|t| t.clone())
             .collect();
         if tables_in_file.equals(&tables_to_query) {
             completions.concat(&Some(all_columns.clone()));
-            return Ok(completions.map(CompletionResponse::Array));
+            return completions.unwrap_or_default();
         } else {
             all_columns.clear();
             tables_in_file.clear();
@@ -128,7 +173,7 @@ impl LanguageServer for Backend {
         }
 
         let Ok(columns) = self.service.get_table_columns(tables_to_query).await else {
-            return Ok(completions.map(CompletionResponse::Array))
+            return completions.unwrap_or_default();
         };
         let column_completions = || -> Option<Vec<CompletionItem>> {
             let mut column_items = Vec::new();
@@ -140,6 +185,7 @@ impl LanguageServer for Backend {
                         ..CompletionItemLabelDetails::default()
                     }),
                     kind: Some(CompletionItemKind::PROPERTY),
+                    documentation: column.description.clone().map(Documentation::String),
                     insert_text: Some(column.name.to_owned()),
                     insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
                     ..CompletionItem::default()
@@ -151,59 +197,518 @@ impl LanguageServer for Backend {
             Some(column_items)
         }();
         completions.concat(&column_completions);
-        Ok(completions.map(CompletionResponse::Array))
+        completions.unwrap_or_default()
     }
-    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        let all_tables = ALL_TABLES.lock().await;
-        let Ok(file_path) = params
-            .text_document_position_params
-            .text_document
-            .uri
-            .to_file_path() else {
+    /// Lists the server's databases, or writes `.active` on the one named in
+    /// `arguments`; watching the file, the cache then loads its catalog.
+    async fn switch_database(&self, arguments: &[serde_json::Value]) -> Option<serde_json::Value> {
+        let databases = match self.service.list_databases().await {
+            Ok(databases) => databases,
+            Err(e) => {
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("Listing databases failed: {}", e),
+                    )
+                    .await;
+                return None;
+            }
+        };
+        let Some(name) = arguments.first().and_then(|a| a.as_str()) else {
+            return Some(serde_json::json!(databases));
+        };
+        let mut repo = FsTenguRepository::new();
+        let (Some(connection), Some(database)) = (
+            repo.get_active_connection(),
+            databases.iter().find(|d| d.eq_ignore_ascii_case(name)),
+        ) else {
+            self.client
+                .show_message(
+                    MessageType::ERROR,
+                    format!("No database {} on the active connection's server", name),
+                )
+                .await;
+            return None;
+        };
+        repo.activate_connection(&connection.on_database(database));
+        self.client
+            .show_message(MessageType::INFO, format!("Switched to {}", database))
+            .await;
+        None
+    }
+
+    /// Opens the DDL of the catalog object the `tengu-ddl:` URI in `arguments`
+    /// names, written to a file since the editor can't open the URI itself.
+    async fn show_ddl(&self, arguments: &[serde_json::Value]) -> Option<serde_json::Value> {
+        let target = arguments
+            .first()
+            .and_then(|a| serde_json::from_value::<Url>(a.clone()).ok())
+            .and_then(|uri| DdlTarget::parse(&uri))?;
+        let ddl = match target.kind {
+            None => {
+                let table = Table {
+                    name: target.name,
+                    schema: target.schema,
+                    description: None,
+                };
+                self.service.get_table_ddl(&table).await
+            }
+            Some(kind) => {
+                let object = DbObject {
+                    name: target.name,
+                    schema: target.schema,
+                    kind,
+                };
+                self.service.get_object_ddl(&object).await
+            }
+        };
+        let written = ddl.and_then(|ddl| Ok((write_results(&ddl, "sql")?, ddl)));
+        match written {
+            Ok((uri, ddl)) => {
+                let _ = self
+                    .client
+                    .show_document(ShowDocumentParams {
+                        uri,
+                        external: Some(false),
+                        take_focus: Some(true),
+                        selection: None,
+                    })
+                    .await;
+                Some(serde_json::Value::String(ddl))
+            }
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("Showing DDL failed: {}", e))
+                    .await;
+                None
+            }
+        }
+    }
+
+    /// Caches what linting `text` needs of the catalog, which edits in
+    /// between then lint against without querying.
+    async fn load_catalog(&self, uri: &Url, text: &str) {
+        let engine = self.config(uri).dialect_or(Some(self.service.engine()));
+        load_catalog(text, engine, &self.service).await;
+    }
+
+    async fn lint(&self, uri: Url, text: &str) {
+        let config = self.config(&uri);
+        let engine = config.dialect_or(Some(self.service.engine()));
+        let mut diagnostics = diagnostics(text, engine, &config, &self.service).await;
+        if let Some(drift) = DRIFT.lock().await.get(&uri) {
+            diagnostics.extend(drift.iter().cloned());
+        }
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+    }
+
+    /// Compares the database with the migrations, when the schema comes from
+    /// them, and reports the differences on the scripts.
+    async fn check_drift(&self) {
+        let TenguService::Migrations(service) = &self.service else {
+            return;
+        };
+        let drift = drift_diagnostics(service).await;
+        *DRIFT.lock().await = drift.clone();
+        for (uri, diagnostics) in drift {
+            let open = DOCUMENTS.lock().await.get(&uri).cloned();
+            match open {
+                Some(text) => self.lint(uri, &text).await,
+                None => {
+                    self.client
+                        .publish_diagnostics(uri, diagnostics, None)
+                        .await
+                }
+            }
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    #[allow(clippy::needless_update)]
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let tables = self.service.get_tables().await.unwrap_or(vec![]);
+        let mut all_tables = ALL_TABLES.lock().await;
+        for table in tables {
+            all_tables.insert(table);
+        }
+        let objects = self.service.get_objects().await.unwrap_or(vec![]);
+        ALL_OBJECTS.lock().await.extend(objects);
+        if let Ok(search_path) = self.service.get_search_path().await {
+            *SEARCH_PATH.lock().await = search_path;
+        }
+        let folders = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|f| f.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        WORKSPACE_FOLDERS
+            .lock()
+            .await
+            .extend(folders.iter().filter_map(|uri| uri.to_file_path().ok()));
+        Ok(InitializeResult {
+            server_info: Some(ServerInfo {
+                name: "tengu-lsp".to_string(),
+                version: Some("0.1.0".to_string()),
+            }),
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: COMMANDS.iter().map(|c| c.to_string()).collect(),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
+                completion_provider: Some(CompletionOptions {
+                    resolve_provider: Some(false),
+                    trigger_characters: Some(vec![".".to_string()]),
+                    completion_item: Some(CompletionOptionsCompletionItem {
+                        label_details_support: Some(true),
+                    }),
+                    ..CompletionOptions::default()
+                }),
+                ..ServerCapabilities::default()
+            },
+            ..InitializeResult::default()
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, "server initialized!")
+            .await;
+        self.check_drift().await;
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let text = params.text_document.text;
+        DOCUMENTS.lock().await.insert(uri.clone(), text.clone());
+        self.load_catalog(&uri, &text).await;
+        self.lint(uri, &text).await;
+    }
+
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        let Some(change) = params.content_changes.pop() else {
+            return;
+        };
+        let uri = params.text_document.uri;
+        DOCUMENTS
+            .lock()
+            .await
+            .insert(uri.clone(), change.text.clone());
+        self.lint(uri, &change.text).await;
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+        if let TenguService::Migrations(service) = &self.service {
+            let migration = uri
+                .to_file_path()
+                .is_ok_and(|path| path.starts_with(service.dir()));
+            if migration {
+                reload_catalog(&self.service).await;
+                self.check_drift().await;
+            }
+        }
+        let Some(text) = document_text(&uri).await else {
+            return;
+        };
+        self.load_catalog(&uri, &text).await;
+        self.lint(uri, &text).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        DOCUMENTS.lock().await.remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let actions = quick_fixes(&params.text_document.uri, &params.context.diagnostics);
+        Ok(Some(actions))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let Some(text) = document_text(&uri).await else {
             return Ok(None);
         };
+        let config = self.config(&uri);
+        let formatted = format_sql(
+            &text,
+            &config.dialect_or(Some(self.service.engine())),
+            &config.format,
+        );
+        if formatted == text {
+            return Ok(Some(vec![]));
+        }
+        Ok(Some(vec![TextEdit::new(full_range(&text), formatted)]))
+    }
 
-        let position = &params.text_document_position_params.position;
-        let Some(word) = get_word_at_position(position.line, position.character, file_path) else {
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let Some(text) = document_text(&uri).await else {
             return Ok(None);
         };
-        let Some(table) = all_tables.iter().find(|t| t.name == word) else {
+        let range = params.range;
+        let lines: Vec<&str> = text.split('\n').collect();
+        let start = range.start.line as usize;
+        // A selection that ends at the start of a line doesn't include that line.
+        let end = if range.end.character == 0 && range.end.line > range.start.line {
+            range.end.line as usize - 1
+        } else {
+            range.end.line as usize
+        }
+        .min(lines.len().saturating_sub(1));
+        if start > end {
             return Ok(None);
+        }
+        let selected = lines[start..=end].join("\n");
+        let config = self.config(&uri);
+        let mut formatted = format_fragment(
+            &selected,
+            &config.dialect_or(Some(self.service.engine())),
+            &config.format,
+        );
+        let replace = if end + 1 < lines.len() {
+            Range::new(
+                Position::new(start as u32, 0),
+                Position::new(end as u32 + 1, 0),
+            )
+        } else {
+            formatted.pop();
+            let full = full_range(&text);
+            Range::new(Position::new(start as u32, 0), full.end)
         };
-        let table_set = HashSet::from_iter(vec![table.clone()]);
-        let Ok(columns) = self.service.get_table_columns(table_set).await else {
+        Ok(Some(vec![TextEdit::new(replace, formatted)]))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+        let Some(text) = document_text(&uri).await else {
             return Ok(None);
         };
-        let mut contents = Vec::new();
-        for column in columns.iter() {
-            contents.push(MarkedString::from_markdown(format!(
-                "**{}**: {} {}",
-                column.name,
-                column.data_type,
-                if column.is_nullable.eq("YES"){
-                    "NULL"
-                } else {
-                    "NOT NULL"
-                }
+        let engine = self.config(&uri).dialect_or(Some(self.service.engine()));
+        Ok(Some(DocumentSymbolResponse::Nested(document_symbols(
+            &text, &engine,
+        ))))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let engine = self.service.engine();
+        Ok(Some(workspace_symbols(&params.query, engine).await))
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri;
+        let Some(text) = document_text(&uri).await else {
+            return Ok(None);
+        };
+        let engine = self.config(&uri).dialect_or(Some(self.service.engine()));
+        let mut lenses = code_lenses(&uri, &text, &engine);
+        lenses.extend(table_lenses(&text, &engine, &self.service).await);
+        Ok(Some(lenses))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        if params.command == SWITCH_DATABASE {
+            return Ok(self.switch_database(&params.arguments).await);
+        }
+        if params.command == SHOW_DDL {
+            return Ok(self.show_ddl(&params.arguments).await);
+        }
+        let Some(run) = Run::parse(&params.command, &params.arguments) else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "Unknown command or arguments: {}",
+                params.command
             )));
+        };
+        let Some(text) = document_text(run.uri()).await else {
+            return Ok(None);
+        };
+        let config = self.config(run.uri());
+        let engine = config.dialect_or(Some(self.service.engine()));
+        let scripts = run.scripts(&text, &engine);
+        let (rendered, extension) = if params.command == EXPLAIN_STATEMENT {
+            let mut plans = Vec::new();
+            for script in scripts {
+                match self.service.explain(&script).await {
+                    Ok(plan) => plans.extend(plan),
+                    Err(e) => {
+                        self.client
+                            .show_message(MessageType::ERROR, format!("Explain failed: {}", e))
+                            .await;
+                        return Ok(None);
+                    }
+                }
+            }
+            (render_plan(&plans), "txt")
+        } else {
+            let mut results = Vec::new();
+            for script in scripts {
+                match self.service.execute(&script).await {
+                    Ok(result) => results.extend(result),
+                    Err(e) => {
+                        self.client
+                            .show_message(MessageType::ERROR, format!("Query failed: {}", e))
+                            .await;
+                        return Ok(None);
+                    }
+                }
+            }
+            (render(&results, config.results), config.results.extension())
+        };
+        match write_results(&rendered, extension) {
+            Ok(uri) => {
+                let _ = self
+                    .client
+                    .show_document(ShowDocumentParams {
+                        uri,
+                        external: Some(false),
+                        take_focus: Some(true),
+                        selection: None,
+                    })
+                    .await;
+            }
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("Error writing results: {}", e))
+                    .await;
+            }
         }
-        Ok(Some(Hover {
-            contents: HoverContents::Array(contents),
-            range: None,
-        }))
+        Ok(Some(serde_json::Value::String(rendered)))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let engine = self.config(&uri).dialect_or(Some(self.service.engine()));
+        Ok(references(&uri, position, &engine, params.context.include_declaration).await)
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let uri = params.text_document.uri;
+        let engine = self.config(&uri).dialect_or(Some(self.service.engine()));
+        Ok(prepare_rename(&uri, params.position, &engine).await)
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let config = self.config(&uri);
+        let engine = config.dialect_or(Some(self.service.engine()));
+        let migrations = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| config.migrations_dir(&path));
+        Ok(rename(&uri, position, &params.new_name, &engine, migrations).await)
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let Some(text) = document_text(uri).await else {
+            let items = self.completion_items(&params, true).await;
+            return Ok(Some(CompletionResponse::Array(items)));
+        };
+        let engine = self.config(uri).dialect_or(Some(self.service.engine()));
+        let position = params.text_document_position.position;
+        let context = completion_context(&text, position, &engine);
+        // After `schema.` the name alone goes in.
+        let mut items = self
+            .completion_items(&params, context.path.is_empty())
+            .await;
+        items.extend(database_completions(&text, position, &context, &engine, &self.service).await);
+        let usage = name_usage().await;
+        Ok(Some(CompletionResponse::List(rank(
+            items, &context, &usage,
+        ))))
+    }
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let Some(text) = document_text(&uri).await else {
+            return Ok(None);
+        };
+        let engine = self.config(&uri).dialect_or(Some(self.service.engine()));
+        let position = params.text_document_position_params.position;
+        Ok(hover(&text, position, &engine, &self.service).await)
     }
 }
 
-pub async fn start_lsp() {
+pub async fn start_lsp(snapshot: Option<PathBuf>, migrations: Option<PathBuf>) {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
     let repo = FsTenguRepository::new();
     let active_connection_path = repo.active_connection_path();
-    let Some(active_connection) = repo.get_active_connection() else {
-        eprintln!("No active connection found");
-        return;
+    let live = match snapshot {
+        Some(path) => match Snapshot::load(&path) {
+            Ok(snapshot) => Some(TenguService::Snapshot(SnapshotService::from_snapshot(
+                path, snapshot,
+            ))),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                return;
+            }
+        },
+        None => match repo.get_active_connection() {
+            Some(connection) => {
+                *LOADED_CONNECTION.lock().await = Some(connection.clone());
+                Some(TenguService::new(connection.engine, repo))
+            }
+            None => None,
+        },
+    };
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let config = WorkspaceConfig::load(&cwd);
+    let migrations = migrations
+        .and_then(|dir| std::path::absolute(dir).ok())
+        .or_else(|| config.schema_migrations_dir(&cwd));
+    let service = match (migrations, live) {
+        (Some(dir), live) => {
+            let engine = config.dialect_or(live.as_ref().map(|s| s.engine()));
+            TenguService::Migrations(MigrationService::new(engine, dir, live))
+        }
+        (None, Some(live)) => live,
+        (None, None) => {
+            eprintln!("No active connection found");
+            return;
+        }
     };
-    let service = TenguService::new(active_connection.engine, repo);
 
     tokio::spawn(async move {
         async_watch(active_connection_path, reset_cache)
@@ -213,6 +718,98 @@ pub async fn start_lsp() {
             })
     });
 
-    let (service, socket) = LspService::new(|client| Backend { client, service });
+    let (service, socket) = LspService::new(|client| {
+        let reporter = client.clone();
+        cancel::report_to(move |message| {
+            let client = reporter.clone();
+            tokio::spawn(async move { client.show_message(MessageType::WARNING, message).await });
+        });
+        Backend { client, service }
+    });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::TableSchema;
+    use crate::db::column::Column;
+    use crate::db::engine::Engine;
+    use crate::lsp::cache::{load_test_catalog, TEST_CACHE};
+
+    #[tokio::test]
+    async fn should_send_descriptions_as_completion_documentation() {
+        let _cache = TEST_CACHE.lock().await;
+        let table = |name: &str, description: Option<&str>| Table {
+            name: name.to_string(),
+            schema: "public".to_string(),
+            description: description.map(str::to_string),
+        };
+        let column = |name: &str, description: Option<&str>| Column {
+            name: name.to_string(),
+            table: "orders".to_string(),
+            schema: "public".to_string(),
+            data_type: "text".to_string(),
+            is_nullable: "YES".to_string(),
+            description: description.map(str::to_string),
+            ..Column::default()
+        };
+        let service = load_test_catalog(
+            Engine::Postgres,
+            vec![
+                TableSchema {
+                    table: table("orders", Some("Orders placed by customers")),
+                    columns: vec![
+                        column("status", Some("new, paid or shipped")),
+                        column("note", None),
+                    ],
+                    indexes: vec![],
+                    constraints: vec![],
+                },
+                TableSchema {
+                    table: table("refunds", None),
+                    columns: vec![],
+                    indexes: vec![],
+                    constraints: vec![],
+                },
+            ],
+        )
+        .await;
+        let path =
+            std::env::temp_dir().join(format!("tengu-completion-{}.sql", std::process::id()));
+        std::fs::write(&path, "SELECT  FROM orders").unwrap();
+        let (lsp, _) = LspService::new(|client| Backend { client, service });
+        let params = CompletionParams {
+            text_document_position: TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(Url::from_file_path(&path).unwrap()),
+                Position::new(0, 7),
+            ),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: None,
+        };
+        let items = lsp.inner().completion_items(&params, false).await;
+        std::fs::remove_file(&path).unwrap();
+
+        let documentation = |label: &str| {
+            items
+                .iter()
+                .find(|i| i.label == label)
+                .unwrap()
+                .documentation
+                .clone()
+        };
+        assert_eq!(
+            documentation("orders"),
+            Some(Documentation::String(
+                "Orders placed by customers".to_string()
+            ))
+        );
+        assert_eq!(
+            documentation("status"),
+            Some(Documentation::String("new, paid or shipped".to_string()))
+        );
+        assert_eq!(documentation("refunds"), None);
+        assert_eq!(documentation("note"), None);
+    }
+}
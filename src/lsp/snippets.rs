@@ -0,0 +1,52 @@
+use std::fs;
+
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, InsertTextFormat, MarkupContent, MarkupKind,
+};
+
+use super::cache::SNIPPETS;
+use crate::snippet::{for_connection, to_lsp_snippet, Snippet, Snippets};
+use crate::terminal_ui::repository::{FsTenguRepository, TenguRepository};
+
+/// Saved snippets for the active connection, inserted with their placeholders
+/// as tab stops.
+pub async fn snippet_completions() -> Vec<CompletionItem> {
+    let snippets = Snippets::open();
+    let Ok(modified) = fs::metadata(snippets.path()).and_then(|m| m.modified()) else {
+        return vec![];
+    };
+    let mut cache = SNIPPETS.lock().await;
+    if cache.as_ref().is_none_or(|(seen, _)| *seen != modified) {
+        *cache = Some((modified, snippets.load().unwrap_or_default()));
+    }
+    let Some((_, all)) = cache.as_ref() else {
+        return vec![];
+    };
+    let connection = FsTenguRepository::new()
+        .get_active_connection()
+        .map(|c| c.name);
+    for_connection(all.clone(), connection.as_deref())
+        .iter()
+        .filter_map(completion_item)
+        .collect()
+}
+
+fn completion_item(snippet: &Snippet) -> Option<CompletionItem> {
+    Some(CompletionItem {
+        label: snippet.name.clone(),
+        kind: Some(CompletionItemKind::SNIPPET),
+        detail: Some(
+            snippet
+                .description
+                .clone()
+                .unwrap_or_else(|| "snippet".to_string()),
+        ),
+        documentation: Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```sql\n{}\n```", snippet.sql),
+        })),
+        insert_text: Some(to_lsp_snippet(&snippet.sql).ok()?),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..CompletionItem::default()
+    })
+}
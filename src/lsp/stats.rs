@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use tower_lsp::lsp_types::{CodeLens, Command, Range};
+
+use super::cache::{search_path, TABLE_STATS};
+use super::commands::{DdlTarget, SHOW_DDL};
+use crate::db::engine::Engine;
+use crate::db::service::{Service, TenguService};
+use crate::db::stats::TableStats;
+use crate::db::table::{resolve, Table};
+use crate::sql::statement::parse;
+
+/// Row counts drift; fetch them again after a while.
+const STATS_TTL: Duration = Duration::from_secs(300);
+
+/// A table named in the file and where its lens goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mention {
+    schema: Option<String>,
+    name: String,
+    range: Range,
+}
+
+/// Lenses with the size and last change of the tables a file creates, alters
+/// or first mentions. Clicking one opens the table's DDL.
+pub async fn table_lenses(text: &str, engine: &Engine, service: &TenguService) -> Vec<CodeLens> {
+    let mentions = mentions(text, engine);
+    if mentions.is_empty() {
+        return vec![];
+    }
+    let stale = TABLE_STATS
+        .lock()
+        .await
+        .as_ref()
+        .is_none_or(|(fetched, _)| fetched.elapsed() > STATS_TTL);
+    if stale {
+        let Ok(stats) = service.get_table_stats().await else {
+            return vec![];
+        };
+        let stats = stats.into_iter().map(|s| (s.table.clone(), s)).collect();
+        *TABLE_STATS.lock().await = Some((Instant::now(), stats));
+    }
+    let search_path = search_path(service).await;
+    let cache = TABLE_STATS.lock().await;
+    let Some((_, stats)) = cache.as_ref() else {
+        return vec![];
+    };
+    lenses(mentions, stats, &search_path)
+}
+
+/// A lens for each table the mentions resolve to, on its first mention.
+fn lenses(
+    mentions: Vec<Mention>,
+    stats: &HashMap<Table, TableStats>,
+    search_path: &[String],
+) -> Vec<CodeLens> {
+    let mut seen = HashSet::new();
+    mentions
+        .into_iter()
+        .filter_map(|mention| {
+            let [table] = resolve(
+                stats.keys(),
+                search_path,
+                &mention.name,
+                mention.schema.as_deref(),
+            )
+            .try_into()
+            .ok()?;
+            let stats = stats.get(&table)?;
+            if !seen.insert(table.clone()) {
+                return None;
+            }
+            let target = DdlTarget {
+                kind: None,
+                schema: table.schema,
+                name: table.name,
+            };
+            Some(CodeLens {
+                range: mention.range,
+                command: Some(Command {
+                    title: stats.summary(),
+                    command: SHOW_DDL.to_string(),
+                    arguments: target.uri().map(|uri| vec![serde_json::json!(uri)]),
+                }),
+                data: None,
+            })
+        })
+        .collect()
+}
+
+/// `CREATE TABLE` and `ALTER TABLE` statements, then the first reference to
+/// every other name, which may still turn out to be one of those tables.
+fn mentions(text: &str, engine: &Engine) -> Vec<Mention> {
+    let mut seen = HashSet::new();
+    let mut mentions = Vec::new();
+    for statement in parse(text, engine) {
+        if let Some(definition) = statement
+            .definition()
+            .filter(|d| d.object == "TABLE" && d.action != "DROP")
+        {
+            seen.insert(key(definition.name.schema(), &definition.name.name()));
+            mentions.push(Mention {
+                schema: definition.name.schema(),
+                name: definition.name.name(),
+                range: statement.range(),
+            });
+            continue;
+        }
+        for leaf in statement.leaves(engine) {
+            let ctes: HashSet<String> = leaf
+                .ctes()
+                .iter()
+                .map(|c| c.name.unquoted().to_lowercase())
+                .collect();
+            for table_ref in leaf.table_refs() {
+                let name = table_ref.name.name();
+                if table_ref.function || ctes.contains(&name.to_lowercase()) {
+                    continue;
+                }
+                if seen.insert(key(table_ref.name.schema(), &name)) {
+                    mentions.push(Mention {
+                        schema: table_ref.name.schema(),
+                        name,
+                        range: table_ref.name.range(),
+                    });
+                }
+            }
+        }
+    }
+    mentions
+}
+
+fn key(schema: Option<String>, name: &str) -> (Option<String>, String) {
+    (schema.map(|s| s.to_lowercase()), name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_mention_definitions_and_first_references() {
+        let sql = "CREATE TABLE dbo.orders (id int)\nGO\nWITH recent AS (SELECT * FROM orders)\nSELECT * FROM recent JOIN customers c ON 1 = 1 JOIN dbo.orders o ON 1 = 1\nGO\nSELECT * FROM customers";
+        let names: Vec<_> = mentions(sql, &Engine::SqlServer)
+            .into_iter()
+            .map(|m| (m.schema, m.name, m.range.start.line))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (Some("dbo".to_string()), "orders".to_string(), 0),
+                (None, "orders".to_string(), 2),
+                (None, "customers".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_resolve_along_the_search_path_and_lens_each_table_once() {
+        let table = |schema: &str| Table {
+            name: "orders".to_string(),
+            schema: schema.to_string(),
+            description: None,
+        };
+        let stats: HashMap<Table, TableStats> = ["dbo", "sales"]
+            .into_iter()
+            .map(|schema| {
+                let stats = TableStats {
+                    table: table(schema),
+                    rows: Some(12),
+                    bytes: None,
+                    modified: None,
+                    analyzed: None,
+                };
+                (table(schema), stats)
+            })
+            .collect();
+        let sql = "SELECT * FROM orders JOIN dbo.orders d ON 1 = 1 JOIN sales.orders s ON 1 = 1";
+        let search_path = vec!["sales".to_string(), "dbo".to_string()];
+        let lenses = lenses(mentions(sql, &Engine::SqlServer), &stats, &search_path);
+        let targets: Vec<_> = lenses
+            .iter()
+            .map(|lens| {
+                let command = lens.command.as_ref().unwrap();
+                assert_eq!(command.command, SHOW_DDL);
+                let uri = serde_json::from_value(command.arguments.as_ref().unwrap()[0].clone());
+                let target = DdlTarget::parse(&uri.unwrap()).unwrap();
+                (target.schema, lens.range.start.character)
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![("sales".to_string(), 14), ("dbo".to_string(), 26)]
+        );
+    }
+}
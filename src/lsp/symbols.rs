@@ -0,0 +1,190 @@
+use tower_lsp::lsp_types::{DocumentSymbol, Range, SymbolKind};
+
+use crate::db::engine::Engine;
+use crate::sql::statement::{parse, Statement};
+
+/// Outline of a SQL file: object definitions, DML statements and the CTEs they
+/// declare, grouped by `GO` batch when a T-SQL file has more than one.
+pub fn document_symbols(text: &str, engine: &Engine) -> Vec<DocumentSymbol> {
+    let statements = parse(text, engine);
+    let symbols: Vec<(usize, DocumentSymbol)> = statements
+        .iter()
+        .filter_map(|s| Some((s.batch, statement_symbol(s)?)))
+        .collect();
+    let batches = statements.last().map_or(0, |s| s.batch + 1);
+    if *engine != Engine::SqlServer || batches < 2 {
+        return symbols.into_iter().map(|(_, s)| s).collect();
+    }
+    (0..batches)
+        .filter_map(|batch| {
+            let in_batch: Vec<&Statement> =
+                statements.iter().filter(|s| s.batch == batch).collect();
+            let range = Range::new(
+                in_batch.first()?.range().start,
+                in_batch.last()?.range().end,
+            );
+            let children = symbols
+                .iter()
+                .filter(|(b, _)| *b == batch)
+                .map(|(_, s)| s.clone())
+                .collect();
+            Some(symbol(
+                format!("Batch {}", batch + 1),
+                None,
+                SymbolKind::MODULE,
+                range,
+                Range::new(range.start, range.start),
+                children,
+            ))
+        })
+        .collect()
+}
+
+fn statement_symbol(statement: &Statement) -> Option<DocumentSymbol> {
+    let range = statement.range();
+    let mut children: Vec<DocumentSymbol> = statement
+        .ctes()
+        .into_iter()
+        .map(|cte| {
+            symbol(
+                cte.name.unquoted(),
+                Some("CTE".to_string()),
+                SymbolKind::CLASS,
+                cte.name.range(),
+                cte.name.range(),
+                vec![],
+            )
+        })
+        .collect();
+    if let Some(definition) = statement.definition() {
+        children.extend(statement.columns().into_iter().map(|column| {
+            symbol(
+                column.unquoted(),
+                None,
+                SymbolKind::FIELD,
+                column.range(),
+                column.range(),
+                vec![],
+            )
+        }));
+        let kind = object_kind(&definition.object);
+        return Some(symbol(
+            definition.name.to_string_unquoted(),
+            Some(format!("{} {}", definition.action, definition.object)),
+            kind,
+            range,
+            definition.name.range(),
+            children,
+        ));
+    }
+    let verb = statement.verb();
+    if !matches!(
+        verb.as_str(),
+        "SELECT" | "INSERT" | "UPDATE" | "DELETE" | "MERGE"
+    ) {
+        return None;
+    }
+    let selection = statement
+        .tokens
+        .iter()
+        .find(|t| t.is_word(&verb))
+        .map_or(range, |t| t.range());
+    Some(symbol(
+        dml_label(statement, &verb),
+        None,
+        SymbolKind::OBJECT,
+        range,
+        selection,
+        children,
+    ))
+}
+
+/// Symbol kind for the object a `CREATE` makes, e.g. `TABLE` or `VIEW`.
+pub fn object_kind(object: &str) -> SymbolKind {
+    match object {
+        "TABLE" => SymbolKind::STRUCT,
+        "VIEW" => SymbolKind::INTERFACE,
+        "PROCEDURE" => SymbolKind::METHOD,
+        "FUNCTION" => SymbolKind::FUNCTION,
+        "TRIGGER" => SymbolKind::EVENT,
+        "INDEX" => SymbolKind::KEY,
+        "SCHEMA" | "DATABASE" => SymbolKind::NAMESPACE,
+        "TYPE" | "DOMAIN" => SymbolKind::TYPE_PARAMETER,
+        "SEQUENCE" => SymbolKind::CONSTANT,
+        _ => SymbolKind::OBJECT,
+    }
+}
+
+/// `SELECT dbo.users, dbo.roles`, `UPDATE dbo.users`, ... naming the first
+/// few tables the statement reads or writes.
+fn dml_label(statement: &Statement, verb: &str) -> String {
+    let ctes = statement.ctes();
+    let mut tables: Vec<String> = Vec::new();
+    for table in statement.table_refs() {
+        let name = table.name.to_string_unquoted();
+        let is_cte = ctes
+            .iter()
+            .any(|c| c.name.unquoted().eq_ignore_ascii_case(&name));
+        if !is_cte && !tables.contains(&name) {
+            tables.push(name);
+        }
+    }
+    match tables.len() {
+        0 => verb.to_string(),
+        1..=3 => format!("{} {}", verb, tables.join(", ")),
+        n => format!("{} {} +{}", verb, tables[..3].join(", "), n - 3),
+    }
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: Range,
+    selection_range: Range,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(symbols: &[DocumentSymbol]) -> Vec<String> {
+        symbols.iter().map(|s| s.name.clone()).collect()
+    }
+
+    #[test]
+    fn should_outline_definitions_and_dml_by_batch() {
+        let sql = "CREATE TABLE dbo.users (id int, name nvarchar(50))\nGO\nCREATE PROC dbo.get_users AS\nSELECT * FROM dbo.users\nGO\nWITH recent AS (SELECT id FROM dbo.users)\nDELETE FROM dbo.users WHERE id IN (SELECT id FROM recent)\nSET NOCOUNT ON";
+        let symbols = document_symbols(sql, &Engine::SqlServer);
+        assert_eq!(names(&symbols), vec!["Batch 1", "Batch 2", "Batch 3"]);
+        let table = &symbols[0].children.as_ref().unwrap()[0];
+        assert_eq!(table.name, "dbo.users");
+        assert_eq!(table.kind, SymbolKind::STRUCT);
+        assert_eq!(names(table.children.as_ref().unwrap()), vec!["id", "name"]);
+        let procedure = &symbols[1].children.as_ref().unwrap()[0];
+        assert_eq!(procedure.detail.as_deref(), Some("CREATE PROCEDURE"));
+        let delete = symbols[2].children.as_ref().unwrap();
+        assert_eq!(names(delete), vec!["DELETE dbo.users"]);
+        assert_eq!(names(delete[0].children.as_ref().unwrap()), vec!["recent"]);
+    }
+
+    #[test]
+    fn should_not_group_postgres_statements() {
+        let sql = "CREATE VIEW v AS SELECT 1;\nINSERT INTO a SELECT * FROM b;";
+        let symbols = document_symbols(sql, &Engine::Postgres);
+        assert_eq!(names(&symbols), vec!["v", "INSERT a, b"]);
+    }
+}
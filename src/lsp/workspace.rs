@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::{Location, Range, SymbolInformation, SymbolKind, Url};
+
+use super::cache::{
+    FileDefinition, ALL_OBJECTS, ALL_TABLES, DOCUMENTS, FILE_DEFINITIONS, LOADED_CONNECTION,
+    WORKSPACE_FOLDERS,
+};
+use super::commands::DdlTarget;
+use super::document::document_text;
+use super::symbols::object_kind;
+use crate::cli::sql_files;
+use crate::config::WorkspaceConfig;
+use crate::db::engine::Engine;
+use crate::db::object::ObjectKind;
+use crate::fuzzy;
+use crate::sql::statement::parse;
+
+const MAX_RESULTS: usize = 200;
+
+/// Objects created in the workspace's SQL files and the catalog of the active
+/// connection, fuzzy-matched against `query`. The container name tells them
+/// apart: the file's path for the former, the connection for the latter, whose
+/// objects point at a `tengu-ddl:` URI that `tengu.showDdl` opens.
+pub async fn workspace_symbols(query: &str, engine: Engine) -> Vec<SymbolInformation> {
+    let mut symbols = file_symbols(engine).await;
+    symbols.extend(catalog_symbols().await);
+    let mut ranked = fuzzy::filter(query, symbols, |s| s.name.as_str());
+    ranked.truncate(MAX_RESULTS);
+    ranked
+}
+
+async fn file_symbols(engine: Engine) -> Vec<SymbolInformation> {
+    let roots = WORKSPACE_FOLDERS.lock().await.clone();
+    let mut symbols = Vec::new();
+    for root in roots {
+        let engine = WorkspaceConfig::load(&root).dialect_or(Some(engine.clone()));
+        for path in sql_files(std::slice::from_ref(&root)) {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let container = path
+                .strip_prefix(&root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            for definition in file_definitions(&path, &uri, &engine).await {
+                symbols.push(workspace_symbol(
+                    definition.name,
+                    object_kind(&definition.object),
+                    container.clone(),
+                    Location::new(uri.clone(), definition.range),
+                ));
+            }
+        }
+    }
+    symbols
+}
+
+/// Definitions in `path`, parsed again only when the file changed on disk.
+/// Files open in the editor are always read from the editor's copy.
+async fn file_definitions(path: &Path, uri: &Url, engine: &Engine) -> Vec<FileDefinition> {
+    let open = DOCUMENTS.lock().await.contains_key(uri);
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    if !open {
+        if let (Some(modified), Some((cached_at, definitions))) =
+            (modified, FILE_DEFINITIONS.lock().await.get(path))
+        {
+            if *cached_at == modified {
+                return definitions.clone();
+            }
+        }
+    }
+    let Some(text) = document_text(uri).await else {
+        return vec![];
+    };
+    let definitions = definitions(&text, engine);
+    if let (false, Some(modified)) = (open, modified) {
+        FILE_DEFINITIONS
+            .lock()
+            .await
+            .insert(PathBuf::from(path), (modified, definitions.clone()));
+    }
+    definitions
+}
+
+fn definitions(text: &str, engine: &Engine) -> Vec<FileDefinition> {
+    parse(text, engine)
+        .iter()
+        .filter_map(|statement| statement.definition())
+        .filter(|d| {
+            d.action == "CREATE"
+                || d.action == "ALTER"
+                    && matches!(
+                        d.object.as_str(),
+                        "VIEW" | "PROCEDURE" | "FUNCTION" | "TRIGGER"
+                    )
+        })
+        .map(|d| FileDefinition {
+            name: d.name.to_string_unquoted(),
+            object: d.object,
+            range: d.name.range(),
+        })
+        .collect()
+}
+
+async fn catalog_symbols() -> Vec<SymbolInformation> {
+    let connection = match &*LOADED_CONNECTION.lock().await {
+        Some(connection) => connection.name.clone(),
+        None => "catalog".to_string(),
+    };
+    let container = format!("{} (database)", connection);
+    let symbol = |kind: Option<ObjectKind>, schema: &str, name: &str| {
+        let target = DdlTarget {
+            kind,
+            schema: schema.to_string(),
+            name: name.to_string(),
+        };
+        Some(workspace_symbol(
+            format!("{}.{}", schema, name),
+            match kind {
+                None => SymbolKind::STRUCT,
+                Some(ObjectKind::View) => SymbolKind::INTERFACE,
+                Some(ObjectKind::Procedure) => SymbolKind::METHOD,
+                Some(ObjectKind::Function) => SymbolKind::FUNCTION,
+            },
+            container.clone(),
+            Location::new(target.uri()?, Range::default()),
+        ))
+    };
+    let objects = ALL_OBJECTS.lock().await.clone();
+    let mut symbols: Vec<SymbolInformation> = objects
+        .iter()
+        .filter_map(|o| symbol(Some(o.kind), &o.schema, &o.name))
+        .collect();
+    // Postgres lists views among the tables too.
+    let tables = ALL_TABLES.lock().await.clone();
+    symbols.extend(
+        tables
+            .iter()
+            .filter(|t| {
+                !objects
+                    .iter()
+                    .any(|o| o.name == t.name && o.schema == t.schema)
+            })
+            .filter_map(|t| symbol(None, &t.schema, &t.name)),
+    );
+    symbols
+}
+
+#[allow(deprecated)]
+fn workspace_symbol(
+    name: String,
+    kind: SymbolKind,
+    container_name: String,
+    location: Location,
+) -> SymbolInformation {
+    SymbolInformation {
+        name,
+        kind,
+        tags: None,
+        deprecated: None,
+        location,
+        container_name: Some(container_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::object::DbObject;
+    use crate::lsp::cache::{load_test_catalog, TEST_CACHE};
+
+    #[tokio::test]
+    async fn should_find_objects_only_the_catalog_knows() {
+        let _cache = TEST_CACHE.lock().await;
+        load_test_catalog(Engine::SqlServer, vec![]).await;
+        WORKSPACE_FOLDERS.lock().await.clear();
+        ALL_OBJECTS.lock().await.insert(DbObject {
+            name: "usp_rebuild_orders".to_string(),
+            schema: "dbo".to_string(),
+            kind: ObjectKind::Procedure,
+        });
+
+        let symbols = workspace_symbols("rebuild", Engine::SqlServer).await;
+
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "dbo.usp_rebuild_orders");
+        assert_eq!(symbols[0].kind, SymbolKind::METHOD);
+        assert!(symbols[0]
+            .container_name
+            .as_ref()
+            .unwrap()
+            .ends_with("(database)"));
+        assert_eq!(
+            DdlTarget::parse(&symbols[0].location.uri),
+            Some(DdlTarget {
+                kind: Some(ObjectKind::Procedure),
+                schema: "dbo".to_string(),
+                name: "usp_rebuild_orders".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_find_objects_created_or_altered_in_a_file() {
+        let sql = "CREATE TABLE dbo.orders (id int)\nGO\nCREATE OR ALTER PROCEDURE dbo.usp_rebuild_orders AS SELECT 1\nGO\nALTER TABLE dbo.orders ADD n int\nGO\nDROP VIEW dbo.v";
+        let names: Vec<_> = definitions(sql, &Engine::SqlServer)
+            .into_iter()
+            .map(|d| (d.name, d.object))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("dbo.orders".to_string(), "TABLE".to_string()),
+                (
+                    "dbo.usp_rebuild_orders".to_string(),
+                    "PROCEDURE".to_string()
+                ),
+            ]
+        );
+    }
+}
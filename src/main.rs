@@ -1,18 +1,195 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use db::engine::Engine;
+use db::export::ExportFormat;
+use db::import::{ImportFormat, BATCH_SIZE};
 use lsp::server::start_lsp;
 use terminal_ui::start_tui;
 use tokio::main;
 
+mod cli;
+mod config;
 mod db;
+mod fuzzy;
+mod history;
 mod lsp;
 mod prelude;
+mod snippet;
+mod sql;
 mod terminal_ui;
 mod tokenizer;
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    Lsp,
+    Lsp {
+        /// Serve the schema in a file written by `tengu schema dump` instead
+        /// of the active connection's
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+        /// Complete and lint against the schema the Flyway, sqitch or dbmate
+        /// migrations in this folder build, defaults to tengu.json's
+        /// `migrations`
+        #[arg(long)]
+        migrations: Option<PathBuf>,
+    },
+    /// Format SQL files in place
+    Fmt {
+        /// Files or directories to format, defaults to the current directory
+        paths: Vec<PathBuf>,
+        /// Only report files that would change and exit with an error if any
+        #[arg(long)]
+        check: bool,
+        /// sqlserver or postgres, defaults to tengu.json or the active connection
+        #[arg(long, value_parser = parse_engine)]
+        dialect: Option<Engine>,
+    },
+    /// Check SQL files against the lint rules
+    Lint {
+        /// Files or directories to lint, defaults to the current directory
+        paths: Vec<PathBuf>,
+        /// sqlserver or postgres, defaults to tengu.json or the active connection
+        #[arg(long, value_parser = parse_engine)]
+        dialect: Option<Engine>,
+        /// List the rules and their default severities
+        #[arg(long)]
+        rules: bool,
+    },
+    /// List the statements run through tengu, most recent first
+    History {
+        /// Only statements fuzzy-matching this text
+        query: Option<String>,
+        /// Number of statements to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Run the statement listed with this number again
+        #[arg(long)]
+        run: Option<usize>,
+    },
+    /// Run a query on the active connection and export its results
+    Export {
+        /// The SQL to run
+        sql: Option<String>,
+        /// Read the SQL from a file instead
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// File to write, stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// csv, excel-csv, json, ndjson, sql or parquet; defaults to the
+        /// output's extension, then csv
+        #[arg(long, value_parser = parse_export_format)]
+        format: Option<ExportFormat>,
+        /// Table named in INSERT statements
+        #[arg(long)]
+        table: Option<String>,
+    },
+    /// Compare the schemas of two saved connections
+    Diff {
+        /// The connection whose schema would change
+        from: String,
+        /// The connection whose schema it would change to
+        to: String,
+        /// Print a migration script from the first schema to the second
+        /// instead of the report
+        #[arg(long)]
+        script: bool,
+    },
+    /// Load a CSV or JSON file into a table on the active connection
+    Import {
+        /// The file to load; its header or keys name the columns
+        file: PathBuf,
+        /// The table, as schema.name or just name in the default schema
+        #[arg(long)]
+        table: String,
+        /// csv, json or ndjson; defaults to the file's extension
+        #[arg(long, value_parser = parse_import_format)]
+        format: Option<ImportFormat>,
+        /// Rows sent per bulk load
+        #[arg(long, default_value_t = BATCH_SIZE)]
+        batch_size: usize,
+        /// Only check the file against the table
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Save the schema of a connection to files
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommand,
+    },
+    /// Manage saved SQL snippets
+    Snippet {
+        #[command(subcommand)]
+        command: SnippetCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SchemaCommand {
+    /// Write every table, view and routine of a saved connection
+    Dump {
+        /// The saved connection
+        connection: String,
+        /// A .json file for a snapshot the LSP can serve, or a directory for
+        /// a .sql file per object
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SnippetCommand {
+    /// Save a snippet, replacing one with the same name
+    Add {
+        name: String,
+        /// The SQL, with placeholders like {{customer_id:int}}
+        sql: Option<String>,
+        /// Read the SQL from a file instead
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Only offer it on this connection
+        #[arg(long)]
+        connection: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// List the saved snippets
+    List,
+    /// Delete a snippet
+    Remove {
+        name: String,
+        /// The connection it was saved for, if any
+        #[arg(long)]
+        connection: Option<String>,
+    },
+}
+
+fn parse_engine(engine: &str) -> Result<Engine, String> {
+    match engine {
+        "sqlserver" | "postgres" | "mysql" => Ok(Engine::from(engine.to_string())),
+        _ => Err(format!("unknown dialect {}", engine)),
+    }
+}
+
+fn parse_export_format(format: &str) -> Result<ExportFormat, String> {
+    ExportFormat::parse(format).ok_or_else(|| {
+        format!(
+            "unknown format {}, expected one of {}",
+            format,
+            ExportFormat::NAMES.join(", ")
+        )
+    })
+}
+
+fn parse_import_format(format: &str) -> Result<ImportFormat, String> {
+    ImportFormat::parse(format).ok_or_else(|| {
+        format!(
+            "unknown format {}, expected one of {}",
+            format,
+            ImportFormat::NAMES.join(", ")
+        )
+    })
 }
 
 #[derive(Parser, Debug)]
@@ -23,11 +200,77 @@ struct Args {
 
 #[main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let result = run(Args::parse()).await;
+    db::tunnel::close_all().await;
+    result
+}
+
+async fn run(args: Args) -> Result<()> {
     match args.commands {
-        Some(Command::Lsp) => {
-            start_lsp().await;
+        Some(Command::Lsp {
+            snapshot,
+            migrations,
+        }) => {
+            start_lsp(snapshot, migrations).await;
+        }
+        Some(Command::Fmt {
+            paths,
+            check,
+            dialect,
+        }) => {
+            if !cli::fmt::run(paths, check, dialect)? && check {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Lint {
+            paths,
+            dialect,
+            rules,
+        }) => {
+            if rules {
+                cli::lint::print_rules();
+            } else if !cli::lint::run(paths, dialect)? {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::History { query, limit, run }) => {
+            cli::history::run(query, limit, run).await?;
+        }
+        Some(Command::Export {
+            sql,
+            file,
+            output,
+            format,
+            table,
+        }) => {
+            cli::export::run(sql, file, output, format, table).await?;
+        }
+        Some(Command::Diff { from, to, script }) => {
+            cli::diff::run(from, to, script).await?;
+        }
+        Some(Command::Import {
+            file,
+            table,
+            format,
+            batch_size,
+            dry_run,
+        }) => {
+            cli::import::run(file, table, format, batch_size, dry_run).await?;
         }
+        Some(Command::Schema { command }) => match command {
+            SchemaCommand::Dump { connection, out } => cli::schema::dump(connection, out).await?,
+        },
+        Some(Command::Snippet { command }) => match command {
+            SnippetCommand::Add {
+                name,
+                sql,
+                file,
+                connection,
+                description,
+            } => cli::snippet::add(name, sql, file, connection, description)?,
+            SnippetCommand::List => cli::snippet::list()?,
+            SnippetCommand::Remove { name, connection } => cli::snippet::remove(name, connection)?,
+        },
         None => {
             start_tui()?;
         }
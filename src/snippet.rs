@@ -0,0 +1,335 @@
+//! Named SQL snippets with typed placeholders such as `{{customer_id:int}}`,
+//! kept in `~/.config/tengu/snippets/snippets.json`.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::db::engine::Engine;
+use crate::prelude::read_file_to_string;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    pub sql: String,
+    /// The connection the snippet belongs to, or `None` for every connection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    Int,
+    Number,
+    Text,
+    Date,
+    Bool,
+}
+
+impl ParamType {
+    fn parse(name: &str) -> Result<ParamType> {
+        match name.to_lowercase().as_str() {
+            "" | "text" | "string" | "varchar" => Ok(ParamType::Text),
+            "int" | "integer" | "bigint" => Ok(ParamType::Int),
+            "number" | "numeric" | "decimal" | "float" => Ok(ParamType::Number),
+            "date" | "datetime" | "timestamp" => Ok(ParamType::Date),
+            "bool" | "boolean" | "bit" => Ok(ParamType::Bool),
+            _ => bail!("unknown placeholder type {}", name),
+        }
+    }
+
+    /// The value as a SQL literal, or an error saying why it isn't one.
+    /// `null` is accepted for every type.
+    /// Text is bound as written, spaces included, and as `N'…'` on SQL
+    /// Server so it survives a non-UTF-8 collation; other types are trimmed.
+    fn literal(&self, raw: &str, engine: &Engine) -> Result<String> {
+        let value = raw.trim();
+        if value.eq_ignore_ascii_case("null") {
+            return Ok("NULL".to_string());
+        }
+        let quoted = |value: &str| format!("'{}'", value.replace('\'', "''"));
+        match self {
+            ParamType::Text => match engine {
+                Engine::SqlServer => Ok(format!("N{}", quoted(raw))),
+                _ => Ok(quoted(raw)),
+            },
+            ParamType::Int => value
+                .parse::<i64>()
+                .map(|n| n.to_string())
+                .map_err(|_| anyhow!("{} isn't an integer", value)),
+            ParamType::Number => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(value.to_string()),
+                _ => bail!("{} isn't a number", value),
+            },
+            ParamType::Date => {
+                let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+                    || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").is_ok();
+                if date {
+                    Ok(quoted(value))
+                } else {
+                    bail!(
+                        "{} isn't a date like 2024-03-02 or 2024-03-02 10:11:12",
+                        value
+                    )
+                }
+            }
+            ParamType::Bool => {
+                let truth = match value.to_lowercase().as_str() {
+                    "true" | "1" | "yes" => true,
+                    "false" | "0" | "no" => false,
+                    _ => bail!("{} isn't true or false", value),
+                };
+                Ok(match (engine, truth) {
+                    (Engine::SqlServer, true) => "1",
+                    (Engine::SqlServer, false) => "0",
+                    (_, true) => "TRUE",
+                    (_, false) => "FALSE",
+                }
+                .to_string())
+            }
+        }
+    }
+}
+
+impl Display for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamType::Int => write!(f, "int"),
+            ParamType::Number => write!(f, "number"),
+            ParamType::Text => write!(f, "text"),
+            ParamType::Date => write!(f, "date"),
+            ParamType::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub param_type: ParamType,
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder),
+}
+
+/// Splits the SQL into text and placeholders. `{{...}}` that isn't a name,
+/// optionally followed by `:type`, is left as text.
+fn parts(sql: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = sql;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        let (name, param_type) = inner.split_once(':').unwrap_or((inner, ""));
+        let name = name.trim();
+        let is_name = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !is_name {
+            parts.push(Part::Text(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        parts.push(Part::Text(&rest[..start]));
+        parts.push(Part::Placeholder(Placeholder {
+            name: name.to_string(),
+            param_type: ParamType::parse(param_type.trim())?,
+        }));
+        rest = &rest[start + 4 + len..];
+    }
+    parts.push(Part::Text(rest));
+    Ok(parts)
+}
+
+/// The distinct placeholders in order of first use.
+pub fn placeholders(sql: &str) -> Result<Vec<Placeholder>> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    for part in parts(sql)? {
+        if let Part::Placeholder(p) = part {
+            if !placeholders.iter().any(|q| q.name == p.name) {
+                placeholders.push(p);
+            }
+        }
+    }
+    Ok(placeholders)
+}
+
+/// The snippet in LSP snippet syntax, each placeholder a tab stop named after
+/// it, repeated uses sharing one.
+pub fn to_lsp_snippet(sql: &str) -> Result<String> {
+    let names: Vec<String> = placeholders(sql)?.into_iter().map(|p| p.name).collect();
+    let mut snippet = String::new();
+    for part in parts(sql)? {
+        match part {
+            Part::Text(text) => {
+                for c in text.chars() {
+                    if matches!(c, '$' | '}' | '\\') {
+                        snippet.push('\\');
+                    }
+                    snippet.push(c);
+                }
+            }
+            Part::Placeholder(p) => {
+                let index = names.iter().position(|n| *n == p.name).unwrap_or(0) + 1;
+                snippet.push_str(&format!("${{{}:{}}}", index, p.name));
+            }
+        }
+    }
+    Ok(snippet)
+}
+
+/// The SQL with every placeholder replaced by its value as a literal.
+pub fn bind(sql: &str, engine: &Engine, values: &HashMap<String, String>) -> Result<String> {
+    let mut bound = String::new();
+    for part in parts(sql)? {
+        match part {
+            Part::Text(text) => bound.push_str(text),
+            Part::Placeholder(p) => {
+                let value = values
+                    .get(&p.name)
+                    .ok_or_else(|| anyhow!("no value for {}", p.name))?;
+                let literal = p
+                    .param_type
+                    .literal(value, engine)
+                    .map_err(|e| anyhow!("{}: {}", p.name, e))?;
+                bound.push_str(&literal);
+            }
+        }
+    }
+    Ok(bound)
+}
+
+#[derive(Debug, Clone)]
+pub struct Snippets {
+    path: PathBuf,
+}
+
+impl Snippets {
+    pub fn open() -> Snippets {
+        let path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".config")
+            .join("tengu")
+            .join("snippets")
+            .join("snippets.json");
+        Snippets { path }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<Vec<Snippet>> {
+        let Ok(contents) = read_file_to_string(self.path.clone()) else {
+            return Ok(vec![]);
+        };
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    fn save(&self, snippets: &[Snippet]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(snippets)?)?;
+        Ok(())
+    }
+
+    /// Saves the snippet, replacing one with the same name and connection.
+    pub fn add(&self, snippet: Snippet) -> Result<()> {
+        placeholders(&snippet.sql)?;
+        let mut snippets = self.load()?;
+        snippets.retain(|s| s.name != snippet.name || s.connection != snippet.connection);
+        snippets.push(snippet);
+        snippets.sort_by(|a, b| (&a.connection, &a.name).cmp(&(&b.connection, &b.name)));
+        self.save(&snippets)
+    }
+
+    /// Removes the snippet, returning whether there was one.
+    pub fn remove(&self, name: &str, connection: Option<&str>) -> Result<bool> {
+        let mut snippets = self.load()?;
+        let count = snippets.len();
+        snippets.retain(|s| s.name != name || s.connection.as_deref() != connection);
+        if snippets.len() == count {
+            return Ok(false);
+        }
+        self.save(&snippets)?;
+        Ok(true)
+    }
+}
+
+/// The global snippets and those of `connection`, which win over a global
+/// one of the same name.
+pub fn for_connection(snippets: Vec<Snippet>, connection: Option<&str>) -> Vec<Snippet> {
+    let mut visible: Vec<Snippet> = snippets
+        .iter()
+        .filter(|s| s.connection.is_some() && s.connection.as_deref() == connection)
+        .cloned()
+        .collect();
+    for snippet in snippets.into_iter().filter(|s| s.connection.is_none()) {
+        if !visible.iter().any(|s| s.name == snippet.name) {
+            visible.push(snippet);
+        }
+    }
+    visible.sort_by(|a, b| a.name.cmp(&b.name));
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQL: &str = "SELECT * FROM orders WHERE customer_id = {{customer_id:int}} \
+        AND created_at >= {{since:date}} AND note <> {{note}} OR id = {{customer_id:int}} -- ${x}";
+
+    #[test]
+    fn should_turn_placeholders_into_tab_stops() {
+        let names: Vec<String> = placeholders(SQL)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["customer_id", "since", "note"]);
+        assert_eq!(
+            to_lsp_snippet(SQL).unwrap(),
+            "SELECT * FROM orders WHERE customer_id = ${1:customer_id} \
+        AND created_at >= ${2:since} AND note <> ${3:note} OR id = ${1:customer_id} -- \\${x\\}"
+        );
+        assert!(placeholders("SELECT {{id:uuid}}").is_err());
+    }
+
+    #[test]
+    fn should_bind_values_as_literals() {
+        let mut values = HashMap::from([
+            ("customer_id".to_string(), " 42".to_string()),
+            ("since".to_string(), "2024-03-02".to_string()),
+            ("note".to_string(), "O'Brien".to_string()),
+        ]);
+        assert_eq!(
+            bind(SQL, &Engine::SqlServer, &values).unwrap(),
+            "SELECT * FROM orders WHERE customer_id = 42 \
+        AND created_at >= '2024-03-02' AND note <> N'O''Brien' OR id = 42 -- ${x}"
+        );
+        values.insert("note".to_string(), "  Zoë ".to_string());
+        assert_eq!(
+            bind("SELECT {{note}}", &Engine::SqlServer, &values).unwrap(),
+            "SELECT N'  Zoë '"
+        );
+        assert_eq!(
+            bind("SELECT {{note}}", &Engine::Postgres, &values).unwrap(),
+            "SELECT '  Zoë '"
+        );
+        values.insert(
+            "customer_id".to_string(),
+            "42; DROP TABLE orders".to_string(),
+        );
+        assert!(bind(SQL, &Engine::SqlServer, &values).is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! Short reference docs for built-in functions and keywords, shown on hover.
+
+use crate::db::engine::Engine;
+
+/// Name, signature and a one-line summary.
+type FunctionDoc = (&'static str, &'static str, &'static str);
+
+const COMMON_FUNCTIONS: &[FunctionDoc] = &[
+    ("abs", "abs(numeric) → numeric", "Absolute value."),
+    (
+        "avg",
+        "avg(expression) → numeric",
+        "Average of the non-null values.",
+    ),
+    (
+        "cast",
+        "cast(expression AS type) → type",
+        "Converts a value to another type.",
+    ),
+    (
+        "ceiling",
+        "ceiling(numeric) → numeric",
+        "Smallest integer not less than the argument.",
+    ),
+    (
+        "coalesce",
+        "coalesce(value, value [, ...]) → value",
+        "First of its arguments that isn't null.",
+    ),
+    (
+        "concat",
+        "concat(value, value [, ...]) → text",
+        "Concatenates the arguments, treating nulls as empty strings.",
+    ),
+    (
+        "count",
+        "count(* | expression) → bigint",
+        "Number of rows, or of non-null values of the expression.",
+    ),
+    (
+        "dense_rank",
+        "dense_rank() OVER (...) → bigint",
+        "Rank of the row within its partition, without gaps.",
+    ),
+    (
+        "floor",
+        "floor(numeric) → numeric",
+        "Largest integer not greater than the argument.",
+    ),
+    (
+        "lag",
+        "lag(value [, offset [, default]]) OVER (...) → value",
+        "Value from a row `offset` rows before the current one in the partition.",
+    ),
+    (
+        "lead",
+        "lead(value [, offset [, default]]) OVER (...) → value",
+        "Value from a row `offset` rows after the current one in the partition.",
+    ),
+    (
+        "lower",
+        "lower(text) → text",
+        "Converts the string to lower case.",
+    ),
+    ("ltrim", "ltrim(text) → text", "Removes leading spaces."),
+    ("max", "max(expression) → value", "Largest non-null value."),
+    ("min", "min(expression) → value", "Smallest non-null value."),
+    (
+        "nullif",
+        "nullif(value1, value2) → value",
+        "Null if both arguments are equal, otherwise `value1`.",
+    ),
+    (
+        "rank",
+        "rank() OVER (...) → bigint",
+        "Rank of the row within its partition, with gaps after ties.",
+    ),
+    (
+        "replace",
+        "replace(text, from, to) → text",
+        "Replaces every occurrence of `from` with `to`.",
+    ),
+    (
+        "round",
+        "round(numeric, digits) → numeric",
+        "Rounds to `digits` decimal places.",
+    ),
+    (
+        "row_number",
+        "row_number() OVER (...) → bigint",
+        "Number of the row within its partition, counting from 1.",
+    ),
+    ("rtrim", "rtrim(text) → text", "Removes trailing spaces."),
+    (
+        "string_agg",
+        "string_agg(expression, delimiter) → text",
+        "Concatenates the non-null values, separated by `delimiter`.",
+    ),
+    (
+        "substring",
+        "substring(text, start, length) → text",
+        "Part of the string, with `start` counting from 1.",
+    ),
+    (
+        "sum",
+        "sum(expression) → numeric",
+        "Sum of the non-null values.",
+    ),
+    (
+        "trim",
+        "trim(text) → text",
+        "Removes leading and trailing spaces.",
+    ),
+    (
+        "upper",
+        "upper(text) → text",
+        "Converts the string to upper case.",
+    ),
+];
+
+const SQLSERVER_FUNCTIONS: &[FunctionDoc] = &[
+    (
+        "charindex",
+        "CHARINDEX(expressionToFind, expressionToSearch [, start_location]) → int",
+        "Position of the first occurrence, or 0 when it isn't found.",
+    ),
+    (
+        "convert",
+        "CONVERT(data_type [(length)], expression [, style]) → data_type",
+        "Converts a value to another type, with an optional date or number style.",
+    ),
+    (
+        "dateadd",
+        "DATEADD(datepart, number, date) → date",
+        "Adds `number` units of `datepart` to the date.",
+    ),
+    (
+        "datediff",
+        "DATEDIFF(datepart, startdate, enddate) → int",
+        "Number of `datepart` boundaries crossed between the two dates.",
+    ),
+    (
+        "datepart",
+        "DATEPART(datepart, date) → int",
+        "The given part of the date as an integer.",
+    ),
+    (
+        "eomonth",
+        "EOMONTH(start_date [, month_to_add]) → date",
+        "Last day of the month of the date.",
+    ),
+    (
+        "format",
+        "FORMAT(value, format [, culture]) → nvarchar",
+        "Formats a date or number with a .NET format string.",
+    ),
+    (
+        "getdate",
+        "GETDATE() → datetime",
+        "Current date and time of the server.",
+    ),
+    (
+        "iif",
+        "IIF(boolean_expression, true_value, false_value) → value",
+        "Shorthand for a two-branch CASE.",
+    ),
+    (
+        "isnull",
+        "ISNULL(check_expression, replacement_value) → value",
+        "`replacement_value` when the expression is null, typed as the expression.",
+    ),
+    (
+        "len",
+        "LEN(string_expression) → int",
+        "Number of characters, not counting trailing spaces.",
+    ),
+    ("newid", "NEWID() → uniqueidentifier", "A new random GUID."),
+    (
+        "object_id",
+        "OBJECT_ID('[database.][schema.]object' [, 'type']) → int",
+        "Id of the object, or null when it doesn't exist.",
+    ),
+    (
+        "scope_identity",
+        "SCOPE_IDENTITY() → numeric(38,0)",
+        "Last identity value inserted in the current scope.",
+    ),
+    (
+        "sysdatetime",
+        "SYSDATETIME() → datetime2(7)",
+        "Current date and time of the server, with more precision than GETDATE.",
+    ),
+    (
+        "try_cast",
+        "TRY_CAST(expression AS data_type) → data_type",
+        "Like CAST, but null instead of an error when the conversion fails.",
+    ),
+    (
+        "try_convert",
+        "TRY_CONVERT(data_type, expression [, style]) → data_type",
+        "Like CONVERT, but null instead of an error when the conversion fails.",
+    ),
+];
+
+const POSTGRES_FUNCTIONS: &[FunctionDoc] = &[
+    (
+        "age",
+        "age(timestamp [, timestamp]) → interval",
+        "Interval between the timestamps, in years, months and days.",
+    ),
+    (
+        "array_agg",
+        "array_agg(expression) → array",
+        "Collects the values, nulls included, into an array.",
+    ),
+    (
+        "date_trunc",
+        "date_trunc(field, source [, time_zone]) → timestamp",
+        "Truncates the timestamp to the given precision, e.g. `'month'`.",
+    ),
+    (
+        "extract",
+        "extract(field FROM source) → numeric",
+        "The given field of a date, timestamp or interval.",
+    ),
+    (
+        "generate_series",
+        "generate_series(start, stop [, step]) → setof value",
+        "Rows from `start` to `stop`, `step` apart.",
+    ),
+    (
+        "greatest",
+        "greatest(value [, ...]) → value",
+        "Largest of the arguments, ignoring nulls.",
+    ),
+    (
+        "json_agg",
+        "json_agg(expression) → json",
+        "Collects the values into a JSON array.",
+    ),
+    (
+        "jsonb_build_object",
+        "jsonb_build_object(key, value [, ...]) → jsonb",
+        "Builds a JSON object from alternating keys and values.",
+    ),
+    (
+        "least",
+        "least(value [, ...]) → value",
+        "Smallest of the arguments, ignoring nulls.",
+    ),
+    ("length", "length(text) → integer", "Number of characters."),
+    (
+        "nextval",
+        "nextval(regclass) → bigint",
+        "Advances the sequence and returns its new value.",
+    ),
+    (
+        "now",
+        "now() → timestamp with time zone",
+        "Start time of the current transaction.",
+    ),
+    (
+        "regexp_replace",
+        "regexp_replace(source, pattern, replacement [, flags]) → text",
+        "Replaces matches of a POSIX regular expression.",
+    ),
+    (
+        "split_part",
+        "split_part(text, delimiter, n) → text",
+        "The `n`th field of the string split on `delimiter`.",
+    ),
+    (
+        "to_char",
+        "to_char(value, format) → text",
+        "Formats a timestamp or number, e.g. `'YYYY-MM-DD'`.",
+    ),
+    (
+        "unnest",
+        "unnest(array) → setof element",
+        "Expands an array into rows.",
+    ),
+];
+
+const COMMON_KEYWORDS: &[(&str, &str)] = &[
+    ("BETWEEN", "`a BETWEEN x AND y` is `a >= x AND a <= y`."),
+    ("CASE", "`CASE WHEN condition THEN result [...] [ELSE result] END` picks the first branch whose condition holds."),
+    ("DELETE", "`DELETE FROM table [WHERE condition]` removes rows; without a WHERE, all of them."),
+    ("DISTINCT", "Removes duplicate rows from the result."),
+    ("EXISTS", "True when the subquery returns at least one row."),
+    ("FROM", "Lists the tables, views and subqueries the statement reads from."),
+    ("FULL", "`FULL [OUTER] JOIN` keeps the unmatched rows of both sides."),
+    ("GROUP", "`GROUP BY expression [, ...]` collapses rows with the same values into one per group."),
+    ("HAVING", "Filters groups after `GROUP BY`, so it can use aggregates."),
+    ("IN", "`a IN (x, y)` or `a IN (subquery)`; a NULL in the list makes `NOT IN` never true."),
+    ("INNER", "`INNER JOIN` keeps only the rows with a match on both sides."),
+    ("INSERT", "`INSERT INTO table [(columns)] VALUES (...) | query` adds rows."),
+    ("JOIN", "Combines rows of two tables on the `ON` condition; plain `JOIN` is an inner join."),
+    ("LEFT", "`LEFT [OUTER] JOIN` keeps every row on the left, with nulls where the right has no match."),
+    ("LIKE", "Pattern match: `%` matches any run of characters, `_` a single one."),
+    ("MERGE", "`MERGE INTO target USING source ON condition WHEN [NOT] MATCHED THEN ...` inserts, updates or deletes in one statement."),
+    ("ORDER", "`ORDER BY expression [ASC | DESC] [, ...]` sorts the result; without it, the order isn't guaranteed."),
+    ("OVER", "Turns an aggregate or ranking function into a window function: `OVER ([PARTITION BY ...] [ORDER BY ...])`."),
+    ("PARTITION", "`PARTITION BY` splits the rows a window function sees into groups."),
+    ("RIGHT", "`RIGHT [OUTER] JOIN` keeps every row on the right, with nulls where the left has no match."),
+    ("SELECT", "`SELECT [DISTINCT] expressions FROM ... [WHERE ...] [GROUP BY ...] [HAVING ...] [ORDER BY ...]` reads rows."),
+    ("UNION", "Appends the rows of two queries and removes duplicates; `UNION ALL` keeps them."),
+    ("UPDATE", "`UPDATE table SET column = value [, ...] [WHERE condition]` changes rows; without a WHERE, all of them."),
+    ("WHERE", "Filters rows before grouping."),
+    ("WITH", "`WITH name AS (query) [, ...]` names subqueries (CTEs) for the statement that follows."),
+];
+
+const SQLSERVER_KEYWORDS: &[(&str, &str)] = &[
+    ("APPLY", "`CROSS APPLY` runs a table-valued expression per row and keeps rows it returns something for; `OUTER APPLY` keeps them all."),
+    ("GO", "Batch separator for client tools, not a T-SQL statement."),
+    ("NOLOCK", "Table hint for READ UNCOMMITTED: reads can see uncommitted, duplicated or missing rows."),
+    ("OFFSET", "`ORDER BY ... OFFSET n ROWS FETCH NEXT m ROWS ONLY` pages through the result."),
+    ("OUTPUT", "`OUTPUT inserted.*, deleted.*` returns the rows an INSERT, UPDATE, DELETE or MERGE changed."),
+    ("TOP", "`SELECT TOP (n) [PERCENT] [WITH TIES]` limits the number of rows; use it with ORDER BY."),
+];
+
+const POSTGRES_KEYWORDS: &[(&str, &str)] = &[
+    ("ILIKE", "Case-insensitive LIKE."),
+    (
+        "LATERAL",
+        "Lets a subquery or function in FROM refer to columns of the items before it.",
+    ),
+    (
+        "LIMIT",
+        "`LIMIT n [OFFSET m]` returns at most `n` rows; use it with ORDER BY.",
+    ),
+    (
+        "OFFSET",
+        "`OFFSET m` skips the first `m` rows of the result.",
+    ),
+    (
+        "CONFLICT",
+        "`ON CONFLICT [(columns)] DO NOTHING | DO UPDATE SET ...` turns an INSERT into an upsert.",
+    ),
+    (
+        "RETURNING",
+        "`RETURNING expressions` returns the rows an INSERT, UPDATE or DELETE changed.",
+    ),
+];
+
+/// Signature and summary of the built-in function `name` in `engine`'s dialect.
+pub fn function_doc(engine: &Engine, name: &str) -> Option<(&'static str, &'static str)> {
+    let dialect = match engine {
+        Engine::SqlServer => SQLSERVER_FUNCTIONS,
+        Engine::Postgres => POSTGRES_FUNCTIONS,
+        Engine::Mysql => &[],
+    };
+    dialect
+        .iter()
+        .chain(COMMON_FUNCTIONS)
+        .find(|(function, _, _)| function.eq_ignore_ascii_case(name))
+        .map(|(_, signature, summary)| (*signature, *summary))
+}
+
+/// What `keyword` does in `engine`'s dialect.
+pub fn keyword_doc(engine: &Engine, keyword: &str) -> Option<&'static str> {
+    let dialect = match engine {
+        Engine::SqlServer => SQLSERVER_KEYWORDS,
+        Engine::Postgres => POSTGRES_KEYWORDS,
+        Engine::Mysql => &[],
+    };
+    dialect
+        .iter()
+        .chain(COMMON_KEYWORDS)
+        .find(|(word, _)| word.eq_ignore_ascii_case(keyword))
+        .map(|(_, doc)| *doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_prefer_the_dialect_docs_over_the_common_ones() {
+        assert_eq!(
+            function_doc(&Engine::SqlServer, "GETDATE").map(|(s, _)| s),
+            Some("GETDATE() → datetime")
+        );
+        assert_eq!(function_doc(&Engine::Postgres, "getdate"), None);
+        assert!(function_doc(&Engine::Postgres, "count").is_some());
+        assert!(keyword_doc(&Engine::SqlServer, "top").is_some());
+        assert_eq!(keyword_doc(&Engine::Postgres, "top"), None);
+        assert_ne!(
+            keyword_doc(&Engine::SqlServer, "offset"),
+            keyword_doc(&Engine::Postgres, "offset")
+        );
+    }
+}
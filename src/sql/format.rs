@@ -0,0 +1,1249 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::engine::Engine;
+use crate::tokenizer::{lex, Lexeme, LexemeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+    Preserve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommaStyle {
+    Trailing,
+    Leading,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    pub keyword_case: KeywordCase,
+    pub indent_width: usize,
+    pub comma_style: CommaStyle,
+    pub max_line_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            keyword_case: KeywordCase::Upper,
+            indent_width: 4,
+            comma_style: CommaStyle::Trailing,
+            max_line_width: 100,
+        }
+    }
+}
+
+/// Keywords that are re-cased wherever they appear. Anything else from the
+/// dialect keyword list is only re-cased when it's called like a function, so
+/// columns called `name` or `type` keep the case they were written with.
+pub(crate) const CORE_KEYWORDS: &[&str] = &[
+    "ADD",
+    "ALL",
+    "ALTER",
+    "AND",
+    "ANY",
+    "AS",
+    "ASC",
+    "BEGIN",
+    "BETWEEN",
+    "BIGINT",
+    "BIT",
+    "BOOLEAN",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CHAR",
+    "CHECK",
+    "COLUMN",
+    "COMMIT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "DATE",
+    "DECIMAL",
+    "DECLARE",
+    "DEFAULT",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "EXCEPT",
+    "EXEC",
+    "EXECUTE",
+    "EXISTS",
+    "FALSE",
+    "FETCH",
+    "FLOAT",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "FUNCTION",
+    "GRANT",
+    "GROUP",
+    "HAVING",
+    "IF",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INT",
+    "INTEGER",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LEFT",
+    "LIKE",
+    "MERGE",
+    "NOT",
+    "NULL",
+    "NUMERIC",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PRIMARY",
+    "PROCEDURE",
+    "REFERENCES",
+    "RETURN",
+    "RETURNS",
+    "RIGHT",
+    "ROLLBACK",
+    "ROWS",
+    "SCHEMA",
+    "SELECT",
+    "SET",
+    "SMALLINT",
+    "TABLE",
+    "THEN",
+    "TIMESTAMP",
+    "TRUE",
+    "TRUNCATE",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VARCHAR",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+const SQLSERVER_KEYWORDS: &[&str] = &[
+    "APPLY",
+    "CLUSTERED",
+    "DATETIME",
+    "DATETIME2",
+    "GO",
+    "IDENTITY",
+    "NOCOUNT",
+    "NOLOCK",
+    "NONCLUSTERED",
+    "NVARCHAR",
+    "OUTPUT",
+    "PERCENT",
+    "PRINT",
+    "PROC",
+    "TIES",
+    "TOP",
+    "TRAN",
+    "TRANSACTION",
+    "UNIQUEIDENTIFIER",
+];
+
+const POSTGRES_KEYWORDS: &[&str] = &[
+    "BIGSERIAL",
+    "CONFLICT",
+    "DO",
+    "ILIKE",
+    "JSONB",
+    "LANGUAGE",
+    "LATERAL",
+    "LIMIT",
+    "NOTHING",
+    "REPLACE",
+    "RETURNING",
+    "SERIAL",
+    "TEXT",
+    "UUID",
+];
+
+const LIST_CLAUSES: &[&str] = &["SELECT", "GROUP", "ORDER", "SET", "VALUES", "RETURNING"];
+
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "ALTER",
+    "BEGIN",
+    "COMMIT",
+    "CREATE",
+    "DECLARE",
+    "DELETE",
+    "DROP",
+    "EXEC",
+    "EXECUTE",
+    "FETCH",
+    "FROM",
+    "GRANT",
+    "HAVING",
+    "IF",
+    "INSERT",
+    "LIMIT",
+    "MERGE",
+    "OFFSET",
+    "PRINT",
+    "RETURN",
+    "RETURNING",
+    "REVOKE",
+    "ROLLBACK",
+    "SELECT",
+    "SET",
+    "TRUNCATE",
+    "UPDATE",
+    "USE",
+    "VALUES",
+    "WHERE",
+    "WITH",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+];
+
+/// Keywords that begin a new statement even when the previous one wasn't
+/// terminated, which is common in T-SQL scripts.
+const STATEMENT_STARTS: &[&str] = &[
+    "ALTER", "CREATE", "DECLARE", "DELETE", "DENY", "DROP", "EXEC", "EXECUTE", "GRANT", "INSERT",
+    "MERGE", "REVOKE", "TRUNCATE", "UPDATE",
+];
+
+const JOIN_MODIFIERS: &[&str] = &[
+    "LEFT", "RIGHT", "FULL", "INNER", "CROSS", "OUTER", "NATURAL",
+];
+
+const SPACE_BEFORE_PAREN: &[&str] = &[
+    "ALL",
+    "AND",
+    "ANY",
+    "AS",
+    "BY",
+    "CHECK",
+    "CONFLICT",
+    "ELSE",
+    "EXISTS",
+    "FROM",
+    "IN",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "NOT",
+    "ON",
+    "OR",
+    "OVER",
+    "REFERENCES",
+    "RETURNS",
+    "SELECT",
+    "SOME",
+    "TABLE",
+    "THEN",
+    "UNIQUE",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+const OBJECT_KEYWORDS: &[&str] = &["EXISTS", "INTO", "ON", "REFERENCES", "TABLE", "VIEW"];
+
+const OBJECT_TYPES: &[&str] = &[
+    "COLUMN",
+    "CONSTRAINT",
+    "DATABASE",
+    "EXTENSION",
+    "FUNCTION",
+    "INDEX",
+    "PROC",
+    "PROCEDURE",
+    "ROLE",
+    "SCHEMA",
+    "SEQUENCE",
+    "TABLE",
+    "TRIGGER",
+    "TYPE",
+    "USER",
+    "VIEW",
+];
+
+/// Formats a whole document.
+pub fn format_sql(sql: &str, engine: &Engine, options: &FormatOptions) -> String {
+    Formatter::new(engine, options).format(sql)
+}
+
+/// Formats a fragment, keeping the indentation of its first line so the
+/// result can be dropped back in place of a selection.
+pub fn format_fragment(sql: &str, engine: &Engine, options: &FormatOptions) -> String {
+    let prefix: String = sql
+        .trim_start_matches(['\r', '\n'])
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    format_sql(sql, engine, options)
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Statement,
+    Begin,
+    /// The statement an `IF` or `ELSE` runs, indented under it.
+    Branch,
+    Subquery,
+    Block,
+    Inline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Clause {
+    None,
+    Select,
+    From,
+    Join,
+    Where,
+    List,
+    With,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    kind: FrameKind,
+    /// Indent level that clause keywords of this frame start at.
+    base: usize,
+    /// Indent level of the line the frame was opened on.
+    opened_at: usize,
+    clause: Clause,
+    /// Whether commas of the current clause put each item on its own line.
+    break_items: bool,
+    /// A list clause is waiting for its first item to start a new line.
+    list_pending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Pending {
+    None,
+    Space,
+    Newline,
+    BlankLine,
+}
+
+struct Item {
+    lexeme: Lexeme,
+    own_line: bool,
+    newline_after: bool,
+}
+
+struct Formatter<'a> {
+    engine: &'a Engine,
+    options: &'a FormatOptions,
+    out: String,
+    /// Length of `out` up to the end of the last lexeme written, so trailing
+    /// whitespace is only ever trimmed from padding the formatter added.
+    written: usize,
+    line_len: usize,
+    line_indent: usize,
+    next_indent: usize,
+    pending: Pending,
+    frames: Vec<Frame>,
+    prev: Option<Lexeme>,
+    prev_unary: bool,
+    between: bool,
+    case_depth: usize,
+    statement: Vec<String>,
+    block_opened: bool,
+    /// The keyword in front of the (possibly qualified) name just written.
+    name_keyword: Option<String>,
+    /// Depth of the frames an `IF` or `ELSE` was written in, until the
+    /// statement it runs starts.
+    branch_at: Option<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(engine: &'a Engine, options: &'a FormatOptions) -> Self {
+        Self {
+            engine,
+            options,
+            out: String::new(),
+            written: 0,
+            line_len: 0,
+            line_indent: 0,
+            next_indent: 0,
+            pending: Pending::None,
+            frames: vec![Self::statement_frame()],
+            prev: None,
+            prev_unary: false,
+            between: false,
+            case_depth: 0,
+            statement: Vec::new(),
+            block_opened: false,
+            name_keyword: None,
+            branch_at: None,
+        }
+    }
+
+    fn statement_frame() -> Frame {
+        Frame {
+            kind: FrameKind::Statement,
+            base: 0,
+            opened_at: 0,
+            clause: Clause::None,
+            break_items: false,
+            list_pending: false,
+        }
+    }
+
+    fn format(mut self, sql: &str) -> String {
+        let items = items(sql);
+        let significant: Vec<&Lexeme> = items
+            .iter()
+            .map(|i| &i.lexeme)
+            .filter(|l| l.kind != LexemeKind::Comment)
+            .collect();
+        let mut n = 0;
+        for item in items.iter() {
+            if item.lexeme.kind == LexemeKind::Comment {
+                self.comment(item);
+            } else {
+                self.token(&significant, n);
+                n += 1;
+            }
+        }
+        self.trim_padding();
+        let mut out = self.out.trim_start().to_string();
+        out.push('\n');
+        out
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("there is always a statement frame")
+    }
+
+    fn comment(&mut self, item: &Item) {
+        let text = item.lexeme.text.trim_end();
+        if item.own_line || self.pending >= Pending::Newline {
+            let indent = if self.pending >= Pending::Newline {
+                self.next_indent
+            } else {
+                self.line_indent
+            };
+            self.newline(indent, self.pending == Pending::BlankLine);
+        } else if !self.out.is_empty() {
+            self.out.push(' ');
+            self.line_len += 1;
+        }
+        self.write(text);
+        let line_comment = text.starts_with("--");
+        if line_comment || item.newline_after {
+            self.pending = self.pending.max(Pending::Newline);
+        } else {
+            self.pending = Pending::Space;
+        }
+    }
+
+    fn token(&mut self, tokens: &[&Lexeme], i: usize) {
+        let token = tokens[i];
+        let upper = token.text.to_uppercase();
+        let is_word = token.kind == LexemeKind::Word;
+        let prev_is_dot = self.prev.as_ref().is_some_and(|p| p.is_punct("."));
+        let next = tokens.get(i + 1).copied();
+        let next_is_dot = next.is_some_and(|n| n.is_punct("."));
+        let keyword = is_word && !prev_is_dot && !next_is_dot && self.is_keyword(&upper, next);
+        let prev_upper = self
+            .prev
+            .as_ref()
+            .filter(|p| p.kind == LexemeKind::Word)
+            .map(|p| p.text.to_uppercase())
+            .unwrap_or_default();
+        let structural = matches!(
+            self.frame().kind,
+            FrameKind::Statement | FrameKind::Begin | FrameKind::Branch | FrameKind::Subquery
+        );
+
+        if keyword && self.statement.len() < 4 {
+            self.statement.push(upper.clone());
+        }
+        if keyword && upper == "CASE" {
+            self.case_depth += 1;
+        }
+
+        if token.is_punct(";") {
+            self.emit(token, false);
+            self.end_statement();
+            return;
+        }
+
+        if keyword && upper == "GO" && *self.engine == Engine::SqlServer {
+            self.end_statement();
+            self.pending = Pending::Newline;
+            self.emit_text(token, "GO".to_string(), true);
+            self.pending = Pending::BlankLine;
+            return;
+        }
+
+        if structural && keyword {
+            if let Some(clause) = self.clause_start(tokens, i, &upper, &prev_upper) {
+                let branch = matches!(upper.as_str(), "IF" | "ELSE");
+                if upper == "ELSE" {
+                    self.end_branch();
+                } else if self.branch_at == Some(self.frames.len()) {
+                    self.branch_at = None;
+                    if upper != "BEGIN" {
+                        self.begin_branch();
+                    }
+                }
+                let base = self.frame().base;
+                if self.prev.is_some() && !self.prev.as_ref().is_some_and(|p| p.is_punct("(")) {
+                    self.pending = self.pending.max(Pending::Newline);
+                }
+                self.next_indent = base;
+                if clause == Clause::Other
+                    && (self.frames.len() == 1 || self.frame().kind == FrameKind::Branch)
+                    && STATEMENT_STARTS.contains(&upper.as_str())
+                {
+                    self.statement = vec![upper.clone()];
+                    self.block_opened = false;
+                }
+                let break_items = LIST_CLAUSES.contains(&upper.as_str()) && has_list(tokens, i + 1)
+                    || clause == Clause::With;
+                let frame = self.frame();
+                frame.clause = clause;
+                frame.break_items = break_items;
+                frame.list_pending = break_items && clause != Clause::With;
+                self.between = false;
+                self.emit(token, keyword);
+                if upper == "BEGIN" && starts_block(tokens.get(i + 1).copied()) {
+                    self.begin_block();
+                }
+                if branch {
+                    self.branch_at = Some(self.frames.len());
+                }
+                return;
+            }
+            if upper == "ON" && self.frame().clause == Clause::Join {
+                self.break_line(1);
+            } else if (upper == "AND" || upper == "OR")
+                && matches!(self.frame().clause, Clause::Where | Clause::Join)
+            {
+                if self.between && upper == "AND" {
+                    self.between = false;
+                } else {
+                    self.break_line(1);
+                }
+            } else if upper == "END" && self.case_depth == 0 {
+                self.end_block();
+            }
+        }
+        if keyword
+            && matches!(upper.as_str(), "TRY" | "CATCH")
+            && matches!(prev_upper.as_str(), "BEGIN" | "END")
+        {
+            self.pending = Pending::Space;
+            self.emit(token, keyword);
+            self.pending = self.pending.max(if prev_upper == "BEGIN" {
+                Pending::Newline
+            } else {
+                Pending::None
+            });
+            return;
+        }
+
+        if keyword && upper == "BETWEEN" {
+            self.between = true;
+        }
+        if keyword && upper == "END" && self.case_depth > 0 {
+            self.case_depth -= 1;
+        }
+
+        if self.frame().list_pending && !self.is_list_modifier(&upper, &prev_upper, token) {
+            self.frame().list_pending = false;
+            self.break_line(1);
+        }
+
+        if token.is_punct("(") {
+            self.open_paren(tokens, i);
+            return;
+        }
+        if token.is_punct(")") {
+            self.close_paren(token);
+            return;
+        }
+        if token.is_punct(",") {
+            self.comma(token);
+            return;
+        }
+        self.emit(token, keyword);
+    }
+
+    fn is_keyword(&self, upper: &str, next: Option<&Lexeme>) -> bool {
+        let dialect = match self.engine {
+            Engine::SqlServer => SQLSERVER_KEYWORDS,
+            Engine::Postgres | Engine::Mysql => POSTGRES_KEYWORDS,
+        };
+        CORE_KEYWORDS.contains(&upper)
+            || dialect.contains(&upper)
+            || (next.is_some_and(|n| n.is_punct("(")) && self.engine.keywords().contains(&upper))
+    }
+
+    fn is_list_modifier(&self, upper: &str, prev_upper: &str, token: &Lexeme) -> bool {
+        matches!(upper, "DISTINCT" | "ALL" | "TOP" | "PERCENT" | "TIES")
+            || (prev_upper == "TOP" && token.kind == LexemeKind::Number)
+            || (prev_upper == "WITH" && upper == "TIES")
+            || (matches!(prev_upper, "TOP" | "ON") && token.is_punct("("))
+            || (prev_upper == "DISTINCT" && upper == "ON")
+            || (self.frame_clause_is(Clause::List) && upper == "BY")
+            || (upper == "WITH" && self.frame_clause_is(Clause::Select))
+    }
+
+    fn frame_clause_is(&self, clause: Clause) -> bool {
+        self.frames.last().is_some_and(|f| f.clause == clause)
+    }
+
+    fn clause_start(
+        &self,
+        tokens: &[&Lexeme],
+        i: usize,
+        upper: &str,
+        prev_upper: &str,
+    ) -> Option<Clause> {
+        let next = tokens.get(i + 1);
+        let next_upper = next
+            .filter(|n| n.kind == LexemeKind::Word)
+            .map(|n| n.text.to_uppercase())
+            .unwrap_or_default();
+        if JOIN_MODIFIERS.contains(&upper) {
+            if JOIN_MODIFIERS.contains(&prev_upper) {
+                return None;
+            }
+            let joins = tokens[i + 1..]
+                .iter()
+                .take(2)
+                .any(|t| t.is_word("JOIN") || t.is_word("APPLY"));
+            return joins.then_some(Clause::Join);
+        }
+        match upper {
+            "JOIN" if !JOIN_MODIFIERS.contains(&prev_upper) => Some(Clause::Join),
+            "SELECT" if self.statement_is("GRANT") || self.statement_is("REVOKE") => None,
+            "SELECT" => Some(Clause::Select),
+            "FROM" if prev_upper == "DELETE" => None,
+            "FROM" => Some(Clause::From),
+            "WHERE" | "HAVING" => Some(Clause::Where),
+            "GROUP" | "ORDER" if next_upper == "BY" => Some(Clause::List),
+            "SET"
+                if self.statement_is("ALTER")
+                    || prev_upper == "DELETE"
+                    || prev_upper == "UPDATE" =>
+            {
+                None
+            }
+            "SET" | "VALUES" | "RETURNING" => Some(Clause::List),
+            "WITH"
+                if !next.is_none_or(|n| n.is_punct("("))
+                    && (self.frame_clause_is(Clause::None)
+                        || self.frame_clause_is(Clause::Other)
+                        || prev_upper == "AS") =>
+            {
+                Some(Clause::With)
+            }
+            "UNION" | "INTERSECT" | "EXCEPT" => Some(Clause::Other),
+            "INSERT" | "UPDATE" | "DELETE" | "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "MERGE"
+            | "DECLARE" | "EXEC" | "EXECUTE" | "GRANT" | "REVOKE" | "COMMIT" | "ROLLBACK"
+            | "USE" | "PRINT" | "RETURN" | "IF" | "BEGIN" | "LIMIT" | "OFFSET" => {
+                let dml = matches!(upper, "INSERT" | "UPDATE" | "DELETE" | "EXEC" | "EXECUTE");
+                if dml
+                    && (matches!(prev_upper, "AFTER" | "BEFORE" | "FOR" | "OF" | "ON" | "DO")
+                        || self.prev.as_ref().is_some_and(|p| p.is_punct(",")))
+                    || upper == "IF" && OBJECT_TYPES.contains(&prev_upper)
+                    || upper == "DROP"
+                        && self.statement_is("ALTER")
+                        && !next.is_some_and(|n| {
+                            OBJECT_TYPES
+                                .iter()
+                                .any(|t| *t != "COLUMN" && *t != "CONSTRAINT" && n.is_word(t))
+                        })
+                    || (self.statement_is("GRANT") || self.statement_is("REVOKE"))
+                        && !matches!(upper, "GRANT" | "REVOKE")
+                {
+                    return None;
+                }
+                Some(Clause::Other)
+            }
+            "ELSE" if self.case_depth == 0 => Some(Clause::Other),
+            _ => None,
+        }
+    }
+
+    fn statement_is(&self, keyword: &str) -> bool {
+        self.statement.first().is_some_and(|s| s == keyword)
+    }
+
+    fn open_paren(&mut self, tokens: &[&Lexeme], i: usize) {
+        let token = tokens[i];
+        let next = tokens.get(i + 1);
+        let subquery = next.is_some_and(|n| n.is_word("SELECT"))
+            || next.is_some_and(|n| n.is_word("WITH"))
+                && tokens.get(i + 2).is_some_and(|n| n.is_identifier());
+        let block = !subquery
+            && !self.block_opened
+            && self.frames.len() == 1
+            && self.statement.first().is_some_and(|s| s == "CREATE")
+            && self.statement.iter().any(|s| s == "TABLE");
+        self.emit(token, false);
+        let opened_at = self.line_indent;
+        let kind = if subquery {
+            FrameKind::Subquery
+        } else if block {
+            self.block_opened = true;
+            FrameKind::Block
+        } else {
+            FrameKind::Inline
+        };
+        let base = match kind {
+            FrameKind::Inline => self.frame().base,
+            _ => opened_at + 1,
+        };
+        self.frames.push(Frame {
+            kind,
+            base,
+            opened_at,
+            clause: Clause::None,
+            break_items: kind == FrameKind::Block,
+            list_pending: false,
+        });
+        if kind != FrameKind::Inline {
+            self.pending = Pending::Newline;
+            self.next_indent = base;
+        }
+    }
+
+    fn close_paren(&mut self, token: &Lexeme) {
+        let frame = if self.frames.len() > 1 {
+            self.frames.pop()
+        } else {
+            None
+        };
+        if let Some(frame) = frame {
+            if frame.kind != FrameKind::Inline {
+                self.pending = self.pending.max(Pending::Newline);
+                self.next_indent = frame.opened_at;
+            }
+        }
+        self.emit(token, false);
+    }
+
+    fn comma(&mut self, token: &Lexeme) {
+        let frame = *self.frame();
+        if !frame.break_items {
+            self.emit(token, false);
+            return;
+        }
+        let indent = match frame.kind {
+            FrameKind::Block => frame.base,
+            _ if frame.clause == Clause::With => frame.base,
+            _ => frame.base + 1,
+        };
+        match self.options.comma_style {
+            CommaStyle::Trailing => {
+                self.emit(token, false);
+                self.pending = Pending::Newline;
+                self.next_indent = indent;
+            }
+            CommaStyle::Leading => {
+                self.pending = Pending::Newline;
+                self.next_indent = indent;
+                self.emit(token, false);
+                self.pending = Pending::Space;
+            }
+        }
+    }
+
+    fn break_line(&mut self, extra: usize) {
+        if self.prev.is_none() {
+            return;
+        }
+        self.pending = self.pending.max(Pending::Newline);
+        self.next_indent = self.frame().base + extra;
+    }
+
+    fn end_statement(&mut self) {
+        while self.frames.len() > 1 && self.frame().kind != FrameKind::Begin {
+            self.frames.pop();
+        }
+        let frame = self.frame();
+        frame.clause = Clause::None;
+        frame.break_items = false;
+        frame.list_pending = false;
+        let (kind, base) = (frame.kind, frame.base);
+        self.statement.clear();
+        self.block_opened = false;
+        self.case_depth = 0;
+        self.between = false;
+        self.branch_at = None;
+        self.pending = if kind == FrameKind::Statement {
+            Pending::BlankLine
+        } else {
+            Pending::Newline
+        };
+        self.next_indent = base;
+    }
+
+    fn begin_block(&mut self) {
+        let base = self.frame().base;
+        self.frames.push(Frame {
+            kind: FrameKind::Begin,
+            base: base + 1,
+            opened_at: base,
+            clause: Clause::None,
+            break_items: false,
+            list_pending: false,
+        });
+        self.pending = Pending::Newline;
+        self.next_indent = base + 1;
+    }
+
+    fn begin_branch(&mut self) {
+        let base = self.frame().base;
+        self.frames.push(Frame {
+            kind: FrameKind::Branch,
+            base: base + 1,
+            opened_at: base,
+            clause: Clause::None,
+            break_items: false,
+            list_pending: false,
+        });
+    }
+
+    /// Closes the branch an `ELSE` follows, which sits at its `IF`'s level
+    /// rather than after a blank line.
+    fn end_branch(&mut self) {
+        if self.frame().kind == FrameKind::Branch {
+            self.frames.pop();
+        }
+        if self.pending == Pending::BlankLine {
+            self.pending = Pending::Newline;
+        }
+        self.branch_at = None;
+    }
+
+    fn end_block(&mut self) {
+        if !self.frames.iter().any(|f| f.kind == FrameKind::Begin) {
+            self.break_line(0);
+            return;
+        }
+        while let Some(frame) = self.frames.pop() {
+            if frame.kind == FrameKind::Begin {
+                self.pending = self.pending.max(Pending::Newline);
+                self.next_indent = frame.opened_at;
+                break;
+            }
+        }
+        let frame = self.frame();
+        frame.clause = Clause::None;
+        frame.break_items = false;
+        frame.list_pending = false;
+    }
+
+    fn emit(&mut self, token: &Lexeme, keyword: bool) {
+        let text = if keyword {
+            match self.options.keyword_case {
+                KeywordCase::Upper => token.text.to_uppercase(),
+                KeywordCase::Lower => token.text.to_lowercase(),
+                KeywordCase::Preserve => token.text.clone(),
+            }
+        } else {
+            token.text.clone()
+        };
+        self.emit_text(token, text, keyword);
+    }
+
+    fn emit_text(&mut self, token: &Lexeme, text: String, keyword: bool) {
+        match self.pending {
+            Pending::Newline | Pending::BlankLine
+                if self.prev.is_some() || !self.out.is_empty() =>
+            {
+                let indent = self.next_indent;
+                self.newline(indent, self.pending == Pending::BlankLine);
+            }
+            _ => {
+                let space = self.needs_space(token);
+                let width = text.chars().count() + usize::from(space);
+                let indent_len = self.line_indent * self.options.indent_width;
+                if self.line_len + width > self.options.max_line_width
+                    && self.line_len > indent_len
+                    && self.can_wrap_before(token)
+                {
+                    let indent = self.wrap_indent();
+                    self.newline(indent, false);
+                } else if space {
+                    self.out.push(' ');
+                    self.line_len += 1;
+                }
+            }
+        }
+        self.pending = Pending::None;
+        self.write(&text);
+        self.prev_unary = (token.text == "-" || token.text == "+")
+            && self.prev.as_ref().is_none_or(|p| {
+                matches!(p.kind, LexemeKind::Operator)
+                    || p.is_punct("(")
+                    || p.is_punct(",")
+                    || p.kind == LexemeKind::Word && self.is_keyword(&p.text.to_uppercase(), None)
+            });
+        self.name_keyword = if keyword {
+            Some(token.text.to_uppercase())
+        } else if token.is_identifier() || token.is_punct(".") {
+            self.name_keyword.take()
+        } else {
+            None
+        };
+        self.prev = Some(token.clone());
+    }
+
+    fn wrap_indent(&self) -> usize {
+        let frame = self
+            .frames
+            .last()
+            .expect("there is always a statement frame");
+        let item = if frame.break_items && frame.clause != Clause::With {
+            frame.base + 1
+        } else {
+            frame.base
+        };
+        item.max(self.line_indent) + 1
+    }
+
+    fn can_wrap_before(&self, token: &Lexeme) -> bool {
+        let Some(prev) = self.prev.as_ref() else {
+            return false;
+        };
+        !(token.is_punct(",") || token.is_punct(")") || token.is_punct(".") || token.is_punct(";"))
+            && !(prev.is_punct(".")
+                || prev.is_punct("(")
+                || prev.text == "::"
+                || token.text == "::")
+            && !self.prev_unary
+    }
+
+    fn needs_space(&self, token: &Lexeme) -> bool {
+        let Some(prev) = self.prev.as_ref() else {
+            return false;
+        };
+        if self.line_len == self.line_indent * self.options.indent_width {
+            return false;
+        }
+        if token.is_punct(",") || token.is_punct(";") || token.is_punct(")") || token.is_punct(".")
+        {
+            return false;
+        }
+        if prev.is_punct("(") || prev.is_punct(".") || prev.text == "::" || token.text == "::" {
+            return false;
+        }
+        if self.prev_unary {
+            return false;
+        }
+        if token.is_punct("(") {
+            return match prev.kind {
+                LexemeKind::Word => {
+                    let upper = prev.text.to_uppercase();
+                    SPACE_BEFORE_PAREN.contains(&upper.as_str()) && self.is_keyword(&upper, None)
+                        || self.follows_object_keyword()
+                }
+                LexemeKind::QuotedIdentifier => self.follows_object_keyword(),
+                LexemeKind::Punctuation => !prev.is_punct(")"),
+                _ => true,
+            };
+        }
+        true
+    }
+
+    /// `INSERT INTO dbo.t (a, b)` reads better with a space before the column
+    /// list than a function call would.
+    fn follows_object_keyword(&self) -> bool {
+        let Some(keyword) = self.name_keyword.as_deref() else {
+            return false;
+        };
+        let prev_is_name = self.prev.as_ref().is_some_and(|p| p.is_identifier());
+        prev_is_name
+            && OBJECT_KEYWORDS.contains(&keyword)
+            && (keyword == "REFERENCES"
+                || self
+                    .statement
+                    .iter()
+                    .any(|s| s == "INSERT" || s == "CREATE"))
+    }
+
+    fn newline(&mut self, indent: usize, blank: bool) {
+        if self.out.is_empty() {
+            self.line_indent = indent;
+            self.out
+                .push_str(&" ".repeat(indent * self.options.indent_width));
+            self.line_len = indent * self.options.indent_width;
+            return;
+        }
+        self.trim_padding();
+        self.out.push('\n');
+        if blank {
+            self.out.push('\n');
+        }
+        self.line_indent = indent;
+        let pad = " ".repeat(indent * self.options.indent_width);
+        self.line_len = pad.len();
+        self.out.push_str(&pad);
+    }
+
+    /// Drops the spaces the formatter left at the end of the current line,
+    /// never reaching back into a string or comment that ends in whitespace.
+    fn trim_padding(&mut self) {
+        let keep = self.out.trim_end_matches(' ').len().max(self.written);
+        self.out.truncate(keep);
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.written = self.out.len();
+        match text.rfind('\n') {
+            Some(n) => self.line_len = text[n + 1..].chars().count(),
+            None => self.line_len += text.chars().count(),
+        }
+    }
+}
+
+fn items(sql: &str) -> Vec<Item> {
+    let lexemes = lex(sql);
+    let mut items = Vec::new();
+    for (n, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.kind == LexemeKind::Whitespace {
+            continue;
+        }
+        let own_line = n == 0
+            || lexemes[..n]
+                .iter()
+                .rev()
+                .take_while(|l| l.kind == LexemeKind::Whitespace)
+                .any(|l| l.text.contains('\n'))
+            || n == 1 && lexemes[0].kind == LexemeKind::Whitespace;
+        let newline_after = lexemes
+            .get(n + 1)
+            .is_none_or(|l| l.kind == LexemeKind::Whitespace && l.text.contains('\n'));
+        items.push(Item {
+            lexeme: lexeme.clone(),
+            own_line,
+            newline_after,
+        });
+    }
+    items
+}
+
+/// `BEGIN` opens a block unless it starts a transaction.
+fn starts_block(next: Option<&Lexeme>) -> bool {
+    next.is_some_and(|n| {
+        !(n.is_punct(";")
+            || ["TRAN", "TRANSACTION", "WORK", "DISTRIBUTED", "ISOLATION"]
+                .iter()
+                .any(|w| n.is_word(w)))
+    })
+}
+
+/// Whether the clause starting after `start` has more than one item.
+fn has_list(tokens: &[&Lexeme], start: usize) -> bool {
+    let mut depth = 0;
+    for token in tokens.iter().skip(start) {
+        if token.is_punct("(") {
+            depth += 1;
+        } else if token.is_punct(")") {
+            if depth == 0 {
+                return false;
+            }
+            depth -= 1;
+        } else if depth == 0 {
+            if token.is_punct(",") {
+                return true;
+            }
+            if token.is_punct(";") {
+                return false;
+            }
+            if token.kind == LexemeKind::Word {
+                let upper = token.text.to_uppercase();
+                if matches!(
+                    upper.as_str(),
+                    "FROM"
+                        | "WHERE"
+                        | "GROUP"
+                        | "ORDER"
+                        | "HAVING"
+                        | "UNION"
+                        | "INTERSECT"
+                        | "EXCEPT"
+                        | "LIMIT"
+                        | "OFFSET"
+                        | "RETURNING"
+                        | "INTO"
+                        | "ON"
+                        | "GO"
+                ) || STATEMENT_KEYWORDS.contains(&upper.as_str())
+                    && upper != "SET"
+                    && upper != "VALUES"
+                {
+                    return false;
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(sql: &str) -> String {
+        format_sql(sql, &Engine::SqlServer, &FormatOptions::default())
+    }
+
+    #[test]
+    fn should_put_clauses_and_select_items_on_their_own_lines() {
+        let sql = "select a, b, count(*) from dbo.t t inner join dbo.x x on x.id = t.id and x.k = 1 where a = 1 and b in (select id from y) group by a, b order by a";
+        let expected = "\
+SELECT
+    a,
+    b,
+    COUNT(*)
+FROM dbo.t t
+INNER JOIN dbo.x x
+    ON x.id = t.id
+    AND x.k = 1
+WHERE a = 1
+    AND b IN (
+        SELECT id
+        FROM y
+    )
+GROUP BY
+    a,
+    b
+ORDER BY a
+";
+        assert_eq!(format(sql), expected);
+    }
+
+    #[test]
+    fn should_be_idempotent() {
+        let sql = "-- users\nselect top 10 u.id, u.name /* display */, case when u.age between 1 and 2 then 'x' else 'y' end as age_group\nfrom dbo.users u left join dbo.roles r on r.id = u.role_id where u.id > -1;\n\ninsert into dbo.t (a, b) values (1, 'x'), (2, 'y');\nupdate dbo.t set a = 1, b = 2 where a = 0\nGO\ncreate table dbo.x (id int not null primary key, name nvarchar(20) null)";
+        let once = format(sql);
+        assert_eq!(format(&once), once);
+        assert!(once.contains("\nGO\n"));
+        assert!(once.contains(
+            "CREATE TABLE dbo.x (\n    id INT NOT NULL PRIMARY KEY,\n    name NVARCHAR(20) NULL\n)"
+        ));
+        assert!(once.contains("INSERT INTO dbo.t (a, b)\nVALUES\n    (1, 'x'),\n    (2, 'y');"));
+        assert!(once.contains("u.id > -1;"));
+    }
+
+    #[test]
+    fn should_indent_the_statements_if_and_else_run() {
+        let sql = "BEGIN IF @a = 1 SELECT 1; ELSE SELECT 2; END";
+        assert_eq!(
+            format(sql),
+            "BEGIN\n    IF @a = 1\n        SELECT 1;\n    ELSE\n        SELECT 2;\nEND\n"
+        );
+        let sql = "IF @a = 1 BEGIN SELECT 1; END ELSE BEGIN SELECT 2; END";
+        assert_eq!(
+            format(sql),
+            "IF @a = 1\nBEGIN\n    SELECT 1;\nEND\nELSE\nBEGIN\n    SELECT 2;\nEND\n"
+        );
+        let once = format("IF EXISTS (SELECT 1 FROM t) DELETE FROM t WHERE a = 1; SELECT 2");
+        assert_eq!(format(&once), once);
+    }
+
+    #[test]
+    fn should_honor_comma_style_and_keyword_case() {
+        let options = FormatOptions {
+            keyword_case: KeywordCase::Lower,
+            comma_style: CommaStyle::Leading,
+            ..FormatOptions::default()
+        };
+        let formatted = format_sql("SELECT a, b FROM t", &Engine::Postgres, &options);
+        assert_eq!(formatted, "select\n    a\n    , b\nfrom t\n");
+    }
+
+    #[test]
+    fn should_wrap_long_lines() {
+        let options = FormatOptions {
+            max_line_width: 40,
+            ..FormatOptions::default()
+        };
+        let sql = "SELECT a FROM t WHERE a IN (1111111111, 2222222222, 3333333333, 4444444444)";
+        let formatted = format_sql(sql, &Engine::Postgres, &options);
+        assert!(formatted.lines().all(|l| l.len() <= 40), "{}", formatted);
+        assert_eq!(
+            format_sql(&formatted, &Engine::Postgres, &options),
+            formatted
+        );
+    }
+
+    #[test]
+    fn should_keep_postgres_casts_and_function_bodies() {
+        let sql = "create function f() returns int as $$ select  1 $$ language sql; select x::text from t limit 1";
+        let formatted = format_sql(sql, &Engine::Postgres, &FormatOptions::default());
+        assert!(
+            formatted.contains("$$ select  1 $$ LANGUAGE sql;"),
+            "{}",
+            formatted
+        );
+        assert!(formatted.contains("x::TEXT"));
+        assert!(formatted.contains("\nLIMIT 1"));
+    }
+
+    #[test]
+    fn should_not_touch_whitespace_inside_literals() {
+        let formatted = format("insert into t values ('a   \nb')");
+        assert!(formatted.contains("('a   \nb')"), "{:?}", formatted);
+        let formatted = format_sql(
+            "select 1 as $$ x  \n y $$",
+            &Engine::Postgres,
+            &FormatOptions::default(),
+        );
+        assert!(formatted.contains("$$ x  \n y $$"), "{:?}", formatted);
+    }
+
+    #[test]
+    fn should_keep_backslash_escapes_in_e_strings() {
+        let formatted = format_sql(
+            r"select e'x\'y' from t",
+            &Engine::Postgres,
+            &FormatOptions::default(),
+        );
+        assert_eq!(formatted, "SELECT e'x\\'y'\nFROM t\n");
+    }
+}
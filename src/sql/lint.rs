@@ -0,0 +1,1043 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Range, TextEdit};
+
+use super::statement::{parse, Statement, TableRef};
+use crate::db::column::Column;
+use crate::db::engine::Engine;
+use crate::db::index::Index;
+use crate::db::table::{resolve, Table};
+use crate::tokenizer::{Lexeme, LexemeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Rule severities from `tengu.json`, keyed by rule id. Rules that aren't
+/// listed keep their default severity.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintOptions {
+    pub rules: HashMap<String, Severity>,
+}
+
+impl LintOptions {
+    fn severity(&self, rule: &Rule) -> Severity {
+        self.rules.get(rule.id).copied().unwrap_or(rule.severity)
+    }
+}
+
+/// A quick fix for a finding, as edits to the linted document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub range: Range,
+    pub fix: Option<Fix>,
+}
+
+/// What the linter knows about the database. Everything is optional: without
+/// a catalog, rules that depend on it assume the worst.
+#[derive(Debug, Clone)]
+pub struct LintContext {
+    pub engine: Engine,
+    pub tables: Vec<Table>,
+    pub columns: Vec<Column>,
+    /// Indexes of the tables the document uses, when the catalog has them.
+    pub indexes: HashMap<Table, Vec<Index>>,
+    /// Schemas unqualified names resolve in, in order; empty when unknown.
+    pub search_path: Vec<String>,
+}
+
+impl LintContext {
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            tables: vec![],
+            columns: vec![],
+            indexes: HashMap::new(),
+            search_path: vec![],
+        }
+    }
+
+    /// The schema an unqualified `table` resolves in, when it's just one.
+    fn schema_for(&self, table: &str) -> Option<String> {
+        match resolve(&self.tables, &self.search_path, table, None).as_slice() {
+            [table] => Some(table.schema.clone()),
+            _ => None,
+        }
+    }
+
+    /// Where an unknown table would be: the first schema on the path that
+    /// isn't Postgres' own.
+    fn default_schema(&self) -> String {
+        let first = self.search_path.iter().find(|s| !s.starts_with("pg_"));
+        match (first, &self.engine) {
+            (Some(schema), _) => schema.clone(),
+            (None, Engine::SqlServer) => "dbo".to_string(),
+            (None, Engine::Postgres | Engine::Mysql) => "public".to_string(),
+        }
+    }
+
+    fn columns_of(&self, table: &TableRef) -> Vec<&Column> {
+        let name = table.name.name();
+        let schema = table.name.schema().or_else(|| self.schema_for(&name));
+        self.columns
+            .iter()
+            .filter(|c| {
+                c.table.eq_ignore_ascii_case(&name)
+                    && schema
+                        .as_ref()
+                        .is_none_or(|s| c.schema.eq_ignore_ascii_case(s))
+            })
+            .collect()
+    }
+
+    /// The indexes of the tables `statement` reads, or none when the catalog
+    /// knows nothing about any of them.
+    fn indexes_of(&self, statement: &Statement) -> Option<Vec<&Index>> {
+        let mut known = false;
+        let mut indexes = Vec::new();
+        for r in statement.table_refs().iter().filter(|r| !r.function) {
+            let name = r.name.name();
+            let schema = r.name.schema().or_else(|| self.schema_for(&name));
+            for (table, table_indexes) in &self.indexes {
+                if table.name.eq_ignore_ascii_case(&name)
+                    && schema
+                        .as_ref()
+                        .is_none_or(|s| table.schema.eq_ignore_ascii_case(s))
+                {
+                    known = true;
+                    indexes.extend(table_indexes);
+                }
+            }
+        }
+        known.then_some(indexes)
+    }
+
+    fn column_nullable(&self, table: &TableRef, column: &str) -> Option<bool> {
+        self.columns_of(table)
+            .into_iter()
+            .find(|c| c.name.eq_ignore_ascii_case(column))
+            .map(|c| c.is_nullable.eq_ignore_ascii_case("YES"))
+    }
+}
+
+/// What a rule reports: a message, where, and an optional fix.
+type Hits = Vec<(String, Range, Option<Fix>)>;
+
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    check: fn(&Statement, &LintContext) -> Hits,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "select-star-in-view",
+        severity: Severity::Warning,
+        description: "Views should list their columns instead of using SELECT *",
+        check: select_star_in_view,
+    },
+    Rule {
+        id: "update-without-where",
+        severity: Severity::Error,
+        description: "UPDATE without a WHERE clause changes every row",
+        check: update_without_where,
+    },
+    Rule {
+        id: "delete-without-where",
+        severity: Severity::Error,
+        description: "DELETE without a WHERE clause removes every row",
+        check: delete_without_where,
+    },
+    Rule {
+        id: "implicit-cross-join",
+        severity: Severity::Warning,
+        description: "Tables listed with commas in FROM and not related in WHERE are cross joined",
+        check: implicit_cross_join,
+    },
+    Rule {
+        id: "not-in-nullable-subquery",
+        severity: Severity::Warning,
+        description: "NOT IN over a subquery matches nothing once the subquery returns a NULL",
+        check: not_in_nullable_subquery,
+    },
+    Rule {
+        id: "non-sargable-predicate",
+        severity: Severity::Warning,
+        description: "Functions and leading wildcards on a column prevent index seeks",
+        check: non_sargable_predicate,
+    },
+    Rule {
+        id: "missing-schema-qualification",
+        severity: Severity::Info,
+        description: "Table references should be qualified with their schema",
+        check: missing_schema_qualification,
+    },
+];
+
+pub fn lint(sql: &str, context: &LintContext, options: &LintOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for statement in parse(sql, &context.engine) {
+        let mut statements = statement.leaves(&context.engine);
+        if statements.len() > 1 {
+            statements.insert(0, statement.clone());
+        }
+        for rule in RULES {
+            let severity = options.severity(rule);
+            if severity == Severity::Off {
+                continue;
+            }
+            for (n, statement) in statements.iter().enumerate() {
+                // The routine itself is only looked at by rules about its
+                // definition, its body statements by everything else.
+                if n == 0 && statements.len() > 1 && rule.id != "select-star-in-view" {
+                    continue;
+                }
+                for (message, range, fix) in (rule.check)(statement, context) {
+                    findings.push(Finding {
+                        rule: rule.id,
+                        severity,
+                        message,
+                        range,
+                        fix,
+                    });
+                }
+            }
+        }
+    }
+    findings.sort_by_key(|f| (f.range.start, f.range.end));
+    findings
+}
+
+fn span(from: &Lexeme, to: &Lexeme) -> Range {
+    Range::new(from.range().start, to.range().end)
+}
+
+fn text_of(tokens: &[Lexeme]) -> String {
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let glued = i == 0
+            || token.is_punct(".")
+            || token.is_punct(",")
+            || token.is_punct(")")
+            || tokens[i - 1].is_punct(".")
+            || tokens[i - 1].is_punct("(");
+        if !glued {
+            text.push(' ');
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+fn select_star_in_view(statement: &Statement, context: &LintContext) -> Hits {
+    let Some(definition) = statement.definition() else {
+        return vec![];
+    };
+    if definition.action == "DROP" || definition.object != "VIEW" {
+        return vec![];
+    }
+    let refs = statement.table_refs();
+    let tokens = &statement.tokens;
+    let mut findings = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != LexemeKind::Operator || token.text != "*" || i == 0 {
+            continue;
+        }
+        let prev = &tokens[i - 1];
+        let in_select_list = prev.is_word("SELECT")
+            || prev.is_word("DISTINCT")
+            || prev.is_word("ALL")
+            || prev.is_punct(",")
+            || prev.is_punct(".")
+            || prev.kind == LexemeKind::Number && i >= 2 && tokens[i - 2].is_word("TOP");
+        if !in_select_list {
+            continue;
+        }
+        let start = if prev.is_punct(".") && i >= 2 {
+            &tokens[i - 2]
+        } else {
+            token
+        };
+        let fix = match refs.as_slice() {
+            [table] if !table.function => {
+                let columns = context.columns_of(table);
+                (!columns.is_empty()).then(|| Fix {
+                    title: "Expand * into the view's columns".to_string(),
+                    edits: vec![TextEdit::new(
+                        span(start, token),
+                        columns
+                            .iter()
+                            .map(|c| c.name.clone())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    )],
+                })
+            }
+            _ => None,
+        };
+        findings.push((
+            format!(
+                "View {} uses SELECT *, its columns will silently change with the tables",
+                definition.name.to_string_unquoted()
+            ),
+            span(start, token),
+            fix,
+        ));
+    }
+    findings
+}
+
+fn without_where(statement: &Statement, verb: &str) -> Hits {
+    if statement.verb() != verb {
+        return vec![];
+    }
+    let depths = statement.depths();
+    let top_level = |word: &str| {
+        statement
+            .tokens
+            .iter()
+            .zip(depths.iter())
+            .any(|(t, d)| *d == 0 && t.is_word(word))
+    };
+    if top_level("WHERE") || top_level("JOIN") {
+        return vec![];
+    }
+    let Some(keyword) = statement.tokens.iter().find(|t| t.is_word(verb)) else {
+        return vec![];
+    };
+    let target = statement
+        .table_refs()
+        .into_iter()
+        .find(|r| {
+            r.index
+                > statement
+                    .tokens
+                    .iter()
+                    .position(|t| t == keyword)
+                    .unwrap_or(0)
+        })
+        .map(|r| r.name.to_string_unquoted())
+        .unwrap_or_else(|| "the table".to_string());
+    let message = match verb {
+        "UPDATE" => format!(
+            "UPDATE without a WHERE clause changes every row of {}",
+            target
+        ),
+        _ => format!(
+            "DELETE without a WHERE clause removes every row of {}",
+            target
+        ),
+    };
+    vec![(message, keyword.range(), None)]
+}
+
+fn update_without_where(statement: &Statement, _: &LintContext) -> Hits {
+    without_where(statement, "UPDATE")
+}
+
+fn delete_without_where(statement: &Statement, _: &LintContext) -> Hits {
+    without_where(statement, "DELETE")
+}
+
+fn implicit_cross_join(statement: &Statement, _: &LintContext) -> Hits {
+    let refs = statement.table_refs();
+    let depths = statement.depths();
+    refs.iter()
+        .filter(|r| !r.function)
+        .filter_map(|r| {
+            let comma = r.comma.as_ref()?;
+            if joined_in_where(statement, &depths, &refs, r) {
+                return None;
+            }
+            Some((
+                format!(
+                    "{} is cross joined by listing it after a comma, use an explicit JOIN",
+                    r.name.to_string_unquoted()
+                ),
+                Range::new(comma.range().start, r.name.range().end),
+                Some(Fix {
+                    title: "Replace the comma with CROSS JOIN".to_string(),
+                    edits: vec![TextEdit::new(comma.range(), " CROSS JOIN".to_string())],
+                }),
+            ))
+        })
+        .collect()
+}
+
+/// Whether a conjunct of the WHERE clause after `table`'s FROM names both it
+/// and another table of that FROM, as `a.id = b.a_id` does for `FROM a, b`.
+fn joined_in_where(
+    statement: &Statement,
+    depths: &[usize],
+    refs: &[TableRef],
+    table: &TableRef,
+) -> bool {
+    let tokens = &statement.tokens;
+    let depth = depths[table.index];
+    let Some(from) = (0..table.index)
+        .rev()
+        .find(|i| depths[*i] == depth && tokens[*i].is_word("FROM"))
+    else {
+        return false;
+    };
+    let mut filter = None;
+    let mut end = tokens.len();
+    for (i, token) in tokens.iter().enumerate().skip(table.index) {
+        if depths[i] < depth || depths[i] == depth && token.is_punct(")") {
+            end = i;
+            break;
+        }
+        if depths[i] != depth || token.kind != LexemeKind::Word {
+            continue;
+        }
+        if token.is_word("WHERE") {
+            filter = Some(i);
+        } else if [
+            "GROUP",
+            "ORDER",
+            "HAVING",
+            "UNION",
+            "EXCEPT",
+            "INTERSECT",
+            "LIMIT",
+            "WINDOW",
+            "RETURNING",
+            "OPTION",
+        ]
+        .iter()
+        .any(|w| token.is_word(w))
+        {
+            end = i;
+            break;
+        }
+    }
+    let Some(filter) = filter else {
+        return false;
+    };
+    let name = |r: &TableRef| {
+        r.alias
+            .as_ref()
+            .map(|a| a.unquoted())
+            .unwrap_or_else(|| r.name.name())
+            .to_lowercase()
+    };
+    let own = name(table);
+    let others: Vec<String> = refs
+        .iter()
+        .filter(|r| r.index > from && r.index < filter && r.index != table.index)
+        .filter(|r| depths[r.index] == depth)
+        .map(name)
+        .collect();
+    let mut between = false;
+    let mut start = filter + 1;
+    for i in filter + 1..=end {
+        let conjunct_ends = i == end
+            || depths[i] == depth
+                && if tokens[i].is_word("BETWEEN") {
+                    between = true;
+                    false
+                } else if tokens[i].is_word("AND") && between {
+                    between = false;
+                    false
+                } else {
+                    tokens[i].is_word("AND")
+                };
+        if !conjunct_ends {
+            continue;
+        }
+        let qualifiers = qualifiers(&tokens[start..i]);
+        if qualifiers.contains(&own) && others.iter().any(|o| qualifiers.contains(o)) {
+            return true;
+        }
+        start = i + 1;
+    }
+    false
+}
+
+/// The table or alias every `qualifier.column` in `tokens` is qualified with.
+fn qualifiers(tokens: &[Lexeme]) -> Vec<String> {
+    tokens
+        .windows(3)
+        .enumerate()
+        .filter(|(_, w)| w[0].is_identifier() && w[1].is_punct(".") && w[2].is_identifier())
+        .filter(|(k, _)| !tokens.get(k + 3).is_some_and(|t| t.is_punct(".")))
+        .map(|(_, w)| w[0].unquoted().to_lowercase())
+        .collect()
+}
+
+fn not_in_nullable_subquery(statement: &Statement, context: &LintContext) -> Hits {
+    let tokens = &statement.tokens;
+    let mut findings = Vec::new();
+    for i in 0..tokens.len().saturating_sub(3) {
+        if !(tokens[i].is_word("NOT")
+            && tokens[i + 1].is_word("IN")
+            && tokens[i + 2].is_punct("(")
+            && tokens[i + 3].is_word("SELECT"))
+        {
+            continue;
+        }
+        let Some(close) = statement.close_paren(i + 2) else {
+            continue;
+        };
+        let subquery = Statement {
+            tokens: tokens[i + 3..close].to_vec(),
+            batch: statement.batch,
+        };
+        let mut s = 1;
+        while subquery
+            .tokens
+            .get(s)
+            .is_some_and(|t| t.is_word("DISTINCT") || t.is_word("ALL"))
+        {
+            s += 1;
+        }
+        let column = subquery.qualified_name(s).filter(|(_, next)| {
+            subquery
+                .tokens
+                .get(*next)
+                .is_some_and(|t| t.is_word("FROM"))
+        });
+        let refs = subquery.table_refs();
+        let table = refs.first();
+        let nullable = match (&column, table) {
+            (Some((column, _)), Some(table)) => context.column_nullable(table, &column.name()),
+            _ => None,
+        };
+        if nullable == Some(false) {
+            continue;
+        }
+        let what = column
+            .as_ref()
+            .map(|(c, _)| c.to_string_unquoted())
+            .unwrap_or_else(|| "the subquery".to_string());
+        let message = match nullable {
+            Some(true) => format!(
+                "{} is nullable, NOT IN matches no rows once it returns a NULL; use NOT EXISTS",
+                what
+            ),
+            _ => format!(
+                "NOT IN matches no rows if {} returns a NULL; use NOT EXISTS",
+                what
+            ),
+        };
+        let fix = not_exists_fix(statement, i, close, &subquery, column.map(|c| c.0));
+        findings.push((message, span(&tokens[i], &tokens[close]), fix));
+    }
+    findings
+}
+
+/// Rewrites `x NOT IN (SELECT c FROM t [WHERE ...])` as
+/// `NOT EXISTS (SELECT 1 FROM t WHERE [...] AND t.c = x)` when the shapes are
+/// simple enough to do it safely.
+fn not_exists_fix(
+    statement: &Statement,
+    not: usize,
+    close: usize,
+    subquery: &Statement,
+    column: Option<super::statement::QualifiedName>,
+) -> Option<Fix> {
+    let column = column?;
+    let tokens = &statement.tokens;
+    // The outer operand has to be a plain column reference right before NOT.
+    let mut start = not.checked_sub(1)?;
+    if !tokens[start].is_identifier() {
+        return None;
+    }
+    while start >= 2 && tokens[start - 1].is_punct(".") && tokens[start - 2].is_identifier() {
+        start -= 2;
+    }
+    let outer = text_of(&tokens[start..not]);
+    let from = subquery.tokens.iter().position(|t| t.is_word("FROM"))?;
+    let refs = subquery.table_refs();
+    let [table] = refs.as_slice() else {
+        return None;
+    };
+    let depths = subquery.depths();
+    let clauses = [
+        "GROUP",
+        "HAVING",
+        "ORDER",
+        "UNION",
+        "EXCEPT",
+        "INTERSECT",
+        "LIMIT",
+        "JOIN",
+    ];
+    if subquery
+        .tokens
+        .iter()
+        .zip(depths.iter())
+        .any(|(t, d)| *d == 0 && clauses.iter().any(|c| t.is_word(c)))
+    {
+        return None;
+    }
+    let qualifier = table
+        .alias
+        .as_ref()
+        .map(|a| a.text.clone())
+        .unwrap_or_else(|| text_of(&table.name.parts));
+    let inner = format!("{}.{}", qualifier, column.parts.last()?.text);
+    let where_at = subquery
+        .tokens
+        .iter()
+        .zip(depths.iter())
+        .position(|(t, d)| *d == 0 && t.is_word("WHERE"));
+    let body = match where_at {
+        Some(w) => format!(
+            "{} WHERE ({}) AND {} = {}",
+            text_of(&subquery.tokens[from..w]),
+            text_of(&subquery.tokens[w + 1..]),
+            inner,
+            outer
+        ),
+        None => format!(
+            "{} WHERE {} = {}",
+            text_of(&subquery.tokens[from..]),
+            inner,
+            outer
+        ),
+    };
+    Some(Fix {
+        title: "Rewrite as NOT EXISTS".to_string(),
+        edits: vec![TextEdit::new(
+            span(&tokens[start], &tokens[close]),
+            format!("NOT EXISTS (SELECT 1 {})", body),
+        )],
+    })
+}
+
+const COMPARISONS: &[&str] = &["=", "<", ">", "<=", ">=", "<>", "!=", "!<", "!>"];
+
+fn non_sargable_predicate(statement: &Statement, context: &LintContext) -> Hits {
+    let tokens = &statement.tokens;
+    // With the catalog's indexes, only predicates that lose one are flagged.
+    let indexes = context.indexes_of(statement);
+    let depths = statement.depths();
+    let mut findings = Vec::new();
+    // Depths at which a WHERE or JOIN ... ON condition is being read.
+    let mut predicates: Vec<usize> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let depth = depths[i];
+        if token.is_punct(")") {
+            predicates.retain(|d| *d < depth);
+            continue;
+        }
+        if token.kind == LexemeKind::Word {
+            let upper = token.text.to_uppercase();
+            match upper.as_str() {
+                "WHERE" | "ON" => {
+                    predicates.retain(|d| *d != depth);
+                    predicates.push(depth);
+                    continue;
+                }
+                "GROUP" | "ORDER" | "HAVING" | "UNION" | "EXCEPT" | "INTERSECT" | "JOIN"
+                | "LIMIT" | "WINDOW" | "RETURNING" | "OPTION" => {
+                    predicates.retain(|d| *d != depth);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        if !predicates.contains(&depth) {
+            continue;
+        }
+        if token.is_word("LIKE") || token.is_word("ILIKE") {
+            let Some(pattern) = tokens.get(i + 1) else {
+                continue;
+            };
+            let leading_wildcard = pattern.kind == LexemeKind::String
+                && pattern
+                    .text
+                    .trim_start_matches(['N', 'n', 'E', 'e'])
+                    .starts_with("'%");
+            if !leading_wildcard {
+                continue;
+            }
+            let message = match &indexes {
+                None => "A LIKE pattern starting with % can't use an index".to_string(),
+                Some(indexes) => {
+                    let column = tokens[..i].last().map(|t| t.unquoted()).unwrap_or_default();
+                    let Some(index) = leading(indexes, &column) else {
+                        continue;
+                    };
+                    format!(
+                        "A LIKE pattern starting with % can't seek on index {}",
+                        index.name
+                    )
+                }
+            };
+            findings.push((message, pattern.range(), None));
+            continue;
+        }
+        let is_call = token.kind == LexemeKind::Word
+            && tokens.get(i + 1).is_some_and(|t| t.is_punct("("))
+            && ![
+                "IN", "EXISTS", "NOT", "AND", "OR", "ANY", "ALL", "SOME", "VALUES", "ON",
+            ]
+            .iter()
+            .any(|w| token.is_word(w))
+            && !tokens
+                .get(i.wrapping_sub(1))
+                .is_some_and(|t| t.is_punct("."));
+        if !is_call {
+            continue;
+        }
+        let Some(close) = statement.close_paren(i + 1) else {
+            continue;
+        };
+        let compared = tokens.get(close + 1).is_some_and(|t| {
+            t.kind == LexemeKind::Operator && COMPARISONS.contains(&t.text.as_str())
+                || t.is_word("LIKE")
+                || t.is_word("IN")
+                || t.is_word("BETWEEN")
+        });
+        if !compared {
+            continue;
+        }
+        let column = tokens[i + 2..close].iter().enumerate().find(|(n, t)| {
+            if !t.is_identifier() || t.text.starts_with('@') {
+                return false;
+            }
+            if t.kind == LexemeKind::Word && is_type_or_keyword(&t.text) {
+                return false;
+            }
+            // Skip nested calls and the qualifier of `alias.column`.
+            !tokens
+                .get(i + 3 + n)
+                .is_some_and(|next| next.is_punct("(") || next.is_punct("."))
+        });
+        let Some((_, column)) = column else {
+            continue;
+        };
+        let message = match &indexes {
+            None => format!(
+                "{}() on column {} prevents an index seek; compare the bare column instead",
+                token.text.to_uppercase(),
+                column.unquoted()
+            ),
+            Some(indexes) => {
+                let call = expression(&tokens[i..=close]);
+                if indexes.iter().any(|index| {
+                    index
+                        .columns
+                        .first()
+                        .is_some_and(|c| expression_eq(c, &call))
+                }) {
+                    continue;
+                }
+                let Some(index) = leading(indexes, &column.unquoted()) else {
+                    continue;
+                };
+                format!(
+                    "{}() on column {} prevents a seek on index {}; compare the bare column instead",
+                    token.text.to_uppercase(),
+                    column.unquoted(),
+                    index.name
+                )
+            }
+        };
+        findings.push((message, span(token, &tokens[close]), None));
+    }
+    findings
+}
+
+/// The first index keyed on `column`.
+fn leading<'a>(indexes: &[&'a Index], column: &str) -> Option<&'a Index> {
+    indexes
+        .iter()
+        .find(|i| {
+            i.columns
+                .first()
+                .is_some_and(|c| c.eq_ignore_ascii_case(column))
+        })
+        .copied()
+}
+
+fn expression(tokens: &[Lexeme]) -> String {
+    tokens.iter().map(|t| t.text.as_str()).collect()
+}
+
+/// Whether an expression index's key, e.g. Postgres' `lower((email)::text)`,
+/// is the call written in the predicate, ignoring case, spacing, quotes,
+/// parentheses and casts to text.
+fn expression_eq(key: &str, call: &str) -> bool {
+    let normalize = |s: &str| {
+        s.to_lowercase()
+            .replace("::text", "")
+            .chars()
+            .filter(|c| !c.is_whitespace() && !"()[]\"".contains(*c))
+            .collect::<String>()
+    };
+    key.contains('(') && normalize(key) == normalize(call)
+}
+
+/// Arguments like the `day` in `DATEADD(day, ...)` or the type in a CAST
+/// aren't columns.
+fn is_type_or_keyword(word: &str) -> bool {
+    let upper = word.to_uppercase();
+    [
+        "AS",
+        "YEAR",
+        "MONTH",
+        "DAY",
+        "HOUR",
+        "MINUTE",
+        "SECOND",
+        "WEEK",
+        "QUARTER",
+        "DATE",
+        "TIME",
+        "INT",
+        "BIGINT",
+        "VARCHAR",
+        "NVARCHAR",
+        "CHAR",
+        "TEXT",
+        "DECIMAL",
+        "NUMERIC",
+        "DATETIME",
+        "DATETIME2",
+        "FROM",
+        "NULL",
+        "TRUE",
+        "FALSE",
+        "CURRENT_TIMESTAMP",
+    ]
+    .contains(&upper.as_str())
+}
+
+fn missing_schema_qualification(statement: &Statement, context: &LintContext) -> Hits {
+    let ctes = statement.ctes();
+    let refs = statement.table_refs();
+    refs.iter()
+        .filter(|r| r.name.parts.len() == 1 && !r.function)
+        .filter(|r| {
+            let name = r.name.name();
+            if name.starts_with('#') || name.starts_with('@') {
+                return false;
+            }
+            let is_cte = ctes
+                .iter()
+                .any(|c| c.name.unquoted().eq_ignore_ascii_case(&name));
+            let is_alias = refs.iter().any(|other| {
+                other
+                    .alias
+                    .as_ref()
+                    .is_some_and(|a| a.unquoted().eq_ignore_ascii_case(&name))
+            });
+            !is_cte && !is_alias
+        })
+        .map(|r| {
+            let name = r.name.name();
+            let schema = context
+                .schema_for(&name)
+                .unwrap_or_else(|| context.default_schema());
+            let start = r.name.range().start;
+            (
+                format!("{} isn't qualified with its schema", name),
+                r.name.range(),
+                Some(Fix {
+                    title: format!("Qualify as {}.{}", schema, name),
+                    edits: vec![TextEdit::new(
+                        Range::new(start, start),
+                        format!("{}.", schema),
+                    )],
+                }),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(sql: &str, engine: Engine) -> Vec<&'static str> {
+        lint(sql, &LintContext::new(engine), &LintOptions::default())
+            .iter()
+            .map(|f| f.rule)
+            .collect()
+    }
+
+    #[test]
+    fn should_flag_updates_and_deletes_without_where() {
+        let sql = "UPDATE dbo.t SET a = 1;\nDELETE FROM dbo.t;\nDELETE FROM dbo.t WHERE a = 1;";
+        assert_eq!(
+            rules(sql, Engine::SqlServer),
+            vec!["update-without-where", "delete-without-where"]
+        );
+    }
+
+    #[test]
+    fn should_look_inside_procedure_bodies_and_views() {
+        let sql = "CREATE VIEW dbo.v AS SELECT * FROM dbo.t\nGO\nCREATE PROCEDURE dbo.p AS\nBEGIN\n  UPDATE dbo.t SET a = 1\nEND";
+        assert_eq!(
+            rules(sql, Engine::SqlServer),
+            vec!["select-star-in-view", "update-without-where"]
+        );
+    }
+
+    #[test]
+    fn should_flag_implicit_cross_joins_with_a_fix() {
+        let sql = "SELECT * FROM public.a, public.b WHERE a.id = 1";
+        let findings = lint(
+            sql,
+            &LintContext::new(Engine::Postgres),
+            &LintOptions::default(),
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "implicit-cross-join");
+        let fix = findings[0].fix.as_ref().unwrap();
+        assert_eq!(fix.edits[0].new_text, " CROSS JOIN");
+    }
+
+    #[test]
+    fn should_leave_comma_joins_related_in_where_alone() {
+        let context = LintContext::new(Engine::SqlServer);
+        let rules = |sql: &str| -> Vec<String> {
+            lint(sql, &context, &LintOptions::default())
+                .into_iter()
+                .filter(|f| f.rule == "implicit-cross-join")
+                .map(|f| f.message)
+                .collect()
+        };
+        assert!(rules(
+            "SELECT * FROM dbo.a x, dbo.b y WHERE y.v BETWEEN 1 AND 2 AND x.id = y.a_id"
+        )
+        .is_empty());
+        assert!(
+            rules("SELECT * FROM dbo.a, dbo.b WHERE (dbo.a.id = dbo.b.a_id OR a.k = b.k)")
+                .is_empty()
+        );
+        assert_eq!(
+            rules("SELECT * FROM dbo.a, dbo.b WHERE a.id = 1 AND b.id = 2").len(),
+            1
+        );
+        assert_eq!(
+            rules("SELECT * FROM dbo.a, dbo.b, dbo.c WHERE a.id = b.a_id").len(),
+            1
+        );
+        assert_eq!(
+            rules("SELECT * FROM dbo.a WHERE a.id IN (SELECT b.id FROM dbo.b, dbo.c WHERE b.id = a.id)").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn should_use_nullability_to_decide_on_not_in() {
+        let sql =
+            "SELECT * FROM dbo.a WHERE a.id NOT IN (SELECT b.a_id FROM dbo.b b WHERE b.x = 1)";
+        let mut context = LintContext::new(Engine::SqlServer);
+        let findings = lint(sql, &context, &LintOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].fix.as_ref().unwrap().edits[0].new_text,
+            "NOT EXISTS (SELECT 1 FROM dbo.b b WHERE (b.x = 1) AND b.a_id = a.id)"
+        );
+        context.columns.push(Column {
+            name: "a_id".to_string(),
+            table: "b".to_string(),
+            schema: "dbo".to_string(),
+            data_type: "int".to_string(),
+            is_nullable: "NO".to_string(),
+            ..Column::default()
+        });
+        assert!(lint(sql, &context, &LintOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn should_flag_non_sargable_predicates() {
+        let sql = "SELECT id FROM dbo.t WHERE YEAR(created_at) = 2020 AND name LIKE '%x' AND id = ABS(@x)";
+        assert_eq!(
+            rules(sql, Engine::SqlServer),
+            vec!["non-sargable-predicate", "non-sargable-predicate"]
+        );
+    }
+
+    #[test]
+    fn should_only_flag_predicates_that_lose_an_index() {
+        let sql = "SELECT id FROM public.users WHERE lower(email) = 'a' AND date(created_at) = '2020-01-01' AND abs(score) > 1 AND name LIKE '%x'";
+        let index = |name: &str, column: &str| Index {
+            name: name.to_string(),
+            columns: vec![column.to_string()],
+            included: vec![],
+            unique: false,
+            primary_key: false,
+            filter: None,
+            kind: Some("btree".to_string()),
+        };
+        let mut context = LintContext::new(Engine::Postgres);
+        context.indexes.insert(
+            Table {
+                name: "users".to_string(),
+                schema: "public".to_string(),
+                description: None,
+            },
+            vec![
+                index("ix_users_email", "lower((email)::text)"),
+                index("ix_users_created_at", "created_at"),
+            ],
+        );
+        let messages: Vec<String> = lint(sql, &context, &LintOptions::default())
+            .into_iter()
+            .map(|f| f.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["DATE() on column created_at prevents a seek on index ix_users_created_at; compare the bare column instead"]
+        );
+    }
+
+    #[test]
+    fn should_qualify_tables_but_not_ctes_temp_tables_or_aliases() {
+        let sql = "WITH c AS (SELECT 1 AS x) UPDATE u SET a = 1 FROM users u JOIN c ON 1 = 1 JOIN #tmp ON 1 = 1 WHERE u.id = 1";
+        let mut context = LintContext::new(Engine::SqlServer);
+        context.tables.push(Table {
+            name: "users".to_string(),
+            schema: "auth".to_string(),
+            description: None,
+        });
+        let findings = lint(sql, &context, &LintOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "missing-schema-qualification");
+        assert_eq!(
+            findings[0].fix.as_ref().unwrap().title,
+            "Qualify as auth.users"
+        );
+    }
+
+    #[test]
+    fn should_honor_configured_severities() {
+        let options = LintOptions {
+            rules: HashMap::from([
+                ("update-without-where".to_string(), Severity::Off),
+                ("missing-schema-qualification".to_string(), Severity::Error),
+            ]),
+        };
+        let findings = lint(
+            "UPDATE t SET a = 1",
+            &LintContext::new(Engine::SqlServer),
+            &options,
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod docs;
+pub mod format;
+pub mod lint;
+pub mod references;
+pub mod schema;
+pub mod statement;
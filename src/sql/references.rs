@@ -0,0 +1,485 @@
+use tower_lsp::lsp_types::{Position, Range};
+
+use super::format::CORE_KEYWORDS;
+use super::statement::{parse, Statement};
+use crate::db::engine::Engine;
+use crate::tokenizer::{Lexeme, LexemeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Table,
+    Column,
+    Alias,
+    Cte,
+}
+
+/// What a reference search or rename is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub kind: TargetKind,
+    pub name: String,
+    /// For tables, the schema it was qualified with; for columns, never set.
+    pub schema: Option<String>,
+    /// For columns, the table they were resolved to.
+    pub table: Option<String>,
+    /// Statement the target is confined to: aliases, CTEs and columns whose
+    /// table couldn't be worked out. `None` means the whole workspace.
+    pub scope: Option<Range>,
+}
+
+impl Target {
+    pub fn is_local(&self) -> bool {
+        self.scope.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub token: Lexeme,
+    /// Where the target is declared: `CREATE TABLE`, a column definition, the
+    /// alias after a table or the name of a CTE.
+    pub declaration: bool,
+}
+
+/// Statements to search: the body of T-SQL routines rather than the routine
+/// itself, so aliases are scoped to the statement that declares them.
+fn scopes(sql: &str, engine: &Engine) -> Vec<Statement> {
+    parse(sql, engine)
+        .iter()
+        .flat_map(|s| s.leaves(engine))
+        .collect()
+}
+
+fn contains(range: Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+/// Positions of the tokens that make up a table name, to tell them apart
+/// from columns.
+fn name_offsets(statement: &Statement) -> Vec<usize> {
+    let mut offsets: Vec<usize> = statement
+        .table_refs()
+        .iter()
+        .flat_map(|r| r.name.parts.iter().map(|p| p.offset))
+        .collect();
+    if let Some(definition) = statement.definition() {
+        offsets.extend(definition.name.parts.iter().map(|p| p.offset));
+    }
+    offsets
+}
+
+fn is_name_like(token: &Lexeme) -> bool {
+    match token.kind {
+        LexemeKind::QuotedIdentifier => true,
+        LexemeKind::Word => {
+            !token.text.starts_with(['@', '#', '$'])
+                && !CORE_KEYWORDS.iter().any(|k| token.is_word(k))
+        }
+        _ => false,
+    }
+}
+
+/// The table, column, alias or CTE under `position`.
+pub fn target_at(sql: &str, engine: &Engine, position: Position) -> Option<(Target, Lexeme)> {
+    let statement = scopes(sql, engine)
+        .into_iter()
+        .find(|s| s.contains(position))?;
+    let tokens = &statement.tokens;
+    let i = tokens
+        .iter()
+        .position(|t| contains(t.range(), position) && t.is_identifier())?;
+    let token = tokens[i].clone();
+    if !is_name_like(&token) {
+        return None;
+    }
+    let name = token.unquoted();
+    let target = |kind, schema, table, local: bool| Target {
+        kind,
+        name: name.clone(),
+        schema,
+        table,
+        scope: local.then(|| statement.range()),
+    };
+    let refs = statement.table_refs();
+    let ctes = statement.ctes();
+    let is_cte = ctes.iter().any(|c| same(&c.name.unquoted(), &name));
+    let after_dot = i > 0 && tokens[i - 1].is_punct(".");
+    let before_dot = tokens.get(i + 1).is_some_and(|t| t.is_punct("."));
+
+    // Part of a table name, as a reference or in CREATE/ALTER/DROP.
+    let in_ref = refs
+        .iter()
+        .find(|r| r.name.parts.iter().any(|p| p.offset == token.offset));
+    let definition = statement
+        .definition()
+        .filter(|d| matches!(d.object.as_str(), "TABLE" | "VIEW"));
+    let qualified = in_ref.map(|r| &r.name).or(definition
+        .as_ref()
+        .map(|d| &d.name)
+        .filter(|n| n.parts.iter().any(|p| p.offset == token.offset)));
+    if let Some(qualified) = qualified {
+        if qualified.parts.last().map(|p| p.offset) != Some(token.offset) {
+            // The schema or database part.
+            return None;
+        }
+        if is_cte && qualified.parts.len() == 1 {
+            return Some((target(TargetKind::Cte, None, None, true), token));
+        }
+        if in_ref.is_some_and(|r| r.function) {
+            return None;
+        }
+        return Some((
+            target(TargetKind::Table, qualified.schema(), None, false),
+            token,
+        ));
+    }
+    if refs
+        .iter()
+        .any(|r| r.alias.as_ref().is_some_and(|a| a.offset == token.offset))
+    {
+        return Some((target(TargetKind::Alias, None, None, true), token));
+    }
+    if ctes.iter().any(|c| c.name.offset == token.offset) {
+        return Some((target(TargetKind::Cte, None, None, true), token));
+    }
+    if !after_dot {
+        if refs
+            .iter()
+            .any(|r| r.alias.as_ref().is_some_and(|a| same(&a.unquoted(), &name)))
+        {
+            return Some((target(TargetKind::Alias, None, None, true), token));
+        }
+        if is_cte {
+            return Some((target(TargetKind::Cte, None, None, true), token));
+        }
+        if before_dot {
+            // `users.id` with `users` unaliased.
+            return refs.iter().find(|r| same(&r.name.name(), &name)).map(|r| {
+                (
+                    target(TargetKind::Table, r.name.schema(), None, false),
+                    token,
+                )
+            });
+        }
+    }
+    if tokens.get(i + 1).is_some_and(|t| t.is_punct("(")) {
+        return None;
+    }
+    // A column: resolve its table from the qualifier, or from the only table
+    // the statement touches.
+    let table = if after_dot && i >= 2 {
+        let qualifier = tokens[i - 2].unquoted();
+        refs.iter()
+            .find(|r| {
+                r.alias
+                    .as_ref()
+                    .map_or(same(&r.name.name(), &qualifier), |a| {
+                        same(&a.unquoted(), &qualifier)
+                    })
+            })
+            .map(|r| r.name.name())
+    } else {
+        let mut tables: Vec<String> = refs
+            .iter()
+            .filter(|r| !r.function)
+            .map(|r| r.name.name())
+            .filter(|t| !ctes.iter().any(|c| same(&c.name.unquoted(), t)))
+            .chain(definition.as_ref().map(|d| d.name.name()))
+            .collect();
+        tables.dedup_by(|a, b| same(a, b));
+        match tables.as_slice() {
+            [table] => Some(table.clone()),
+            _ => None,
+        }
+    };
+    let local = table.is_none();
+    Some((target(TargetKind::Column, None, table, local), token))
+}
+
+/// Every place `target` appears in `sql`.
+pub fn occurrences(sql: &str, engine: &Engine, target: &Target) -> Vec<Occurrence> {
+    let mut found: Vec<Occurrence> = Vec::new();
+    for statement in scopes(sql, engine) {
+        if let Some(scope) = target.scope {
+            let range = statement.range();
+            if range.start < scope.start || range.end > scope.end {
+                continue;
+            }
+        }
+        match target.kind {
+            TargetKind::Table => table_occurrences(&statement, target, &mut found),
+            TargetKind::Column => column_occurrences(&statement, target, &mut found),
+            TargetKind::Alias | TargetKind::Cte => {
+                local_occurrences(&statement, target, &mut found)
+            }
+        }
+    }
+    found.sort_by_key(|o| o.token.offset);
+    found.dedup_by_key(|o| o.token.offset);
+    found
+}
+
+fn table_occurrences(statement: &Statement, target: &Target, found: &mut Vec<Occurrence>) {
+    let matches = |name: &super::statement::QualifiedName| {
+        same(&name.name(), &target.name)
+            && match (name.schema(), &target.schema) {
+                (Some(a), Some(b)) => same(&a, b),
+                _ => true,
+            }
+    };
+    let refs = statement.table_refs();
+    let ctes = statement.ctes();
+    let shadowed = ctes.iter().any(|c| same(&c.name.unquoted(), &target.name));
+    for r in refs.iter().filter(|r| !r.function && matches(&r.name)) {
+        if shadowed && r.name.parts.len() == 1 {
+            continue;
+        }
+        if let Some(last) = r.name.parts.last() {
+            found.push(Occurrence {
+                token: last.clone(),
+                declaration: false,
+            });
+        }
+    }
+    if let Some(definition) = statement.definition() {
+        if matches!(definition.object.as_str(), "TABLE" | "VIEW") && matches(&definition.name) {
+            if let Some(last) = definition.name.parts.last() {
+                found.push(Occurrence {
+                    token: last.clone(),
+                    declaration: definition.action == "CREATE",
+                });
+            }
+        }
+    }
+    // `users.id` where `users` isn't aliased.
+    let unaliased = refs
+        .iter()
+        .any(|r| r.alias.is_none() && same(&r.name.name(), &target.name));
+    if !unaliased || shadowed {
+        return;
+    }
+    let names = name_offsets(statement);
+    let tokens = &statement.tokens;
+    for (i, token) in tokens.iter().enumerate() {
+        let qualifier = tokens.get(i + 1).is_some_and(|t| t.is_punct("."))
+            && tokens.get(i + 2).is_some_and(|t| t.is_identifier());
+        if qualifier
+            && token.is_identifier()
+            && same(&token.unquoted(), &target.name)
+            && !names.contains(&token.offset)
+        {
+            found.push(Occurrence {
+                token: token.clone(),
+                declaration: false,
+            });
+        }
+    }
+}
+
+fn column_occurrences(statement: &Statement, target: &Target, found: &mut Vec<Occurrence>) {
+    let refs = statement.table_refs();
+    let definition = statement.definition();
+    if let Some(table) = &target.table {
+        let involved = refs.iter().any(|r| same(&r.name.name(), table))
+            || definition
+                .as_ref()
+                .is_some_and(|d| same(&d.name.name(), table));
+        if !involved {
+            return;
+        }
+    }
+    let declared: Vec<usize> = statement.columns().iter().map(|c| c.offset).collect();
+    let names = name_offsets(statement);
+    let aliases: Vec<usize> = refs
+        .iter()
+        .filter_map(|r| r.alias.as_ref().map(|a| a.offset))
+        .collect();
+    let tokens = &statement.tokens;
+    for (i, token) in tokens.iter().enumerate() {
+        if !token.is_identifier()
+            || !same(&token.unquoted(), &target.name)
+            || names.contains(&token.offset)
+            || aliases.contains(&token.offset)
+        {
+            continue;
+        }
+        let next = tokens.get(i + 1);
+        if next.is_some_and(|t| t.is_punct("(") || t.is_punct(".")) {
+            continue;
+        }
+        if i >= 2 && tokens[i - 1].is_punct(".") {
+            let Some(table) = &target.table else {
+                continue;
+            };
+            let qualifier = tokens[i - 2].unquoted();
+            let resolves = refs.iter().any(|r| {
+                same(&r.name.name(), table)
+                    && r.alias
+                        .as_ref()
+                        .map_or(same(&r.name.name(), &qualifier), |a| {
+                            same(&a.unquoted(), &qualifier)
+                        })
+            });
+            if !resolves {
+                continue;
+            }
+        }
+        found.push(Occurrence {
+            token: token.clone(),
+            declaration: declared.contains(&token.offset),
+        });
+    }
+}
+
+fn local_occurrences(statement: &Statement, target: &Target, found: &mut Vec<Occurrence>) {
+    let declarations: Vec<usize> = match target.kind {
+        TargetKind::Alias => statement
+            .table_refs()
+            .iter()
+            .filter_map(|r| r.alias.as_ref().map(|a| a.offset))
+            .collect(),
+        _ => statement.ctes().iter().map(|c| c.name.offset).collect(),
+    };
+    let tokens = &statement.tokens;
+    for (i, token) in tokens.iter().enumerate() {
+        let after_dot = i > 0 && tokens[i - 1].is_punct(".");
+        if token.is_identifier() && !after_dot && same(&token.unquoted(), &target.name) {
+            found.push(Occurrence {
+                token: token.clone(),
+                declaration: declarations.contains(&token.offset),
+            });
+        }
+    }
+}
+
+/// `new_name` quoted the same way as `token` was.
+pub fn renamed(token: &Lexeme, new_name: &str) -> String {
+    if token.kind != LexemeKind::QuotedIdentifier {
+        return new_name.to_string();
+    }
+    let open = &token.text[..1];
+    let close = &token.text[token.text.len() - 1..];
+    format!("{}{}{}", open, new_name, close)
+}
+
+/// Script that renames the table or column in the database, to keep a
+/// migration alongside the rename of the files.
+pub fn rename_script(
+    engine: &Engine,
+    target: &Target,
+    schema: &str,
+    new_name: &str,
+) -> Option<String> {
+    match (engine, target.kind) {
+        (Engine::SqlServer, TargetKind::Table) => Some(format!(
+            "EXEC sp_rename '{}.{}', '{}';\n",
+            schema, target.name, new_name
+        )),
+        (Engine::SqlServer, TargetKind::Column) => Some(format!(
+            "EXEC sp_rename '{}.{}.{}', '{}', 'COLUMN';\n",
+            schema,
+            target.table.as_ref()?,
+            target.name,
+            new_name
+        )),
+        (_, TargetKind::Table) => Some(format!(
+            "ALTER TABLE {}.{} RENAME TO {};\n",
+            schema, target.name, new_name
+        )),
+        (_, TargetKind::Column) => Some(format!(
+            "ALTER TABLE {}.{} RENAME COLUMN {} TO {};\n",
+            schema,
+            target.table.as_ref()?,
+            target.name,
+            new_name
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(sql: &str, needle: &str, nth: usize) -> Position {
+        let offset = sql.match_indices(needle).nth(nth).unwrap().0;
+        let line = sql[..offset].matches('\n').count() as u32;
+        let start = sql[..offset].rfind('\n').map_or(0, |n| n + 1);
+        Position::new(line, (offset - start) as u32)
+    }
+
+    fn found(sql: &str, target: &Target) -> Vec<(u32, u32, bool)> {
+        occurrences(sql, &Engine::SqlServer, target)
+            .iter()
+            .map(|o| (o.token.line, o.token.character, o.declaration))
+            .collect()
+    }
+
+    #[test]
+    fn should_find_tables_across_statements() {
+        let sql = "CREATE TABLE dbo.users (id int, name varchar(10))\nGO\nSELECT u.id, users.name FROM users JOIN dbo.users u ON 1 = 1\nUPDATE [users] SET name = 'x'";
+        let (target, _) = target_at(sql, &Engine::SqlServer, at(sql, "users", 2)).unwrap();
+        assert_eq!(target.kind, TargetKind::Table);
+        assert!(!target.is_local());
+        assert_eq!(
+            found(sql, &target),
+            vec![
+                (0, 17, true),
+                (2, 13, false),
+                (2, 29, false),
+                (2, 44, false),
+                (3, 7, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_resolve_columns_through_aliases() {
+        let sql = "SELECT u.name, r.name FROM dbo.users u JOIN dbo.roles r ON r.id = u.role_id\nGO\nUPDATE dbo.users SET name = 'x' WHERE name = 'y'";
+        let (target, _) = target_at(sql, &Engine::SqlServer, at(sql, "name", 0)).unwrap();
+        assert_eq!(target.kind, TargetKind::Column);
+        assert_eq!(target.table.as_deref(), Some("users"));
+        assert_eq!(
+            found(sql, &target),
+            vec![(0, 9, false), (2, 21, false), (2, 38, false)]
+        );
+    }
+
+    #[test]
+    fn should_keep_aliases_and_ctes_local() {
+        let sql = "WITH recent AS (SELECT id FROM dbo.orders)\nSELECT o.id FROM recent JOIN dbo.orders o ON o.id = recent.id;\nSELECT o.id FROM dbo.items o";
+        let (alias, _) = target_at(sql, &Engine::Postgres, at(sql, "o.id", 0)).unwrap();
+        assert_eq!(alias.kind, TargetKind::Alias);
+        let alias_found: Vec<_> = occurrences(sql, &Engine::Postgres, &alias)
+            .iter()
+            .map(|o| (o.token.line, o.declaration))
+            .collect();
+        assert_eq!(alias_found, vec![(1, false), (1, true), (1, false)]);
+        let (cte, _) = target_at(sql, &Engine::Postgres, at(sql, "recent", 2)).unwrap();
+        assert_eq!(cte.kind, TargetKind::Cte);
+        assert_eq!(occurrences(sql, &Engine::Postgres, &cte).len(), 3);
+    }
+
+    #[test]
+    fn should_write_rename_scripts_per_dialect() {
+        let column = Target {
+            kind: TargetKind::Column,
+            name: "name".to_string(),
+            schema: None,
+            table: Some("users".to_string()),
+            scope: None,
+        };
+        assert_eq!(
+            rename_script(&Engine::SqlServer, &column, "dbo", "full_name").unwrap(),
+            "EXEC sp_rename 'dbo.users.name', 'full_name', 'COLUMN';\n"
+        );
+        assert_eq!(
+            rename_script(&Engine::Postgres, &column, "public", "full_name").unwrap(),
+            "ALTER TABLE public.users RENAME COLUMN name TO full_name;\n"
+        );
+    }
+}
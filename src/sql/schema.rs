@@ -0,0 +1,1385 @@
+//! Evaluates `CREATE`, `ALTER` and `DROP` statements into a catalog, so the
+//! schema a set of scripts leaves behind is known without running them.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::Range;
+
+use super::statement::{parse, QualifiedName, Statement};
+use crate::db::catalog::{Catalog, ObjectSchema, TableSchema};
+use crate::db::column::Column;
+use crate::db::engine::Engine;
+use crate::db::index::{Constraint, ConstraintKind, Index};
+use crate::db::object::{DbObject, ObjectKind};
+use crate::db::table::Table;
+use crate::tokenizer::{Lexeme, LexemeKind};
+
+/// Words that end a column's type or a `DEFAULT` expression.
+const COLUMN_CLAUSES: &[&str] = &[
+    "NOT",
+    "NULL",
+    "DEFAULT",
+    "CONSTRAINT",
+    "PRIMARY",
+    "UNIQUE",
+    "REFERENCES",
+    "CHECK",
+    "IDENTITY",
+    "GENERATED",
+    "COLLATE",
+];
+
+const TABLE_CONSTRAINTS: &[&str] = &[
+    "CONSTRAINT",
+    "PRIMARY",
+    "UNIQUE",
+    "FOREIGN",
+    "CHECK",
+    "EXCLUDE",
+    "INDEX",
+    "KEY",
+    "PERIOD",
+    "LIKE",
+];
+
+/// The statement that last created or altered a table or object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub path: PathBuf,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VirtualSchema {
+    pub catalog: Catalog,
+    /// Keyed by lower-cased `schema.name`.
+    pub origins: HashMap<String, Origin>,
+    /// Lower-cased `schema.name` of every table and object a script created,
+    /// altered, renamed or dropped.
+    pub touched: HashSet<String>,
+}
+
+impl VirtualSchema {
+    pub fn touches(&self, schema: &str, name: &str) -> bool {
+        self.touched.contains(&key(schema, name))
+    }
+}
+
+pub fn key(schema: &str, name: &str) -> String {
+    format!("{}.{}", schema, name).to_lowercase()
+}
+
+/// Applies scripts one after the other. Statements it doesn't understand are
+/// skipped rather than failing the whole schema.
+pub struct SchemaBuilder {
+    engine: Engine,
+    schema: VirtualSchema,
+}
+
+/// What a constraint clause declares, before it's attached to its table.
+struct ConstraintClause {
+    constraint: Constraint,
+    /// Key columns of a primary key or unique constraint.
+    columns: Vec<String>,
+}
+
+impl SchemaBuilder {
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            schema: VirtualSchema::default(),
+        }
+    }
+
+    pub fn finish(mut self) -> VirtualSchema {
+        for table in &mut self.schema.catalog.tables {
+            table.columns.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        self.schema
+    }
+
+    pub fn apply(&mut self, sql: &str, path: &Path) {
+        for statement in parse(sql, &self.engine) {
+            let origin = Origin {
+                path: path.to_path_buf(),
+                range: statement.range(),
+            };
+            if matches!(statement.keyword().as_str(), "EXEC" | "EXECUTE") {
+                self.describe(&statement);
+                self.sp_rename(&statement, origin);
+                continue;
+            }
+            if statement.keyword() == "COMMENT" {
+                self.comment_on(&statement);
+                continue;
+            }
+            let Some(definition) = statement.definition() else {
+                continue;
+            };
+            let object = definition.object.as_str();
+            let Some((name, next)) = self.object_name(&statement, object) else {
+                continue;
+            };
+            match (definition.action.as_str(), object) {
+                ("CREATE", "TABLE") => self.create_table(&statement, sql, name, next, origin),
+                ("ALTER", "TABLE") => self.alter_table(&statement, sql, name, next, origin),
+                ("DROP", "TABLE") => {
+                    for table in self.name_list(&statement, name, next) {
+                        self.drop_table(&table);
+                    }
+                }
+                ("CREATE", "INDEX") => self.create_index(&statement, sql, origin),
+                ("DROP", "INDEX") => {
+                    for index in self.name_list(&statement, name, next) {
+                        self.drop_index(&index.name);
+                    }
+                }
+                ("CREATE" | "ALTER", "VIEW" | "FUNCTION" | "PROCEDURE") => {
+                    let object = DbObject {
+                        schema: self.schema_of(&name),
+                        name: self.ident(name.parts.last().expect("names have a part")),
+                        kind: object_kind(object),
+                    };
+                    self.define_object(object, statement.text(sql).to_string(), origin);
+                }
+                ("DROP", "VIEW" | "FUNCTION" | "PROCEDURE") => {
+                    for table in self.name_list(&statement, name, next) {
+                        self.touch(&table.schema, &table.name);
+                        self.schema.catalog.objects.retain(|o| {
+                            !(o.object.schema.eq_ignore_ascii_case(&table.schema)
+                                && o.object.name.eq_ignore_ascii_case(&table.name))
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn default_schema(&self) -> &'static str {
+        match self.engine {
+            Engine::SqlServer => "dbo",
+            Engine::Postgres | Engine::Mysql => "public",
+        }
+    }
+
+    /// An identifier as the catalog will spell it: Postgres folds unquoted
+    /// names to lower case.
+    fn ident(&self, lexeme: &Lexeme) -> String {
+        match (lexeme.kind, &self.engine) {
+            (LexemeKind::Word, Engine::Postgres) => lexeme.text.to_lowercase(),
+            _ => lexeme.unquoted(),
+        }
+    }
+
+    fn schema_of(&self, name: &QualifiedName) -> String {
+        let n = name.parts.len();
+        if n >= 2 {
+            self.ident(&name.parts[n - 2])
+        } else {
+            self.default_schema().to_string()
+        }
+    }
+
+    fn table_of(&self, name: &QualifiedName) -> Table {
+        Table {
+            name: self.ident(name.parts.last().expect("names have a part")),
+            schema: self.schema_of(name),
+            description: None,
+        }
+    }
+
+    /// The name after the object keyword, past `IF [NOT] EXISTS`, `ONLY` and
+    /// `CONCURRENTLY`, with the index of the token following it.
+    fn object_name(&self, statement: &Statement, object: &str) -> Option<(QualifiedName, usize)> {
+        let at = statement
+            .tokens
+            .iter()
+            .position(|t| t.is_word(object) || object == "PROCEDURE" && t.is_word("PROC"))?;
+        let mut i = at + 1;
+        while statement.tokens.get(i).is_some_and(|t| {
+            ["IF", "NOT", "EXISTS", "ONLY", "CONCURRENTLY"]
+                .iter()
+                .any(|w| t.is_word(w))
+        }) {
+            i += 1;
+        }
+        statement.qualified_name(i)
+    }
+
+    /// The comma-separated names of a `DROP`, skipping routine signatures.
+    fn name_list(&self, statement: &Statement, first: QualifiedName, next: usize) -> Vec<Table> {
+        let mut tables = vec![self.table_of(&first)];
+        let mut i = next;
+        loop {
+            if statement.tokens.get(i).is_some_and(|t| t.is_punct("(")) {
+                i = statement
+                    .close_paren(i)
+                    .map_or(statement.tokens.len(), |c| c + 1);
+            }
+            if !statement.tokens.get(i).is_some_and(|t| t.is_punct(",")) {
+                break;
+            }
+            let Some((name, after)) = statement.qualified_name(i + 1) else {
+                break;
+            };
+            tables.push(self.table_of(&name));
+            i = after;
+        }
+        tables
+    }
+
+    fn touch(&mut self, schema: &str, name: &str) {
+        self.schema.touched.insert(key(schema, name));
+    }
+
+    fn find_table(&mut self, table: &Table) -> Option<&mut TableSchema> {
+        self.schema.catalog.tables.iter_mut().find(|t| {
+            t.table.schema.eq_ignore_ascii_case(&table.schema)
+                && t.table.name.eq_ignore_ascii_case(&table.name)
+        })
+    }
+
+    fn drop_table(&mut self, table: &Table) {
+        self.touch(&table.schema, &table.name);
+        self.schema.catalog.tables.retain(|t| {
+            !(t.table.schema.eq_ignore_ascii_case(&table.schema)
+                && t.table.name.eq_ignore_ascii_case(&table.name))
+        });
+    }
+
+    fn define_object(&mut self, object: DbObject, definition: String, origin: Origin) {
+        self.touch(&object.schema, &object.name);
+        self.schema
+            .origins
+            .insert(key(&object.schema, &object.name), origin);
+        let objects = &mut self.schema.catalog.objects;
+        objects.retain(|o| {
+            !(o.object.schema.eq_ignore_ascii_case(&object.schema)
+                && o.object.name.eq_ignore_ascii_case(&object.name))
+        });
+        objects.push(ObjectSchema { object, definition });
+    }
+
+    fn create_table(
+        &mut self,
+        statement: &Statement,
+        sql: &str,
+        name: QualifiedName,
+        next: usize,
+        origin: Origin,
+    ) {
+        // `CREATE TABLE ... AS SELECT` leaves the columns unknown.
+        let items = match statement.tokens.get(next) {
+            Some(t) if t.is_punct("(") => match statement.close_paren(next) {
+                Some(close) => elements(&statement.tokens[next + 1..close]),
+                // Half written, as a script being edited can be.
+                None => return,
+            },
+            _ => vec![],
+        };
+        let table = self.table_of(&name);
+        self.touch(&table.schema, &table.name);
+        if self.find_table(&table).is_some() {
+            // `IF NOT EXISTS`, or a script that would fail here anyway.
+            return;
+        }
+        self.schema
+            .origins
+            .insert(key(&table.schema, &table.name), origin);
+        self.schema.catalog.tables.push(TableSchema {
+            table: table.clone(),
+            columns: vec![],
+            indexes: vec![],
+            constraints: vec![],
+        });
+        for element in items {
+            let Some(first) = element.first() else {
+                continue;
+            };
+            if TABLE_CONSTRAINTS.iter().any(|w| first.is_word(w)) {
+                self.add_constraint(&table, element, sql);
+            } else {
+                self.add_column(&table, element, sql);
+            }
+        }
+    }
+
+    fn alter_table(
+        &mut self,
+        statement: &Statement,
+        sql: &str,
+        name: QualifiedName,
+        next: usize,
+        origin: Origin,
+    ) {
+        let mut table = self.table_of(&name);
+        self.touch(&table.schema, &table.name);
+        if self.find_table(&table).is_none() {
+            return;
+        }
+        // T-SQL lists several columns after one `ADD` or `DROP COLUMN`.
+        let mut verb = String::new();
+        for clause in elements(&statement.tokens[next..]) {
+            let Some(first) = clause.first() else {
+                continue;
+            };
+            let word = first.text.to_uppercase();
+            let rest = if matches!(word.as_str(), "ADD" | "DROP" | "ALTER" | "RENAME" | "SET") {
+                verb = word.clone();
+                &clause[1..]
+            } else {
+                clause
+            };
+            let rest = skip_words(rest, &["IF", "NOT", "EXISTS"]);
+            match verb.as_str() {
+                "ADD" => {
+                    let rest = skip_words(rest, &["COLUMN"]);
+                    let rest = skip_words(rest, &["IF", "NOT", "EXISTS"]);
+                    match rest.first() {
+                        Some(t) if TABLE_CONSTRAINTS.iter().any(|w| t.is_word(w)) => {
+                            self.add_constraint(&table, rest, sql)
+                        }
+                        Some(_) => self.add_column(&table, rest, sql),
+                        None => {}
+                    }
+                }
+                "DROP" if rest.first().is_some_and(|t| t.is_word("CONSTRAINT")) => {
+                    let names = skip_words(&rest[1..], &["IF", "EXISTS"]);
+                    if let Some(constraint) = names.first().map(|t| self.ident(t)) {
+                        self.drop_constraint(&table, &constraint);
+                    }
+                }
+                "DROP" => {
+                    let rest = skip_words(rest, &["COLUMN"]);
+                    let rest = skip_words(rest, &["IF", "EXISTS"]);
+                    if let Some(column) = rest.first().map(|t| self.ident(t)) {
+                        self.drop_column(&table, &column);
+                    }
+                }
+                "ALTER" => self.alter_column(&table, skip_words(rest, &["COLUMN"]), sql),
+                "RENAME" => {
+                    let rest = skip_words(rest, &["COLUMN"]);
+                    match rest {
+                        [to, name, ..] if to.is_word("TO") => {
+                            let renamed = Table {
+                                name: self.ident(name),
+                                schema: table.schema.clone(),
+                                description: None,
+                            };
+                            self.rename_table(&table, &renamed);
+                            table = renamed;
+                        }
+                        [constraint, from, to, name, ..]
+                            if constraint.is_word("CONSTRAINT") && to.is_word("TO") =>
+                        {
+                            let (from, name) = (self.ident(from), self.ident(name));
+                            if let Some(schema) = self.find_table(&table) {
+                                for c in &mut schema.constraints {
+                                    if c.name.eq_ignore_ascii_case(&from) {
+                                        c.name = name.clone();
+                                    }
+                                }
+                            }
+                        }
+                        [from, to, name, ..] if to.is_word("TO") => {
+                            let (from, name) = (self.ident(from), self.ident(name));
+                            self.rename_column(&table, &from, &name);
+                        }
+                        _ => {}
+                    }
+                }
+                "SET" => {
+                    if let [schema, name, ..] = rest {
+                        if schema.is_word("SCHEMA") {
+                            let moved = Table {
+                                name: table.name.clone(),
+                                schema: self.ident(name),
+                                description: None,
+                            };
+                            self.rename_table(&table, &moved);
+                            table = moved;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.schema
+            .origins
+            .insert(key(&table.schema, &table.name), origin);
+    }
+
+    fn add_column(&mut self, table: &Table, tokens: &[Lexeme], sql: &str) {
+        let Some(name) = tokens.first().map(|t| self.ident(t)) else {
+            return;
+        };
+        let depths = depths(tokens);
+        let end = (1..tokens.len())
+            .find(|i| {
+                depths[*i] == 0
+                    && (COLUMN_CLAUSES.iter().any(|w| tokens[*i].is_word(w))
+                        || tokens[*i].is_word("AS"))
+            })
+            .unwrap_or(tokens.len());
+        let mut column = self.column_type(table, &name, &tokens[1..end]);
+        let mut clauses = Vec::new();
+        let mut constraint_name = None;
+        let mut i = end;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            let clause_end = (i + 1..tokens.len())
+                .find(|j| depths[*j] == 0 && COLUMN_CLAUSES.iter().any(|w| tokens[*j].is_word(w)))
+                .unwrap_or(tokens.len());
+            let word = token.text.to_uppercase();
+            match word.as_str() {
+                "NOT" if tokens.get(i + 1).is_some_and(|t| t.is_word("NULL")) => {
+                    column.is_nullable = "NO".to_string();
+                    i += 2;
+                    continue;
+                }
+                "NULL" => column.is_nullable = "YES".to_string(),
+                "DEFAULT" if clause_end > i + 1 => {
+                    column.column_default = Some(source(sql, &tokens[i + 1..clause_end]));
+                }
+                "CONSTRAINT" => {
+                    constraint_name = tokens.get(i + 1).map(|t| self.ident(t));
+                    i += 2;
+                    continue;
+                }
+                // Also ends `GENERATED ... AS IDENTITY`.
+                "IDENTITY" => column.is_nullable = "NO".to_string(),
+                "PRIMARY" => {
+                    column.is_nullable = "NO".to_string();
+                    clauses.push((
+                        constraint_name.take(),
+                        ConstraintKind::PrimaryKey,
+                        format!("PRIMARY KEY ({})", name),
+                    ));
+                }
+                "UNIQUE" => clauses.push((
+                    constraint_name.take(),
+                    ConstraintKind::Unique,
+                    format!("UNIQUE ({})", name),
+                )),
+                "REFERENCES" => clauses.push((
+                    constraint_name.take(),
+                    ConstraintKind::ForeignKey,
+                    format!(
+                        "FOREIGN KEY ({}) {}",
+                        name,
+                        source(sql, &tokens[i..clause_end])
+                    ),
+                )),
+                "CHECK" => clauses.push((
+                    constraint_name.take(),
+                    ConstraintKind::Check,
+                    source(sql, &tokens[i..clause_end]),
+                )),
+                _ => {}
+            }
+            i = clause_end;
+        }
+        let Some(schema) = self.find_table(table) else {
+            return;
+        };
+        if schema
+            .columns
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(&name))
+        {
+            return;
+        }
+        column.ordinal_position = schema
+            .columns
+            .iter()
+            .map(|c| c.ordinal_position)
+            .max()
+            .unwrap_or_default()
+            + 1;
+        schema.columns.push(column);
+        for (constraint_name, kind, definition) in clauses {
+            let constraint = Constraint {
+                name: constraint_name.unwrap_or_else(|| {
+                    self.constraint_name(table, kind, std::slice::from_ref(&name))
+                }),
+                kind,
+                definition,
+                columns: vec![name.clone()],
+            };
+            self.attach(
+                table,
+                ConstraintClause {
+                    constraint,
+                    columns: vec![name.clone()],
+                },
+            );
+        }
+    }
+
+    /// A column of `table` typed from the tokens of its type, e.g.
+    /// `varchar(50)` or `timestamp(3) with time zone`.
+    fn column_type(&self, table: &Table, name: &str, tokens: &[Lexeme]) -> Column {
+        let mut words = Vec::new();
+        let mut args = Vec::new();
+        let mut array = false;
+        let mut depth = 0;
+        for token in tokens {
+            if token.is_punct("(") {
+                depth += 1;
+            } else if token.is_punct(")") {
+                depth -= 1;
+            } else if depth > 0 {
+                if token.kind == LexemeKind::Number || token.is_word("MAX") {
+                    args.push(token.text.to_lowercase());
+                }
+            } else if token.text == "[]" || token.is_word("ARRAY") {
+                array = true;
+            } else if token.is_identifier() {
+                words.push(token.unquoted().to_lowercase());
+            }
+        }
+        let written = words.join(" ");
+        let arg = |i: usize| -> Option<i32> {
+            args.get(i).map(|a| match a.as_str() {
+                "max" => -1,
+                a => a.parse().unwrap_or_default(),
+            })
+        };
+        let mut column = Column {
+            name: name.to_string(),
+            table: table.name.clone(),
+            schema: table.schema.clone(),
+            is_nullable: "YES".to_string(),
+            ..Column::default()
+        };
+        let data_type = match self.engine {
+            Engine::SqlServer => match written.as_str() {
+                "integer" => "int",
+                "dec" => "decimal",
+                "double precision" => "float",
+                "rowversion" => "timestamp",
+                "character" => "char",
+                "character varying" | "char varying" => "varchar",
+                "national character" | "national char" => "nchar",
+                "national character varying" | "national char varying" => "nvarchar",
+                other => other,
+            },
+            _ if array => "ARRAY",
+            _ => match written.as_str() {
+                "int" | "int4" | "serial" | "serial4" => "integer",
+                "int8" | "bigserial" | "serial8" => "bigint",
+                "int2" | "smallserial" | "serial2" => "smallint",
+                "varchar" | "char varying" => "character varying",
+                "char" | "bpchar" => "character",
+                "bool" => "boolean",
+                "timestamptz" => "timestamp with time zone",
+                "timestamp" => "timestamp without time zone",
+                "timetz" => "time with time zone",
+                "time" => "time without time zone",
+                "float8" | "float" => "double precision",
+                "float4" => "real",
+                "decimal" => "numeric",
+                other => other,
+            },
+        }
+        .to_string();
+        match data_type.as_str() {
+            "char" | "varchar" | "nchar" | "nvarchar" | "binary" | "varbinary" => {
+                column.character_maximum_length = arg(0).or(Some(1));
+            }
+            "character" => column.character_maximum_length = arg(0).or(Some(1)),
+            "character varying" | "bit varying" => column.character_maximum_length = arg(0),
+            "decimal" | "numeric" => {
+                let default = (self.engine == Engine::SqlServer).then_some(18);
+                column.numeric_precision = arg(0).or(default);
+                column.numeric_scale = arg(1).or(column.numeric_precision.map(|_| 0));
+            }
+            _ => {}
+        }
+        if self.engine == Engine::Postgres && written.contains("serial") {
+            let sequence = format!("{}_{}_seq", table.name, name);
+            let sequence = match table.schema.as_str() {
+                "public" => sequence,
+                schema => format!("{}.{}", schema, sequence),
+            };
+            column.column_default = Some(format!("nextval('{}'::regclass)", sequence));
+            column.is_nullable = "NO".to_string();
+        }
+        column.data_type = data_type;
+        column
+    }
+
+    fn alter_column(&mut self, table: &Table, tokens: &[Lexeme], sql: &str) {
+        let Some(name) = tokens.first().map(|t| self.ident(t)) else {
+            return;
+        };
+        let rest = &tokens[1..];
+        let words: Vec<String> = rest.iter().take(3).map(|t| t.text.to_uppercase()).collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let typed = |at: usize| {
+            let end = rest
+                .iter()
+                .position(|t| t.is_word("USING") || t.is_word("COLLATE"))
+                .unwrap_or(rest.len());
+            &rest[at..end.max(at)]
+        };
+        let engine = self.engine.clone();
+        let Some(column) = self.find_table(table).and_then(|t| {
+            t.columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(&name))
+        }) else {
+            return;
+        };
+        let retyped = |builder: &SchemaBuilder, tokens: &[Lexeme]| {
+            let depths = depths(tokens);
+            let end = (0..tokens.len())
+                .find(|i| {
+                    depths[*i] == 0 && (tokens[*i].is_word("NULL") || tokens[*i].is_word("NOT"))
+                })
+                .unwrap_or(tokens.len());
+            (builder.column_type(table, &name, &tokens[..end]), end)
+        };
+        let (changed, nullable, default) = match words.as_slice() {
+            ["TYPE", ..] => (Some(retyped(self, typed(1)).0), None, None),
+            ["SET", "DATA", "TYPE"] => (Some(retyped(self, typed(3)).0), None, None),
+            ["SET", "NOT", "NULL"] => (None, Some("NO"), None),
+            ["DROP", "NOT", "NULL"] => (None, Some("YES"), None),
+            ["SET", "DEFAULT", ..] => (None, None, Some(Some(source(sql, &rest[2..])))),
+            ["DROP", "DEFAULT", ..] => (None, None, Some(None)),
+            _ if engine == Engine::SqlServer => {
+                let (typed, end) = retyped(self, rest);
+                let nullable = match rest.get(end) {
+                    Some(t) if t.is_word("NOT") => "NO",
+                    _ => "YES",
+                };
+                (Some(typed), Some(nullable), None)
+            }
+            _ => (None, None, None),
+        };
+        let Some(schema) = self.find_table(table) else {
+            return;
+        };
+        let column = &mut schema.columns[column];
+        if let Some(changed) = changed {
+            column.data_type = changed.data_type;
+            column.character_maximum_length = changed.character_maximum_length;
+            column.numeric_precision = changed.numeric_precision;
+            column.numeric_scale = changed.numeric_scale;
+        }
+        if let Some(nullable) = nullable {
+            column.is_nullable = nullable.to_string();
+        }
+        if let Some(default) = default {
+            column.column_default = default;
+        }
+    }
+
+    fn add_constraint(&mut self, table: &Table, tokens: &[Lexeme], sql: &str) {
+        let (name, tokens) = match tokens {
+            [constraint, name, rest @ ..] if constraint.is_word("CONSTRAINT") => {
+                (Some(self.ident(name)), rest)
+            }
+            _ => (None, tokens),
+        };
+        let Some(first) = tokens.first() else {
+            return;
+        };
+        let kind = match first.text.to_uppercase().as_str() {
+            "PRIMARY" => ConstraintKind::PrimaryKey,
+            "UNIQUE" => ConstraintKind::Unique,
+            "FOREIGN" => ConstraintKind::ForeignKey,
+            "CHECK" => ConstraintKind::Check,
+            "DEFAULT" => {
+                // T-SQL's `DEFAULT (0) FOR column`.
+                if let Some(at) = tokens.iter().position(|t| t.is_word("FOR")) {
+                    if let Some(column) = tokens.get(at + 1).map(|t| self.ident(t)) {
+                        let default = source(sql, &tokens[1..at]);
+                        if let Some(schema) = self.find_table(table) {
+                            for c in &mut schema.columns {
+                                if c.name.eq_ignore_ascii_case(&column) {
+                                    c.column_default = Some(default.clone());
+                                }
+                            }
+                        }
+                    }
+                }
+                return;
+            }
+            "INDEX" => {
+                // T-SQL's inline `INDEX name (columns)`.
+                if let Some(index) = tokens.get(1).map(|t| self.ident(t)) {
+                    let columns = self.key_columns(&tokens[2..]);
+                    let (included, filter) = self.index_options(sql, &tokens[2..]);
+                    let kind = self.index_kind(&tokens[2..], false);
+                    if let Some(schema) = self.find_table(table) {
+                        schema.indexes.push(Index {
+                            name: index,
+                            columns,
+                            included,
+                            unique: false,
+                            primary_key: false,
+                            filter,
+                            kind,
+                        });
+                    }
+                }
+                return;
+            }
+            _ => return,
+        };
+        let columns = match kind {
+            ConstraintKind::PrimaryKey | ConstraintKind::Unique | ConstraintKind::ForeignKey => {
+                self.key_columns(tokens)
+            }
+            _ => vec![],
+        };
+        let constraint = Constraint {
+            name: name.unwrap_or_else(|| self.constraint_name(table, kind, &columns)),
+            kind,
+            definition: source(sql, tokens),
+            columns: columns.clone(),
+        };
+        self.attach(
+            table,
+            ConstraintClause {
+                constraint,
+                columns,
+            },
+        );
+    }
+
+    /// The names in the first parenthesized list of `tokens`, without
+    /// `ASC`/`DESC` or expressions.
+    fn key_columns(&self, tokens: &[Lexeme]) -> Vec<String> {
+        let Some(open) = tokens.iter().position(|t| t.is_punct("(")) else {
+            return vec![];
+        };
+        let depths = depths(tokens);
+        let close = (open + 1..tokens.len())
+            .find(|i| depths[*i] == 1 && tokens[*i].is_punct(")"))
+            .unwrap_or(tokens.len());
+        elements(&tokens[open + 1..close])
+            .into_iter()
+            .filter(|element| !element.get(1).is_some_and(|t| t.is_punct("(")))
+            .filter_map(|element| element.first())
+            .filter(|t| t.is_identifier())
+            .map(|t| self.ident(t))
+            .collect()
+    }
+
+    /// The access method, `btree` unless a later `USING` says otherwise, or
+    /// the `[NON]CLUSTERED [COLUMNSTORE]` words among `tokens` in SQL Server.
+    fn index_kind(&self, tokens: &[Lexeme], primary_key: bool) -> Option<String> {
+        if self.engine != Engine::SqlServer {
+            return Some("btree".to_string());
+        }
+        let clustered = tokens
+            .iter()
+            .find(|t| t.is_word("CLUSTERED") || t.is_word("NONCLUSTERED"))
+            .map(|t| t.text.to_uppercase())
+            .unwrap_or_else(|| {
+                if primary_key {
+                    "CLUSTERED"
+                } else {
+                    "NONCLUSTERED"
+                }
+                .to_string()
+            });
+        if tokens.iter().any(|t| t.is_word("COLUMNSTORE")) {
+            return Some(format!("{} COLUMNSTORE", clustered));
+        }
+        Some(clustered)
+    }
+
+    /// The `INCLUDE (...)` columns and the `WHERE` filter that follow an
+    /// index's key columns.
+    fn index_options(&self, sql: &str, tokens: &[Lexeme]) -> (Vec<String>, Option<String>) {
+        let depths = depths(tokens);
+        let at =
+            |word: &str| (0..tokens.len()).find(|i| depths[*i] == 0 && tokens[*i].is_word(word));
+        let included = at("INCLUDE")
+            .map(|i| self.key_columns(&tokens[i + 1..]))
+            .unwrap_or_default();
+        let filter = at("WHERE").and_then(|i| {
+            let end = (i + 1..tokens.len())
+                .find(|j| {
+                    depths[*j] == 0
+                        && (tokens[*j].is_word("WITH")
+                            || tokens[*j].is_word("ON")
+                            || tokens[*j].is_punct(";"))
+                })
+                .unwrap_or(tokens.len());
+            let filter = source(sql, &tokens[i + 1..end]);
+            (!filter.is_empty()).then_some(filter)
+        });
+        (included, filter)
+    }
+
+    fn constraint_name(&self, table: &Table, kind: ConstraintKind, columns: &[String]) -> String {
+        let columns = columns.join("_");
+        match (&self.engine, kind) {
+            (Engine::SqlServer, ConstraintKind::PrimaryKey) => format!("PK_{}", table.name),
+            (Engine::SqlServer, ConstraintKind::Unique) => format!("UQ_{}_{}", table.name, columns),
+            (Engine::SqlServer, ConstraintKind::ForeignKey) => {
+                format!("FK_{}_{}", table.name, columns)
+            }
+            (Engine::SqlServer, _) => format!("CK_{}", table.name),
+            (_, ConstraintKind::PrimaryKey) => format!("{}_pkey", table.name),
+            (_, ConstraintKind::Unique) => format!("{}_{}_key", table.name, columns),
+            (_, ConstraintKind::ForeignKey) => format!("{}_{}_fkey", table.name, columns),
+            (_, _) => format!("{}_check", table.name),
+        }
+    }
+
+    /// Adds a constraint to its table, with the index backing a primary key
+    /// or unique constraint.
+    fn attach(&mut self, table: &Table, clause: ConstraintClause) {
+        let ConstraintClause {
+            constraint,
+            columns,
+        } = clause;
+        let primary_key = constraint.kind == ConstraintKind::PrimaryKey;
+        let kind = match self.engine {
+            Engine::SqlServer => constraint
+                .definition
+                .split_whitespace()
+                .find(|w| {
+                    w.eq_ignore_ascii_case("CLUSTERED") || w.eq_ignore_ascii_case("NONCLUSTERED")
+                })
+                .map(str::to_uppercase)
+                .or_else(|| {
+                    Some(
+                        if primary_key {
+                            "CLUSTERED"
+                        } else {
+                            "NONCLUSTERED"
+                        }
+                        .to_string(),
+                    )
+                }),
+            _ => Some("btree".to_string()),
+        };
+        let Some(schema) = self.find_table(table) else {
+            return;
+        };
+        if primary_key {
+            for column in &mut schema.columns {
+                if columns.iter().any(|c| c.eq_ignore_ascii_case(&column.name)) {
+                    column.is_nullable = "NO".to_string();
+                }
+            }
+        }
+        if primary_key || constraint.kind == ConstraintKind::Unique {
+            schema.indexes.push(Index {
+                name: constraint.name.clone(),
+                columns,
+                included: vec![],
+                unique: true,
+                primary_key,
+                filter: None,
+                kind,
+            });
+        }
+        schema.constraints.push(constraint);
+    }
+
+    fn drop_constraint(&mut self, table: &Table, name: &str) {
+        if let Some(schema) = self.find_table(table) {
+            schema
+                .constraints
+                .retain(|c| !c.name.eq_ignore_ascii_case(name));
+            schema
+                .indexes
+                .retain(|i| !i.name.eq_ignore_ascii_case(name));
+        }
+    }
+
+    fn drop_column(&mut self, table: &Table, name: &str) {
+        if let Some(schema) = self.find_table(table) {
+            schema
+                .columns
+                .retain(|c| !c.name.eq_ignore_ascii_case(name));
+            schema
+                .indexes
+                .retain(|i| !i.columns.iter().any(|c| c.eq_ignore_ascii_case(name)));
+        }
+    }
+
+    fn rename_column(&mut self, table: &Table, from: &str, to: &str) {
+        if let Some(schema) = self.find_table(table) {
+            for column in &mut schema.columns {
+                if column.name.eq_ignore_ascii_case(from) {
+                    column.name = to.to_string();
+                }
+            }
+            for index in &mut schema.indexes {
+                for column in &mut index.columns {
+                    if column.eq_ignore_ascii_case(from) {
+                        *column = to.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    fn rename_table(&mut self, from: &Table, to: &Table) {
+        self.touch(&to.schema, &to.name);
+        if let Some(origin) = self.schema.origins.remove(&key(&from.schema, &from.name)) {
+            self.schema
+                .origins
+                .insert(key(&to.schema, &to.name), origin);
+        }
+        if let Some(schema) = self.find_table(from) {
+            schema.table.name = to.name.clone();
+            schema.table.schema = to.schema.clone();
+            for column in &mut schema.columns {
+                column.table = to.name.clone();
+                column.schema = to.schema.clone();
+            }
+        }
+    }
+
+    fn create_index(&mut self, statement: &Statement, sql: &str, origin: Origin) {
+        let tokens = &statement.tokens;
+        let Some(at) = tokens.iter().position(|t| t.is_word("INDEX")) else {
+            return;
+        };
+        let unique = tokens[..at].iter().any(|t| t.is_word("UNIQUE"));
+        let mut i = at + 1;
+        while tokens.get(i).is_some_and(|t| {
+            ["CONCURRENTLY", "IF", "NOT", "EXISTS"]
+                .iter()
+                .any(|w| t.is_word(w))
+        }) {
+            i += 1;
+        }
+        let name = match tokens.get(i) {
+            Some(t) if !t.is_word("ON") => {
+                i += 1;
+                Some(self.ident(t))
+            }
+            _ => None,
+        };
+        if !tokens.get(i).is_some_and(|t| t.is_word("ON")) {
+            return;
+        }
+        i += 1;
+        if tokens.get(i).is_some_and(|t| t.is_word("ONLY")) {
+            i += 1;
+        }
+        let Some((table, next)) = statement.qualified_name(i) else {
+            return;
+        };
+        let table = self.table_of(&table);
+        let mut kind = self.index_kind(&tokens[..at], false);
+        let mut next = next;
+        if tokens.get(next).is_some_and(|t| t.is_word("USING")) {
+            kind = tokens.get(next + 1).map(|t| t.text.to_lowercase());
+            next += 2;
+        }
+        let columns = self.key_columns(&tokens[next..]);
+        let (included, filter) = self.index_options(sql, &tokens[next..]);
+        let name = name.unwrap_or_else(|| format!("{}_{}_idx", table.name, columns.join("_")));
+        self.schema
+            .origins
+            .insert(key(&table.schema, &table.name), origin);
+        if let Some(schema) = self.find_table(&table) {
+            schema
+                .indexes
+                .retain(|i| !i.name.eq_ignore_ascii_case(&name));
+            schema.indexes.push(Index {
+                name,
+                columns,
+                included,
+                unique,
+                primary_key: false,
+                filter,
+                kind,
+            });
+        }
+    }
+
+    fn drop_index(&mut self, name: &str) {
+        for table in &mut self.schema.catalog.tables {
+            table.indexes.retain(|i| !i.name.eq_ignore_ascii_case(name));
+        }
+    }
+
+    /// `EXEC sp_rename 'dbo.t', 'u'` and `EXEC sp_rename 'dbo.t.c', 'd', 'COLUMN'`.
+    fn sp_rename(&mut self, statement: &Statement, origin: Origin) {
+        let tokens = &statement.tokens;
+        if !tokens
+            .iter()
+            .take(4)
+            .any(|t| t.is_word("sp_rename") || t.unquoted().eq_ignore_ascii_case("sp_rename"))
+        {
+            return;
+        }
+        let strings: Vec<String> = tokens
+            .iter()
+            .filter(|t| t.kind == LexemeKind::String)
+            .map(|t| string_value(&t.text))
+            .collect();
+        let [object, new_name, ..] = strings.as_slice() else {
+            return;
+        };
+        let parts: Vec<String> = object
+            .split('.')
+            .map(|p| p.trim_matches(|c| c == '[' || c == ']').to_string())
+            .collect();
+        let column = strings
+            .get(2)
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("COLUMN"));
+        let (schema, table) = match (column, parts.as_slice()) {
+            (true, [schema, table, _]) | (false, [schema, table]) => {
+                (schema.clone(), table.clone())
+            }
+            (true, [table, _]) | (false, [table]) => {
+                (self.default_schema().to_string(), table.clone())
+            }
+            _ => return,
+        };
+        let table = Table {
+            name: table,
+            schema,
+            description: None,
+        };
+        if column {
+            let from = parts.last().expect("split yields a part");
+            self.rename_column(&table, from, new_name);
+            self.schema
+                .origins
+                .insert(key(&table.schema, &table.name), origin);
+        } else {
+            let renamed = Table {
+                name: new_name.clone(),
+                schema: table.schema.clone(),
+                description: None,
+            };
+            self.touch(&table.schema, &table.name);
+            self.rename_table(&table, &renamed);
+            self.schema
+                .origins
+                .insert(key(&renamed.schema, &renamed.name), origin);
+        }
+    }
+
+    /// `COMMENT ON TABLE t IS '...'` and `COMMENT ON COLUMN t.c IS '...'`,
+    /// where `IS NULL` drops the comment.
+    fn comment_on(&mut self, statement: &Statement) {
+        let tokens = &statement.tokens;
+        let [_, on, object, ..] = tokens.as_slice() else {
+            return;
+        };
+        if !on.is_word("ON") {
+            return;
+        }
+        let Some((name, next)) = statement.qualified_name(3) else {
+            return;
+        };
+        if !tokens.get(next).is_some_and(|t| t.is_word("IS")) {
+            return;
+        }
+        let description = tokens
+            .get(next + 1)
+            .filter(|t| t.kind == LexemeKind::String)
+            .map(|t| string_value(&t.text));
+        if object.is_word("TABLE") {
+            let table = self.table_of(&name);
+            if let Some(schema) = self.find_table(&table) {
+                schema.table.description = description;
+            }
+        } else if object.is_word("COLUMN") && name.parts.len() >= 2 {
+            let (column, table) = name.parts.split_last().expect("names have a part");
+            let column = self.ident(column);
+            let table = self.table_of(&QualifiedName {
+                parts: table.to_vec(),
+            });
+            if let Some(schema) = self.find_table(&table) {
+                if let Some(c) = schema
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.name.eq_ignore_ascii_case(&column))
+                {
+                    c.description = description;
+                }
+            }
+        }
+    }
+
+    /// `sp_addextendedproperty` or `sp_updateextendedproperty` setting the
+    /// `MS_Description` of a table or column, with its arguments named or not.
+    fn describe(&mut self, statement: &Statement) {
+        let tokens = &statement.tokens;
+        if !tokens.iter().take(4).any(|t| {
+            ["sp_addextendedproperty", "sp_updateextendedproperty"]
+                .iter()
+                .any(|p| t.unquoted().eq_ignore_ascii_case(p))
+        }) {
+            return;
+        }
+        let strings: Vec<String> = tokens
+            .iter()
+            .filter(|t| t.kind == LexemeKind::String)
+            .map(|t| string_value(&t.text))
+            .collect();
+        let [property, value, level0, schema, level1, table, rest @ ..] = strings.as_slice() else {
+            return;
+        };
+        if !property.eq_ignore_ascii_case("MS_Description")
+            || !level0.eq_ignore_ascii_case("SCHEMA")
+            || !level1.eq_ignore_ascii_case("TABLE")
+        {
+            return;
+        }
+        let table = Table {
+            name: table.clone(),
+            schema: schema.clone(),
+            description: None,
+        };
+        let Some(schema) = self.find_table(&table) else {
+            return;
+        };
+        match rest {
+            [] => schema.table.description = Some(value.clone()),
+            [level2, column, ..] if level2.eq_ignore_ascii_case("COLUMN") => {
+                if let Some(c) = schema
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.name.eq_ignore_ascii_case(column))
+                {
+                    c.description = Some(value.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn object_kind(object: &str) -> ObjectKind {
+    match object {
+        "VIEW" => ObjectKind::View,
+        "PROCEDURE" => ObjectKind::Procedure,
+        _ => ObjectKind::Function,
+    }
+}
+
+/// Splits on the commas outside parens.
+fn elements(tokens: &[Lexeme]) -> Vec<&[Lexeme]> {
+    let depths = depths(tokens);
+    let mut elements = Vec::new();
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if depths[i] == 0 && token.is_punct(",") {
+            elements.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    elements.push(&tokens[start..]);
+    elements
+}
+
+/// Paren depth of each token, counting a paren as outside its own group.
+fn depths(tokens: &[Lexeme]) -> Vec<usize> {
+    let mut depth = 0usize;
+    tokens
+        .iter()
+        .map(|t| {
+            if t.is_punct("(") {
+                depth += 1;
+                depth - 1
+            } else if t.is_punct(")") {
+                depth = depth.saturating_sub(1);
+                depth
+            } else {
+                depth
+            }
+        })
+        .collect()
+}
+
+fn skip_words<'a>(tokens: &'a [Lexeme], words: &[&str]) -> &'a [Lexeme] {
+    let skipped = tokens
+        .iter()
+        .take_while(|t| words.iter().any(|w| t.is_word(w)))
+        .count();
+    &tokens[skipped..]
+}
+
+/// The source of `tokens` with runs of whitespace collapsed.
+fn source(sql: &str, tokens: &[Lexeme]) -> String {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => sql[first.offset..last.offset + last.text.len()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+/// The text of a string literal, without its quotes or `N` prefix.
+fn string_value(literal: &str) -> String {
+    let literal = literal.trim_start_matches(['N', 'n']);
+    literal
+        .strip_prefix('\'')
+        .and_then(|l| l.strip_suffix('\''))
+        .unwrap_or(literal)
+        .replace("''", "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(engine: Engine, scripts: &[&str]) -> VirtualSchema {
+        let mut builder = SchemaBuilder::new(engine);
+        for (i, script) in scripts.iter().enumerate() {
+            builder.apply(script, Path::new(&format!("V{}__test.sql", i + 1)));
+        }
+        builder.finish()
+    }
+
+    fn columns(schema: &VirtualSchema, table: &str) -> Vec<String> {
+        let table = schema
+            .catalog
+            .tables
+            .iter()
+            .find(|t| t.table.name == table)
+            .unwrap();
+        let mut columns: Vec<&Column> = table.columns.iter().collect();
+        columns.sort_by_key(|c| c.ordinal_position);
+        columns
+            .iter()
+            .map(|c| format!("{} {} {}", c.name, c.declared_type(), c.is_nullable))
+            .collect()
+    }
+
+    #[test]
+    fn should_apply_postgres_migrations_in_order() {
+        let schema = build(
+            Engine::Postgres,
+            &[
+                "CREATE TABLE Sales.Customers (id serial PRIMARY KEY, name varchar(100) NOT NULL, note text);
+                 CREATE TABLE sales.orders (id bigint GENERATED ALWAYS AS IDENTITY, customer_id int REFERENCES sales.customers(id), total numeric(10, 2), tags text[]);
+                 CREATE TABLE sales.scratch (x int);",
+                "ALTER TABLE sales.customers ADD COLUMN email varchar(200), DROP COLUMN note;
+                 ALTER TABLE sales.orders ALTER COLUMN total TYPE numeric(12,2), ALTER COLUMN customer_id SET NOT NULL;
+                 COMMENT ON TABLE sales.orders IS 'Orders placed by customers';
+                 COMMENT ON COLUMN sales.customers.email IS 'Where receipts go';
+                 ALTER TABLE sales.orders RENAME COLUMN total TO amount;
+                 ALTER TABLE sales.orders RENAME TO purchases;
+                 CREATE UNIQUE INDEX CONCURRENTLY ux_customers_email ON sales.customers (lower(email));
+                 DROP TABLE IF EXISTS sales.scratch;
+                 CREATE OR REPLACE VIEW sales.v AS SELECT 1;",
+            ],
+        );
+
+        assert_eq!(
+            columns(&schema, "customers"),
+            vec![
+                "id integer NO",
+                "name character varying(100) NO",
+                "email character varying(200) YES"
+            ]
+        );
+        assert_eq!(
+            columns(&schema, "purchases"),
+            vec![
+                "id bigint NO",
+                "customer_id integer NO",
+                "amount numeric(12,2) YES",
+                "tags ARRAY YES"
+            ]
+        );
+        let customers = &schema.catalog.tables[0];
+        assert_eq!(
+            customers.columns[1].column_default.as_deref(),
+            Some("nextval('sales.customers_id_seq'::regclass)")
+        );
+        assert_eq!(
+            customers
+                .indexes
+                .iter()
+                .map(|i| (i.name.as_str(), i.unique, i.primary_key))
+                .collect::<Vec<_>>(),
+            vec![
+                ("customers_pkey", true, true),
+                ("ux_customers_email", true, false)
+            ]
+        );
+        assert_eq!(
+            schema.catalog.tables[1].constraints[0].definition,
+            "FOREIGN KEY (customer_id) REFERENCES sales.customers(id)"
+        );
+        assert_eq!(
+            schema.catalog.tables[1].table.description.as_deref(),
+            Some("Orders placed by customers")
+        );
+        assert_eq!(
+            customers
+                .columns
+                .iter()
+                .find(|c| c.name == "email")
+                .unwrap()
+                .description
+                .as_deref(),
+            Some("Where receipts go")
+        );
+        assert_eq!(schema.catalog.tables.len(), 2);
+        assert_eq!(schema.catalog.objects[0].object.name, "v");
+        assert!(schema.touches("sales", "orders") && schema.touches("sales", "scratch"));
+        assert_eq!(
+            schema.origins["sales.purchases"].path,
+            PathBuf::from("V2__test.sql")
+        );
+    }
+
+    #[test]
+    fn should_follow_tsql_alters_and_sp_rename() {
+        let schema = build(
+            Engine::SqlServer,
+            &[
+                "CREATE TABLE [dbo].[Orders] (Id INT IDENTITY(1,1) NOT NULL, Code NVARCHAR(20), Notes VARCHAR(MAX), CONSTRAINT PK_Orders PRIMARY KEY CLUSTERED (Id))
+                 GO
+                 ALTER TABLE dbo.Orders ADD Qty DECIMAL NULL, CreatedAt DATETIME2 NOT NULL CONSTRAINT DF_Orders_CreatedAt DEFAULT (sysdatetime())
+                 ALTER TABLE dbo.Orders ALTER COLUMN Code NVARCHAR(40) NOT NULL
+                 ALTER TABLE dbo.Orders DROP COLUMN Notes
+                 EXEC sys.sp_addextendedproperty @name = N'MS_Description', @value = N'Sales orders', @level0type = N'SCHEMA', @level0name = N'dbo', @level1type = N'TABLE', @level1name = N'Orders'
+                 EXEC sp_rename 'dbo.Orders.Qty', 'Quantity', 'COLUMN'
+                 EXEC sp_rename N'dbo.Orders', N'SalesOrders'",
+            ],
+        );
+
+        assert_eq!(
+            columns(&schema, "SalesOrders"),
+            vec![
+                "Id int NO",
+                "Code nvarchar(40) NO",
+                "Quantity decimal(18,0) YES",
+                "CreatedAt datetime2 NO"
+            ]
+        );
+        let table = &schema.catalog.tables[0];
+        let created_at = table.columns.iter().find(|c| c.name == "CreatedAt");
+        assert_eq!(table.constraints[0].name, "PK_Orders");
+        assert_eq!(
+            created_at.unwrap().column_default.as_deref(),
+            Some("(sysdatetime())")
+        );
+        assert_eq!(table.table.description.as_deref(), Some("Sales orders"));
+        assert!(schema.touches("dbo", "orders"));
+    }
+
+    #[test]
+    fn should_skip_a_half_written_create_table() {
+        let schema = build(
+            Engine::SqlServer,
+            &["CREATE TABLE dbo.t (id int)\nGO\nCREATE TABLE dbo.x ("],
+        );
+        assert_eq!(columns(&schema, "t"), vec!["id int YES"]);
+        assert_eq!(schema.catalog.tables.len(), 1);
+    }
+}
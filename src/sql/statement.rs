@@ -0,0 +1,822 @@
+use tower_lsp::lsp_types::{Position, Range};
+
+use crate::db::engine::Engine;
+use crate::tokenizer::{lex, Lexeme, LexemeKind};
+
+/// Words that can't be a table alias, so `FROM t WHERE` doesn't read `WHERE`
+/// as the alias of `t`.
+const NOT_ALIASES: &[&str] = &[
+    "APPLY",
+    "AS",
+    "CROSS",
+    "EXCEPT",
+    "FETCH",
+    "FOR",
+    "FROM",
+    "FULL",
+    "GO",
+    "GROUP",
+    "HAVING",
+    "INNER",
+    "INTERSECT",
+    "INTO",
+    "JOIN",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "OFFSET",
+    "ON",
+    "OPTION",
+    "ORDER",
+    "OUTER",
+    "OUTPUT",
+    "PIVOT",
+    "RETURNING",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "UNION",
+    "UNPIVOT",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+];
+
+/// Keywords that start a new statement even when the previous one wasn't
+/// terminated with `;`, as is usual in T-SQL.
+const STATEMENT_STARTS: &[&str] = &[
+    "ALTER",
+    "BEGIN",
+    "BREAK",
+    "CLOSE",
+    "COMMIT",
+    "CONTINUE",
+    "CREATE",
+    "DEALLOCATE",
+    "DECLARE",
+    "DELETE",
+    "DENY",
+    "DROP",
+    "ELSE",
+    "END",
+    "EXEC",
+    "EXECUTE",
+    "FETCH",
+    "GRANT",
+    "IF",
+    "INSERT",
+    "MERGE",
+    "OPEN",
+    "PRINT",
+    "RAISERROR",
+    "RETURN",
+    "REVOKE",
+    "ROLLBACK",
+    "SAVE",
+    "SELECT",
+    "SET",
+    "THROW",
+    "TRUNCATE",
+    "UPDATE",
+    "USE",
+    "WAITFOR",
+    "WHILE",
+    "WITH",
+];
+
+const ROUTINES: &[&str] = &["PROC", "PROCEDURE", "FUNCTION", "TRIGGER"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    /// The statement's tokens without whitespace and comments.
+    pub tokens: Vec<Lexeme>,
+    /// `GO`-separated batch the statement belongs to, always 0 outside T-SQL.
+    pub batch: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualifiedName {
+    /// The dotted parts of the name, e.g. `[dbo]`, `users`.
+    pub parts: Vec<Lexeme>,
+}
+
+impl QualifiedName {
+    pub fn name(&self) -> String {
+        self.parts.last().map(|p| p.unquoted()).unwrap_or_default()
+    }
+
+    /// The schema part, if the name is qualified.
+    pub fn schema(&self) -> Option<String> {
+        let n = self.parts.len();
+        (n >= 2).then(|| self.parts[n - 2].unquoted())
+    }
+
+    /// The database part of a three- or four-part name.
+    pub fn database(&self) -> Option<String> {
+        let n = self.parts.len();
+        (n >= 3).then(|| self.parts[n - 3].unquoted())
+    }
+
+    /// The linked server part of a four-part name.
+    pub fn server(&self) -> Option<String> {
+        let n = self.parts.len();
+        (n >= 4).then(|| self.parts[n - 4].unquoted())
+    }
+
+    pub fn range(&self) -> Range {
+        let start = self
+            .parts
+            .first()
+            .map(|p| p.range().start)
+            .unwrap_or_default();
+        let end = self.parts.last().map(|p| p.range().end).unwrap_or_default();
+        Range::new(start, end)
+    }
+
+    pub fn to_string_unquoted(&self) -> String {
+        self.parts
+            .iter()
+            .map(|p| p.unquoted())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub name: QualifiedName,
+    pub alias: Option<Lexeme>,
+    /// Index of the first token of the name within the statement.
+    pub index: usize,
+    /// A table-valued function call rather than a table or view.
+    pub function: bool,
+    /// The comma before the table when it's listed as `FROM a, b`.
+    pub comma: Option<Lexeme>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cte {
+    pub name: Lexeme,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectDefinition {
+    /// `CREATE`, `ALTER` or `DROP`.
+    pub action: String,
+    /// `TABLE`, `VIEW`, `PROCEDURE`, `FUNCTION`, `INDEX`, ...
+    pub object: String,
+    pub name: QualifiedName,
+}
+
+/// Splits a script into statements, on `;`, on T-SQL `GO` batches and on
+/// keywords that can only start a new statement.
+pub fn parse(sql: &str, engine: &Engine) -> Vec<Statement> {
+    let tokens: Vec<Lexeme> = lex(sql)
+        .into_iter()
+        .filter(|l| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment))
+        .collect();
+    split(tokens, engine, 0, false)
+}
+
+fn split(tokens: Vec<Lexeme>, engine: &Engine, batch: usize, nested: bool) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut current: Vec<Lexeme> = Vec::new();
+    let mut batch = batch;
+    let mut depth = 0usize;
+    let mut case_depth = 0usize;
+    let tsql = *engine == Engine::SqlServer;
+    let flush = |current: &mut Vec<Lexeme>, statements: &mut Vec<Statement>, batch: usize| {
+        if !current.is_empty() {
+            statements.push(Statement {
+                tokens: std::mem::take(current),
+                batch,
+            });
+        }
+    };
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let routine = !nested && tsql && is_routine(&current);
+        if token.is_punct("(") {
+            depth += 1;
+        } else if token.is_punct(")") {
+            depth = depth.saturating_sub(1);
+        } else if token.is_word("CASE") {
+            case_depth += 1;
+        }
+        if tsql && !nested && token.is_word("GO") && depth == 0 {
+            flush(&mut current, &mut statements, batch);
+            batch += 1;
+            continue;
+        }
+        if routine {
+            current.push(token);
+            continue;
+        }
+        if token.is_punct(";") && depth == 0 {
+            flush(&mut current, &mut statements, batch);
+            case_depth = 0;
+            continue;
+        }
+        if token.is_word("END") && case_depth > 0 {
+            case_depth -= 1;
+            current.push(token);
+            continue;
+        }
+        if depth == 0 && case_depth == 0 && starts_statement(&current, &token, tokens.peek(), tsql)
+        {
+            flush(&mut current, &mut statements, batch);
+        }
+        let control = token.is_word("END")
+            || token.is_word("ELSE")
+            || token.is_word("BEGIN") && current.is_empty() && tsql;
+        current.push(token);
+        if control && tsql {
+            flush(&mut current, &mut statements, batch);
+        }
+    }
+    flush(&mut current, &mut statements, batch);
+    statements
+}
+
+fn is_routine(tokens: &[Lexeme]) -> bool {
+    let words: Vec<String> = tokens
+        .iter()
+        .take(4)
+        .filter(|t| t.kind == LexemeKind::Word)
+        .map(|t| t.text.to_uppercase())
+        .collect();
+    matches!(words.first().map(|w| w.as_str()), Some("CREATE" | "ALTER"))
+        && words.iter().skip(1).any(|w| ROUTINES.contains(&w.as_str()))
+}
+
+fn starts_statement(current: &[Lexeme], token: &Lexeme, next: Option<&Lexeme>, tsql: bool) -> bool {
+    let Some(first) = current.first() else {
+        return false;
+    };
+    if token.kind != LexemeKind::Word {
+        return false;
+    }
+    let upper = token.text.to_uppercase();
+    if !STATEMENT_STARTS.contains(&upper.as_str()) {
+        return false;
+    }
+    let prev = current.last().expect("current is not empty");
+    let prev_upper = if prev.kind == LexemeKind::Word {
+        prev.text.to_uppercase()
+    } else {
+        String::new()
+    };
+    let first_upper = first.text.to_uppercase();
+    if prev.is_punct(".") || prev.is_punct(",") {
+        return false;
+    }
+    match upper.as_str() {
+        "SELECT" => {
+            !matches!(
+                first_upper.as_str(),
+                "INSERT" | "CREATE" | "ALTER" | "WITH" | "DECLARE" | "GRANT" | "REVOKE" | "DENY"
+            ) && !matches!(
+                prev_upper.as_str(),
+                "UNION" | "ALL" | "EXCEPT" | "INTERSECT" | "MINUS" | "DISTINCT" | "FOR"
+            )
+        }
+        "INSERT" | "UPDATE" | "DELETE" => {
+            // Trigger events, `FOR UPDATE`, `ON DELETE CASCADE`, MERGE actions
+            // and the body of a CTE all belong to the current statement.
+            let cte_body = first_upper == "WITH"
+                && !has_top_level(current, &["SELECT", "INSERT", "UPDATE", "DELETE"]);
+            !matches!(
+                prev_upper.as_str(),
+                "AFTER" | "BEFORE" | "FOR" | "OF" | "ON" | "DO" | "THEN" | "INSTEAD"
+            ) && !matches!(first_upper.as_str(), "GRANT" | "REVOKE" | "DENY" | "MERGE")
+                && !cte_body
+        }
+        "SET" => {
+            !has_top_level(current, &["UPDATE", "MERGE", "ALTER"])
+                && !matches!(prev_upper.as_str(), "DELETE" | "UPDATE" | "DO")
+        }
+        // A CTE has to follow a `;` in T-SQL, so it never splits on its own.
+        "WITH" => false,
+        "BEGIN" | "END" | "ELSE" | "IF" | "WHILE" | "RETURN" | "PRINT" | "DECLARE" | "THROW"
+        | "RAISERROR" | "WAITFOR" | "BREAK" | "CONTINUE" | "OPEN" | "CLOSE" | "FETCH"
+        | "DEALLOCATE" | "SAVE" | "USE" => {
+            // `OFFSET ... FETCH`, `DROP TABLE IF EXISTS` and `RETURNS` clauses
+            // aren't control flow.
+            let continues = match upper.as_str() {
+                "FETCH" => matches!(first_upper.as_str(), "SELECT" | "WITH"),
+                "IF" => matches!(first_upper.as_str(), "DROP" | "CREATE" | "ALTER"),
+                "RETURN" => first_upper == "CREATE",
+                _ => false,
+            };
+            tsql && !continues
+                && !matches!(prev_upper.as_str(), "BEGIN" | "DROP" | "EXISTS" | "NOT")
+        }
+        "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "MERGE" | "EXEC" | "EXECUTE" | "GRANT"
+        | "REVOKE" | "DENY" | "COMMIT" | "ROLLBACK" => {
+            let continues = match upper.as_str() {
+                "DROP" => first_upper == "ALTER",
+                // `ALTER TABLE t ALTER COLUMN c ...`; Postgres may leave out
+                // `COLUMN`, but its statements end with `;`.
+                "ALTER" => {
+                    first_upper == "ALTER" && (!tsql || next.is_some_and(|n| n.is_word("COLUMN")))
+                }
+                "EXEC" | "EXECUTE" => {
+                    matches!(first_upper.as_str(), "GRANT" | "REVOKE" | "DENY" | "INSERT")
+                }
+                _ => false,
+            };
+            !continues && !matches!(prev_upper.as_str(), "WITH" | "GRANT" | "REVOKE" | "DENY")
+        }
+        _ => false,
+    }
+}
+
+/// Whether any of `words` appears in `tokens` outside of parens.
+fn has_top_level(tokens: &[Lexeme], words: &[&str]) -> bool {
+    let mut depth = 0i32;
+    tokens.iter().any(|t| {
+        if t.is_punct("(") {
+            depth += 1;
+        } else if t.is_punct(")") {
+            depth -= 1;
+        }
+        depth == 0 && words.iter().any(|w| t.is_word(w))
+    })
+}
+
+impl Statement {
+    pub fn range(&self) -> Range {
+        let start = self
+            .tokens
+            .first()
+            .map(|t| t.range().start)
+            .unwrap_or_default();
+        let end = self
+            .tokens
+            .last()
+            .map(|t| t.range().end)
+            .unwrap_or_default();
+        Range::new(start, end)
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        let range = self.range();
+        range.start <= position && position <= range.end
+    }
+
+    /// The statement's source, comments inside it included.
+    pub fn text<'a>(&self, sql: &'a str) -> &'a str {
+        match (self.tokens.first(), self.tokens.last()) {
+            (Some(first), Some(last)) => &sql[first.offset..last.offset + last.text.len()],
+            _ => "",
+        }
+    }
+
+    /// The first keyword of the statement, upper-cased.
+    pub fn keyword(&self) -> String {
+        self.tokens
+            .first()
+            .map(|t| t.text.to_uppercase())
+            .unwrap_or_default()
+    }
+
+    /// The DML keyword the statement executes, looking past a leading CTE.
+    pub fn verb(&self) -> String {
+        let keyword = self.keyword();
+        if keyword != "WITH" {
+            return keyword;
+        }
+        let depths = self.depths();
+        self.tokens
+            .iter()
+            .zip(depths)
+            .find(|(t, depth)| {
+                *depth == 0
+                    && ["SELECT", "INSERT", "UPDATE", "DELETE", "MERGE"]
+                        .iter()
+                        .any(|w| t.is_word(w))
+            })
+            .map(|(t, _)| t.text.to_uppercase())
+            .unwrap_or(keyword)
+    }
+
+    /// Paren depth of every token; a paren counts as inside its own group.
+    pub fn depths(&self) -> Vec<usize> {
+        let mut depth = 0usize;
+        self.tokens
+            .iter()
+            .map(|t| {
+                if t.is_punct("(") {
+                    depth += 1;
+                    depth
+                } else if t.is_punct(")") {
+                    let d = depth;
+                    depth = depth.saturating_sub(1);
+                    d
+                } else {
+                    depth
+                }
+            })
+            .collect()
+    }
+
+    /// `CREATE VIEW dbo.v`, `DROP TABLE t`, ... and the object they act on.
+    pub fn definition(&self) -> Option<ObjectDefinition> {
+        let action = self.keyword();
+        if !matches!(action.as_str(), "CREATE" | "ALTER" | "DROP") {
+            return None;
+        }
+        let mut i = 1;
+        let skip = [
+            "OR",
+            "REPLACE",
+            "ALTER",
+            "TEMP",
+            "TEMPORARY",
+            "UNIQUE",
+            "CLUSTERED",
+            "NONCLUSTERED",
+            "MATERIALIZED",
+            "GLOBAL",
+            "LOCAL",
+            "UNLOGGED",
+            "RECURSIVE",
+        ];
+        while self
+            .tokens
+            .get(i)
+            .is_some_and(|t| skip.iter().any(|s| t.is_word(s)))
+        {
+            i += 1;
+        }
+        let object = self.tokens.get(i)?;
+        if object.kind != LexemeKind::Word {
+            return None;
+        }
+        let mut object = object.text.to_uppercase();
+        if object == "PROC" {
+            object = "PROCEDURE".to_string();
+        }
+        i += 1;
+        // IF [NOT] EXISTS
+        if self.tokens.get(i).is_some_and(|t| t.is_word("IF")) {
+            i += 1;
+            if self.tokens.get(i).is_some_and(|t| t.is_word("NOT")) {
+                i += 1;
+            }
+            i += 1;
+        }
+        let (name, _) = self.qualified_name(i)?;
+        Some(ObjectDefinition {
+            action,
+            object,
+            name,
+        })
+    }
+
+    /// Reads a dotted name starting at token `i`, returning it with the index
+    /// of the token that follows it.
+    pub fn qualified_name(&self, i: usize) -> Option<(QualifiedName, usize)> {
+        let mut parts = Vec::new();
+        let mut i = i;
+        loop {
+            let token = self.tokens.get(i)?;
+            if !token.is_identifier() {
+                break;
+            }
+            parts.push(token.clone());
+            i += 1;
+            if self.tokens.get(i).is_some_and(|t| t.is_punct(".")) {
+                i += 1;
+                // `dbo..t` skips the schema in T-SQL.
+                while self.tokens.get(i).is_some_and(|t| t.is_punct(".")) {
+                    i += 1;
+                }
+                continue;
+            }
+            break;
+        }
+        (!parts.is_empty()).then_some((QualifiedName { parts }, i))
+    }
+
+    pub fn ctes(&self) -> Vec<Cte> {
+        let mut ctes = Vec::new();
+        if self.keyword() != "WITH" {
+            return ctes;
+        }
+        let mut i = 1;
+        if self.tokens.get(i).is_some_and(|t| t.is_word("RECURSIVE")) {
+            i += 1;
+        }
+        while let Some(name) = self.tokens.get(i).filter(|t| t.is_identifier()) {
+            ctes.push(Cte { name: name.clone() });
+            let mut j = i + 1;
+            if self.tokens.get(j).is_some_and(|t| t.is_punct("(")) {
+                j = self.close_paren(j).map_or(self.tokens.len(), |c| c + 1);
+            }
+            if !self.tokens.get(j).is_some_and(|t| t.is_word("AS")) {
+                break;
+            }
+            j += 1;
+            while self
+                .tokens
+                .get(j)
+                .is_some_and(|t| t.is_word("NOT") || t.is_word("MATERIALIZED"))
+            {
+                j += 1;
+            }
+            if !self.tokens.get(j).is_some_and(|t| t.is_punct("(")) {
+                break;
+            }
+            j = self.close_paren(j).map_or(self.tokens.len(), |c| c + 1);
+            if !self.tokens.get(j).is_some_and(|t| t.is_punct(",")) {
+                break;
+            }
+            i = j + 1;
+        }
+        ctes
+    }
+
+    /// Tables, views and table functions the statement reads or writes,
+    /// including the ones in subqueries.
+    pub fn table_refs(&self) -> Vec<TableRef> {
+        let mut refs = Vec::new();
+        let depths = self.depths();
+        let mut from_depths: Vec<usize> = Vec::new();
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let token = &self.tokens[i];
+            let depth = depths[i];
+            let prev = i.checked_sub(1).map(|p| &self.tokens[p]);
+            let starts_ref = if token.kind == LexemeKind::Word {
+                let upper = token.text.to_uppercase();
+                match upper.as_str() {
+                    "FROM" => {
+                        from_depths.retain(|d| *d < depth);
+                        from_depths.push(depth);
+                        true
+                    }
+                    "JOIN" | "APPLY" => true,
+                    "UPDATE" => !prev
+                        .is_some_and(|p| p.is_word("FOR") || p.is_word("DO") || p.is_word("ON")),
+                    "INTO" => prev.is_some_and(|p| p.is_word("INSERT") || p.is_word("MERGE")),
+                    "USING" => self.keyword() == "MERGE" && depth == 0,
+                    "TABLE" => prev.is_some_and(|p| p.is_word("TRUNCATE")),
+                    "WHERE" | "GROUP" | "ORDER" | "HAVING" | "UNION" | "SET" | "LIMIT"
+                    | "WINDOW" | "EXCEPT" | "INTERSECT" | "RETURNING" | "OPTION" => {
+                        from_depths.retain(|d| *d < depth);
+                        false
+                    }
+                    _ => false,
+                }
+            } else {
+                if token.is_punct(")") {
+                    from_depths.retain(|d| *d < depth);
+                }
+                token.is_punct(",") && from_depths.last() == Some(&depth)
+            };
+            if !starts_ref {
+                i += 1;
+                continue;
+            }
+            let mut j = i + 1;
+            // `DELETE FROM`, `UPDATE TOP (10)`, `FROM ONLY t`
+            while self
+                .tokens
+                .get(j)
+                .is_some_and(|t| t.is_word("ONLY") || t.is_word("LATERAL"))
+            {
+                j += 1;
+            }
+            if self.tokens.get(j).is_some_and(|t| t.is_word("TOP")) {
+                j += 1;
+                if self.tokens.get(j).is_some_and(|t| t.is_punct("(")) {
+                    j = self.close_paren(j).map_or(self.tokens.len(), |c| c + 1);
+                } else {
+                    j += 1;
+                }
+            }
+            if self.tokens.get(j).is_some_and(|t| t.is_punct("(")) {
+                // A derived table: its own tables are picked up as the loop
+                // walks into it.
+                i = j;
+                continue;
+            }
+            let Some((name, mut k)) = self.qualified_name(j) else {
+                i = j;
+                continue;
+            };
+            if name.parts.len() == 1
+                && name.parts[0].kind == LexemeKind::Word
+                && NOT_ALIASES.iter().any(|w| name.parts[0].is_word(w))
+            {
+                i = j;
+                continue;
+            }
+            let mut function = false;
+            if self.tokens.get(k).is_some_and(|t| t.is_punct("(")) {
+                function = !self.tokens[i].is_word("INTO") && !self.tokens[i].is_word("TABLE");
+                if function {
+                    k = self.close_paren(k).map_or(self.tokens.len(), |c| c + 1);
+                }
+            }
+            let alias = self.alias_at(k);
+            let comma = self.tokens[i].is_punct(",").then(|| self.tokens[i].clone());
+            refs.push(TableRef {
+                name,
+                alias,
+                index: j,
+                function,
+                comma,
+            });
+            i = k;
+        }
+        refs
+    }
+
+    fn alias_at(&self, k: usize) -> Option<Lexeme> {
+        let mut k = k;
+        if self.tokens.get(k).is_some_and(|t| t.is_word("AS")) {
+            k += 1;
+        }
+        let token = self.tokens.get(k)?;
+        if !token.is_identifier() {
+            return None;
+        }
+        if token.kind == LexemeKind::Word && NOT_ALIASES.iter().any(|w| token.is_word(w)) {
+            return None;
+        }
+        Some(token.clone())
+    }
+
+    /// Column names declared by a `CREATE TABLE`, skipping table constraints.
+    pub fn columns(&self) -> Vec<Lexeme> {
+        match self.definition() {
+            Some(d) if d.action == "CREATE" && d.object == "TABLE" => {}
+            _ => return vec![],
+        }
+        let Some(open) = self.tokens.iter().position(|t| t.is_punct("(")) else {
+            return vec![];
+        };
+        let close = self.close_paren(open).unwrap_or(self.tokens.len());
+        let depths = self.depths();
+        let constraints = [
+            "CONSTRAINT",
+            "PRIMARY",
+            "FOREIGN",
+            "UNIQUE",
+            "CHECK",
+            "INDEX",
+            "KEY",
+            "EXCLUDE",
+            "PERIOD",
+        ];
+        (open..close)
+            .filter(|i| {
+                depths[*i] == 1 && (self.tokens[*i].is_punct("(") || self.tokens[*i].is_punct(","))
+            })
+            .filter_map(|i| self.tokens.get(i + 1))
+            .filter(|t| t.is_identifier() && !constraints.iter().any(|c| t.is_word(c)))
+            .cloned()
+            .collect()
+    }
+
+    /// Index of the paren that closes the one at `open`, `None` when the
+    /// statement ends before it does.
+    pub fn close_paren(&self, open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            if token.is_punct("(") {
+                depth += 1;
+            } else if token.is_punct(")") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// For T-SQL procedures, functions and triggers, the statements of the
+    /// body after `AS`.
+    pub fn body(&self, engine: &Engine) -> Vec<Statement> {
+        if *engine != Engine::SqlServer || !is_routine(&self.tokens) {
+            return vec![];
+        }
+        let depths = self.depths();
+        let starts = [
+            "BEGIN", "SET", "SELECT", "RETURN", "INSERT", "UPDATE", "DELETE", "DECLARE", "IF",
+            "WITH", "EXEC", "EXECUTE", "MERGE", "EXTERNAL", "WHILE",
+        ];
+        let Some(at) = self.tokens.iter().enumerate().position(|(i, t)| {
+            depths[i] == 0
+                && t.is_word("AS")
+                && self
+                    .tokens
+                    .get(i + 1)
+                    .is_some_and(|n| starts.iter().any(|s| n.is_word(s)))
+        }) else {
+            return vec![];
+        };
+        split(self.tokens[at + 1..].to_vec(), engine, self.batch, true)
+    }
+
+    /// The statement itself, or the statements in its body for routines.
+    pub fn leaves(&self, engine: &Engine) -> Vec<Statement> {
+        let body = self.body(engine);
+        if body.is_empty() {
+            vec![self.clone()]
+        } else {
+            body
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_list_the_columns_of_a_create_table() {
+        let sql = "CREATE TABLE dbo.t (id int NOT NULL, [name] nvarchar(50), CONSTRAINT pk PRIMARY KEY (id))";
+        let statements = parse(sql, &Engine::SqlServer);
+        let columns: Vec<_> = statements[0]
+            .columns()
+            .iter()
+            .map(|c| c.unquoted())
+            .collect();
+        assert_eq!(columns, vec!["id", "name"]);
+    }
+
+    fn names(refs: &[TableRef]) -> Vec<String> {
+        refs.iter().map(|r| r.name.to_string_unquoted()).collect()
+    }
+
+    #[test]
+    fn should_split_statements_on_semicolons_go_and_statement_keywords() {
+        let sql = "SELECT 1; SELECT 2\nUPDATE t SET a = 1 WHERE b = 2\nGO\nINSERT INTO t (a) SELECT a FROM u";
+        let statements = parse(sql, &Engine::SqlServer);
+        let keywords: Vec<_> = statements.iter().map(|s| (s.keyword(), s.batch)).collect();
+        assert_eq!(
+            keywords,
+            vec![
+                ("SELECT".to_string(), 0),
+                ("SELECT".to_string(), 0),
+                ("UPDATE".to_string(), 0),
+                ("INSERT".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_keep_tsql_routines_whole_and_split_their_body() {
+        let sql = "CREATE PROCEDURE dbo.p @a int AS\nBEGIN\n  SET NOCOUNT ON;\n  DELETE FROM t WHERE a = @a;\n  SELECT * FROM t\nEND\nGO\nSELECT 1";
+        let statements = parse(sql, &Engine::SqlServer);
+        assert_eq!(statements.len(), 2);
+        let definition = statements[0].definition().unwrap();
+        assert_eq!(definition.object, "PROCEDURE");
+        assert_eq!(definition.name.to_string_unquoted(), "dbo.p");
+        let body: Vec<_> = statements[0]
+            .body(&Engine::SqlServer)
+            .iter()
+            .map(|s| s.keyword())
+            .collect();
+        assert_eq!(body, vec!["BEGIN", "SET", "DELETE", "SELECT", "END"]);
+    }
+
+    #[test]
+    fn should_find_table_references_and_aliases() {
+        let sql = "SELECT * FROM dbo.users u WITH (NOLOCK) JOIN [sales].[orders] AS o ON o.user_id = u.id, roles WHERE u.id IN (SELECT user_id FROM audit.logins) ORDER BY 1";
+        let statement = &parse(sql, &Engine::SqlServer)[0];
+        let refs = statement.table_refs();
+        assert_eq!(
+            names(&refs),
+            vec!["dbo.users", "sales.orders", "roles", "audit.logins"]
+        );
+        assert_eq!(refs[0].alias.as_ref().unwrap().text, "u");
+        assert_eq!(refs[1].alias.as_ref().unwrap().text, "o");
+        assert_eq!(refs[1].name.schema(), Some("sales".to_string()));
+        assert!(refs[2].alias.is_none());
+    }
+
+    #[test]
+    fn should_find_dml_targets_and_ctes() {
+        let sql = "WITH recent AS (SELECT id FROM orders), totals (id, n) AS (SELECT id, 1 FROM recent) UPDATE public.t SET a = 1 FROM totals WHERE t.id = totals.id";
+        let statement = &parse(sql, &Engine::Postgres)[0];
+        let ctes: Vec<_> = statement
+            .ctes()
+            .iter()
+            .map(|c| c.name.text.clone())
+            .collect();
+        assert_eq!(ctes, vec!["recent", "totals"]);
+        assert_eq!(statement.verb(), "UPDATE");
+        assert_eq!(
+            names(&statement.table_refs()),
+            vec!["orders", "recent", "public.t", "totals"]
+        );
+    }
+}
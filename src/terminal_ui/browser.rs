@@ -0,0 +1,647 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+
+use anyhow::Result;
+use tui::widgets::ListState;
+
+use super::block_on;
+use super::export::ExportSource;
+use super::models::Connection;
+use super::repository::FsTenguRepository;
+use crate::db::column::Column;
+use crate::db::engine::Engine;
+use crate::db::object::{DbObject, ObjectKind};
+use crate::db::query::render;
+use crate::db::query::ResultFormat;
+use crate::db::service::{Service, TenguService};
+use crate::db::table::Table;
+use crate::fuzzy;
+
+/// Rows shown when previewing a table or view.
+const PREVIEW_ROWS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Database,
+    Schema,
+    Table,
+    View,
+    Procedure,
+    Function,
+    Columns,
+    Indexes,
+    Constraints,
+    Column,
+    Index,
+    Constraint,
+}
+
+impl NodeKind {
+    fn expandable(&self) -> bool {
+        matches!(
+            self,
+            NodeKind::Database
+                | NodeKind::Schema
+                | NodeKind::Table
+                | NodeKind::Columns
+                | NodeKind::Indexes
+                | NodeKind::Constraints
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub label: String,
+    pub kind: NodeKind,
+    /// Schema and name of the object the node is or belongs to.
+    pub schema: String,
+    pub name: String,
+    /// `None` until the node is expanded for the first time.
+    pub children: Option<Vec<Node>>,
+    pub expanded: bool,
+}
+
+impl Node {
+    fn new(label: String, kind: NodeKind, schema: &str, name: &str) -> Node {
+        Node {
+            label,
+            kind,
+            schema: schema.to_string(),
+            name: name.to_string(),
+            children: (!kind.expandable()).then(Vec::new),
+            expanded: false,
+        }
+    }
+
+    fn table(&self) -> Table {
+        Table {
+            name: self.name.clone(),
+            schema: self.schema.clone(),
+            description: None,
+        }
+    }
+
+    /// Whether the node or one of its loaded descendants matches `filter`.
+    fn matches(&self, filter: &str) -> bool {
+        fuzzy::score(filter, &self.label).is_some()
+            || self
+                .children
+                .iter()
+                .flatten()
+                .any(|child| child.matches(filter))
+    }
+}
+
+/// A tree of the active connection's catalog, loaded a level at a time as
+/// nodes are expanded, under the databases of its server.
+pub struct Browser {
+    pub connection: String,
+    /// The database the connection is on, the only one that expands.
+    pub database: String,
+    engine: Engine,
+    service: TenguService,
+    pub root: Vec<Node>,
+    pub list_state: ListState,
+    pub filter: String,
+    /// What the last action produced: rows, a template, DDL or an error.
+    pub detail: String,
+    pub detail_scroll: u16,
+    /// The rows of the preview on show, if that's what the detail is.
+    pub exportable: Option<ExportSource>,
+}
+
+impl Browser {
+    pub fn new(connection: Connection) -> Browser {
+        let engine = connection.engine.clone();
+        let service = TenguService::new(engine.clone(), FsTenguRepository::new());
+        let mut databases = block_on(service.list_databases()).unwrap_or_default();
+        if !databases.contains(&connection.database) {
+            databases.push(connection.database.clone());
+            databases.sort();
+        }
+        let current = databases
+            .iter()
+            .position(|d| *d == connection.database)
+            .unwrap_or(0);
+        let root = databases
+            .iter()
+            .map(|database| {
+                let label = match database == &connection.database {
+                    true => format!("{} ({})", database, connection.name),
+                    false => database.clone(),
+                };
+                Node::new(label, NodeKind::Database, "", database)
+            })
+            .collect();
+        let mut browser = Browser {
+            connection: connection.name,
+            database: connection.database,
+            engine,
+            service,
+            root,
+            list_state: ListState::default(),
+            filter: String::new(),
+            detail: String::new(),
+            detail_scroll: 0,
+            exportable: None,
+        };
+        browser.list_state.select(Some(current));
+        browser.toggle();
+        browser
+    }
+
+    /// The visible nodes in order, with their depth and path from the root.
+    pub fn rows(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut rows = Vec::new();
+        collect_rows(&self.root, &self.filter, 0, &mut Vec::new(), &mut rows);
+        rows
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&Node> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.root.get(*first)?;
+        for i in rest {
+            node = node.children.as_ref()?.get(*i)?;
+        }
+        Some(node)
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.root.get_mut(*first)?;
+        for i in rest {
+            node = node.children.as_mut()?.get_mut(*i)?;
+        }
+        Some(node)
+    }
+
+    fn selected_path(&self) -> Option<Vec<usize>> {
+        let rows = self.rows();
+        let selected = self.list_state.selected()?;
+        rows.into_iter().nth(selected).map(|(_, path)| path)
+    }
+
+    pub fn selected(&self) -> Option<&Node> {
+        self.node(&self.selected_path()?)
+    }
+
+    pub fn move_up(&mut self) {
+        let selected = self
+            .list_state
+            .selected()
+            .map_or(0, |v| v.saturating_sub(1));
+        self.list_state.select(Some(selected));
+    }
+
+    pub fn move_down(&mut self) {
+        let last = self.rows().len().saturating_sub(1);
+        let selected = self.list_state.selected().map_or(0, |v| (v + 1).min(last));
+        self.list_state.select(Some(selected));
+    }
+
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        let last = self.rows().len().saturating_sub(1);
+        let selected = self.list_state.selected().unwrap_or(0).min(last);
+        self.list_state.select(Some(selected));
+    }
+
+    /// Expands or collapses the selected node, loading its children the
+    /// first time.
+    pub fn toggle(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        let Some(node) = self.node(&path).cloned() else {
+            return;
+        };
+        if !node.kind.expandable() {
+            return;
+        }
+        if node.kind == NodeKind::Database && node.name != self.database {
+            self.show(format!("Press u to switch to {}", node.name));
+            return;
+        }
+        if node.children.is_none() {
+            match block_on(self.load(&node)) {
+                Ok(children) => {
+                    if let Some(node) = self.node_mut(&path) {
+                        node.children = Some(children);
+                    }
+                }
+                Err(e) => {
+                    self.show(format!("Error: {}", e));
+                    return;
+                }
+            }
+        }
+        if let Some(node) = self.node_mut(&path) {
+            node.expanded = !node.expanded;
+        }
+    }
+
+    /// Collapses the selected node, or selects its parent if it's a leaf or
+    /// already collapsed.
+    pub fn collapse(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        if let Some(node) = self.node_mut(&path) {
+            if node.expanded {
+                node.expanded = false;
+                return;
+            }
+        }
+        let parent = &path[..path.len() - 1];
+        if let Some(i) = self.rows().iter().position(|(_, p)| p == parent) {
+            self.list_state.select(Some(i));
+        }
+    }
+
+    async fn load(&self, node: &Node) -> Result<Vec<Node>> {
+        let children = match node.kind {
+            NodeKind::Database => {
+                let mut schemas: BTreeMap<String, Vec<Node>> = BTreeMap::new();
+                let mut tables = self.service.get_tables().await?;
+                tables.sort_by(|a, b| a.name.cmp(&b.name));
+                let mut objects = self.service.get_objects().await?;
+                objects.sort_by(|a, b| a.name.cmp(&b.name));
+                for table in &tables {
+                    // Postgres lists views among the tables too.
+                    if objects
+                        .iter()
+                        .any(|o| o.name == table.name && o.schema == table.schema)
+                    {
+                        continue;
+                    }
+                    schemas
+                        .entry(table.schema.clone())
+                        .or_default()
+                        .push(Node::new(
+                            described(table.name.clone(), &table.description),
+                            NodeKind::Table,
+                            &table.schema,
+                            &table.name,
+                        ));
+                }
+                for object in &objects {
+                    let kind = match object.kind {
+                        ObjectKind::View => NodeKind::View,
+                        ObjectKind::Procedure => NodeKind::Procedure,
+                        ObjectKind::Function => NodeKind::Function,
+                    };
+                    schemas
+                        .entry(object.schema.clone())
+                        .or_default()
+                        .push(Node::new(
+                            format!(
+                                "{} ({})",
+                                object.name,
+                                object.kind.to_string().to_lowercase()
+                            ),
+                            kind,
+                            &object.schema,
+                            &object.name,
+                        ));
+                }
+                schemas
+                    .into_iter()
+                    .map(|(schema, objects)| Node {
+                        children: Some(objects),
+                        ..Node::new(schema.clone(), NodeKind::Schema, &schema, "")
+                    })
+                    .collect()
+            }
+            NodeKind::Table => [
+                ("columns", NodeKind::Columns),
+                ("indexes", NodeKind::Indexes),
+                ("constraints", NodeKind::Constraints),
+            ]
+            .into_iter()
+            .map(|(label, kind)| Node::new(label.to_string(), kind, &node.schema, &node.name))
+            .collect(),
+            NodeKind::Columns => self
+                .columns(&node.table())
+                .await?
+                .into_iter()
+                .map(|c| {
+                    Node::new(
+                        described(
+                            format!(
+                                "{} {}{}",
+                                c.name,
+                                c.data_type,
+                                if c.is_nullable == "YES" {
+                                    ""
+                                } else {
+                                    " not null"
+                                }
+                            ),
+                            &c.description,
+                        ),
+                        NodeKind::Column,
+                        &node.schema,
+                        &node.name,
+                    )
+                })
+                .collect(),
+            NodeKind::Indexes => self
+                .service
+                .get_indexes(&node.table())
+                .await?
+                .into_iter()
+                .map(|i| {
+                    let mut label = format!("{} ({})", i.name, i.columns.join(", "));
+                    if i.primary_key {
+                        label.push_str(" primary key");
+                    } else if i.unique {
+                        label.push_str(" unique");
+                    }
+                    if let Some(kind) = &i.kind {
+                        label.push_str(&format!(" {}", kind.to_lowercase()));
+                    }
+                    if !i.included.is_empty() {
+                        label.push_str(&format!(" include ({})", i.included.join(", ")));
+                    }
+                    if let Some(filter) = &i.filter {
+                        label.push_str(&format!(" where {}", filter));
+                    }
+                    Node::new(label, NodeKind::Index, &node.schema, &node.name)
+                })
+                .collect(),
+            NodeKind::Constraints => self
+                .service
+                .get_constraints(&node.table())
+                .await?
+                .into_iter()
+                .map(|c| {
+                    Node::new(
+                        format!("{}: {}", c.name, c.definition),
+                        NodeKind::Constraint,
+                        &node.schema,
+                        &node.name,
+                    )
+                })
+                .collect(),
+            _ => vec![],
+        };
+        Ok(children)
+    }
+
+    async fn columns(&self, table: &Table) -> Result<Vec<Column>> {
+        let mut columns: Vec<Column> = self
+            .service
+            .get_table_columns(HashSet::from([table.clone()]))
+            .await?
+            .into_iter()
+            .collect();
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(columns)
+    }
+
+    fn show(&mut self, detail: String) {
+        self.detail = detail;
+        self.detail_scroll = 0;
+        self.exportable = None;
+    }
+
+    /// Shows the first rows of the selected table or view.
+    pub fn preview(&mut self) {
+        let Some(node) = self.selected().cloned() else {
+            return;
+        };
+        if matches!(
+            node.kind,
+            NodeKind::Database | NodeKind::Schema | NodeKind::Procedure | NodeKind::Function
+        ) {
+            return;
+        }
+        let sql = preview_sql(&self.engine, &node.schema, &node.name, PREVIEW_ROWS);
+        match block_on(self.service.execute(&sql)) {
+            Ok(results) => {
+                self.show(format!(
+                    "{}\n\n{}",
+                    sql,
+                    render(&results, ResultFormat::Markdown)
+                ));
+                self.exportable = Some(ExportSource {
+                    results,
+                    engine: self.engine.clone(),
+                    table: format!("{}.{}", node.schema, node.name),
+                });
+            }
+            Err(e) => self.show(format!("Error: {}", e)),
+        }
+    }
+
+    /// Copies the selected node's name, schema-qualified for objects.
+    pub fn copy_name(&mut self) {
+        let Some(node) = self.selected().cloned() else {
+            return;
+        };
+        let name = match node.kind {
+            NodeKind::Schema => self.engine.quote(&node.schema),
+            NodeKind::Column => {
+                let column = node.label.split(' ').next().unwrap_or_default();
+                self.engine.quote(column)
+            }
+            NodeKind::Database => self.engine.quote(&node.name),
+            _ => self.engine.qualified(&node.schema, &node.name),
+        };
+        copy_to_clipboard(&name);
+        self.show(format!("Copied {}", name));
+    }
+
+    /// Shows a SELECT or INSERT template for the selected table or view and
+    /// copies it.
+    pub fn template(&mut self, insert: bool) {
+        let Some(node) = self.selected().cloned() else {
+            return;
+        };
+        if matches!(
+            node.kind,
+            NodeKind::Database | NodeKind::Schema | NodeKind::Procedure | NodeKind::Function
+        ) {
+            return;
+        }
+        let table = node.table();
+        let template = match block_on(self.columns(&table)) {
+            Ok(columns) if insert => insert_template(&self.engine, &table, &columns),
+            Ok(columns) => select_template(&self.engine, &table, &columns),
+            Err(e) => {
+                self.show(format!("Error: {}", e));
+                return;
+            }
+        };
+        copy_to_clipboard(&template);
+        self.show(template);
+    }
+
+    /// Shows the DDL of the selected table, view or routine.
+    pub fn ddl(&mut self) {
+        let Some(node) = self.selected().cloned() else {
+            return;
+        };
+        let object = |kind| DbObject {
+            name: node.name.clone(),
+            schema: node.schema.clone(),
+            kind,
+        };
+        let ddl = match node.kind {
+            NodeKind::View => block_on(self.service.get_object_ddl(&object(ObjectKind::View))),
+            NodeKind::Procedure => {
+                block_on(self.service.get_object_ddl(&object(ObjectKind::Procedure)))
+            }
+            NodeKind::Function => {
+                block_on(self.service.get_object_ddl(&object(ObjectKind::Function)))
+            }
+            NodeKind::Database | NodeKind::Schema => return,
+            _ => block_on(self.service.get_table_ddl(&node.table())),
+        };
+        self.show(ddl.unwrap_or_else(|e| format!("Error: {}", e)));
+    }
+}
+
+/// `label` followed by the first line of the description, if there's one.
+fn described(label: String, description: &Option<String>) -> String {
+    match description.as_deref().and_then(|d| d.lines().next()) {
+        Some(line) if !line.trim().is_empty() => format!("{} -- {}", label, line.trim()),
+        _ => label,
+    }
+}
+
+fn collect_rows(
+    nodes: &[Node],
+    filter: &str,
+    depth: usize,
+    path: &mut Vec<usize>,
+    rows: &mut Vec<(usize, Vec<usize>)>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        if !filter.is_empty() && !node.matches(filter) {
+            continue;
+        }
+        path.push(i);
+        rows.push((depth, path.clone()));
+        // While filtering, matches inside loaded nodes show up even if
+        // they're collapsed.
+        if node.expanded || !filter.is_empty() {
+            if let Some(children) = &node.children {
+                collect_rows(children, filter, depth + 1, path, rows);
+            }
+        }
+        path.pop();
+    }
+}
+
+pub fn preview_sql(engine: &Engine, schema: &str, name: &str, rows: usize) -> String {
+    let name = engine.qualified(schema, name);
+    match engine {
+        Engine::SqlServer => format!("SELECT TOP {} * FROM {};", rows, name),
+        _ => format!("SELECT * FROM {} LIMIT {};", name, rows),
+    }
+}
+
+pub fn select_template(engine: &Engine, table: &Table, columns: &[Column]) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|c| format!("    {}", engine.quote(&c.name)))
+        .collect();
+    format!(
+        "SELECT\n{}\nFROM {};\n",
+        columns.join(",\n"),
+        engine.qualified(&table.schema, &table.name)
+    )
+}
+
+pub fn insert_template(engine: &Engine, table: &Table, columns: &[Column]) -> String {
+    let names: Vec<String> = columns
+        .iter()
+        .map(|c| format!("    {}", engine.quote(&c.name)))
+        .collect();
+    let values: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let comma = if i + 1 < columns.len() { "," } else { "" };
+            format!("    NULL{} -- {} {}", comma, c.name, c.data_type)
+        })
+        .collect();
+    format!(
+        "INSERT INTO {} (\n{}\n)\nVALUES (\n{}\n);\n",
+        engine.qualified(&table.schema, &table.name),
+        names.join(",\n"),
+        values.join("\n")
+    )
+}
+
+/// Sets the system clipboard through the terminal with an OSC 52 sequence,
+/// which also works over SSH.
+fn copy_to_clipboard(text: &str) {
+    let mut stdout = std::io::stdout();
+    let _ = write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    let _ = stdout.flush();
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str) -> Column {
+        Column {
+            name: name.to_string(),
+            table: "order".to_string(),
+            schema: "sales".to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: "YES".to_string(),
+            ..Column::default()
+        }
+    }
+
+    #[test]
+    fn should_generate_templates() {
+        let table = Table {
+            name: "order".to_string(),
+            schema: "sales".to_string(),
+            description: None,
+        };
+        let columns = vec![column("id", "int"), column("Code", "text")];
+        assert_eq!(
+            select_template(&Engine::Postgres, &table, &columns),
+            "SELECT\n    id,\n    \"Code\"\nFROM sales.\"order\";\n"
+        );
+        assert_eq!(
+            insert_template(&Engine::SqlServer, &table, &columns),
+            "INSERT INTO sales.[order] (\n    id,\n    Code\n)\nVALUES (\n    NULL, -- id int\n    NULL -- Code text\n);\n"
+        );
+        assert_eq!(
+            preview_sql(&Engine::SqlServer, "sales", "order", 100),
+            "SELECT TOP 100 * FROM sales.[order];"
+        );
+    }
+
+    #[test]
+    fn should_encode_base64() {
+        assert_eq!(base64(b"dbo.orders"), "ZGJvLm9yZGVycw==");
+        assert_eq!(base64(b"abc"), "YWJj");
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use super::models::InputMode;
+use crate::db::engine::Engine;
+use crate::db::export::{export, exportable, numbered_path, ExportFormat};
+use crate::db::query::QueryResult;
+
+/// Rows a panel last fetched, ready to be written to a file.
+#[derive(Debug, Clone)]
+pub struct ExportSource {
+    pub results: Vec<QueryResult>,
+    pub engine: Engine,
+    /// Table named in `INSERT` scripts.
+    pub table: String,
+}
+
+/// Asks where and how to write a panel's rows.
+pub struct ExportPrompt {
+    source: ExportSource,
+    pub format: ExportFormat,
+    pub path: String,
+    pub message: String,
+    /// The panel the rows came from, to go back to.
+    pub back: InputMode,
+}
+
+impl ExportPrompt {
+    pub fn new(source: ExportSource, back: InputMode) -> ExportPrompt {
+        let format = ExportFormat::Csv;
+        let path = format!(
+            "results-{}.{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            format.extension()
+        );
+        ExportPrompt {
+            source,
+            format,
+            path,
+            message: String::new(),
+            back,
+        }
+    }
+
+    /// Switches to the next format, changing the file's extension to match.
+    pub fn next_format(&mut self) {
+        let names = ExportFormat::NAMES;
+        let current = names.iter().position(|n| *n == self.format.name());
+        let next = names[current.map_or(0, |i| (i + 1) % names.len())];
+        self.format = ExportFormat::parse(next).unwrap_or(ExportFormat::Csv);
+        let path = PathBuf::from(&self.path).with_extension(self.format.extension());
+        self.path = path.to_string_lossy().to_string();
+    }
+
+    pub fn write(&mut self) {
+        self.message = match self.write_files() {
+            Ok(written) => written.join("\n"),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    fn write_files(&self) -> anyhow::Result<Vec<String>> {
+        let path = PathBuf::from(self.path.trim());
+        let mut written = Vec::new();
+        for (i, result) in exportable(&self.source.results)?.into_iter().enumerate() {
+            let path = numbered_path(&path, i);
+            let mut out = BufWriter::new(File::create(&path)?);
+            export(
+                result,
+                self.format,
+                &self.source.engine,
+                &self.source.table,
+                &mut out,
+            )?;
+            out.flush()?;
+            written.push(format!(
+                "{} rows written to {}",
+                result.rows.len(),
+                path.display()
+            ));
+        }
+        Ok(written)
+    }
+}
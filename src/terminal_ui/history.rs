@@ -0,0 +1,118 @@
+use tui::widgets::ListState;
+
+use super::block_on;
+use super::export::ExportSource;
+use super::repository::{FsTenguRepository, TenguRepository};
+use crate::db::query::{render, ResultFormat};
+use crate::db::service::{Service, TenguService};
+use crate::history::{search, History, HistoryEntry, Status};
+
+/// The query history, searched as you type, with the selected statement and
+/// the results of running it again.
+pub struct HistoryPanel {
+    entries: Vec<HistoryEntry>,
+    pub matches: Vec<HistoryEntry>,
+    pub search: String,
+    pub list_state: ListState,
+    /// Results of the last run, shown instead of the selected entry's details.
+    pub output: Option<String>,
+    pub output_scroll: u16,
+    pub exportable: Option<ExportSource>,
+}
+
+impl HistoryPanel {
+    pub fn load() -> HistoryPanel {
+        let entries = History::open().load();
+        let mut panel = HistoryPanel {
+            matches: entries.clone(),
+            entries,
+            search: String::new(),
+            list_state: ListState::default(),
+            output: None,
+            output_scroll: 0,
+            exportable: None,
+        };
+        panel.select(0);
+        panel
+    }
+
+    fn select(&mut self, index: usize) {
+        let selected = (!self.matches.is_empty()).then(|| index.min(self.matches.len() - 1));
+        self.list_state.select(selected);
+        self.output = None;
+        self.output_scroll = 0;
+        self.exportable = None;
+    }
+
+    pub fn selected(&self) -> Option<&HistoryEntry> {
+        self.list_state.selected().and_then(|i| self.matches.get(i))
+    }
+
+    pub fn move_up(&mut self) {
+        let selected = self.list_state.selected().unwrap_or(0);
+        self.select(selected.saturating_sub(1));
+    }
+
+    pub fn move_down(&mut self) {
+        let selected = self.list_state.selected().map_or(0, |v| v + 1);
+        self.select(selected);
+    }
+
+    pub fn set_search(&mut self, text: String) {
+        self.search = text;
+        self.matches = search(&self.search, self.entries.clone());
+        self.select(0);
+    }
+
+    /// The selected statement with when, where and how it ran.
+    pub fn detail(&self) -> String {
+        if let Some(output) = &self.output {
+            return output.clone();
+        }
+        let Some(entry) = self.selected() else {
+            return String::new();
+        };
+        let outcome = match (entry.status, entry.rows, &entry.error) {
+            (Status::Ok, Some(rows), _) => format!("{} rows", rows),
+            (Status::Ok, None, _) => "ok".to_string(),
+            (Status::Error, _, error) => format!("error: {}", error.as_deref().unwrap_or("")),
+        };
+        format!(
+            "{} on {} in {} ms, {}\n\n{}",
+            entry.time(),
+            entry.connection,
+            entry.duration_ms,
+            outcome,
+            entry.sql
+        )
+    }
+
+    /// Runs the selected statement again on the active connection.
+    pub fn run(&mut self) {
+        let Some(sql) = self.selected().map(|e| e.sql.clone()) else {
+            return;
+        };
+        let repo = FsTenguRepository::new();
+        let Some(connection) = repo.get_active_connection() else {
+            self.output = Some("No active connection".to_string());
+            return;
+        };
+        let engine = connection.engine.clone();
+        let service = TenguService::new(connection.engine, repo);
+        let (output, results) = match block_on(service.execute(&sql)) {
+            Ok(results) => (render(&results, ResultFormat::Markdown), Some(results)),
+            Err(e) => (format!("Error: {}", e), None),
+        };
+        // The run is now the most recent entry; keep it selected.
+        self.entries = History::open().load();
+        self.matches = search(&self.search, self.entries.clone());
+        let index = self.matches.iter().position(|e| e.sql == sql).unwrap_or(0);
+        self.select(index);
+        self.output = Some(output);
+        self.exportable = results.map(|results| ExportSource {
+            results,
+            engine,
+            table: "results".to_string(),
+        });
+    }
+}
@@ -1,20 +1,57 @@
 use self::models::{InputMode, Tengu};
 use self::repository::FsTenguRepository;
 use self::ui::ui;
-use anyhow::Result;
+use crate::db::cancel::cancelled;
+use anyhow::{anyhow, Result};
 use crossterm::event::Event::Key;
 use crossterm::{
-    event::{self, KeyCode},
+    event::{self, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use std::time::Duration;
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
+pub mod browser;
+pub mod export;
+pub mod history;
 pub mod models;
 pub mod repository;
+pub mod snippets;
 pub mod ui;
 
+/// Runs a service call from the TUI, which lives inside the tokio runtime
+/// without being async itself. Ctrl-C gives up on it, and the service then
+/// cancels on the server what it was running, saying so if it couldn't.
+pub(crate) fn block_on<T>(future: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    let interruptible = async {
+        tokio::select! {
+            result = future => result,
+            _ = ctrl_c() => match cancelled().await {
+                Ok(()) => Err(anyhow!("Cancelled")),
+                Err(e) => Err(anyhow!("Cancelled here, but the server kept running it: {:#}", e)),
+            },
+        }
+    };
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(interruptible))
+}
+
+/// Resolves once Ctrl-C is pressed; other keys pressed meanwhile are dropped.
+async fn ctrl_c() {
+    loop {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Key(key)) = event::read() {
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    return;
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[allow(clippy::to_string_in_format_args)]
 pub fn start_tui() -> Result<()> {
     let mut state = Tengu::new();
     enable_raw_mode()?;
@@ -59,6 +96,18 @@ pub fn run_app(
                     KeyCode::Char('i') => {
                         state.change_mode(InputMode::Name);
                     }
+                    KeyCode::Char('x') => {
+                        state.change_mode(InputMode::Explain);
+                    }
+                    KeyCode::Char('b') => {
+                        state.open_browser();
+                    }
+                    KeyCode::Char('h') => {
+                        state.open_history();
+                    }
+                    KeyCode::Char('t') => {
+                        state.open_snippets();
+                    }
                     _ => {}
                 },
 
@@ -258,6 +307,258 @@ pub fn run_app(
                     KeyCode::Char('d') => {
                         state.check_delete();
                     }
+                    KeyCode::Char('x') => {
+                        state.change_mode(InputMode::Explain);
+                    }
+                    KeyCode::Char('b') => {
+                        state.open_browser();
+                    }
+                    KeyCode::Char('h') => {
+                        state.open_history();
+                    }
+                    KeyCode::Char('t') => {
+                        state.open_snippets();
+                    }
+                    _ => {}
+                },
+
+                InputMode::Browser => {
+                    let Some(browser) = state.browser.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            state.change_mode(InputMode::Normal);
+                        }
+                        KeyCode::Char('j') | KeyCode::Down => {
+                            browser.move_down();
+                        }
+                        KeyCode::Char('k') | KeyCode::Up => {
+                            browser.move_up();
+                        }
+                        KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => {
+                            browser.toggle();
+                        }
+                        KeyCode::Char('h') | KeyCode::Left => {
+                            browser.collapse();
+                        }
+                        KeyCode::Char('/') => {
+                            state.change_mode(InputMode::BrowserFilter);
+                        }
+                        KeyCode::Char('p') => {
+                            browser.preview();
+                        }
+                        KeyCode::Char('y') => {
+                            browser.copy_name();
+                        }
+                        KeyCode::Char('s') => {
+                            browser.template(false);
+                        }
+                        KeyCode::Char('n') => {
+                            browser.template(true);
+                        }
+                        KeyCode::Char('d') => {
+                            browser.ddl();
+                        }
+                        KeyCode::Char('e') => {
+                            state.open_export();
+                        }
+                        KeyCode::Char('u') => {
+                            state.switch_database();
+                        }
+                        KeyCode::PageDown => {
+                            browser.detail_scroll = browser.detail_scroll.saturating_add(10);
+                        }
+                        KeyCode::PageUp => {
+                            browser.detail_scroll = browser.detail_scroll.saturating_sub(10);
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::BrowserFilter => {
+                    let Some(browser) = state.browser.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            browser.set_filter(String::new());
+                            state.change_mode(InputMode::Browser);
+                        }
+                        KeyCode::Enter => {
+                            state.change_mode(InputMode::Browser);
+                        }
+                        KeyCode::Char(c) => {
+                            let filter = format!("{}{}", browser.filter, c);
+                            browser.set_filter(filter);
+                        }
+                        KeyCode::Backspace => {
+                            let mut filter = browser.filter.clone();
+                            filter.pop();
+                            browser.set_filter(filter);
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::History => {
+                    let Some(history) = state.history.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            state.history = None;
+                            state.change_mode(InputMode::Normal);
+                        }
+                        KeyCode::Enter => {
+                            history.run();
+                        }
+                        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            state.open_export();
+                        }
+                        KeyCode::Up => {
+                            history.move_up();
+                        }
+                        KeyCode::Down => {
+                            history.move_down();
+                        }
+                        KeyCode::Char(c) => {
+                            let search = format!("{}{}", history.search, c);
+                            history.set_search(search);
+                        }
+                        KeyCode::Backspace => {
+                            let mut search = history.search.clone();
+                            search.pop();
+                            history.set_search(search);
+                        }
+                        KeyCode::PageDown => {
+                            history.output_scroll = history.output_scroll.saturating_add(10);
+                        }
+                        KeyCode::PageUp => {
+                            history.output_scroll = history.output_scroll.saturating_sub(10);
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::Snippets => {
+                    let Some(snippets) = state.snippets.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            state.snippets = None;
+                            state.change_mode(InputMode::Normal);
+                        }
+                        KeyCode::Char('j') | KeyCode::Down => {
+                            snippets.move_down();
+                        }
+                        KeyCode::Char('k') | KeyCode::Up => {
+                            snippets.move_up();
+                        }
+                        KeyCode::Char('e') => {
+                            state.open_export();
+                        }
+                        KeyCode::Enter => {
+                            if snippets.prepare() {
+                                state.change_mode(InputMode::SnippetParams);
+                            } else if snippets.params.is_empty() {
+                                snippets.run();
+                            }
+                        }
+                        KeyCode::PageDown => {
+                            snippets.output_scroll = snippets.output_scroll.saturating_add(10);
+                        }
+                        KeyCode::PageUp => {
+                            snippets.output_scroll = snippets.output_scroll.saturating_sub(10);
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::SnippetParams => {
+                    let Some(snippets) = state.snippets.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            state.change_mode(InputMode::Snippets);
+                        }
+                        KeyCode::Enter => {
+                            snippets.run();
+                            state.change_mode(InputMode::Snippets);
+                        }
+                        KeyCode::Tab => {
+                            snippets.next_field();
+                        }
+                        KeyCode::BackTab => {
+                            snippets.previous_field();
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(input) = snippets.input() {
+                                input.push(c);
+                            }
+                        }
+                        KeyCode::Backspace => {
+                            if let Some(input) = snippets.input() {
+                                input.pop();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::Export => {
+                    let Some(export) = state.export.as_mut() else {
+                        state.change_mode(InputMode::Normal);
+                        continue;
+                    };
+                    match key.code {
+                        KeyCode::Esc => {
+                            let back = export.back;
+                            state.export = None;
+                            state.change_mode(back);
+                        }
+                        KeyCode::Enter => {
+                            export.write();
+                        }
+                        KeyCode::Tab => {
+                            export.next_format();
+                        }
+                        KeyCode::Char(c) => {
+                            export.path.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            export.path.pop();
+                        }
+                        _ => {}
+                    }
+                }
+
+                InputMode::Explain => match key.code {
+                    KeyCode::Esc => {
+                        state.change_mode(InputMode::Normal);
+                    }
+                    KeyCode::Enter => {
+                        state.explain();
+                    }
+                    KeyCode::Char(c) => {
+                        state.explain_sql.push(c);
+                    }
+                    KeyCode::Backspace => {
+                        state.explain_sql.pop();
+                    }
+                    KeyCode::Up => {
+                        state.explain_scroll = state.explain_scroll.saturating_sub(1);
+                    }
+                    KeyCode::Down => {
+                        state.explain_scroll = state.explain_scroll.saturating_add(1);
+                    }
                     _ => {}
                 },
 
@@ -1,8 +1,18 @@
+use super::block_on;
+use super::browser::{Browser, NodeKind};
+use super::export::ExportPrompt;
+use super::history::HistoryPanel;
 use super::repository::{FsTenguRepository, TenguRepository};
+use super::snippets::SnippetPanel;
 use crate::db::engine::Engine;
+use crate::db::plan::render;
+use crate::db::service::{Service, TenguService};
+use crate::db::tunnel::SshTunnel;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use tui::widgets::ListState;
 
+#[derive(Clone, Copy)]
 pub enum InputMode {
     Normal,
     Name,
@@ -16,6 +26,13 @@ pub enum InputMode {
     Search,
     List,
     Delete,
+    Explain,
+    Browser,
+    BrowserFilter,
+    History,
+    Snippets,
+    SnippetParams,
+    Export,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -27,6 +44,22 @@ pub struct Connection {
     pub username: String,
     pub password: String,
     pub database: String,
+    /// A file written by `tengu schema dump` to read the schema from instead
+    /// of connecting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<PathBuf>,
+    /// Postgres schemas to look unqualified names up in, e.g. `app, public`,
+    /// instead of the server's default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_path: Option<String>,
+    /// Seconds a statement or catalog query may run before it's cancelled.
+    /// On SQL Server cancelling ends the session with `KILL`, which takes the
+    /// ALTER ANY CONNECTION permission.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement_timeout: Option<u64>,
+    /// A jump host to reach the database through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshTunnel>,
 }
 
 impl Connection {
@@ -47,8 +80,28 @@ impl Connection {
             username,
             password,
             database,
+            snapshot: None,
+            search_path: None,
+            statement_timeout: None,
+            ssh: None,
         }
     }
+
+    /// The same connection to another database on its server.
+    pub fn on_database(&self, database: &str) -> Connection {
+        Connection {
+            database: database.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Who connects to which server, whatever the database.
+    pub fn server(&self) -> String {
+        format!(
+            "{}://{}@{}:{}",
+            self.engine, self.username, self.host, self.port
+        )
+    }
 }
 
 pub struct Tengu<R: TenguRepository> {
@@ -70,6 +123,13 @@ pub struct Tengu<R: TenguRepository> {
     pub edit_mode: bool,
     pub edit_index: Option<usize>,
     pub active_connection: Option<Connection>,
+    pub explain_sql: String,
+    pub explain_output: String,
+    pub explain_scroll: u16,
+    pub browser: Option<Browser>,
+    pub history: Option<HistoryPanel>,
+    pub snippets: Option<SnippetPanel>,
+    pub export: Option<ExportPrompt>,
 }
 
 impl Tengu<FsTenguRepository> {
@@ -95,6 +155,13 @@ impl Tengu<FsTenguRepository> {
             edit_mode: false,
             edit_index: None,
             active_connection: None,
+            explain_sql: String::new(),
+            explain_output: String::new(),
+            explain_scroll: 0,
+            browser: None,
+            history: None,
+            snippets: None,
+            export: None,
         }
     }
     pub fn change_mode(&mut self, mode: InputMode) {
@@ -259,4 +326,147 @@ impl Tengu<FsTenguRepository> {
             self.active_connection = Some(connection);
         }
     }
+
+    /// Shows the estimated plan of `explain_sql` on the active connection.
+    pub fn explain(&mut self) {
+        self.explain_scroll = 0;
+        let Some(connection) = self.repo.get_active_connection() else {
+            self.explain_output = "No active connection".to_string();
+            return;
+        };
+        if !matches!(connection.engine, Engine::SqlServer | Engine::Postgres) {
+            self.explain_output = format!("Explain isn't supported for {}", connection.engine);
+            return;
+        }
+        let service = TenguService::new(connection.engine, FsTenguRepository::new());
+        let plans = block_on(service.explain(&self.explain_sql));
+        self.explain_output = match plans {
+            Ok(plans) if plans.is_empty() => "No plan returned".to_string(),
+            Ok(plans) => render(&plans),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    /// Opens the schema browser on the active connection, keeping what was
+    /// already loaded if the connection didn't change.
+    pub fn open_browser(&mut self) {
+        let Some(connection) = self.repo.get_active_connection() else {
+            return;
+        };
+        if !matches!(connection.engine, Engine::SqlServer | Engine::Postgres) {
+            return;
+        }
+        if self
+            .browser
+            .as_ref()
+            .is_none_or(|b| b.connection != connection.name || b.database != connection.database)
+        {
+            self.browser = Some(Browser::new(connection));
+        }
+        self.change_mode(InputMode::Browser);
+    }
+
+    /// Moves the active connection to the database selected in the browser
+    /// and browses that one instead.
+    pub fn switch_database(&mut self) {
+        let Some(node) = self.browser.as_ref().and_then(|b| b.selected()) else {
+            return;
+        };
+        if node.kind != NodeKind::Database {
+            return;
+        }
+        let database = node.name.clone();
+        let Some(connection) = self.repo.get_active_connection() else {
+            return;
+        };
+        if connection.database == database {
+            return;
+        }
+        let connection = connection.on_database(&database);
+        self.repo.activate_connection(&connection);
+        self.active_connection = Some(connection.clone());
+        self.browser = Some(Browser::new(connection));
+    }
+
+    /// Opens the query history, read afresh so it includes runs made from the
+    /// editor or the CLI since the TUI started.
+    pub fn open_history(&mut self) {
+        self.history = Some(HistoryPanel::load());
+        self.change_mode(InputMode::History);
+    }
+
+    /// Opens the snippets saved for the active connection or for all of them.
+    pub fn open_snippets(&mut self) {
+        let Some(connection) = self.repo.get_active_connection() else {
+            return;
+        };
+        if !matches!(connection.engine, Engine::SqlServer | Engine::Postgres) {
+            return;
+        }
+        self.snippets = Some(SnippetPanel::new(connection));
+        self.change_mode(InputMode::Snippets);
+    }
+
+    /// Asks where to write the rows the current panel last fetched.
+    pub fn open_export(&mut self) {
+        let source = match self.mode {
+            InputMode::Browser => self.browser.as_ref().and_then(|b| b.exportable.clone()),
+            InputMode::History => self.history.as_ref().and_then(|h| h.exportable.clone()),
+            InputMode::Snippets => self.snippets.as_ref().and_then(|s| s.exportable.clone()),
+            _ => None,
+        };
+        let Some(source) = source else {
+            return;
+        };
+        self.export = Some(ExportPrompt::new(source, self.mode));
+        self.change_mode(InputMode::Export);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        Connection::new(
+            "local".to_string(),
+            Engine::Postgres,
+            "localhost".to_string(),
+            "5432".to_string(),
+            "postgres".to_string(),
+            "secret".to_string(),
+            "shop".to_string(),
+        )
+    }
+
+    #[test]
+    fn should_change_only_the_database() {
+        let connection = Connection {
+            search_path: Some("app, public".to_string()),
+            ..connection()
+        };
+
+        let crm = connection.on_database("crm");
+
+        assert_eq!(crm.database, "crm");
+        assert_eq!(crm.name, "local");
+        assert_eq!(crm.search_path.as_deref(), Some("app, public"));
+        assert_eq!(connection.database, "shop");
+    }
+
+    #[test]
+    fn should_name_server_without_database() {
+        let shop = connection();
+
+        assert_eq!(shop.server(), "postgres://postgres@localhost:5432");
+        assert_eq!(shop.on_database("crm").server(), shop.server());
+        assert_ne!(
+            Connection {
+                port: "5433".to_string(),
+                ..shop.clone()
+            }
+            .server(),
+            shop.server()
+        );
+    }
 }
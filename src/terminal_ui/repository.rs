@@ -5,6 +5,8 @@ use std::{fs, io::Write, path::PathBuf};
 pub struct FsTenguRepository {
     base_path: PathBuf,
     active_conn_file_path: PathBuf,
+    /// Stands in for the active connection when set.
+    pinned: Option<Connection>,
 }
 
 impl FsTenguRepository {
@@ -18,8 +20,24 @@ impl FsTenguRepository {
         FsTenguRepository {
             base_path,
             active_conn_file_path,
+            pinned: None,
         }
     }
+
+    /// A repository whose active connection is the saved connection `name`,
+    /// whatever `.active` says, for commands working on several at once.
+    pub(crate) fn pinned(name: &str) -> anyhow::Result<FsTenguRepository> {
+        let repo = FsTenguRepository::new();
+        let connection = repo
+            .list()
+            .into_iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow::anyhow!("no saved connection named {}", name))?;
+        Ok(FsTenguRepository {
+            pinned: Some(connection),
+            ..repo
+        })
+    }
 }
 
 pub trait TenguRepository {
@@ -55,7 +73,11 @@ impl TenguRepository for FsTenguRepository {
         active_conn.write_all(conn_json.as_bytes()).unwrap();
     }
 
+    #[allow(clippy::manual_ok_err)]
     fn get_active_connection(&self) -> Option<Connection> {
+        if let Some(connection) = &self.pinned {
+            return Some(connection.clone());
+        }
         let Ok(active_conn_file) = fs::File::open(self.active_conn_file_path.clone()) else {
             println!("No active connection found");
             return None;
@@ -86,6 +108,7 @@ impl TenguRepository for FsTenguRepository {
         connections
     }
 
+    #[allow(clippy::needless_return)]
     fn active_connection_path(&self) -> PathBuf {
         let base_path = dirs::home_dir().unwrap().join(".config").join("tengu");
         let active_conn_file_path = base_path.join(".active");
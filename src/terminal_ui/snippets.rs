@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use tui::widgets::ListState;
+
+use super::block_on;
+use super::export::ExportSource;
+use super::models::Connection;
+use super::repository::FsTenguRepository;
+use crate::db::query::{render, ResultFormat};
+use crate::db::service::{Service, TenguService};
+use crate::snippet::{bind, for_connection, placeholders, Placeholder, Snippet, Snippets};
+
+/// The snippets of the active connection, and the parameters of the one
+/// about to run.
+pub struct SnippetPanel {
+    connection: Connection,
+    pub snippets: Vec<Snippet>,
+    pub list_state: ListState,
+    /// Placeholders of the selected snippet with the values typed so far.
+    pub params: Vec<(Placeholder, String)>,
+    pub field: usize,
+    pub output: String,
+    pub output_scroll: u16,
+    pub exportable: Option<ExportSource>,
+}
+
+impl SnippetPanel {
+    pub fn new(connection: Connection) -> SnippetPanel {
+        let (snippets, output) = match Snippets::open().load() {
+            Ok(snippets) => (
+                for_connection(snippets, Some(&connection.name)),
+                String::new(),
+            ),
+            Err(e) => (vec![], format!("Error reading snippets: {}", e)),
+        };
+        let mut list_state = ListState::default();
+        list_state.select((!snippets.is_empty()).then_some(0));
+        SnippetPanel {
+            connection,
+            snippets,
+            list_state,
+            params: vec![],
+            field: 0,
+            output,
+            output_scroll: 0,
+            exportable: None,
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Snippet> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.snippets.get(i))
+    }
+
+    fn select(&mut self, index: usize) {
+        self.list_state.select(Some(index));
+        self.output.clear();
+        self.output_scroll = 0;
+        self.exportable = None;
+    }
+
+    pub fn move_up(&mut self) {
+        let selected = self
+            .list_state
+            .selected()
+            .map_or(0, |v| v.saturating_sub(1));
+        self.select(selected);
+    }
+
+    pub fn move_down(&mut self) {
+        let last = self.snippets.len().saturating_sub(1);
+        let selected = self.list_state.selected().map_or(0, |v| (v + 1).min(last));
+        self.select(selected);
+    }
+
+    /// Readies the parameters of the selected snippet, returning whether
+    /// there are any to ask for.
+    pub fn prepare(&mut self) -> bool {
+        let Some(snippet) = self.selected() else {
+            return false;
+        };
+        match placeholders(&snippet.sql) {
+            Ok(placeholders) => {
+                self.params = placeholders
+                    .into_iter()
+                    .map(|p| (p, String::new()))
+                    .collect();
+                self.field = 0;
+                !self.params.is_empty()
+            }
+            Err(e) => {
+                self.params.clear();
+                self.output = format!("Error: {}", e);
+                false
+            }
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.field = (self.field + 1).min(self.params.len().saturating_sub(1));
+    }
+
+    pub fn previous_field(&mut self) {
+        self.field = self.field.saturating_sub(1);
+    }
+
+    pub fn input(&mut self) -> Option<&mut String> {
+        self.params.get_mut(self.field).map(|(_, value)| value)
+    }
+
+    /// Runs the selected snippet with the parameters typed in.
+    pub fn run(&mut self) {
+        self.output_scroll = 0;
+        let Some(snippet) = self.selected() else {
+            return;
+        };
+        let values: HashMap<String, String> = self
+            .params
+            .iter()
+            .map(|(p, value)| (p.name.clone(), value.clone()))
+            .collect();
+        let engine = self.connection.engine.clone();
+        let sql = match bind(&snippet.sql, &engine, &values) {
+            Ok(sql) => sql,
+            Err(e) => {
+                self.output = format!("Error: {}", e);
+                return;
+            }
+        };
+        let service = TenguService::new(engine.clone(), FsTenguRepository::new());
+        match block_on(service.execute(&sql)) {
+            Ok(results) => {
+                self.output = format!("{}\n\n{}", sql, render(&results, ResultFormat::Markdown));
+                self.exportable = Some(ExportSource {
+                    results,
+                    engine,
+                    table: "results".to_string(),
+                });
+            }
+            Err(e) => {
+                self.output = format!("{}\n\nError: {}", sql, e);
+                self.exportable = None;
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::io;
 
 use crate::db::engine::Engine;
+use crate::history::Status;
 
 use super::models::{InputMode, Tengu};
 use super::repository::{FsTenguRepository, TenguRepository};
@@ -21,6 +22,10 @@ d:           On list, It's Delete connection
 e:           On list, It's Edit connection
 s:           Search
 i:           Insert new Connection
+x:           Explain a query on the active connection
+b:           Browse the active connection's schema
+h:           Search the query history
+t:           Run a saved snippet
 Tab:         Go to next field
 Shift+Tab:   Go to previous filed
 Esc:         Exit insert mode
@@ -67,6 +72,393 @@ pub fn ui(
     list_section(f, state, parent_chunk[0]);
 
     delete_popup(f, state);
+    explain_popup(f, state);
+    browser_popup(f, state);
+    history_popup(f, state);
+    snippets_popup(f, state);
+    export_popup(f, state);
+}
+
+fn export_popup(f: &mut Frame<CrosstermBackend<io::Stdout>>, state: &mut Tengu<FsTenguRepository>) {
+    let InputMode::Export = state.mode else {
+        return;
+    };
+    let Some(export) = state.export.as_ref() else {
+        return;
+    };
+    let block = Block::default()
+        .title("EXPORT")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let area = centered_rect(60, 40, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunk = Layout::default()
+        .margin(2)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let path_input = Paragraph::new(export.path.to_owned())
+        .block(
+            Block::default()
+                .title("File")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(path_input, chunk[0]);
+
+    let format = Paragraph::new(format!("Format: {}", export.format.name()));
+    f.render_widget(format, chunk[1]);
+
+    let message = Paragraph::new(export.message.to_owned());
+    f.render_widget(message, chunk[2]);
+
+    let keys_desc = Paragraph::new("Enter: Write   Tab: Next format   Esc: Close")
+        .style(Style::default().fg(Color::LightMagenta));
+    f.render_widget(keys_desc, chunk[3]);
+}
+
+const SNIPPETS_KEYS_DESC: &str =
+    "j/k: Select  Enter: Run  e: Export  PgUp/PgDn: Scroll  Esc: Close";
+const SNIPPET_PARAMS_KEYS_DESC: &str =
+    "Tab/Shift+Tab: Next/previous parameter  Enter: Run  Esc: Cancel";
+
+fn snippets_popup(
+    f: &mut Frame<CrosstermBackend<io::Stdout>>,
+    state: &mut Tengu<FsTenguRepository>,
+) {
+    let prompting = match state.mode {
+        InputMode::Snippets => false,
+        InputMode::SnippetParams => true,
+        _ => return,
+    };
+    let Some(snippets) = state.snippets.as_mut() else {
+        return;
+    };
+    let block = Block::default()
+        .title("SNIPPETS")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let area = centered_rect(95, 90, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .margin(1)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+        .split(rows[0]);
+
+    let items: Vec<ListItem> = snippets
+        .snippets
+        .iter()
+        .map(|s| ListItem::new(s.name.to_owned()))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::LightGreen),
+        );
+    f.render_stateful_widget(list, columns[0], &mut snippets.list_state);
+
+    let detail_area = if prompting {
+        let mut constraints = vec![Constraint::Length(3); snippets.params.len()];
+        constraints.push(Constraint::Min(1));
+        let fields = Layout::default().constraints(constraints).split(columns[1]);
+        for (i, (placeholder, value)) in snippets.params.iter().enumerate() {
+            let input = Paragraph::new(value.to_owned())
+                .block(
+                    Block::default()
+                        .title(format!("{} ({})", placeholder.name, placeholder.param_type))
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                )
+                .style(if i == snippets.field {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                });
+            f.render_widget(input, fields[i]);
+        }
+        fields[snippets.params.len()]
+    } else {
+        columns[1]
+    };
+    let detail = if snippets.output.is_empty() || prompting {
+        snippets
+            .selected()
+            .map(|s| s.sql.to_owned())
+            .unwrap_or_default()
+    } else {
+        snippets.output.to_owned()
+    };
+    let detail = Paragraph::new(detail)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .scroll((snippets.output_scroll, 0));
+    f.render_widget(detail, detail_area);
+
+    let keys_desc = Paragraph::new(if prompting {
+        SNIPPET_PARAMS_KEYS_DESC
+    } else {
+        SNIPPETS_KEYS_DESC
+    })
+    .style(Style::default().fg(Color::LightMagenta));
+    f.render_widget(keys_desc, rows[1]);
+}
+
+const HISTORY_KEYS_DESC: &str =
+    "Type to search  Up/Down: Select  Enter: Run again  Ctrl+E: Export  PgUp/PgDn: Scroll  Esc: Close";
+
+fn history_popup(
+    f: &mut Frame<CrosstermBackend<io::Stdout>>,
+    state: &mut Tengu<FsTenguRepository>,
+) {
+    let InputMode::History = state.mode else {
+        return;
+    };
+    let Some(history) = state.history.as_mut() else {
+        return;
+    };
+    let block = Block::default()
+        .title("HISTORY")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let area = centered_rect(95, 90, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .margin(1)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(rows[0]);
+    let list_area = Layout::default()
+        .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+        .split(columns[0]);
+
+    let search_input = Paragraph::new(history.search.to_owned())
+        .block(
+            Block::default()
+                .title("Search")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(search_input, list_area[0]);
+
+    let width = list_area[1].width.saturating_sub(24) as usize;
+    let items: Vec<ListItem> = history
+        .matches
+        .iter()
+        .map(|entry| {
+            let style = match entry.status {
+                Status::Ok => Style::default(),
+                Status::Error => Style::default().fg(Color::LightRed),
+            };
+            ListItem::new(format!("{}  {}", entry.time(), entry.summary(width))).style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::LightGreen),
+        );
+    f.render_stateful_widget(list, list_area[1], &mut history.list_state);
+
+    let detail = Paragraph::new(history.detail())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .scroll((history.output_scroll, 0));
+    f.render_widget(detail, columns[1]);
+
+    let keys_desc =
+        Paragraph::new(HISTORY_KEYS_DESC).style(Style::default().fg(Color::LightMagenta));
+    f.render_widget(keys_desc, rows[1]);
+}
+
+const BROWSER_KEYS_DESC: &str =
+    "Enter/l: Expand  h: Collapse  /: Filter  p: Preview  y: Copy name  s: SELECT  n: INSERT  d: DDL  e: Export preview  u: Use database  PgUp/PgDn: Scroll  Esc: Close";
+
+fn browser_popup(
+    f: &mut Frame<CrosstermBackend<io::Stdout>>,
+    state: &mut Tengu<FsTenguRepository>,
+) {
+    let filtering = match state.mode {
+        InputMode::Browser => false,
+        InputMode::BrowserFilter => true,
+        _ => return,
+    };
+    let Some(browser) = state.browser.as_mut() else {
+        return;
+    };
+    let block = Block::default()
+        .title("SCHEMA")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let area = centered_rect(95, 90, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .margin(1)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(rows[0]);
+    let tree = Layout::default()
+        .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+        .split(columns[0]);
+
+    let filter_input = Paragraph::new(browser.filter.to_owned())
+        .block(
+            Block::default()
+                .title("Filter")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .style(if filtering {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        });
+    f.render_widget(filter_input, tree[0]);
+
+    let items: Vec<ListItem> = browser
+        .rows()
+        .into_iter()
+        .filter_map(|(depth, path)| {
+            let node = browser.node(&path)?;
+            let marker = match (&node.children, node.expanded) {
+                (Some(children), _) if children.is_empty() => " ",
+                (_, true) => "▾",
+                (_, false) => "▸",
+            };
+            Some(ListItem::new(format!(
+                "{}{} {}",
+                "  ".repeat(depth),
+                marker,
+                node.label
+            )))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::LightGreen),
+        );
+    f.render_stateful_widget(list, tree[1], &mut browser.list_state);
+
+    let detail = Paragraph::new(browser.detail.to_owned())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .scroll((browser.detail_scroll, 0));
+    f.render_widget(detail, columns[1]);
+
+    let keys_desc =
+        Paragraph::new(BROWSER_KEYS_DESC).style(Style::default().fg(Color::LightMagenta));
+    f.render_widget(keys_desc, rows[1]);
+}
+
+fn explain_popup(
+    f: &mut Frame<CrosstermBackend<io::Stdout>>,
+    state: &mut Tengu<FsTenguRepository>,
+) {
+    if let InputMode::Explain = state.mode {
+        let block = Block::default()
+            .title("EXPLAIN")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let area = centered_rect(90, 80, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let chunk = Layout::default()
+            .margin(2)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(1),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let sql_input = Paragraph::new(state.explain_sql.to_owned())
+            .block(
+                Block::default()
+                    .title("Query")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(sql_input, chunk[0]);
+
+        let plan = Paragraph::new(state.explain_output.to_owned())
+            .block(
+                Block::default()
+                    .title("Estimated plan")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .scroll((state.explain_scroll, 0));
+        f.render_widget(plan, chunk[1]);
+
+        let keys_desc = Paragraph::new("Enter: Explain   Up/Down: Scroll   Esc: Close")
+            .style(Style::default().fg(Color::LightMagenta));
+        f.render_widget(keys_desc, chunk[2]);
+    }
 }
 
 fn delete_popup(f: &mut Frame<CrosstermBackend<io::Stdout>>, state: &mut Tengu<FsTenguRepository>) {
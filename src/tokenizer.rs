@@ -1,7 +1,10 @@
 use std::collections::HashSet;
 
+use tower_lsp::lsp_types::{Position, Range};
+
 use crate::db::table::Table;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub enum Token {
     Number(String),
@@ -60,14 +63,12 @@ impl FromIterator<Token> for HashSet<Table> {
     fn from_iter<I: IntoIterator<Item = Token>>(iter: I) -> Self {
         let mut tables = HashSet::new();
         for token in iter {
-            match token {
-                Token::Token(token) => {
-                    tables.insert(Table {
-                        name: token,
-                        schema: "".to_string(),
-                    });
-                }
-                _ => {}
+            if let Token::Token(token) = token {
+                tables.insert(Table {
+                    name: token,
+                    schema: "".to_string(),
+                    description: None,
+                });
             }
         }
         tables
@@ -80,6 +81,297 @@ pub fn intersection(list1: Vec<String>, list2: Vec<String>) -> Vec<String> {
     set1.intersection(&set2).cloned().collect()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexemeKind {
+    Whitespace,
+    Comment,
+    Word,
+    QuotedIdentifier,
+    String,
+    Number,
+    Punctuation,
+    Operator,
+}
+
+/// A token that remembers where it came from, so callers can map it back to
+/// an LSP position. `character` is counted in UTF-16 code units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+    pub kind: LexemeKind,
+    pub text: String,
+    pub offset: usize,
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Lexeme {
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == LexemeKind::Word && self.text.eq_ignore_ascii_case(word)
+    }
+
+    pub fn is_punct(&self, punct: &str) -> bool {
+        self.kind == LexemeKind::Punctuation && self.text == punct
+    }
+
+    pub fn is_identifier(&self) -> bool {
+        matches!(self.kind, LexemeKind::Word | LexemeKind::QuotedIdentifier)
+    }
+
+    /// The identifier without its `[]`, `""` or backtick quoting.
+    pub fn unquoted(&self) -> String {
+        if self.kind != LexemeKind::QuotedIdentifier || self.text.len() < 2 {
+            return self.text.clone();
+        }
+        let inner = &self.text[1..self.text.len() - 1];
+        match self.text.chars().next() {
+            Some('[') => inner.replace("]]", "]"),
+            Some('"') => inner.replace("\"\"", "\""),
+            _ => inner.replace("``", "`"),
+        }
+    }
+
+    pub fn range(&self) -> Range {
+        let (line, character) = self.end();
+        Range::new(
+            Position::new(self.line, self.character),
+            Position::new(line, character),
+        )
+    }
+
+    /// Line and UTF-16 character right after the last char of the lexeme.
+    pub fn end(&self) -> (u32, u32) {
+        let mut line = self.line;
+        let mut character = self.character;
+        for c in self.text.chars() {
+            if c == '\n' {
+                line += 1;
+                character = 0;
+            } else {
+                character += c.len_utf16() as u32;
+            }
+        }
+        (line, character)
+    }
+}
+
+/// Splits `sql` into lexemes, keeping whitespace and comments so the input can
+/// be rebuilt exactly by concatenating the `text` of every lexeme.
+pub fn lex(sql: &str) -> Vec<Lexeme> {
+    let chars: Vec<(usize, char)> = sql.char_indices().collect();
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+    let mut line = 0;
+    let mut character = 0;
+    while i < chars.len() {
+        let start = i;
+        let (offset, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        let kind = match c {
+            c if c.is_whitespace() => {
+                while i < chars.len() && chars[i].1.is_whitespace() {
+                    i += 1;
+                }
+                LexemeKind::Whitespace
+            }
+            '-' if next == Some('-') => {
+                while i < chars.len() && chars[i].1 != '\n' {
+                    i += 1;
+                }
+                LexemeKind::Comment
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len()
+                    && !(chars[i].1 == '*' && chars.get(i + 1).map(|c| c.1) == Some('/'))
+                {
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                LexemeKind::Comment
+            }
+            '\'' => {
+                i = skip_quoted(&chars, i, '\'');
+                LexemeKind::String
+            }
+            'E' | 'e' if next == Some('\'') => {
+                i = skip_escaped(&chars, i + 1);
+                LexemeKind::String
+            }
+            'N' | 'n' | 'X' | 'x' | 'B' | 'b' if next == Some('\'') => {
+                i = skip_quoted(&chars, i + 1, '\'');
+                LexemeKind::String
+            }
+            '"' => {
+                i = skip_quoted(&chars, i, '"');
+                LexemeKind::QuotedIdentifier
+            }
+            '`' => {
+                i = skip_quoted(&chars, i, '`');
+                LexemeKind::QuotedIdentifier
+            }
+            '[' => {
+                i = skip_quoted(&chars, i, ']');
+                LexemeKind::QuotedIdentifier
+            }
+            '$' if dollar_tag(&chars, i).is_some() => {
+                let tag = dollar_tag(&chars, i).unwrap_or_default();
+                i += tag.chars().count();
+                while i < chars.len() && !starts_with(&chars, i, &tag) {
+                    i += 1;
+                }
+                i = (i + tag.chars().count()).min(chars.len());
+                LexemeKind::String
+            }
+            '0'..='9' => {
+                i = skip_number(&chars, i);
+                LexemeKind::Number
+            }
+            '.' if next.is_some_and(|c| c.is_ascii_digit())
+                && !lexemes.last().is_some_and(|l: &Lexeme| l.is_identifier()) =>
+            {
+                i = skip_number(&chars, i);
+                LexemeKind::Number
+            }
+            c if c.is_alphabetic() || c == '_' || c == '@' || c == '#' || c == '$' => {
+                i += 1;
+                while i < chars.len()
+                    && (chars[i].1.is_alphanumeric() || matches!(chars[i].1, '_' | '@' | '#' | '$'))
+                {
+                    i += 1;
+                }
+                LexemeKind::Word
+            }
+            '(' | ')' | ',' | ';' | '.' => {
+                i += 1;
+                LexemeKind::Punctuation
+            }
+            _ => {
+                i += 1;
+                let two: String = chars[start..(start + 2).min(chars.len())]
+                    .iter()
+                    .map(|c| c.1)
+                    .collect();
+                let three: String = chars[start..(start + 3).min(chars.len())]
+                    .iter()
+                    .map(|c| c.1)
+                    .collect();
+                if three == "->>" {
+                    i += 2;
+                } else if matches!(
+                    two.as_str(),
+                    "<=" | ">="
+                        | "<>"
+                        | "!="
+                        | "::"
+                        | "||"
+                        | "->"
+                        | "+="
+                        | "-="
+                        | "*="
+                        | "/="
+                        | "!<"
+                        | "!>"
+                ) {
+                    i += 1;
+                }
+                LexemeKind::Operator
+            }
+        };
+        let end = chars.get(i).map_or(sql.len(), |c| c.0);
+        let text = sql[offset..end].to_string();
+        let lexeme = Lexeme {
+            kind,
+            text,
+            offset,
+            line,
+            character,
+        };
+        (line, character) = lexeme.end();
+        lexemes.push(lexeme);
+        debug_assert!(i > start);
+    }
+    lexemes
+}
+
+fn skip_quoted(chars: &[(usize, char)], start: usize, close: char) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i].1 == close {
+            // A doubled closing quote is an escaped quote, not the end.
+            if chars.get(i + 1).map(|c| c.1) == Some(close) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// Postgres `E'…'` strings, where a backslash escapes the next char as well
+/// as the usual doubled quote.
+fn skip_escaped(chars: &[(usize, char)], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i].1 {
+            '\\' => i += 2,
+            '\'' if chars.get(i + 1).map(|c| c.1) == Some('\'') => i += 2,
+            '\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn skip_number(chars: &[(usize, char)], start: usize) -> usize {
+    let mut i = start;
+    let mut seen_dot = false;
+    while i < chars.len() {
+        match chars[i].1 {
+            '0'..='9' => i += 1,
+            '.' if !seen_dot => {
+                seen_dot = true;
+                i += 1;
+            }
+            'e' | 'E'
+                if chars
+                    .get(i + 1)
+                    .is_some_and(|c| c.1.is_ascii_digit() || c.1 == '-' || c.1 == '+') =>
+            {
+                i += 2;
+            }
+            _ => break,
+        }
+    }
+    i
+}
+
+/// Postgres dollar quoting: `$$` or `$tag$`.
+fn dollar_tag(chars: &[(usize, char)], start: usize) -> Option<String> {
+    let mut tag = String::from("$");
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if c == '$' {
+            tag.push(c);
+            return Some(tag);
+        }
+        if !(c.is_alphanumeric() || c == '_') || (i == start + 1 && c.is_ascii_digit()) {
+            return None;
+        }
+        tag.push(c);
+        i += 1;
+    }
+    None
+}
+
+fn starts_with(chars: &[(usize, char)], start: usize, tag: &str) -> bool {
+    tag.chars()
+        .enumerate()
+        .all(|(n, c)| chars.get(start + n).map(|c| c.1) == Some(c))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +390,49 @@ mod tests {
         assert!(tokens.contains(&Token::Ignored("=".to_string())));
         assert!(tokens.contains(&Token::Number("1".to_string())));
     }
+
+    #[test]
+    fn should_lex_with_positions() {
+        let sql = "SELECT [first name], N'it''s'\n  FROM dbo.t -- note\nWHERE a <> 1.5";
+        let lexemes = lex(sql);
+        assert_eq!(
+            lexemes.iter().map(|l| l.text.as_str()).collect::<String>(),
+            sql
+        );
+        let significant: Vec<_> = lexemes
+            .iter()
+            .filter(|l| !matches!(l.kind, LexemeKind::Whitespace | LexemeKind::Comment))
+            .collect();
+        assert_eq!(significant[1].kind, LexemeKind::QuotedIdentifier);
+        assert_eq!(significant[1].unquoted(), "first name");
+        assert_eq!(significant[3].kind, LexemeKind::String);
+        assert_eq!(significant[3].text, "N'it''s'");
+        let from = significant.iter().find(|l| l.is_word("from")).unwrap();
+        assert_eq!((from.line, from.character), (1, 2));
+        assert!(significant
+            .iter()
+            .any(|l| l.kind == LexemeKind::Operator && l.text == "<>"));
+        assert_eq!(significant.last().unwrap().text, "1.5");
+    }
+
+    #[test]
+    fn should_lex_dollar_quoted_bodies_as_one_string() {
+        let sql = "CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ LANGUAGE sql";
+        let strings: Vec<_> = lex(sql)
+            .into_iter()
+            .filter(|l| l.kind == LexemeKind::String)
+            .collect();
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings[0].text, "$body$ SELECT 1; $body$");
+    }
+
+    #[test]
+    fn should_lex_backslash_escapes_in_e_strings() {
+        let strings: Vec<_> = lex(r"select e'x\'y', E'a\\' from t")
+            .into_iter()
+            .filter(|l| l.kind == LexemeKind::String)
+            .map(|l| l.text)
+            .collect();
+        assert_eq!(strings, vec![r"e'x\'y'", r"E'a\\'"]);
+    }
 }